@@ -0,0 +1,129 @@
+//! Generates the request bodies of `src/generated.rs` from the schemas of
+//! `openapi/chroma-v1.json`.
+
+use serde_json::{Map, Value};
+use std::fmt::Write;
+use std::path::Path;
+
+const SPEC: &str = "openapi/chroma-v1.json";
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", SPEC);
+    let spec: Value = serde_json::from_str(&std::fs::read_to_string(SPEC).unwrap()).unwrap();
+    let schemas = spec["components"]["schemas"].as_object().unwrap();
+
+    let mut code = String::new();
+    for (name, schema) in schemas {
+        if let Some(values) = schema["enum"].as_array() {
+            write_enum(&mut code, name, schema, values);
+        } else if let Some(properties) = schema["properties"].as_object() {
+            write_struct(&mut code, name, schema, properties);
+        } else {
+            doc(&mut code, name, schema);
+            writeln!(code, "pub type {} = {};\n", name, rust_type(schema)).unwrap();
+        }
+    }
+
+    let out = std::env::var("OUT_DIR").unwrap();
+    std::fs::write(Path::new(&out).join("generated.rs"), code).unwrap();
+}
+
+fn doc(code: &mut String, name: &str, schema: &Value) {
+    writeln!(code, "/// Schema `{}`.", name).unwrap();
+    if let Some(description) = schema["description"].as_str() {
+        writeln!(code, "/// {}", description).unwrap();
+    }
+}
+
+fn write_enum(code: &mut String, name: &str, schema: &Value, values: &[Value]) {
+    doc(code, name, schema);
+    code.push_str("#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]\n");
+    writeln!(code, "pub enum {} {{", name).unwrap();
+    for value in values {
+        let value = value.as_str().unwrap();
+        writeln!(code, "    #[serde(rename = \"{}\")]", value).unwrap();
+        writeln!(code, "    {},", pascal_case(value)).unwrap();
+    }
+    code.push_str("}\n\n");
+}
+
+fn write_struct(code: &mut String, name: &str, schema: &Value, properties: &Map<String, Value>) {
+    let required: Vec<&str> = schema["required"]
+        .as_array()
+        .map(|r| r.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    doc(code, name, schema);
+    code.push_str("#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]\n");
+    writeln!(code, "pub struct {} {{", name).unwrap();
+    for (field, property) in properties {
+        if let Some(default) = property.get("default") {
+            writeln!(code, "    /// {} when left out.", default).unwrap();
+        }
+        let (nullable, inner) = nullable(property);
+        let ty = rust_type(inner);
+        let ty = if required.contains(&field.as_str()) {
+            if nullable {
+                format!("Option<{}>", ty)
+            } else {
+                ty
+            }
+        } else {
+            code.push_str("    #[serde(default, skip_serializing_if = \"Option::is_none\")]\n");
+            format!("Option<{}>", ty)
+        };
+        writeln!(code, "    pub {}: {},", field_name(field), ty).unwrap();
+    }
+    code.push_str("}\n\n");
+}
+
+/// Whether the schema is `anyOf` a type and null, and that type.
+fn nullable(schema: &Value) -> (bool, &Value) {
+    match schema["anyOf"].as_array().map(Vec::as_slice) {
+        Some([inner, null]) if null["type"] == "null" => (true, inner),
+        _ => (false, schema),
+    }
+}
+
+fn rust_type(schema: &Value) -> String {
+    if let Some(reference) = schema["$ref"].as_str() {
+        return reference.rsplit('/').next().unwrap().to_string();
+    }
+    let (nullable, inner) = nullable(schema);
+    if nullable {
+        return format!("Option<{}>", rust_type(inner));
+    }
+    match schema["type"].as_str() {
+        Some("string") => "String".into(),
+        Some("boolean") => "bool".into(),
+        // Counts, limits and offsets.
+        Some("integer") => "usize".into(),
+        // Embeddings, sent as 32 bit floats.
+        Some("number") => "f32".into(),
+        Some("array") => format!("Vec<{}>", rust_type(&schema["items"])),
+        Some("object") if schema.get("additionalProperties").is_some() => {
+            "serde_json::Map<String, Value>".into()
+        }
+        _ => "Value".into(),
+    }
+}
+
+fn field_name(name: &str) -> String {
+    match name {
+        "where" | "type" | "ref" | "match" => format!("r#{}", name),
+        _ => name.to_string(),
+    }
+}
+
+fn pascal_case(value: &str) -> String {
+    value
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|c| c.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect()
+}
@@ -0,0 +1,141 @@
+//! Derive macros for [chromadb-rs](https://crates.io/crates/chromadb-rs), use
+//! them through its `derive` feature.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, LitStr};
+
+/// Maps a struct with named fields to Chroma metadata, see
+/// `chromadb_rs::metadata::ChromaMetadata`.
+///
+/// Field attributes:
+/// - `#[chroma(rename = "key")]` stores the field under another key.
+/// - `#[chroma(flatten)]` merges the keys of a nested `ChromaMetadata` struct.
+#[proc_macro_derive(ChromaMetadata, attributes(chroma))]
+pub fn derive_chroma_metadata(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+struct FieldAttributes {
+    rename: Option<String>,
+    flatten: bool,
+}
+
+fn field_attributes(field: &syn::Field) -> syn::Result<FieldAttributes> {
+    let mut attributes = FieldAttributes {
+        rename: None,
+        flatten: false,
+    };
+    for attr in field.attrs.iter().filter(|a| a.path().is_ident("chroma")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                attributes.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else if meta.path.is_ident("flatten") {
+                attributes.flatten = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `rename = \"...\"` or `flatten`"))
+            }
+        })?;
+    }
+    Ok(attributes)
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let vis = &input.vis;
+    let fields_name = format_ident!("{}Fields", name);
+    let named = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(Error::new_spanned(name, "expected named fields")),
+        },
+        _ => return Err(Error::new(Span::call_site(), "expected a struct")),
+    };
+
+    let krate = quote!(::chromadb_rs);
+    let mut accessors = Vec::new();
+    let mut inserts = Vec::new();
+    let mut reads = Vec::new();
+
+    for field in named {
+        let ident = field.ident.as_ref().unwrap();
+        let ty = &field.ty;
+        let attributes = field_attributes(field)?;
+
+        if attributes.flatten {
+            accessors.push(quote! {
+                pub fn #ident(&self) -> <#ty as #krate::metadata::ChromaMetadata>::Fields {
+                    <#ty as #krate::metadata::ChromaMetadata>::fields()
+                }
+            });
+            inserts.push(quote! {
+                metadata.extend(#krate::metadata::ChromaMetadata::to_metadata(&self.#ident));
+            });
+            reads.push(quote! {
+                #ident: <#ty as #krate::metadata::ChromaMetadata>::from_metadata(metadata)?
+            });
+            continue;
+        }
+
+        let key = attributes.rename.unwrap_or_else(|| ident.to_string());
+        accessors.push(quote! {
+            pub fn #ident(
+                &self,
+            ) -> #krate::metadata::Field<<#ty as #krate::metadata::MetadataField>::Filter> {
+                #krate::metadata::Field::new(#key)
+            }
+        });
+        inserts.push(quote! {
+            if let Some(value) = #krate::metadata::MetadataField::to_value(&self.#ident) {
+                metadata.insert(#key.to_string(), value);
+            }
+        });
+        reads.push(quote! {
+            #ident: <#ty as #krate::metadata::MetadataField>::from_value(metadata.get(#key))
+                .ok_or_else(|| {
+                    #krate::error::ChromaClientError::MetadataError(format!(
+                        "`{}` is missing or not a {}",
+                        #key,
+                        stringify!(#ty)
+                    ))
+                })?
+        });
+    }
+
+    Ok(quote! {
+        /// Typed keys of the metadata, to build filters.
+        #vis struct #fields_name;
+
+        impl #fields_name {
+            #(#accessors)*
+        }
+
+        impl #krate::metadata::ChromaMetadata for #name {
+            type Fields = #fields_name;
+
+            fn fields() -> Self::Fields {
+                #fields_name
+            }
+
+            fn to_metadata(&self) -> #krate::collection::Metadata {
+                let mut metadata = #krate::collection::Metadata::new();
+                #(#inserts)*
+                metadata
+            }
+
+            fn from_metadata(
+                metadata: &#krate::collection::Metadata,
+            ) -> ::std::result::Result<Self, #krate::error::ChromaClientError> {
+                ::std::result::Result::Ok(#name {
+                    #(#reads),*
+                })
+            }
+        }
+    })
+}
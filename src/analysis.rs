@@ -0,0 +1,344 @@
+//! Local analysis of the embeddings of a collection: similarity statistics
+//! and clustering, with the labels written back as metadata.
+
+use crate::client::ChromaClient;
+use crate::collection::{Collection, Entries, GetParams, Include, Metadata, PAGE_SIZE};
+use crate::error::ChromaClientError;
+use crate::simd::{common_dimension, cosine_similarities, squared_l2, squared_l2_distances};
+use crate::stats::Distribution;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Label of the records left out of every cluster by `density_clusters`.
+pub const NOISE: i64 = -1;
+
+/// Ids and embeddings of records.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Embeddings {
+    pub ids: Vec<String>,
+    pub vectors: Vec<Vec<f32>>,
+}
+
+/// Groups of near-duplicate records found by `Collection::find_duplicates`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Duplicates {
+    /// Ids of each group, in collection order.
+    pub groups: Vec<Vec<String>>,
+}
+
+impl Duplicates {
+    /// Every id but the first of each group: the records to delete to keep
+    /// one record per group.
+    pub fn redundant_ids(&self) -> Vec<String> {
+        self.groups
+            .iter()
+            .flat_map(|group| group.iter().skip(1).cloned())
+            .collect()
+    }
+}
+
+/// Result of `kmeans`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Clustering {
+    /// Cluster of each vector.
+    pub labels: Vec<usize>,
+    pub centroids: Vec<Vec<f32>>,
+    pub iterations: usize,
+}
+
+impl Collection {
+    /// Fetch the embeddings of up to `limit` records, every record when `None`.
+    pub async fn embeddings(
+        &self,
+        client: &ChromaClient,
+        limit: Option<usize>,
+    ) -> Result<Embeddings, ChromaClientError> {
+        let params = GetParams {
+            limit,
+            include: Some(vec![Include::Embeddings]),
+            ..Default::default()
+        };
+        let mut pages = self.pages(client, params, PAGE_SIZE);
+        let mut embeddings = Embeddings::default();
+        while let Some(page) = pages.next().await? {
+            embeddings.ids.extend(page.ids);
+            embeddings
+                .vectors
+                .extend(page.embeddings.unwrap_or_default());
+        }
+        Ok(embeddings)
+    }
+
+    /// Group the records whose embeddings have a cosine similarity of
+    /// `threshold` or more, directly or through other records of the group.
+    /// Embeddings are fetched and compared locally, every pair of records
+    /// once, so this is meant for collections of up to a few ten thousand
+    /// records.
+    pub async fn find_duplicates(
+        &self,
+        client: &ChromaClient,
+        threshold: f32,
+    ) -> Result<Duplicates, ChromaClientError> {
+        let embeddings = self.embeddings(client, None).await?;
+        duplicate_groups(&embeddings, threshold)
+    }
+
+    /// Store the label of each record under the `key` metadata, e.g. the
+    /// result of `kmeans` or `density_clusters`.
+    pub async fn write_labels(
+        &self,
+        client: &ChromaClient,
+        ids: &[String],
+        labels: &[i64],
+        key: &str,
+    ) -> Result<(), ChromaClientError> {
+        if ids.len() != labels.len() {
+            return Err(ChromaClientError::MetadataError(format!(
+                "expected {} labels, one per id",
+                ids.len()
+            )));
+        }
+        let metadatas = labels
+            .iter()
+            .map(|label| Metadata::from_iter([(key.to_string(), Value::from(*label))]))
+            .collect();
+
+        let entries = Entries {
+            ids: ids.to_vec(),
+            metadatas: Some(metadatas),
+            ..Default::default()
+        };
+        let size = client.max_batch_size().await?.unwrap_or(usize::MAX);
+        for batch in entries.into_batches(size) {
+            self.update(client, batch).await?;
+        }
+        Ok(())
+    }
+}
+
+fn duplicate_groups(
+    embeddings: &Embeddings,
+    threshold: f32,
+) -> Result<Duplicates, ChromaClientError> {
+    let labels = density_clusters(&embeddings.vectors, threshold, 1)?;
+    let mut groups: Vec<Vec<String>> = Vec::new();
+    for (id, label) in embeddings.ids.iter().zip(labels) {
+        if label == NOISE {
+            continue;
+        }
+        let label = label as usize;
+        if groups.len() <= label {
+            groups.resize_with(label + 1, Vec::new);
+        }
+        groups[label].push(id.clone());
+    }
+    Ok(Duplicates { groups })
+}
+
+/// Cosine similarity of every pair of vectors. The functions comparing
+/// vectors fail with `EmbeddingError` when their lengths differ.
+pub fn similarity_matrix(vectors: &[Vec<f32>]) -> Result<Vec<Vec<f32>>, ChromaClientError> {
+    common_dimension(vectors)?;
+    Ok(vectors
+        .iter()
+        .map(|v| cosine_similarities(v, vectors))
+        .collect())
+}
+
+/// Distribution of the cosine similarity between distinct vectors.
+pub fn similarity_stats(vectors: &[Vec<f32>]) -> Result<Distribution, ChromaClientError> {
+    let values = similarity_matrix(vectors)?
+        .into_iter()
+        .enumerate()
+        .flat_map(|(i, row)| row.into_iter().skip(i + 1))
+        .map(f64::from)
+        .collect();
+    Ok(Distribution::from_values(values))
+}
+
+/// Group the vectors into `k` clusters with k-means, starting from centroids
+/// picked far apart, so the result is the same on every run.
+pub fn kmeans(
+    vectors: &[Vec<f32>],
+    k: usize,
+    max_iterations: usize,
+) -> Result<Clustering, ChromaClientError> {
+    common_dimension(vectors)?;
+    let k = k.min(vectors.len());
+    if k == 0 {
+        return Ok(Clustering::default());
+    }
+
+    // Farthest point initialization, from the first vector.
+    let mut centroids = vec![vectors[0].clone()];
+    let mut nearest = squared_l2_distances(&vectors[0], vectors);
+    while centroids.len() < k {
+        let farthest = nearest
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map_or(0, |(i, _)| i);
+        centroids.push(vectors[farthest].clone());
+        for (distance, vector) in nearest.iter_mut().zip(vectors) {
+            *distance = distance.min(squared_l2(&vectors[farthest], vector));
+        }
+    }
+
+    let mut labels = vec![0; vectors.len()];
+    let mut iterations = 0;
+    while iterations < max_iterations {
+        iterations += 1;
+        let assigned: Vec<usize> = vectors.iter().map(|v| closest(v, &centroids)).collect();
+        let changed = assigned != labels;
+        labels = assigned;
+
+        for (c, centroid) in centroids.iter_mut().enumerate() {
+            let members: Vec<&Vec<f32>> = vectors
+                .iter()
+                .zip(&labels)
+                .filter(|(_, label)| **label == c)
+                .map(|(v, _)| v)
+                .collect();
+            if let Some(mean) = mean(&members) {
+                *centroid = mean;
+            }
+        }
+        if !changed && iterations > 1 {
+            break;
+        }
+    }
+
+    Ok(Clustering {
+        labels,
+        centroids,
+        iterations,
+    })
+}
+
+/// Density based clustering: vectors with at least `min_neighbors` others
+/// of cosine similarity `threshold` or more are cluster cores, and clusters
+/// grow through the neighbors of their cores. Vectors reached by no cluster
+/// get the `NOISE` label. Compares every pair of vectors.
+pub fn density_clusters(
+    vectors: &[Vec<f32>],
+    threshold: f32,
+    min_neighbors: usize,
+) -> Result<Vec<i64>, ChromaClientError> {
+    common_dimension(vectors)?;
+    let neighbors = neighbors(vectors, threshold);
+    let mut labels = vec![NOISE; vectors.len()];
+    let mut cluster = 0;
+    for start in 0..vectors.len() {
+        if labels[start] != NOISE || neighbors[start].len() < min_neighbors {
+            continue;
+        }
+        labels[start] = cluster;
+        let mut queue = neighbors[start].clone();
+        while let Some(i) = queue.pop() {
+            if labels[i] != NOISE {
+                continue;
+            }
+            labels[i] = cluster;
+            if neighbors[i].len() >= min_neighbors {
+                queue.extend(&neighbors[i]);
+            }
+        }
+        cluster += 1;
+    }
+    Ok(labels)
+}
+
+/// Indices of the other vectors of cosine similarity `threshold` or more,
+/// for each vector, without keeping the whole similarity matrix.
+fn neighbors(vectors: &[Vec<f32>], threshold: f32) -> Vec<Vec<usize>> {
+    vectors
+        .iter()
+        .enumerate()
+        .map(|(i, vector)| {
+            cosine_similarities(vector, vectors)
+                .into_iter()
+                .enumerate()
+                .filter(|(j, similarity)| *j != i && *similarity >= threshold)
+                .map(|(j, _)| j)
+                .collect()
+        })
+        .collect()
+}
+
+fn closest(vector: &[f32], centroids: &[Vec<f32>]) -> usize {
+    squared_l2_distances(vector, centroids)
+        .iter()
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(b.1))
+        .map_or(0, |(i, _)| i)
+}
+
+fn mean(vectors: &[&Vec<f32>]) -> Option<Vec<f32>> {
+    let first = vectors.first()?;
+    let mut sum = vec![0.0; first.len()];
+    for vector in vectors {
+        for (total, x) in sum.iter_mut().zip(vector.iter()) {
+            *total += x;
+        }
+    }
+    Some(sum.into_iter().map(|x| x / vectors.len() as f32).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clusters_of_two_groups() {
+        let vectors = vec![
+            vec![1.0, 0.0],
+            vec![0.9, 0.1],
+            vec![0.0, 1.0],
+            vec![0.1, 0.9],
+            vec![-1.0, -1.0],
+        ];
+
+        let clustering = kmeans(&vectors[..4], 2, 10).unwrap();
+        assert_eq!(clustering.labels[0], clustering.labels[1]);
+        assert_eq!(clustering.labels[2], clustering.labels[3]);
+        assert_ne!(clustering.labels[0], clustering.labels[2]);
+
+        assert_eq!(
+            density_clusters(&vectors, 0.9, 1).unwrap(),
+            [0, 0, 1, 1, NOISE]
+        );
+        let stats = similarity_stats(&vectors[..2]).unwrap();
+        assert_eq!(stats.min, stats.max);
+        assert!(stats.mean > 0.99);
+    }
+
+    #[test]
+    fn mismatched_dimensions() {
+        let vectors = vec![vec![1.0, 0.0], vec![0.0, 1.0, 0.0]];
+
+        assert!(matches!(
+            kmeans(&vectors, 2, 10),
+            Err(ChromaClientError::EmbeddingError(_))
+        ));
+        assert!(density_clusters(&vectors, 0.9, 1).is_err());
+        assert!(similarity_stats(&vectors).is_err());
+    }
+
+    #[test]
+    fn duplicate_groups_of_similar_records() {
+        let embeddings = Embeddings {
+            ids: vec!["a".into(), "b".into(), "c".into(), "d".into()],
+            vectors: vec![
+                vec![1.0, 0.0],
+                vec![0.0, 1.0],
+                vec![0.999, 0.01],
+                vec![-1.0, 0.0],
+            ],
+        };
+
+        let duplicates = duplicate_groups(&embeddings, 0.99).unwrap();
+
+        assert_eq!(duplicates.groups, [vec!["a", "c"]]);
+        assert_eq!(duplicates.redundant_ids(), ["c"]);
+    }
+}
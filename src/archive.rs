@@ -0,0 +1,212 @@
+//! Single file snapshots of every collection of a server.
+//!
+//! An archive is a tar file compressed with zstd (`.tar.zst`) holding:
+//!
+//! - `manifest.json`, always the first entry: a `BackupManifest` listing
+//!   each collection with its metadata, its file and its record count;
+//! - `collections/<name>.jsonl` for each collection, the records as written
+//!   by `Collection::export`, one JSON object per line.
+//!
+//! The layout can be read with the `tar` and `zstd` command line tools, e.g.
+//! `tar --zstd -xf snapshot.tar.zst`.
+
+use crate::backup::{collection_metadata, BackupEntry, BackupManifest, MANIFEST_FILE};
+use crate::client::ChromaClient;
+use crate::collection::PAGE_SIZE;
+use crate::error::ChromaClientError;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Directory of the collection files in the archive.
+const COLLECTIONS_DIR: &str = "collections";
+
+/// zstd compression level, the default of the `zstd` command.
+const COMPRESSION_LEVEL: i32 = 3;
+
+impl ChromaClient {
+    /// Write every collection to `writer` as a `.tar.zst` archive. Each
+    /// collection is exported to a temporary file first, so the manifest
+    /// holds the number of records actually written to the archive.
+    pub async fn backup_all<W: Write>(
+        &self,
+        writer: W,
+    ) -> Result<BackupManifest, ChromaClientError> {
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let collections = self.list_collections().await?;
+
+        let mut entries = Vec::new();
+        let mut files = Vec::new();
+        for collection in &collections {
+            let mut file = tempfile::tempfile().map_err(ChromaClientError::IoError)?;
+            let records = collection
+                .export(self, BufWriter::new(&mut file), PAGE_SIZE)
+                .await?;
+            entries.push(BackupEntry {
+                name: collection.name.clone(),
+                metadata: collection.metadata.clone(),
+                file: format!("{}/{}.jsonl", COLLECTIONS_DIR, collection.name),
+                records,
+            });
+            files.push(file);
+        }
+        let manifest = BackupManifest {
+            created_at,
+            collections: entries,
+        };
+
+        let encoder =
+            zstd::Encoder::new(writer, COMPRESSION_LEVEL).map_err(ChromaClientError::IoError)?;
+        let mut archive = tar::Builder::new(encoder);
+        let content =
+            serde_json::to_vec_pretty(&manifest).map_err(ChromaClientError::ResponseParseError)?;
+        append(&mut archive, MANIFEST_FILE, created_at, &content)
+            .map_err(ChromaClientError::IoError)?;
+
+        for (mut file, entry) in files.into_iter().zip(&manifest.collections) {
+            append_file(&mut archive, &entry.file, created_at, &mut file)
+                .map_err(ChromaClientError::IoError)?;
+        }
+
+        archive
+            .into_inner()
+            .and_then(|encoder| encoder.finish())
+            .and_then(|mut writer| writer.flush())
+            .map_err(ChromaClientError::IoError)?;
+        Ok(manifest)
+    }
+
+    /// Restore an archive written by `backup_all`, creating the missing
+    /// collections and upserting the saved records. Returns the number of
+    /// records restored.
+    pub async fn restore_all<R: Read>(&self, reader: R) -> Result<usize, ChromaClientError> {
+        let decoder = zstd::Decoder::new(reader).map_err(ChromaClientError::IoError)?;
+        let mut archive = tar::Archive::new(decoder);
+        let mut entries = archive.entries().map_err(ChromaClientError::IoError)?;
+
+        let mut manifest = entries
+            .next()
+            .ok_or_else(|| invalid_archive("empty archive"))?
+            .map_err(ChromaClientError::IoError)?;
+        if manifest
+            .path()
+            .map_err(ChromaClientError::IoError)?
+            .as_os_str()
+            != MANIFEST_FILE
+        {
+            return Err(invalid_archive("the first entry is not the manifest"));
+        }
+        let mut content = Vec::new();
+        manifest
+            .read_to_end(&mut content)
+            .map_err(ChromaClientError::IoError)?;
+        let manifest: BackupManifest =
+            serde_json::from_slice(&content).map_err(ChromaClientError::ResponseParseError)?;
+
+        let mut restored = 0;
+        for file in entries {
+            let file = file.map_err(ChromaClientError::IoError)?;
+            let path = file.path().map_err(ChromaClientError::IoError)?;
+            let Some(entry) = manifest
+                .collections
+                .iter()
+                .find(|entry| path.as_os_str() == entry.file.as_str())
+            else {
+                continue;
+            };
+
+            let collection = self
+                .get_or_create_collection_with_metadata(
+                    &entry.name,
+                    collection_metadata(&entry.metadata),
+                )
+                .await?;
+            restored += collection
+                .import(self, BufReader::new(file), PAGE_SIZE)
+                .await?;
+        }
+        Ok(restored)
+    }
+}
+
+fn append<W: Write>(
+    archive: &mut tar::Builder<W>,
+    path: &str,
+    mtime: u64,
+    data: &[u8],
+) -> io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(mtime);
+    header.set_cksum();
+    archive.append_data(&mut header, path, data)
+}
+
+/// Append a spooled file from its start, its size read from its metadata.
+fn append_file<W: Write>(
+    archive: &mut tar::Builder<W>,
+    path: &str,
+    mtime: u64,
+    file: &mut File,
+) -> io::Result<()> {
+    file.seek(SeekFrom::Start(0))?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(file.metadata()?.len());
+    header.set_mode(0o644);
+    header.set_mtime(mtime);
+    header.set_cksum();
+    archive.append_data(&mut header, path, file)
+}
+
+fn invalid_archive(reason: &str) -> ChromaClientError {
+    ChromaClientError::IoError(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid archive: {}", reason),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn archive_layout() {
+        let mut encoder = zstd::Encoder::new(Vec::new(), COMPRESSION_LEVEL).unwrap();
+        {
+            let mut archive = tar::Builder::new(&mut encoder);
+            append(&mut archive, MANIFEST_FILE, 0, b"{}").unwrap();
+            let mut records = tempfile::tempfile().unwrap();
+            records.write_all(b"{\"id\":\"a\"}\n").unwrap();
+            append_file(&mut archive, "collections/docs.jsonl", 0, &mut records).unwrap();
+            archive.finish().unwrap();
+        }
+        let compressed = encoder.finish().unwrap();
+
+        let mut archive = tar::Archive::new(zstd::Decoder::new(&compressed[..]).unwrap());
+        let entries: Vec<(String, String)> = archive
+            .entries()
+            .unwrap()
+            .map(|e| {
+                let mut e = e.unwrap();
+                let path = e.path().unwrap().display().to_string();
+                let mut content = String::new();
+                e.read_to_string(&mut content).unwrap();
+                (path, content)
+            })
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                (MANIFEST_FILE.to_string(), "{}".to_string()),
+                (
+                    "collections/docs.jsonl".to_string(),
+                    "{\"id\":\"a\"}\n".to_string()
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,260 @@
+//! Conversion of results to Arrow record batches, to analyze them with
+//! Polars, DataFusion or any Arrow based tool.
+
+use crate::collection::{GetResult, QueryResult};
+use crate::export::ExportRecord;
+use crate::hits::QueryHit;
+use arrow_array::builder::{FixedSizeListBuilder, Float32Builder};
+use arrow_array::{
+    ArrayRef, BooleanArray, Float32Array, Float64Array, Int64Array, RecordBatch, StringArray,
+    StructArray, UInt32Array,
+};
+use arrow_schema::{ArrowError, DataType, Field, Schema};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+/// The fields included in a result.
+struct Included {
+    distances: bool,
+    documents: bool,
+    metadatas: bool,
+    embeddings: bool,
+}
+
+impl GetResult {
+    /// One row per record: an `id` column, then `document`, `metadata` and
+    /// `embedding` columns for the fields included in the request.
+    ///
+    /// `metadata` is a struct with one field per metadata key. Keys holding
+    /// values of different types become strings, and `embedding` is a fixed
+    /// size list of floats.
+    pub fn to_arrow(&self) -> Result<RecordBatch, ArrowError> {
+        let included = Included {
+            distances: false,
+            documents: self.documents.is_some(),
+            metadatas: self.metadatas.is_some(),
+            embeddings: self.embeddings.is_some(),
+        };
+        let hits: Vec<QueryHit> = ExportRecord::from_result(self.clone())
+            .into_iter()
+            .map(|record| QueryHit {
+                id: record.id,
+                distance: None,
+                document: record.document,
+                metadata: record.metadata,
+                embedding: record.embedding,
+            })
+            .collect();
+
+        record_batch(None, &hits, &included)
+    }
+}
+
+impl QueryResult {
+    /// One row per hit: `query` (the index of the query embedding), `id`,
+    /// `distance` and `score` columns, then the other fields as in
+    /// `GetResult::to_arrow`.
+    pub fn to_arrow(&self) -> Result<RecordBatch, ArrowError> {
+        let included = Included {
+            distances: self.distances.is_some(),
+            documents: self.documents.is_some(),
+            metadatas: self.metadatas.is_some(),
+            embeddings: self.embeddings.is_some(),
+        };
+        let mut queries = Vec::new();
+        let mut hits = Vec::new();
+        for (query, query_hits) in self.clone().into_hits().into_iter().enumerate() {
+            queries.extend(std::iter::repeat_n(query as u32, query_hits.len()));
+            hits.extend(query_hits);
+        }
+
+        record_batch(Some(queries), &hits, &included)
+    }
+}
+
+fn record_batch(
+    queries: Option<Vec<u32>>,
+    hits: &[QueryHit],
+    included: &Included,
+) -> Result<RecordBatch, ArrowError> {
+    let mut fields = Vec::new();
+    let mut columns: Vec<ArrayRef> = Vec::new();
+    let mut push = |name: &str, nullable: bool, column: ArrayRef| {
+        fields.push(Field::new(name, column.data_type().clone(), nullable));
+        columns.push(column);
+    };
+
+    if let Some(queries) = queries {
+        push("query", false, Arc::new(UInt32Array::from(queries)));
+    }
+    push(
+        "id",
+        false,
+        Arc::new(StringArray::from_iter_values(hits.iter().map(|h| &h.id))),
+    );
+    if included.distances {
+        let distances: Vec<Option<f32>> = hits.iter().map(|h| h.distance).collect();
+        let scores: Vec<Option<f32>> = hits.iter().map(QueryHit::score).collect();
+        push("distance", true, Arc::new(Float32Array::from(distances)));
+        push("score", true, Arc::new(Float32Array::from(scores)));
+    }
+    if included.documents {
+        let documents: Vec<Option<&str>> = hits.iter().map(|h| h.document.as_deref()).collect();
+        push("document", true, Arc::new(StringArray::from(documents)));
+    }
+    if included.metadatas {
+        if let Some(metadata) = metadata_column(hits) {
+            push("metadata", true, Arc::new(metadata));
+        }
+    }
+    if included.embeddings {
+        push("embedding", true, embedding_column(hits)?);
+    }
+
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+}
+
+/// A struct of the metadata keys, `None` when no hit has metadata.
+fn metadata_column(hits: &[QueryHit]) -> Option<StructArray> {
+    let keys: BTreeSet<&str> = hits
+        .iter()
+        .filter_map(|h| h.metadata.as_ref())
+        .flat_map(|m| m.keys().map(String::as_str))
+        .collect();
+    if keys.is_empty() {
+        return None;
+    }
+
+    let children = keys
+        .into_iter()
+        .map(|key| {
+            let values: Vec<Option<&Value>> = hits
+                .iter()
+                .map(|h| h.metadata.as_ref()?.get(key).filter(|v| !v.is_null()))
+                .collect();
+            let column = metadata_values(&values);
+            (
+                Arc::new(Field::new(key, column.data_type().clone(), true)),
+                column,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Some(StructArray::from(children))
+}
+
+/// The values of a metadata key, typed after the values present.
+fn metadata_values(values: &[Option<&Value>]) -> ArrayRef {
+    let present: Vec<&Value> = values.iter().flatten().copied().collect();
+    let all = |check: fn(&Value) -> bool| !present.is_empty() && present.iter().all(|v| check(v));
+
+    if all(Value::is_boolean) {
+        Arc::new(BooleanArray::from(
+            values
+                .iter()
+                .map(|v| v.and_then(Value::as_bool))
+                .collect::<Vec<_>>(),
+        ))
+    } else if all(Value::is_i64) {
+        Arc::new(Int64Array::from(
+            values
+                .iter()
+                .map(|v| v.and_then(Value::as_i64))
+                .collect::<Vec<_>>(),
+        ))
+    } else if all(Value::is_number) {
+        Arc::new(Float64Array::from(
+            values
+                .iter()
+                .map(|v| v.and_then(Value::as_f64))
+                .collect::<Vec<_>>(),
+        ))
+    } else {
+        Arc::new(StringArray::from(
+            values
+                .iter()
+                .map(|v| {
+                    v.map(|v| match v {
+                        Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    })
+                })
+                .collect::<Vec<_>>(),
+        ))
+    }
+}
+
+fn embedding_column(hits: &[QueryHit]) -> Result<ArrayRef, ArrowError> {
+    let dimension = hits
+        .iter()
+        .find_map(|h| h.embedding.as_ref())
+        .map_or(0, Vec::len);
+    let mut builder = FixedSizeListBuilder::new(Float32Builder::new(), dimension as i32)
+        .with_field(Field::new("item", DataType::Float32, true));
+
+    for hit in hits {
+        match &hit.embedding {
+            Some(embedding) if embedding.len() == dimension => {
+                builder.values().append_slice(embedding);
+                builder.append(true);
+            }
+            Some(embedding) => {
+                return Err(ArrowError::InvalidArgumentError(format!(
+                    "embedding of {} has {} dimensions, expected {}",
+                    hit.id,
+                    embedding.len(),
+                    dimension
+                )))
+            }
+            None => {
+                builder.values().append_nulls(dimension);
+                builder.append(false);
+            }
+        }
+    }
+    Ok(Arc::new(builder.finish()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::Int64Type;
+    use serde_json::json;
+
+    #[test]
+    fn query_result_to_arrow() {
+        let result: QueryResult = serde_json::from_value(json!({
+            "ids": [["a", "b"], ["c"]],
+            "distances": [[0.25, 0.5], [0.75]],
+            "embeddings": [[[1.0, 0.0], [0.0, 1.0]], [[0.5, 0.5]]],
+            "documents": null,
+            "metadatas": [[{"page": 1, "lang": "en"}, {"page": 2}], [null]],
+        }))
+        .unwrap();
+
+        let batch = result.to_arrow().unwrap();
+
+        let columns: Vec<&str> = batch
+            .schema_ref()
+            .fields()
+            .iter()
+            .map(|f| f.name().as_str())
+            .collect();
+        assert_eq!(
+            columns,
+            ["query", "id", "distance", "score", "metadata", "embedding"]
+        );
+        assert_eq!(batch.num_rows(), 3);
+
+        let metadata = batch.column_by_name("metadata").unwrap().as_struct();
+        let pages = metadata.column_by_name("page").unwrap();
+        assert_eq!(pages.as_primitive::<Int64Type>().value(1), 2);
+        assert!(pages.is_null(2));
+
+        let embeddings = batch.column_by_name("embedding").unwrap();
+        let item = Arc::new(Field::new("item", DataType::Float32, true));
+        assert_eq!(embeddings.data_type(), &DataType::FixedSizeList(item, 2));
+    }
+}
@@ -0,0 +1,143 @@
+use crate::error::ChromaClientError;
+use crate::policy::Operation;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A mutating call made by a client with an audit sink.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub operation: Operation,
+    pub collection: Option<String>,
+    /// Number of record ids in the request, 0 for filters and collection operations.
+    pub ids: usize,
+    /// Set with `ChromaClient::with_audit_context`, e.g. the user or job making the call.
+    pub context: Option<String>,
+    /// Milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    pub succeeded: bool,
+}
+
+impl AuditEvent {
+    pub(crate) fn new(
+        operation: Operation,
+        collection: Option<&str>,
+        ids: usize,
+        context: Option<&str>,
+        succeeded: bool,
+    ) -> Self {
+        AuditEvent {
+            operation,
+            collection: collection.map(str::to_string),
+            ids,
+            context: context.map(str::to_string),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            succeeded,
+        }
+    }
+}
+
+/// Receives the audit events of a client, see `ChromaClient::with_audit`.
+/// Closures taking an `&AuditEvent` are sinks too.
+pub trait AuditSink: Send + Sync {
+    fn record(&self, event: &AuditEvent);
+}
+
+impl<F: Fn(&AuditEvent) + Send + Sync> AuditSink for F {
+    fn record(&self, event: &AuditEvent) {
+        self(event)
+    }
+}
+
+/// A shared sink, kept by clients and their clones.
+#[derive(Clone)]
+pub(crate) struct AuditHook(pub(crate) Arc<dyn AuditSink>);
+
+impl fmt::Debug for AuditHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("AuditHook")
+    }
+}
+
+/// Appends events to a file, one JSON object per line.
+#[derive(Debug)]
+pub struct FileAuditSink {
+    file: Mutex<File>,
+}
+
+impl FileAuditSink {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ChromaClientError> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(ChromaClientError::IoError)?;
+        Ok(FileAuditSink {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl AuditSink for FileAuditSink {
+    fn record(&self, event: &AuditEvent) {
+        let Ok(mut line) = serde_json::to_vec(event) else {
+            return;
+        };
+        line.push(b'\n');
+        // An audit failure should not fail the call that was audited.
+        let _ = self.file.lock().unwrap().write_all(&line);
+    }
+}
+
+/// Emits events as `tracing` events with the `chromadb_rs::audit` target.
+#[cfg(feature = "tracing")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TracingAuditSink;
+
+#[cfg(feature = "tracing")]
+impl AuditSink for TracingAuditSink {
+    fn record(&self, event: &AuditEvent) {
+        tracing::info!(
+            target: "chromadb_rs::audit",
+            operation = event.operation.as_str(),
+            collection = event.collection.as_deref(),
+            ids = event.ids,
+            context = event.context.as_deref(),
+            timestamp = event.timestamp,
+            succeeded = event.succeeded,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_sink_appends_json_lines() {
+        let path =
+            std::env::temp_dir().join(format!("chroma-rs-audit-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let sink = FileAuditSink::open(&path).unwrap();
+
+        let event = AuditEvent::new(Operation::Write, Some("docs"), 3, Some("job-7"), true);
+        sink.record(&event);
+        sink.record(&event);
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<AuditEvent> = content
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines, vec![event.clone(), event]);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
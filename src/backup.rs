@@ -0,0 +1,282 @@
+//! Timestamped backups of collections, kept in a directory with rotation.
+
+use crate::client::ChromaClient;
+use crate::collection::PAGE_SIZE;
+use crate::error::ChromaClientError;
+use crate::jobs::JobHandle;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// File describing a backup, in the directory of the backup.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Prefix of the backup directories, followed by the creation time in
+/// seconds since the Unix epoch and its milliseconds, e.g.
+/// `backup-1700000000.250`.
+const BACKUP_PREFIX: &str = "backup-";
+
+/// Content of `manifest.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupManifest {
+    /// Seconds since the Unix epoch.
+    pub created_at: u64,
+    pub collections: Vec<BackupEntry>,
+}
+
+/// A collection saved in a backup.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupEntry {
+    pub name: String,
+    pub metadata: Option<Value>,
+    /// JSON Lines file written by `Collection::export`, relative to the
+    /// manifest.
+    pub file: String,
+    pub records: usize,
+}
+
+/// Backs up collections into `<dir>/backup-<timestamp>/`, one JSON Lines
+/// file per collection and a `manifest.json`, removing old backups.
+///
+/// ```no_run
+/// # async fn run(client: &chromadb_rs::client::ChromaClient) -> Result<(), chromadb_rs::error::ChromaClientError> {
+/// use chromadb_rs::backup::BackupPlan;
+///
+/// let manifest = BackupPlan::new(client).to_dir("backups").keep(7).run().await?;
+/// client.restore_backup(manifest).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct BackupPlan {
+    client: ChromaClient,
+    collections: Option<Vec<String>>,
+    dir: PathBuf,
+    keep: Option<usize>,
+}
+
+impl BackupPlan {
+    /// Back up every collection into the current directory, keeping every
+    /// previous backup.
+    pub fn new(client: &ChromaClient) -> Self {
+        BackupPlan {
+            client: client.clone(),
+            collections: None,
+            dir: PathBuf::from("."),
+            keep: None,
+        }
+    }
+
+    /// Only back up the collections with these names.
+    pub fn collections<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.collections = Some(names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn to_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.dir = dir.as_ref().to_path_buf();
+        self
+    }
+
+    /// Keep the `count` most recent backups of the directory, removing the
+    /// older ones after each successful backup.
+    pub fn keep(mut self, count: usize) -> Self {
+        self.keep = Some(count.max(1));
+        self
+    }
+
+    /// Back up the collections now. Returns the path of the manifest.
+    pub async fn run(&self) -> Result<PathBuf, ChromaClientError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let created_at = now.as_secs();
+        let dir = self.dir.join(format!(
+            "{}{}.{:03}",
+            BACKUP_PREFIX,
+            created_at,
+            now.subsec_millis()
+        ));
+        std::fs::create_dir_all(&self.dir).map_err(ChromaClientError::IoError)?;
+        // Fails rather than mixing two backups started in the same millisecond.
+        std::fs::create_dir(&dir).map_err(ChromaClientError::IoError)?;
+
+        let mut collections = self.client.list_collections().await?;
+        if let Some(names) = &self.collections {
+            collections.retain(|c| names.contains(&c.name));
+        }
+
+        let mut entries = Vec::new();
+        for collection in collections {
+            let file = format!("{}.jsonl", collection.name);
+            let writer = File::create(dir.join(&file)).map_err(ChromaClientError::IoError)?;
+            let records = collection
+                .export(&self.client, BufWriter::new(writer), PAGE_SIZE)
+                .await?;
+            entries.push(BackupEntry {
+                name: collection.name,
+                metadata: collection.metadata,
+                file,
+                records,
+            });
+        }
+
+        // The manifest is written last: a directory without one is an
+        // interrupted backup, or one still running.
+        let manifest = BackupManifest {
+            created_at,
+            collections: entries,
+        };
+        let path = dir.join(MANIFEST_FILE);
+        let content = serde_json::to_string_pretty(&manifest)
+            .map_err(ChromaClientError::ResponseParseError)?;
+        std::fs::write(&path, content).map_err(ChromaClientError::IoError)?;
+
+        if let Some(keep) = self.keep {
+            self.rotate(keep)?;
+        }
+        Ok(path)
+    }
+
+    /// Call `run` every `interval` in a background task, until the job is
+    /// cancelled or dropped, or the client is shut down. Its progress is the
+    /// number of completed backups. Failed backups are retried at the next
+    /// interval.
+    pub fn every(self, interval: Duration) -> JobHandle {
+        JobHandle::spawn(|completed| async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if self.client.is_shut_down() {
+                    break;
+                }
+                if self.run().await.is_ok() {
+                    completed.add(1);
+                }
+            }
+        })
+    }
+
+    /// Remove the backups older than the `keep` most recent complete ones,
+    /// with the interrupted ones among them. Directories without a manifest
+    /// newer than that may still be running and are left alone.
+    fn rotate(&self, keep: usize) -> Result<(), ChromaClientError> {
+        let mut backups: Vec<((u64, u32), PathBuf)> = Vec::new();
+        for entry in std::fs::read_dir(&self.dir).map_err(ChromaClientError::IoError)? {
+            let path = entry.map_err(ChromaClientError::IoError)?.path();
+            let created_at = path
+                .file_name()
+                .and_then(|name| backup_time(name.to_str()?));
+            if let Some(created_at) = created_at {
+                backups.push((created_at, path));
+            }
+        }
+
+        backups.sort_by_key(|(created_at, _)| std::cmp::Reverse(*created_at));
+        let mut complete = 0;
+        for (_, path) in backups {
+            if complete >= keep {
+                std::fs::remove_dir_all(path).map_err(ChromaClientError::IoError)?;
+            } else if path.join(MANIFEST_FILE).exists() {
+                complete += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Seconds and milliseconds of a backup directory name, the milliseconds
+/// being 0 for the directories named before they were added.
+fn backup_time(name: &str) -> Option<(u64, u32)> {
+    let time = name.strip_prefix(BACKUP_PREFIX)?;
+    match time.split_once('.') {
+        Some((secs, millis)) => Some((secs.parse().ok()?, millis.parse().ok()?)),
+        None => Some((time.parse().ok()?, 0)),
+    }
+}
+
+impl ChromaClient {
+    /// Recreate the collections of a backup from its manifest, creating the
+    /// missing collections and upserting the saved records. Returns the
+    /// number of records restored.
+    pub async fn restore_backup(
+        &self,
+        manifest: impl AsRef<Path>,
+    ) -> Result<usize, ChromaClientError> {
+        let manifest_path = manifest.as_ref();
+        let content = std::fs::read_to_string(manifest_path).map_err(ChromaClientError::IoError)?;
+        let manifest: BackupManifest =
+            serde_json::from_str(&content).map_err(ChromaClientError::ResponseParseError)?;
+        let dir = manifest_path.parent().unwrap_or(Path::new("."));
+
+        let mut restored = 0;
+        for entry in &manifest.collections {
+            let collection = self
+                .get_or_create_collection_with_metadata(
+                    &entry.name,
+                    collection_metadata(&entry.metadata),
+                )
+                .await?;
+            let file = File::open(dir.join(&entry.file)).map_err(ChromaClientError::IoError)?;
+            restored += collection
+                .import(self, BufReader::new(file), PAGE_SIZE)
+                .await?;
+        }
+        Ok(restored)
+    }
+}
+
+/// Collection metadata in the form accepted at creation, keeping the JSON
+/// type of each value.
+pub(crate) fn collection_metadata(metadata: &Option<Value>) -> Option<Map<String, Value>> {
+    metadata.as_ref()?.as_object().cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::ChromaClientParams;
+
+    #[test]
+    fn rotation_keeps_the_latest_backups() {
+        let dir =
+            std::env::temp_dir().join(format!("chroma-rs-backup-rotation-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        for name in ["100", "200", "300.500", "300.020", "1000"] {
+            let backup = dir.join(format!("backup-{}", name));
+            std::fs::create_dir_all(&backup).unwrap();
+            std::fs::write(backup.join(MANIFEST_FILE), "{}").unwrap();
+        }
+        for interrupted in ["50", "400"] {
+            std::fs::create_dir_all(dir.join(format!("backup-{}", interrupted))).unwrap();
+        }
+
+        let client = ChromaClient::new(ChromaClientParams::default());
+        BackupPlan::new(&client).to_dir(&dir).rotate(2).unwrap();
+
+        let mut left: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["backup-1000", "backup-300.500", "backup-400"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn metadata_keeps_its_json_types() {
+        let metadata = Some(serde_json::json!({ "hnsw:space": "cosine", "dimensions": 384 }));
+
+        let restored = collection_metadata(&metadata).unwrap();
+
+        assert_eq!(restored["dimensions"], 384);
+        assert_eq!(restored["hnsw:space"], "cosine");
+    }
+}
@@ -0,0 +1,289 @@
+use crate::client::ChromaClient;
+use crate::collection::{Collection, DeleteParams, Entries, GetParams};
+use crate::error::ChromaClientError;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Writes staged locally and applied in order on `commit`.
+///
+/// If a step fails, the records created by the previous steps are deleted
+/// again. Records that already existed before an add are kept, and updates
+/// and deletes that already went through are not reverted.
+#[derive(Debug, Clone)]
+pub struct WriteBatch {
+    collection: Collection,
+    ops: Vec<WriteOp>,
+}
+
+#[derive(Debug, Clone)]
+enum WriteOp {
+    Add(Entries),
+    Update(Entries),
+    Delete(Vec<String>),
+}
+
+impl WriteBatch {
+    /// Creates an empty batch for the given collection.
+    pub fn new(collection: &Collection) -> Self {
+        WriteBatch {
+            collection: collection.clone(),
+            ops: Vec::new(),
+        }
+    }
+
+    /// Stage new records.
+    pub fn add(&mut self, entries: Entries) -> &mut Self {
+        self.ops.push(WriteOp::Add(entries));
+        self
+    }
+
+    /// Stage an update of existing records.
+    pub fn update(&mut self, entries: Entries) -> &mut Self {
+        self.ops.push(WriteOp::Update(entries));
+        self
+    }
+
+    /// Stage the deletion of records.
+    pub fn delete(&mut self, ids: &[&str]) -> &mut Self {
+        self.ops.push(WriteOp::Delete(
+            ids.iter().map(|id| id.to_string()).collect(),
+        ));
+        self
+    }
+
+    /// Number of staged steps.
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// Whether no step is staged.
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Apply the staged steps in order. On failure, records created by the
+    /// batch so far are deleted and the error of the failing step is returned.
+    pub async fn commit(self, client: &ChromaClient) -> Result<(), ChromaClientError> {
+        let WriteBatch { collection, ops } = self;
+        let mut added: Vec<String> = Vec::new();
+
+        for op in ops {
+            let (result, ids) = match op {
+                WriteOp::Add(entries) => match new_ids(&collection, client, &entries.ids).await {
+                    Ok(ids) => (collection.add(client, entries).await, ids),
+                    Err(e) => (Err(e), vec![]),
+                },
+                WriteOp::Update(entries) => (collection.update(client, entries).await, vec![]),
+                WriteOp::Delete(ids) => {
+                    let params = DeleteParams {
+                        ids: Some(ids),
+                        ..Default::default()
+                    };
+                    (collection.delete(client, params).await, vec![])
+                }
+            };
+
+            if let Err(e) = result {
+                if !added.is_empty() {
+                    let params = DeleteParams {
+                        ids: Some(added),
+                        ..Default::default()
+                    };
+                    // Best effort, the original error is the one worth reporting.
+                    let _ = collection.delete(client, params).await;
+                }
+                return Err(e);
+            }
+            added.extend(ids);
+        }
+
+        Ok(())
+    }
+}
+
+/// The ids not stored yet, the ones an add creates.
+async fn new_ids(
+    collection: &Collection,
+    client: &ChromaClient,
+    ids: &[String],
+) -> Result<Vec<String>, ChromaClientError> {
+    let params = GetParams {
+        ids: Some(ids.to_vec()),
+        include: Some(vec![]),
+        ..Default::default()
+    };
+    let existing = collection.get(client, params).await?.ids;
+    Ok(ids
+        .iter()
+        .filter(|id| !existing.contains(id))
+        .cloned()
+        .collect())
+}
+
+/// Settings of `BatchSizer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdaptiveBatching {
+    /// Size of the first batch.
+    pub initial: usize,
+    pub min: usize,
+    /// Upper bound, lowered to the maximum batch size of the server.
+    pub max: usize,
+    /// Batches answered slower than this are halved.
+    pub target_latency: Duration,
+    /// Records added to the size after each full batch answered in time.
+    pub step: usize,
+}
+
+impl Default for AdaptiveBatching {
+    fn default() -> Self {
+        AdaptiveBatching {
+            initial: 100,
+            min: 1,
+            max: 5000,
+            target_latency: Duration::from_secs(1),
+            step: 50,
+        }
+    }
+}
+
+/// Batch size adjusted from the responses to the previous batches: it
+/// grows by `step` while the server answers within the target latency,
+/// and is halved after slow responses, timeouts and 413 responses. Can be
+/// shared by concurrent writers.
+#[derive(Debug)]
+pub struct BatchSizer {
+    settings: AdaptiveBatching,
+    state: Mutex<SizerState>,
+}
+
+#[derive(Debug)]
+struct SizerState {
+    size: usize,
+    /// Lowered below `max` by 413 responses.
+    ceiling: usize,
+}
+
+impl BatchSizer {
+    pub fn new(settings: AdaptiveBatching) -> Self {
+        let min = settings.min.max(1);
+        let max = settings.max.max(min);
+        BatchSizer {
+            settings: AdaptiveBatching {
+                min,
+                max,
+                ..settings
+            },
+            state: Mutex::new(SizerState {
+                size: settings.initial.clamp(min, max),
+                ceiling: max,
+            }),
+        }
+    }
+
+    /// Size of the next batch.
+    pub fn size(&self) -> usize {
+        self.state.lock().unwrap().size
+    }
+
+    fn succeeded(&self, sent: usize, latency: Duration) {
+        let mut state = self.state.lock().unwrap();
+        if latency > self.settings.target_latency {
+            state.size = (sent / 2).max(self.settings.min);
+        } else if sent >= state.size {
+            state.size = (state.size + self.settings.step).min(state.ceiling);
+        }
+    }
+
+    fn failed(&self, sent: usize, too_large: bool) {
+        let mut state = self.state.lock().unwrap();
+        if too_large {
+            state.ceiling = sent.saturating_sub(1).max(self.settings.min);
+        }
+        state.size = (sent / 2).clamp(self.settings.min, state.ceiling);
+    }
+
+    fn limit(&self, server_max: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.ceiling = state.ceiling.min(server_max).max(self.settings.min);
+        state.size = state.size.min(state.ceiling);
+    }
+}
+
+/// Errors after which the same records are sent again in smaller batches.
+fn batch_overloaded(error: &ChromaClientError) -> Option<bool> {
+    match error {
+        ChromaClientError::BatchTooLarge { .. } => Some(true),
+        ChromaClientError::ResponseStatusError(error) if error.status == 413 => Some(true),
+        ChromaClientError::ResponseStatusError(error) if error.status == 504 => Some(false),
+        ChromaClientError::RequestError(e) if e.is_timeout() => Some(false),
+        _ => None,
+    }
+}
+
+impl Collection {
+    /// Upsert the records in batches sized by `sizer`, sending a batch again
+    /// in smaller ones when it timed out or was too large. Fails when a batch
+    /// of the minimum size does.
+    pub async fn upsert_adaptive(
+        &self,
+        client: &ChromaClient,
+        entries: Entries,
+        sizer: &BatchSizer,
+    ) -> Result<(), ChromaClientError> {
+        if let Some(max) = client.max_batch_size().await? {
+            sizer.limit(max);
+        }
+
+        let mut start = 0;
+        while start < entries.ids.len() {
+            let end = (start + sizer.size()).min(entries.ids.len());
+            let sent = end - start;
+
+            let started = Instant::now();
+            match self.upsert(client, entries.slice(start..end)).await {
+                Ok(()) => {
+                    sizer.succeeded(sent, started.elapsed());
+                    start = end;
+                }
+                Err(e) => match batch_overloaded(&e) {
+                    Some(too_large) if sent > sizer.settings.min => sizer.failed(sent, too_large),
+                    _ => return Err(e),
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn additive_increase_multiplicative_decrease() {
+        let sizer = BatchSizer::new(AdaptiveBatching {
+            initial: 100,
+            max: 220,
+            ..Default::default()
+        });
+        let fast = Duration::from_millis(10);
+
+        sizer.succeeded(100, fast);
+        assert_eq!(sizer.size(), 150);
+        // Partial batches say nothing about larger ones.
+        sizer.succeeded(20, fast);
+        assert_eq!(sizer.size(), 150);
+        sizer.succeeded(150, fast);
+        sizer.succeeded(200, fast);
+        assert_eq!(sizer.size(), 220);
+
+        sizer.succeeded(220, Duration::from_secs(2));
+        assert_eq!(sizer.size(), 110);
+        sizer.failed(110, true);
+        assert_eq!(sizer.size(), 55);
+        for _ in 0..5 {
+            sizer.succeeded(sizer.size(), fast);
+        }
+        assert_eq!(sizer.size(), 109);
+    }
+}
@@ -0,0 +1,203 @@
+use crate::client::ChromaClient;
+use crate::collection::{Entries, QueryParams};
+use crate::error::ChromaClientError;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// The parameters of a benchmark run.
+#[derive(Debug, Clone)]
+pub struct BenchConfig {
+    /// Collection created for the run.
+    pub collection: String,
+    pub dimensions: usize,
+    pub records: usize,
+    pub batch_size: usize,
+    pub queries: usize,
+    pub n_results: usize,
+    /// Seed of the synthetic embeddings, runs with the same seed send the same data.
+    pub seed: u64,
+    /// Delete the collection at the end of the run.
+    pub cleanup: bool,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        BenchConfig {
+            collection: String::from("chroma-rs-bench"),
+            dimensions: 384,
+            records: 10_000,
+            batch_size: 500,
+            queries: 200,
+            n_results: 10,
+            seed: 42,
+            cleanup: true,
+        }
+    }
+}
+
+/// Results of a benchmark run.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BenchReport {
+    pub records: usize,
+    pub dimensions: usize,
+    pub ingest_seconds: f64,
+    pub records_per_second: f64,
+    pub queries: usize,
+    pub query_latency_ms: Percentiles,
+}
+
+/// Latency distribution, in milliseconds.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Percentiles {
+    pub mean: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+impl Percentiles {
+    pub fn from_durations(durations: &[Duration]) -> Self {
+        if durations.is_empty() {
+            return Percentiles::default();
+        }
+
+        let mut ms: Vec<f64> = durations.iter().map(|d| d.as_secs_f64() * 1000.0).collect();
+        ms.sort_by(|a, b| a.total_cmp(b));
+        let at = |p: f64| ms[((ms.len() as f64 * p).ceil() as usize).clamp(1, ms.len()) - 1];
+
+        Percentiles {
+            mean: ms.iter().sum::<f64>() / ms.len() as f64,
+            p50: at(0.5),
+            p90: at(0.9),
+            p99: at(0.99),
+            max: ms[ms.len() - 1],
+        }
+    }
+}
+
+impl BenchReport {
+    /// The report as a CSV header and row.
+    pub fn to_csv(&self) -> String {
+        let l = &self.query_latency_ms;
+        format!(
+            "records,dimensions,ingest_seconds,records_per_second,queries,mean_ms,p50_ms,p90_ms,p99_ms,max_ms\n\
+             {},{},{:.3},{:.1},{},{:.3},{:.3},{:.3},{:.3},{:.3}\n",
+            self.records,
+            self.dimensions,
+            self.ingest_seconds,
+            self.records_per_second,
+            self.queries,
+            l.mean,
+            l.p50,
+            l.p90,
+            l.p99,
+            l.max
+        )
+    }
+}
+
+/// Ingest synthetic embeddings into a new collection, then query it, measuring
+/// the ingestion throughput and the latency of each query. When the run and
+/// the cleanup both fail, the error of the run is returned.
+pub async fn run(
+    client: &ChromaClient,
+    config: &BenchConfig,
+) -> Result<BenchReport, ChromaClientError> {
+    let collection = client.create_collection(&config.collection, None).await?;
+    let mut rng = XorShift(config.seed.max(1));
+
+    let result = async {
+        let started = Instant::now();
+        let mut ingested = 0;
+        while ingested < config.records {
+            let size = config.batch_size.max(1).min(config.records - ingested);
+            let entries = Entries {
+                ids: (ingested..ingested + size).map(|i| i.to_string()).collect(),
+                embeddings: Some(
+                    (0..size)
+                        .map(|_| rng.embedding(config.dimensions))
+                        .collect(),
+                ),
+                ..Default::default()
+            };
+            collection.add(client, entries).await?;
+            ingested += size;
+        }
+        let ingest_seconds = started.elapsed().as_secs_f64();
+
+        let mut latencies = Vec::with_capacity(config.queries);
+        for _ in 0..config.queries {
+            let params = QueryParams {
+                query_embeddings: vec![rng.embedding(config.dimensions)],
+                n_results: config.n_results,
+                ..Default::default()
+            };
+            let started = Instant::now();
+            collection.query(client, params).await?;
+            latencies.push(started.elapsed());
+        }
+
+        Ok(BenchReport {
+            records: config.records,
+            dimensions: config.dimensions,
+            ingest_seconds,
+            records_per_second: config.records as f64 / ingest_seconds.max(f64::EPSILON),
+            queries: config.queries,
+            query_latency_ms: Percentiles::from_durations(&latencies),
+        })
+    }
+    .await;
+
+    if !config.cleanup {
+        return result;
+    }
+    let cleanup = client.delete_collection(&config.collection).await;
+    match (result, cleanup) {
+        (Ok(_), Err(e)) => Err(e),
+        (result, _) => result,
+    }
+}
+
+/// Small deterministic generator, good enough for synthetic vectors.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next_f32(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32 * 2.0 - 1.0
+    }
+
+    fn embedding(&mut self, dimensions: usize) -> Vec<f32> {
+        (0..dimensions).map(|_| self.next_f32()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles() {
+        let durations: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+
+        let p = Percentiles::from_durations(&durations);
+
+        assert_eq!(p.p50, 50.0);
+        assert_eq!(p.p90, 90.0);
+        assert_eq!(p.p99, 99.0);
+        assert_eq!(p.max, 100.0);
+        assert_eq!(p.mean, 50.5);
+    }
+
+    #[test]
+    fn synthetic_embeddings_are_deterministic() {
+        let a = XorShift(7).embedding(16);
+        let b = XorShift(7).embedding(16);
+
+        assert_eq!(a, b);
+        assert!(a.iter().all(|v| (-1.0..1.0).contains(v)));
+    }
+}
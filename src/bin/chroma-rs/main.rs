@@ -0,0 +1,270 @@
+mod output;
+mod shell;
+#[cfg(feature = "tui")]
+mod tui;
+
+use chromadb_rs::bench::{self, BenchConfig};
+use chromadb_rs::client::{ChromaClient, ChromaClientParams, PreflightMode, Settings};
+use chromadb_rs::collection::{Include, QueryParams};
+use chromadb_rs::export::ExportRecord;
+use clap::{Parser, Subcommand};
+use output::{bench_rows, collection_rows, print, query_rows, Output};
+use reqwest::header::HeaderMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter};
+
+const BATCH_SIZE: usize = 100;
+
+/// Command line client for the Chroma vector database.
+#[derive(Parser)]
+#[command(name = "chroma-rs", version, about)]
+struct Cli {
+    #[arg(long, global = true, default_value = "localhost")]
+    host: String,
+    #[arg(long, global = true, default_value = "8000")]
+    port: String,
+    /// Connect using https.
+    #[arg(long, global = true)]
+    ssl: bool,
+    /// Token sent in the `X-Chroma-Token` header.
+    #[arg(long, global = true, env = "CHROMA_API_KEY")]
+    api_key: Option<String>,
+    #[arg(long, global = true, default_value = "default_tenant")]
+    tenant: String,
+    #[arg(long, global = true, default_value = "default_database")]
+    database: String,
+    /// Use HTTP/2 without upgrade, for servers accepting it.
+    #[arg(long, global = true)]
+    http2: bool,
+    /// Skip the pre-flight checks, for gateways blocking them.
+    #[arg(long, global = true)]
+    no_preflight: bool,
+    #[arg(long, global = true, value_enum, default_value_t = Output::Table)]
+    output: Output,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Check that the server is alive.
+    Heartbeat,
+    /// Print the server version.
+    Version,
+    /// List all collections.
+    List,
+    /// Create a collection.
+    Create {
+        name: String,
+        /// Collection metadata, as `key=value`.
+        #[arg(long = "metadata", value_parser = parse_key_value)]
+        metadata: Vec<(String, String)>,
+    },
+    /// Delete a collection.
+    Delete { name: String },
+    /// Count the records of a collection.
+    Count { name: String },
+    /// Query the nearest neighbors of an embedding.
+    Query {
+        name: String,
+        /// Query embedding, as a JSON array.
+        #[arg(long)]
+        embedding: String,
+        #[arg(short, long, default_value_t = 10)]
+        n_results: usize,
+        /// Metadata filter, as a JSON object.
+        #[arg(long = "where")]
+        where_metadata: Option<String>,
+    },
+    /// Add records from a JSON Lines file, in the `export` format.
+    Add {
+        name: String,
+        #[arg(long)]
+        file: String,
+    },
+    /// Write every record of a collection as JSON Lines.
+    Export {
+        name: String,
+        /// Output file, stdout when not set.
+        #[arg(long)]
+        file: Option<String>,
+    },
+    /// Upsert records from a JSON Lines file, creating the collection if needed.
+    Import {
+        name: String,
+        #[arg(long)]
+        file: String,
+    },
+    /// Start an interactive shell.
+    Shell,
+    /// Measure ingestion throughput and query latency with synthetic embeddings.
+    Bench {
+        #[arg(long, default_value_t = 10_000)]
+        records: usize,
+        #[arg(long, default_value_t = 384)]
+        dimensions: usize,
+        #[arg(long, default_value_t = 500)]
+        batch_size: usize,
+        #[arg(long, default_value_t = 200)]
+        queries: usize,
+        /// Also write the report as CSV to this file.
+        #[arg(long)]
+        csv: Option<String>,
+    },
+    /// Browse collections and records in a terminal UI.
+    #[cfg(feature = "tui")]
+    Tui,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let cli = Cli::parse();
+    let client = client(&cli)?;
+
+    match cli.command {
+        Command::Shell => shell::run(&client, cli.output).await?,
+        Command::Bench {
+            records,
+            dimensions,
+            batch_size,
+            queries,
+            csv,
+        } => {
+            let config = BenchConfig {
+                records,
+                dimensions,
+                batch_size,
+                queries,
+                ..Default::default()
+            };
+            let report = bench::run(&client, &config).await?;
+            if let Some(csv) = csv {
+                std::fs::write(csv, report.to_csv())?;
+            }
+            print(cli.output, &report, || bench_rows(&report));
+        }
+        #[cfg(feature = "tui")]
+        Command::Tui => tui::run(&client).await?,
+        Command::Heartbeat => {
+            let heartbeat = client.heartbeat().await?;
+            print(cli.output, &heartbeat, || vec![vec![heartbeat.to_string()]]);
+        }
+        Command::Version => {
+            let version = client.version().await?;
+            let version = version.trim_matches('"').to_string();
+            print(cli.output, &version, || vec![vec![version.clone()]]);
+        }
+        Command::List => {
+            let collections = client.list_collections().await?;
+            print(cli.output, &collections, || collection_rows(&collections));
+        }
+        Command::Create { name, metadata } => {
+            let metadata = (!metadata.is_empty()).then(|| metadata.into_iter().collect());
+            let collection = client.create_collection(&name, metadata).await?;
+            print(cli.output, &collection, || {
+                collection_rows(std::slice::from_ref(&collection))
+            });
+        }
+        Command::Delete { name } => {
+            client.delete_collection(&name).await?;
+            print(cli.output, &name, || {
+                vec![vec![format!("Deleted {}", name)]]
+            });
+        }
+        Command::Count { name } => {
+            let count = client.get_collection(&name).await?.count(&client).await?;
+            print(cli.output, &count, || vec![vec![count.to_string()]]);
+        }
+        Command::Query {
+            name,
+            embedding,
+            n_results,
+            where_metadata,
+        } => {
+            let collection = client.get_collection(&name).await?;
+            let params = QueryParams {
+                query_embeddings: vec![serde_json::from_str(&embedding)?],
+                n_results,
+                where_metadata: where_metadata
+                    .map(|w| serde_json::from_str(&w))
+                    .transpose()?,
+                include: Some(vec![
+                    Include::Documents,
+                    Include::Metadatas,
+                    Include::Distances,
+                ]),
+                ..Default::default()
+            };
+            let result = collection.query(&client, params).await?;
+            print(cli.output, &result, || query_rows(&result));
+        }
+        Command::Add { name, file } => {
+            let collection = client.get_collection(&name).await?;
+            let records: Vec<ExportRecord> = BufReader::new(File::open(file)?)
+                .lines()
+                .filter(|line| !matches!(line, Ok(l) if l.trim().is_empty()))
+                .map(|line| Ok(serde_json::from_str(&line?)?))
+                .collect::<Result<_, Box<dyn Error>>>()?;
+
+            for batch in records.chunks(BATCH_SIZE) {
+                collection
+                    .add(&client, batch.iter().cloned().collect())
+                    .await?;
+            }
+            print(cli.output, &records.len(), || {
+                vec![vec![format!("Added {} records", records.len())]]
+            });
+        }
+        Command::Export { name, file } => {
+            let collection = client.get_collection(&name).await?;
+            let exported = match file {
+                Some(file) => {
+                    let writer = BufWriter::new(File::create(file)?);
+                    collection.export(&client, writer, BATCH_SIZE).await?
+                }
+                None => collection.export(&client, io::stdout(), BATCH_SIZE).await?,
+            };
+            eprintln!("Exported {} records", exported);
+        }
+        Command::Import { name, file } => {
+            let collection = client.get_or_create_collection(&name, None).await?;
+            let reader = BufReader::new(File::open(file)?);
+            let imported = collection.import(&client, reader, BATCH_SIZE).await?;
+            print(cli.output, &imported, || {
+                vec![vec![format!("Imported {} records", imported)]]
+            });
+        }
+    }
+
+    Ok(())
+}
+
+fn client(cli: &Cli) -> Result<ChromaClient, Box<dyn Error>> {
+    let mut headers = HeaderMap::new();
+    if let Some(api_key) = &cli.api_key {
+        headers.insert("X-Chroma-Token", api_key.parse()?);
+    }
+
+    let mut params = ChromaClientParams::default();
+    params.host = cli.host.clone();
+    params.port = cli.port.clone();
+    params.ssl = cli.ssl;
+    params.headers = Some(headers);
+    params.settings = Some(Settings {
+        tenant: cli.tenant.clone(),
+        database: cli.database.clone(),
+    });
+    params.http2_prior_knowledge = cli.http2;
+    if cli.no_preflight {
+        params.preflight = PreflightMode::Never;
+    }
+    Ok(ChromaClient::try_new(params)?)
+}
+
+fn parse_key_value(value: &str) -> Result<(String, String), String> {
+    value
+        .split_once('=')
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .ok_or_else(|| format!("expected `key=value`, got `{}`", value))
+}
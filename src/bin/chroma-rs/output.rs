@@ -0,0 +1,118 @@
+use chromadb_rs::bench::BenchReport;
+use chromadb_rs::collection::{Collection, GetResult, QueryResult};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Output {
+    Json,
+    Table,
+}
+
+pub fn header(columns: &[&str]) -> Vec<String> {
+    columns.iter().map(|c| c.to_string()).collect()
+}
+
+pub fn collection_rows(collections: &[Collection]) -> Vec<Vec<String>> {
+    let mut rows = vec![header(&["NAME", "ID", "METADATA"])];
+    rows.extend(collections.iter().map(|c| {
+        let metadata = c.metadata.as_ref().map(|m| m.to_string());
+        vec![c.name.clone(), c.id.clone(), metadata.unwrap_or_default()]
+    }));
+    rows
+}
+
+/// Rows of the first query of the result.
+pub fn query_rows(result: &QueryResult) -> Vec<Vec<String>> {
+    let mut rows = vec![header(&["ID", "DISTANCE", "DOCUMENT"])];
+    for (i, id) in result.ids.first().into_iter().flatten().enumerate() {
+        let distance = result
+            .distances
+            .as_ref()
+            .and_then(|d| d.first()?.get(i))
+            .map(|d| d.to_string());
+        let document = result
+            .documents
+            .as_ref()
+            .and_then(|d| d.first()?.get(i).cloned().flatten());
+        rows.push(vec![
+            id.clone(),
+            distance.unwrap_or_default(),
+            document.unwrap_or_default(),
+        ]);
+    }
+    rows
+}
+
+pub fn record_rows(result: &GetResult) -> Vec<Vec<String>> {
+    let mut rows = vec![header(&["ID", "DOCUMENT", "METADATA"])];
+    for (i, id) in result.ids.iter().enumerate() {
+        let document = result
+            .documents
+            .as_ref()
+            .and_then(|d| d.get(i).cloned().flatten());
+        let metadata = result.metadatas.as_ref().and_then(|m| m.get(i)?.as_ref());
+        rows.push(vec![
+            id.clone(),
+            document.unwrap_or_default(),
+            metadata
+                .map(|m| serde_json::to_string(m).unwrap_or_default())
+                .unwrap_or_default(),
+        ]);
+    }
+    rows
+}
+
+pub fn bench_rows(report: &BenchReport) -> Vec<Vec<String>> {
+    let l = &report.query_latency_ms;
+    vec![
+        header(&[
+            "RECORDS",
+            "INGEST (s)",
+            "RECORDS/s",
+            "QUERIES",
+            "P50 (ms)",
+            "P90 (ms)",
+            "P99 (ms)",
+        ]),
+        vec![
+            report.records.to_string(),
+            format!("{:.2}", report.ingest_seconds),
+            format!("{:.0}", report.records_per_second),
+            report.queries.to_string(),
+            format!("{:.2}", l.p50),
+            format!("{:.2}", l.p90),
+            format!("{:.2}", l.p99),
+        ],
+    ]
+}
+
+/// Print the value as JSON, or the rows built by `table` as aligned columns.
+pub fn print<T: Serialize>(output: Output, value: &T, table: impl FnOnce() -> Vec<Vec<String>>) {
+    if output == Output::Json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(value).unwrap_or_default()
+        );
+        return;
+    }
+
+    let rows = table();
+    let mut widths: HashMap<usize, usize> = HashMap::new();
+    for row in &rows {
+        for (i, cell) in row.iter().enumerate() {
+            let width = widths.entry(i).or_default();
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    for row in rows {
+        let line: Vec<String> = row
+            .iter()
+            .enumerate()
+            .map(|(i, cell)| format!("{:width$}", cell, width = widths[&i]))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
+}
@@ -0,0 +1,158 @@
+use crate::output::{collection_rows, print, query_rows, record_rows, Output};
+use chromadb_rs::client::ChromaClient;
+use chromadb_rs::collection::{Collection, GetParams, Include, QueryParams};
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use std::error::Error;
+use std::path::PathBuf;
+
+const HELP: &str = "\
+list                     list all collections
+use <collection>         select the collection used by the commands below
+count                    count the records
+peek [n]                 show the first n records (default 10)
+get <id>...              show records by id
+query <embedding> [n]    query the n nearest neighbors (default 10) of a JSON array embedding
+help                     show this message
+exit                     leave the shell";
+
+/// Interactive prompt, with history saved in `~/.chroma_rs_history`.
+pub async fn run(client: &ChromaClient, output: Output) -> Result<(), Box<dyn Error>> {
+    let mut editor = DefaultEditor::new()?;
+    let history = history_file();
+    if let Some(history) = &history {
+        let _ = editor.load_history(history);
+    }
+
+    let mut collection: Option<Collection> = None;
+    loop {
+        let prompt = match &collection {
+            Some(c) => format!("chroma:{}> ", c.name),
+            None => "chroma> ".to_string(),
+        };
+
+        let line = match editor.readline(&prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        editor.add_history_entry(line)?;
+
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        match command {
+            "exit" | "quit" => break,
+            command => {
+                if let Err(e) = execute(client, output, &mut collection, command, rest).await {
+                    eprintln!("error: {}", e);
+                }
+            }
+        }
+    }
+
+    if let Some(history) = &history {
+        let _ = editor.save_history(history);
+    }
+    Ok(())
+}
+
+async fn execute(
+    client: &ChromaClient,
+    output: Output,
+    collection: &mut Option<Collection>,
+    command: &str,
+    rest: &str,
+) -> Result<(), Box<dyn Error>> {
+    let args: Vec<&str> = rest.split_whitespace().collect();
+    match command {
+        "help" => println!("{}", HELP),
+        "list" => {
+            let collections = client.list_collections().await?;
+            print(output, &collections, || collection_rows(&collections));
+        }
+        "use" => {
+            let name = args.first().ok_or("usage: use <collection>")?;
+            *collection = Some(client.get_collection(name).await?);
+        }
+        "count" => {
+            let count = selected(collection)?.count(client).await?;
+            print(output, &count, || vec![vec![count.to_string()]]);
+        }
+        "peek" => {
+            let limit = args.first().map(|n| n.parse()).transpose()?.unwrap_or(10);
+            let params = GetParams {
+                limit: Some(limit),
+                ..Default::default()
+            };
+            let result = selected(collection)?.get(client, params).await?;
+            print(output, &result, || record_rows(&result));
+        }
+        "get" => {
+            if args.is_empty() {
+                return Err("usage: get <id>...".into());
+            }
+            let params = GetParams {
+                ids: Some(args.iter().map(|id| id.to_string()).collect()),
+                ..Default::default()
+            };
+            let result = selected(collection)?.get(client, params).await?;
+            print(output, &result, || record_rows(&result));
+        }
+        "query" => {
+            let (embedding, n_results) = query_args(rest)?;
+            let params = QueryParams {
+                query_embeddings: vec![embedding],
+                n_results,
+                include: Some(vec![
+                    Include::Documents,
+                    Include::Metadatas,
+                    Include::Distances,
+                ]),
+                ..Default::default()
+            };
+            let result = selected(collection)?.query(client, params).await?;
+            print(output, &result, || query_rows(&result));
+        }
+        other => return Err(format!("unknown command `{}`, try `help`", other).into()),
+    }
+    Ok(())
+}
+
+/// The embedding and the number of results of `query`: the embedding is a
+/// JSON array, which may contain spaces.
+fn query_args(rest: &str) -> Result<(Vec<f32>, usize), Box<dyn Error>> {
+    let end = rest.rfind(']').ok_or("usage: query <embedding> [n]")?;
+    let embedding = serde_json::from_str(&rest[..=end])?;
+    let n_results = match rest[end + 1..].trim() {
+        "" => 10,
+        n => n.parse()?,
+    };
+    Ok((embedding, n_results))
+}
+
+fn selected(collection: &Option<Collection>) -> Result<&Collection, Box<dyn Error>> {
+    collection
+        .as_ref()
+        .ok_or_else(|| "no collection selected, try `use <collection>`".into())
+}
+
+fn history_file() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".chroma_rs_history"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_embedding_with_spaces() {
+        assert_eq!(query_args("[0.1, 0.2]").unwrap(), (vec![0.1, 0.2], 10));
+        assert_eq!(query_args(" [0.1,0.2]  3").unwrap(), (vec![0.1, 0.2], 3));
+        assert!(query_args("").is_err());
+        assert!(query_args("[0.1, 0.2] many").is_err());
+    }
+}
@@ -0,0 +1,216 @@
+use chromadb_rs::client::ChromaClient;
+use chromadb_rs::collection::{Collection, GetParams, GetResult, Include};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+use serde_json::json;
+use std::error::Error;
+use std::time::Duration;
+
+const PAGE_SIZE: usize = 20;
+
+const KEYS: &str = "↑/↓ collection  ←/→ page  / search  r refresh  q quit";
+
+/// Collection browser: collections with their counts on the left, a page of
+/// records of the selected one on the right, and a search box filtering
+/// records by document content.
+pub async fn run(client: &ChromaClient) -> Result<(), Box<dyn Error>> {
+    let mut app = App::default();
+    app.refresh(client).await;
+
+    let mut terminal = ratatui::init();
+    let result = app.run(client, &mut terminal).await;
+    ratatui::restore();
+    result
+}
+
+#[derive(Default)]
+struct App {
+    collections: Vec<(Collection, usize)>,
+    list: ListState,
+    records: GetResult,
+    page: usize,
+    search: String,
+    editing: bool,
+    status: String,
+}
+
+impl App {
+    async fn run(
+        &mut self,
+        client: &ChromaClient,
+        terminal: &mut DefaultTerminal,
+    ) -> Result<(), Box<dyn Error>> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            if !event::poll(Duration::from_millis(250))? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            if self.editing {
+                match key.code {
+                    KeyCode::Enter => {
+                        self.editing = false;
+                        self.page = 0;
+                        self.load_records(client).await;
+                    }
+                    KeyCode::Esc => self.editing = false,
+                    KeyCode::Backspace => {
+                        self.search.pop();
+                    }
+                    KeyCode::Char(c) => self.search.push(c),
+                    _ => {}
+                }
+                continue;
+            }
+
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('/') => self.editing = true,
+                KeyCode::Char('r') => self.refresh(client).await,
+                KeyCode::Down | KeyCode::Up => {
+                    if self.collections.is_empty() {
+                        continue;
+                    }
+                    let last = self.collections.len() - 1;
+                    let selected = self.list.selected().unwrap_or(0);
+                    let selected = match key.code {
+                        KeyCode::Down => (selected + 1).min(last),
+                        _ => selected.saturating_sub(1),
+                    };
+                    self.list.select(Some(selected));
+                    self.page = 0;
+                    self.load_records(client).await;
+                }
+                KeyCode::Right if self.records.ids.len() == PAGE_SIZE => {
+                    self.page += 1;
+                    self.load_records(client).await;
+                }
+                KeyCode::Left if self.page > 0 => {
+                    self.page -= 1;
+                    self.load_records(client).await;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    async fn refresh(&mut self, client: &ChromaClient) {
+        self.collections.clear();
+        match client.list_collections().await {
+            Ok(collections) => {
+                for collection in collections {
+                    let count = collection.count(client).await.unwrap_or_default();
+                    self.collections.push((collection, count));
+                }
+                self.status = format!("{} collections", self.collections.len());
+            }
+            Err(e) => self.status = e.to_string(),
+        }
+
+        if self.list.selected().is_none() && !self.collections.is_empty() {
+            self.list.select(Some(0));
+        }
+        self.load_records(client).await;
+    }
+
+    async fn load_records(&mut self, client: &ChromaClient) {
+        let Some((collection, _)) = self.list.selected().and_then(|i| self.collections.get(i))
+        else {
+            self.records = GetResult::default();
+            return;
+        };
+
+        let params = GetParams {
+            where_document: (!self.search.is_empty()).then(|| json!({ "$contains": self.search })),
+            limit: Some(PAGE_SIZE),
+            offset: Some(self.page * PAGE_SIZE),
+            include: Some(vec![Include::Documents, Include::Metadatas]),
+            ..Default::default()
+        };
+        match collection.get(client, params).await {
+            Ok(records) => {
+                self.records = records;
+                self.status = format!("{} - page {}", collection.name, self.page + 1);
+            }
+            Err(e) => self.status = e.to_string(),
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, search, status] = Layout::vertical([
+            Constraint::Min(3),
+            Constraint::Length(3),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [left, right] =
+            Layout::horizontal([Constraint::Percentage(30), Constraint::Percentage(70)])
+                .areas(main);
+
+        let items: Vec<ListItem> = self
+            .collections
+            .iter()
+            .map(|(c, count)| ListItem::new(format!("{} ({})", c.name, count)))
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title("Collections"))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, left, &mut self.list);
+
+        let rows = self.records.ids.iter().enumerate().map(|(i, id)| {
+            let document = self
+                .records
+                .documents
+                .as_ref()
+                .and_then(|d| d.get(i).cloned().flatten());
+            let metadata = self
+                .records
+                .metadatas
+                .as_ref()
+                .and_then(|m| m.get(i)?.as_ref());
+            Row::new(vec![
+                id.clone(),
+                document.unwrap_or_default().replace('\n', " "),
+                metadata
+                    .map(|m| serde_json::to_string(m).unwrap_or_default())
+                    .unwrap_or_default(),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Percentage(20),
+                Constraint::Percentage(50),
+                Constraint::Percentage(30),
+            ],
+        )
+        .header(
+            Row::new(vec!["ID", "DOCUMENT", "METADATA"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::default().borders(Borders::ALL).title("Records"));
+        frame.render_widget(table, right);
+
+        let title = if self.editing {
+            "Search (enter to apply, esc to cancel)"
+        } else {
+            "Search"
+        };
+        let search_box = Paragraph::new(self.search.as_str())
+            .block(Block::default().borders(Borders::ALL).title(title));
+        frame.render_widget(search_box, search);
+
+        frame.render_widget(Line::from(format!("{}  |  {}", self.status, KEYS)), status);
+    }
+}
@@ -1,61 +1,541 @@
+use crate::audit::{AuditEvent, AuditHook, AuditSink};
 use crate::collection::Collection;
-use crate::error::ChromaClientError;
-use reqwest::header::{HeaderMap, ACCEPT, CONTENT_TYPE};
-use reqwest::Client;
+use crate::compat::{ServerApi, ServerVersion};
+use crate::encoding::EmbeddingEncoding;
+use crate::error::{ApiError, ChromaClientError};
+use crate::policy::{Operation, OperationPolicy};
+use crate::schema::Schema;
+use bytes::{BufMut, Bytes, BytesMut};
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, CONTENT_TYPE, USER_AGENT};
+use reqwest::{Client, StatusCode};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot, Notify};
 use url::Url;
 
+/// Responses larger than this, or without a known length, are parsed while
+/// they are downloaded instead of being buffered first.
+const STREAMING_THRESHOLD: u64 = 8 * 1024 * 1024;
+
+/// Header naming the application sending the requests, as in `my-app/1.2.0`.
+pub const CLIENT_HEADER: &str = "X-Chroma-Client";
+
+/// Headers whose values are hidden in `Debug` output and `redacted_config`.
+pub const SECRET_HEADERS: [&str; 3] = ["authorization", "proxy-authorization", "x-chroma-token"];
+
+/// Tenant used when none is configured.
+pub const DEFAULT_TENANT: &str = "default_tenant";
+
+/// Database used when none is configured.
+pub const DEFAULT_DATABASE: &str = "default_database";
+
+/// Shown in place of secret header values.
+const REDACTED: &str = "<redacted>";
+
+/// When the client calls the pre-flight checks endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PreflightMode {
+    /// Before every request.
+    #[default]
+    Always,
+    /// Before requests until a check succeeds.
+    Once,
+    /// Never, for gateways blocking the endpoint. The maximum batch size of
+    /// the server is then unknown. Set `ChromaClientParams::server_version`
+    /// to skip the version detection as well.
+    Never,
+}
+
+/// Order in which requests waiting for a slot are sent, see
+/// `ChromaClientParams::max_in_flight_requests`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Priority {
+    /// Bulk work such as ingestion or reindexing.
+    Background,
+    #[default]
+    Normal,
+    /// Requests a user is waiting for, such as queries.
+    Interactive,
+}
+
 /// Chroma Client instance.
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct ChromaClient {
     path: String,
     client: Client,
-    headers: HeaderMap,
+    headers: Arc<HeaderMap>,
+    body_buffer: Arc<Mutex<BytesMut>>,
     tenant: String,
     database: String,
+    embedding_encoding: EmbeddingEncoding,
+    preflight: PreflightMode,
+    read_only: bool,
+    policy: Arc<OperationPolicy>,
+    audit: Option<AuditHook>,
+    audit_context: Option<String>,
+    server: Arc<OnceLock<ServerVersion>>,
+    max_batch_size: Arc<OnceLock<Option<usize>>>,
+    /// Set by `connect`: the checks were run, requests skip them.
+    connected: bool,
+    lifecycle: Arc<Lifecycle>,
+    limiter: Option<Arc<Limiter>>,
+    priority: Priority,
+    #[cfg(feature = "replay")]
+    cassette: Option<Arc<crate::replay::Cassette>>,
+}
+
+/// Shutdown state shared by the clones of a client.
+#[derive(Debug, Default)]
+struct Lifecycle {
+    shut_down: AtomicBool,
+    in_flight: AtomicUsize,
+    idle: Notify,
+}
+
+/// Bounds the requests in flight across the clones of a client. Waiting
+/// requests get the free slots by priority, then in arrival order.
+#[derive(Debug)]
+struct Limiter {
+    slots: Mutex<Slots>,
+    requests: AtomicU64,
+    wait_nanos: AtomicU64,
+    max_wait_nanos: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct Slots {
+    available: usize,
+    /// One queue per `Priority`, lowest first.
+    waiting: [VecDeque<oneshot::Sender<Permit>>; 3],
+}
+
+/// A slot of the limiter, given back when dropped.
+#[derive(Debug)]
+struct Permit(Option<Arc<Limiter>>);
+
+impl Limiter {
+    fn new(max_in_flight: usize) -> Self {
+        Limiter {
+            slots: Mutex::new(Slots {
+                available: max_in_flight.max(1),
+                ..Default::default()
+            }),
+            requests: AtomicU64::new(0),
+            wait_nanos: AtomicU64::new(0),
+            max_wait_nanos: AtomicU64::new(0),
+        }
+    }
+
+    async fn acquire(self: &Arc<Self>, priority: Priority) -> Permit {
+        let started = Instant::now();
+        let receiver = {
+            let mut slots = self.slots.lock().unwrap();
+            if slots.available > 0 {
+                slots.available -= 1;
+                None
+            } else {
+                let (sender, receiver) = oneshot::channel();
+                slots.waiting[priority as usize].push_back(sender);
+                Some(receiver)
+            }
+        };
+        let permit = match receiver {
+            // The sender is only dropped along with the limiter.
+            Some(receiver) => receiver.await.unwrap(),
+            None => Permit(Some(self.clone())),
+        };
+        let waited = started.elapsed().as_nanos() as u64;
+
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.wait_nanos.fetch_add(waited, Ordering::Relaxed);
+        self.max_wait_nanos.fetch_max(waited, Ordering::Relaxed);
+        permit
+    }
+
+    fn release(&self, mut permit: Permit) {
+        let mut slots = self.slots.lock().unwrap();
+        for queue in slots.waiting.iter_mut().rev() {
+            while let Some(waiter) = queue.pop_front() {
+                // A waiter that gave up hands the permit back.
+                match waiter.send(permit) {
+                    Ok(()) => return,
+                    Err(returned) => permit = returned,
+                }
+            }
+        }
+        permit.0 = None;
+        slots.available += 1;
+    }
+
+    fn metrics(&self) -> QueueMetrics {
+        QueueMetrics {
+            requests: self.requests.load(Ordering::Relaxed),
+            available: self.slots.lock().unwrap().available,
+            total_wait: Duration::from_nanos(self.wait_nanos.load(Ordering::Relaxed)),
+            max_wait: Duration::from_nanos(self.max_wait_nanos.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if let Some(limiter) = self.0.take() {
+            limiter.release(Permit(Some(limiter.clone())));
+        }
+    }
+}
+
+/// Time spent by requests waiting for a slot, see
+/// `ChromaClientParams::max_in_flight_requests`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueMetrics {
+    /// Requests sent since the client was created.
+    pub requests: u64,
+    /// Free slots right now.
+    pub available: usize,
+    pub total_wait: Duration,
+    pub max_wait: Duration,
+}
+
+impl QueueMetrics {
+    pub fn mean_wait(&self) -> Duration {
+        match self.requests {
+            0 => Duration::ZERO,
+            n => self.total_wait / n as u32,
+        }
+    }
+}
+
+/// Counts a request as in flight until dropped.
+struct InFlight<'a>(&'a Lifecycle);
+
+impl<'a> InFlight<'a> {
+    fn start(lifecycle: &'a Lifecycle) -> Result<Self, ChromaClientError> {
+        lifecycle.in_flight.fetch_add(1, Ordering::SeqCst);
+        if lifecycle.shut_down.load(Ordering::SeqCst) {
+            drop(InFlight(lifecycle));
+            return Err(ChromaClientError::ShutDown);
+        }
+        Ok(InFlight(lifecycle))
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        if self.0.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.idle.notify_waiters();
+        }
+    }
 }
 
 impl ChromaClient {
-    /// Creates a new ChromaClient instance.
+    /// Creates a new ChromaClient instance. Nothing is sent until the first
+    /// request: use `connect` to reach the server up front.
+    ///
+    /// Panics if the HTTP client cannot be built, see `try_new`.
     pub fn new(params: ChromaClientParams) -> Self {
+        Self::try_new(params).expect("failed to build the HTTP client")
+    }
+
+    /// Same as `new`, returning the error of the HTTP client builder, for
+    /// instance when the TLS backend cannot be initialized.
+    pub fn try_new(params: ChromaClientParams) -> Result<Self, ChromaClientError> {
         let http = if params.ssl { "https" } else { "http" };
-        let mut headers = params.headers.unwrap_or(HeaderMap::new());
+        let mut headers = params.headers.unwrap_or_default();
         headers.insert(ACCEPT, "application/json".parse().unwrap());
-        let settings = params.settings.unwrap_or(Settings::default());
+        let app = app_id(params.app_name.as_deref(), params.app_version.as_deref());
+        headers.insert(USER_AGENT, user_agent(app.as_deref()));
+        if let Some(value) = app.and_then(|app| HeaderValue::from_str(&app).ok()) {
+            headers.insert(CLIENT_HEADER, value);
+        }
+        for name in SECRET_HEADERS {
+            if let Some(value) = headers.get_mut(name) {
+                value.set_sensitive(true);
+            }
+        }
+        let settings = params.settings.unwrap_or_default();
+        let server = OnceLock::new();
+        if let Some(version) = &params.server_version {
+            let _ = server.set(ServerVersion::parse(version));
+        }
 
-        ChromaClient {
+        let mut builder = Client::builder().default_headers(headers.clone());
+        if params.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        if let Some(interval) = params.keep_alive_interval {
+            builder = builder
+                .tcp_keepalive(interval)
+                .http2_keep_alive_interval(interval)
+                .http2_keep_alive_while_idle(true);
+        }
+
+        Ok(ChromaClient {
             path: format!("{}://{}:{}", http, params.host, params.port),
-            client: Client::new(),
-            headers,
+            client: builder.build().map_err(ChromaClientError::RequestError)?,
+            headers: Arc::new(headers),
+            body_buffer: Arc::new(Mutex::new(BytesMut::new())),
             tenant: settings.tenant,
             database: settings.database,
+            embedding_encoding: params.embedding_encoding,
+            preflight: params.preflight,
+            read_only: false,
+            policy: Arc::default(),
+            audit: None,
+            audit_context: None,
+            server: Arc::new(server),
+            max_batch_size: Arc::new(OnceLock::new()),
+            connected: false,
+            lifecycle: Arc::default(),
+            limiter: params
+                .max_in_flight_requests
+                .map(|max| Arc::new(Limiter::new(max))),
+            priority: Priority::Normal,
+            #[cfg(feature = "replay")]
+            cassette: None,
+        })
+    }
+
+    /// Reach the server: detect its version, run the pre-flight checks as
+    /// set by `preflight` and check that it supports the tenant and database
+    /// of the client. The returned client keeps the capabilities found and
+    /// its requests skip these checks, whatever `preflight` is.
+    pub async fn connect(mut self) -> Result<ConnectedClient, ChromaClientError> {
+        self.check_pre_flight_status().await?;
+        let server = self.server_version().await?;
+        let max_batch_size = self.max_batch_size.get().copied().flatten();
+        self.connected = true;
+        Ok(ConnectedClient {
+            client: self,
+            server,
+            max_batch_size,
+        })
+    }
+
+    /// The configuration of the client with secret header values hidden,
+    /// safe to log.
+    pub fn redacted_config(&self) -> RedactedConfig {
+        RedactedConfig {
+            url: self.path.clone(),
+            tenant: self.tenant.clone(),
+            database: self.database.clone(),
+            headers: redact_headers(&self.headers),
+            embedding_encoding: format!("{:?}", self.embedding_encoding),
+            read_only: self.read_only,
         }
     }
 
-    async fn check_pre_flight_status(&self) -> Result<(), ChromaClientError> {
-        let res = self
-            .client
-            .get(&format!("{}/api/v1/pre-flight-checks", self.path))
-            .headers(self.headers.clone())
+    /// Make every mutating method (add, update, upsert, delete, create,
+    /// reset) fail with `ReadOnlyMode` without sending anything.
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Restrict the operations of the client. Denied operations fail with
+    /// `OperationDenied` without sending anything.
+    pub fn policy(mut self, policy: OperationPolicy) -> Self {
+        self.policy = Arc::new(policy);
+        self
+    }
+
+    /// Priority of the requests of this client when they wait for a slot,
+    /// e.g. `client.clone().priority(Priority::Background)` for an
+    /// ingestion job sharing the slots of an interactive application.
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub(crate) fn check_operation(&self, operation: Operation) -> Result<(), ChromaClientError> {
+        if self.read_only && operation.is_mutating() {
+            return Err(ChromaClientError::ReadOnlyMode);
+        }
+        self.policy.check(operation)
+    }
+
+    /// Report every mutating call to the sink.
+    pub fn with_audit(mut self, sink: impl AuditSink + 'static) -> Self {
+        self.audit = Some(AuditHook(Arc::new(sink)));
+        self
+    }
+
+    /// Context attached to the audit events of the calls made with this
+    /// client, such as the user or job on whose behalf they are made.
+    pub fn with_audit_context(mut self, context: impl Into<String>) -> Self {
+        self.audit_context = Some(context.into());
+        self
+    }
+
+    pub(crate) fn audit<T>(
+        &self,
+        operation: Operation,
+        collection: Option<&str>,
+        ids: usize,
+        result: &Result<T, ChromaClientError>,
+    ) {
+        if let Some(AuditHook(sink)) = &self.audit {
+            let context = self.audit_context.as_deref();
+            sink.record(&AuditEvent::new(
+                operation,
+                collection,
+                ids,
+                context,
+                result.is_ok(),
+            ));
+        }
+    }
+
+    /// Record the responses of the server to a cassette, or answer requests
+    /// from it, depending on its mode.
+    #[cfg(feature = "replay")]
+    pub fn with_cassette(mut self, cassette: crate::replay::Cassette) -> Self {
+        self.cassette = Some(Arc::new(cassette));
+        self
+    }
+
+    /// Refuse new requests, from this client and its clones, with
+    /// `ShutDown`, and wait up to `grace_period` for the requests in flight
+    /// to complete. Background helpers such as `Collection::expire_every`
+    /// and `BackupPlan::every` stop at their next run, and `monitor` ends.
+    /// Returns whether every request completed in time.
+    pub async fn shutdown(&self, grace_period: Duration) -> bool {
+        let lifecycle = &self.lifecycle;
+        lifecycle.shut_down.store(true, Ordering::SeqCst);
+
+        let drained = async {
+            loop {
+                let idle = lifecycle.idle.notified();
+                if lifecycle.in_flight.load(Ordering::SeqCst) == 0 {
+                    return;
+                }
+                idle.await;
+            }
+        };
+        tokio::time::timeout(grace_period, drained).await.is_ok()
+    }
+
+    pub fn is_shut_down(&self) -> bool {
+        self.lifecycle.shut_down.load(Ordering::SeqCst)
+    }
+
+    /// Waits of the requests for a slot, `None` without
+    /// `max_in_flight_requests`.
+    pub fn queue_metrics(&self) -> Option<QueueMetrics> {
+        self.limiter.as_ref().map(|limiter| limiter.metrics())
+    }
+
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, ChromaClientError> {
+        let _in_flight = InFlight::start(&self.lifecycle)?;
+        let _permit = match &self.limiter {
+            Some(limiter) => Some(limiter.acquire(self.priority).await),
+            None => None,
+        };
+        #[cfg(feature = "replay")]
+        if let Some(cassette) = &self.cassette {
+            let request = request.build().map_err(ChromaClientError::RequestError)?;
+            return cassette.send(&self.client, request).await;
+        }
+        request
             .send()
             .await
-            .map_err(|e| ChromaClientError::RequestError(e))?;
+            .map_err(ChromaClientError::RequestError)
+    }
+
+    /// Version of the connected server, detected on the first request unless
+    /// set with `ChromaClientParams::server_version`.
+    pub async fn server_version(&self) -> Result<ServerVersion, ChromaClientError> {
+        if let Some(server) = self.server.get() {
+            return Ok(server.clone());
+        }
+
+        let version_url = |path: &str| format!("{}/{}", self.path, path);
+        let mut res = self
+            .send(self.client.get(version_url("api/v2/version")))
+            .await?;
+        if !res.status().is_success() {
+            res = self
+                .send(self.client.get(version_url("api/v1/version")))
+                .await?;
+        }
+        if !res.status().is_success() {
+            let context = "Unable to detect the server version, status: ";
+            let error = ApiError::from_response(context, res).await;
+            return Err(ChromaClientError::ResponseStatusError(error));
+        }
+
+        let version = res.text().await.map_err(ChromaClientError::ResponseError)?;
+        let detected = ServerVersion::parse(&version);
+        Ok(self.server.get_or_init(|| detected).clone())
+    }
+
+    async fn check_pre_flight_status(&self) -> Result<(), ChromaClientError> {
+        if self.connected {
+            return Ok(());
+        }
+        let server = self.server_version().await?;
+        if self.tenant != DEFAULT_TENANT || self.database != DEFAULT_DATABASE {
+            server.require("0.4.15")?;
+        }
+        match self.preflight {
+            PreflightMode::Never => return Ok(()),
+            PreflightMode::Once if self.max_batch_size.get().is_some() => return Ok(()),
+            _ => {}
+        }
+
+        let res = self
+            .send(self.client.get(self.get_url("api/v1/pre-flight-checks")?))
+            .await?;
 
         if res.status().is_success() {
+            if self.max_batch_size.get().is_none() {
+                let checks: Value = res.json().await.unwrap_or_default();
+                let max = checks["max_batch_size"].as_u64().map(|max| max as usize);
+                self.max_batch_size.get_or_init(|| max);
+            }
             Ok(())
         } else {
-            let error_message = format!("Preflight request failed, status: {}", res.status());
-            Err(ChromaClientError::PreflightError(error_message))
+            let error = ApiError::from_response("", res).await;
+            Err(ChromaClientError::PreflightError(error))
         }
     }
 
+    /// Most records the server accepts in one write, as reported by its
+    /// pre-flight checks. `None` for servers not reporting it.
+    pub async fn max_batch_size(&self) -> Result<Option<usize>, ChromaClientError> {
+        if let Some(max) = self.max_batch_size.get() {
+            return Ok(*max);
+        }
+        self.check_pre_flight_status().await?;
+        Ok(self.max_batch_size.get().copied().flatten())
+    }
+
+    /// Fail with `BatchTooLarge` before sending a write the server would reject.
+    pub(crate) async fn check_batch_size(&self, sent: usize) -> Result<(), ChromaClientError> {
+        match self.max_batch_size().await? {
+            Some(max) if sent > max => Err(ChromaClientError::BatchTooLarge { sent, max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Url of an `api/v1` path, rewritten for the API of the server.
     fn get_url(&self, path: &str) -> Result<Url, ChromaClientError> {
+        let path = self.api_path(path);
         Url::parse(&format!("{}/{}", self.path, path)).map_err(ChromaClientError::UrlParseError)
     }
 
     fn get_url_with_params(&self, path: &str) -> Result<Url, ChromaClientError> {
+        let path = self.api_path(path);
         Url::parse_with_params(
             &format!("{}/{}", self.path, path),
             &[
@@ -66,218 +546,444 @@ impl ChromaClient {
         .map_err(ChromaClientError::UrlParseError)
     }
 
+    /// Every request detects the server version, in
+    /// `check_pre_flight_status`, before building its url.
+    fn api_path(&self, path: &str) -> String {
+        let server = self.server.get();
+        debug_assert!(server.is_some(), "url built before the version detection");
+        let api = server.map_or(ServerApi::V1, |s| s.api);
+        api.path(path, &self.tenant, &self.database)
+    }
+
+    /// Send a JSON body to the given path and parse the JSON response.
+    pub(crate) async fn post_json<B, R>(&self, path: &str, body: &B) -> Result<R, ChromaClientError>
+    where
+        B: Serialize + ?Sized,
+        R: DeserializeOwned + Send + 'static,
+    {
+        // Held until the body is read, so `shutdown` waits for it too.
+        let _in_flight = InFlight::start(&self.lifecycle)?;
+        let response = self.post_raw(path, body).await?;
+        Self::parse_response(response).await
+    }
+
+    /// Send a JSON body to the given path and return the body of the
+    /// response, unparsed.
+    pub(crate) async fn post_bytes<B>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<Bytes, ChromaClientError>
+    where
+        B: Serialize + ?Sized,
+    {
+        let _in_flight = InFlight::start(&self.lifecycle)?;
+        let response = self.post_raw(path, body).await?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response
+            .bytes()
+            .await
+            .map_err(ChromaClientError::ResponseError)?;
+
+        if !status.is_success() {
+            let error = ApiError::new("", status, &headers, &body);
+            return Err(ChromaClientError::ResponseStatusError(error));
+        }
+        Ok(body)
+    }
+
+    /// Send a JSON body to the given path, returning the response as is.
+    pub(crate) async fn post_raw<B>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<reqwest::Response, ChromaClientError>
+    where
+        B: Serialize + ?Sized,
+    {
+        self.check_pre_flight_status().await?;
+        let url = self.get_url(path)?;
+
+        let body = match self.server.get() {
+            Some(server) if server.needs_request_shims() => {
+                let mut body =
+                    serde_json::to_value(body).map_err(ChromaClientError::ResponseParseError)?;
+                server.adapt_request(&mut body);
+                self.serialize_body(&body)?
+            }
+            _ => self.serialize_body(body)?,
+        };
+
+        self.send(
+            self.client
+                .post(url)
+                .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+                .body(body),
+        )
+        .await
+    }
+
+    /// Serialize a request body into the shared buffer. Its allocation is
+    /// reclaimed once the body of the previous request has been dropped, so
+    /// sustained ingestion does not allocate a new buffer per batch.
+    fn serialize_body<B: Serialize + ?Sized>(&self, body: &B) -> Result<Bytes, ChromaClientError> {
+        let mut buffer = self.body_buffer.lock().unwrap();
+        let result = if self.embedding_encoding == EmbeddingEncoding::Json {
+            serde_json::to_writer((&mut *buffer).writer(), body)
+        } else {
+            serde_json::to_value(body).and_then(|mut value| {
+                self.embedding_encoding.encode_body(&mut value);
+                serde_json::to_writer((&mut *buffer).writer(), &value)
+            })
+        };
+        let bytes = buffer.split().freeze();
+        result.map_err(ChromaClientError::ResponseParseError)?;
+        Ok(bytes)
+    }
+
+    /// Send a GET request to the given path and parse the JSON response.
+    pub(crate) async fn get_json<R: DeserializeOwned + Send + 'static>(
+        &self,
+        path: &str,
+    ) -> Result<R, ChromaClientError> {
+        let _in_flight = InFlight::start(&self.lifecycle)?;
+        self.check_pre_flight_status().await?;
+        let url = self.get_url(path)?;
+
+        let response = self.send(self.client.get(url)).await?;
+
+        Self::parse_response(response).await
+    }
+
+    async fn parse_response<R: DeserializeOwned + Send + 'static>(
+        response: reqwest::Response,
+    ) -> Result<R, ChromaClientError> {
+        let status = response.status();
+        let large = response
+            .content_length()
+            .is_none_or(|length| length > STREAMING_THRESHOLD);
+        if status.is_success() && large {
+            return Self::parse_streaming(response).await;
+        }
+
+        let headers = response.headers().clone();
+        let body = response
+            .bytes()
+            .await
+            .map_err(ChromaClientError::ResponseError)?;
+
+        if !status.is_success() {
+            let error = ApiError::new("", status, &headers, &body);
+            return Err(ChromaClientError::ResponseStatusError(error));
+        }
+
+        serde_json::from_slice(&body).map_err(ChromaClientError::ResponseParseError)
+    }
+
+    /// Deserialize the body chunk by chunk as it arrives, on a blocking thread.
+    async fn parse_streaming<R: DeserializeOwned + Send + 'static>(
+        mut response: reqwest::Response,
+    ) -> Result<R, ChromaClientError> {
+        let (tx, rx) = mpsc::channel(16);
+        let parser = tokio::task::spawn_blocking(move || {
+            serde_json::from_reader(ChunkReader::new(rx))
+                .map_err(ChromaClientError::ResponseParseError)
+        });
+
+        loop {
+            match response.chunk().await {
+                Ok(Some(chunk)) => {
+                    // The parser stopped early, its result holds the error.
+                    if tx.send(chunk).await.is_err() {
+                        break;
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    parser.abort();
+                    return Err(ChromaClientError::ResponseError(e));
+                }
+            }
+        }
+        drop(tx);
+
+        parser
+            .await
+            .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))
+    }
+
     /// Get the current time in nanoseconds since epoch. Used to check if the server is alive.
     pub async fn heartbeat(&self) -> Result<u64, ChromaClientError> {
         self.check_pre_flight_status().await?;
         let url = self.get_url("api/v1/heartbeat")?;
 
-        let res = self
-            .client
-            .get(url)
-            .headers(self.headers.clone())
-            .send()
-            .await
-            .map_err(|e| ChromaClientError::RequestError(e))?;
+        let res = self.send(self.client.get(url)).await?;
 
-        let res_text = res
-            .text()
-            .await
-            .map_err(|e| ChromaClientError::ResponseError(e))?;
+        if res.status().is_success() {
+            let body = res
+                .bytes()
+                .await
+                .map_err(ChromaClientError::ResponseError)?;
 
-        let body_json: HeartbeatResponse = serde_json::from_str(&res_text)
-            .map_err(|e| ChromaClientError::ResponseParseError(e))?;
+            let body_json: HeartbeatResponse =
+                serde_json::from_slice(&body).map_err(ChromaClientError::ResponseParseError)?;
 
-        Ok(body_json.nanosecond_heartbeat)
+            Ok(body_json.nanosecond_heartbeat)
+        } else {
+            let context = "Failed to get heartbeat with status code: ";
+            let error = ApiError::from_response(context, res).await;
+            Err(ChromaClientError::ResponseStatusError(error))
+        }
     }
 
-    /// Create a new collection with the given name and metadata.
+    /// Create a new collection with the given name and metadata. Same as
+    /// `create_collection_strict`.
     pub async fn create_collection(
         &self,
         name: &str,
         metadata: Option<HashMap<String, String>>,
     ) -> Result<Collection, ChromaClientError> {
-        self.check_pre_flight_status().await?;
-        let url = self.get_url_with_params("api/v1/collections")?;
-
-        let mut headers = self.headers.clone();
-        headers.insert(CONTENT_TYPE, "application/json".parse().unwrap());
+        self.create_collection_strict(name, metadata).await
+    }
 
-        let request_body = CreateCollectionRequest {
+    /// Create a new collection, failing with `CollectionAlreadyExists` when a
+    /// collection has the same name. Use `get_or_create_collection` to reuse it instead.
+    pub async fn create_collection_strict(
+        &self,
+        name: &str,
+        metadata: Option<HashMap<String, String>>,
+    ) -> Result<Collection, ChromaClientError> {
+        self.send_create_collection(CreateCollectionRequest {
             name: name.to_string(),
-            metadata: Some(metadata).unwrap_or(None),
+            metadata: string_metadata(metadata),
+            schema: None,
             get_or_create: false,
-        };
-
-        let response = self
-            .client
-            .post(url)
-            .headers(headers)
-            .json(&request_body)
-            .send()
-            .await
-            .map_err(ChromaClientError::RequestError)?;
-
-        let response_text = response
-            .text()
-            .await
-            .map_err(|e| ChromaClientError::ResponseError(e))?;
-
-        let response_json: CreateCollectionResponse = serde_json::from_str(&response_text)
-            .map_err(|e| ChromaClientError::ResponseParseError(e))?;
-
-        Ok(Collection {
-            name: response_json.name,
-            id: response_json.id,
-            metadata: response_json.metadata,
         })
+        .await
     }
 
     /// Get a collection with the given name.
     pub async fn get_collection(&self, name: &str) -> Result<Collection, ChromaClientError> {
+        self.check_operation(Operation::Read)?;
         self.check_pre_flight_status().await?;
         let url = self.get_url_with_params(&format!("api/v1/collections/{}", name))?;
 
-        let response = self
-            .client
-            .get(url)
-            .headers(self.headers.clone())
-            .send()
-            .await
-            .map_err(ChromaClientError::RequestError)?;
+        let response = self.send(self.client.get(url)).await?;
 
-        let response_text = response
-            .text()
-            .await
-            .map_err(|e| ChromaClientError::ResponseError(e))?;
+        if response.status().is_success() {
+            let body = response
+                .bytes()
+                .await
+                .map_err(ChromaClientError::ResponseError)?;
 
-        let response_json: Collection = serde_json::from_str(&response_text)
-            .map_err(|e| ChromaClientError::ResponseParseError(e))?;
+            let response_json: Collection =
+                serde_json::from_slice(&body).map_err(ChromaClientError::ResponseParseError)?;
 
-        Ok(response_json)
+            Ok(response_json)
+        } else {
+            let context = "Failed to get collection with status code: ";
+            let error = ApiError::from_response(context, response).await;
+            Err(ChromaClientError::ResponseStatusError(error))
+        }
     }
 
-    /// Get or create a collection with the given name and metadata.
+    /// Get the collection with the given name, creating it when missing. The
+    /// metadata is only used when the collection is created. Clients denied
+    /// `CreateCollection` can still open an existing collection.
     pub async fn get_or_create_collection(
         &self,
         name: &str,
         metadata: Option<HashMap<String, String>>,
     ) -> Result<Collection, ChromaClientError> {
-        self.check_pre_flight_status().await?;
-        let url = self.get_url_with_params("api/v1/collections")?;
+        self.send_create_collection(CreateCollectionRequest {
+            name: name.to_string(),
+            metadata: string_metadata(metadata),
+            schema: None,
+            get_or_create: true,
+        })
+        .await
+    }
 
-        let mut headers = self.headers.clone();
-        headers.insert(CONTENT_TYPE, "application/json".parse().unwrap());
+    /// Create a new collection with metadata index configuration.
+    pub async fn create_collection_with_schema(
+        &self,
+        name: &str,
+        metadata: Option<HashMap<String, String>>,
+        schema: Schema,
+    ) -> Result<Collection, ChromaClientError> {
+        self.send_create_collection(CreateCollectionRequest {
+            name: name.to_string(),
+            metadata: string_metadata(metadata),
+            schema: Some(schema),
+            get_or_create: false,
+        })
+        .await
+    }
 
-        let request_body = CreateCollectionRequest {
+    /// Get or create a collection, configuring its metadata indexes when it is created.
+    pub async fn get_or_create_collection_with_schema(
+        &self,
+        name: &str,
+        metadata: Option<HashMap<String, String>>,
+        schema: Schema,
+    ) -> Result<Collection, ChromaClientError> {
+        self.send_create_collection(CreateCollectionRequest {
             name: name.to_string(),
-            metadata: Some(metadata).unwrap_or(None),
+            metadata: string_metadata(metadata),
+            schema: Some(schema),
             get_or_create: true,
-        };
+        })
+        .await
+    }
 
-        let response = self
-            .client
-            .post(url)
-            .headers(headers)
-            .json(&request_body)
-            .send()
-            .await
-            .map_err(ChromaClientError::RequestError)?;
+    /// `get_or_create_collection` with metadata of any JSON type, e.g. as
+    /// returned by the server.
+    pub(crate) async fn get_or_create_collection_with_metadata(
+        &self,
+        name: &str,
+        metadata: Option<serde_json::Map<String, Value>>,
+    ) -> Result<Collection, ChromaClientError> {
+        self.send_create_collection(CreateCollectionRequest {
+            name: name.to_string(),
+            metadata,
+            schema: None,
+            get_or_create: true,
+        })
+        .await
+    }
 
-        let response_text = response
-            .text()
+    async fn send_create_collection(
+        &self,
+        request_body: CreateCollectionRequest,
+    ) -> Result<Collection, ChromaClientError> {
+        if let Err(denied) = self.check_operation(Operation::CreateCollection) {
+            if !request_body.get_or_create {
+                return Err(denied);
+            }
+            // Opening an existing collection is only a read.
+            return match self.get_collection(&request_body.name).await {
+                Ok(collection) => Ok(collection),
+                Err(e @ ChromaClientError::RequestError(_)) => Err(e),
+                Err(_) => Err(denied),
+            };
+        }
+        let name = request_body.name.clone();
+        let result = self.post_create_collection(request_body).await;
+        self.audit(Operation::CreateCollection, Some(&name), 0, &result);
+        result
+    }
+
+    async fn post_create_collection(
+        &self,
+        request_body: CreateCollectionRequest,
+    ) -> Result<Collection, ChromaClientError> {
+        self.check_pre_flight_status().await?;
+        if request_body.schema.is_some() {
+            self.server_version().await?.require("1.0.0")?;
+        }
+        let url = self.get_url_with_params("api/v1/collections")?;
+
+        let response = self.send(self.client.post(url).json(&request_body)).await?;
+
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response
+            .bytes()
             .await
-            .map_err(|e| ChromaClientError::ResponseError(e))?;
+            .map_err(ChromaClientError::ResponseError)?;
+
+        if !status.is_success() {
+            let message = String::from_utf8_lossy(&body);
+            if status == StatusCode::CONFLICT || message.contains("already exists") {
+                return Err(ChromaClientError::CollectionAlreadyExists(
+                    request_body.name,
+                ));
+            }
+            let context = "Failed to create collection with status code: ";
+            let error = ApiError::new(context, status, &headers, &body);
+            return Err(ChromaClientError::ResponseStatusError(error));
+        }
 
-        let response_json: CreateCollectionResponse = serde_json::from_str(&response_text)
-            .map_err(|e| ChromaClientError::ResponseParseError(e))?;
+        let response_json: CreateCollectionResponse =
+            serde_json::from_slice(&body).map_err(ChromaClientError::ResponseParseError)?;
 
-        Ok(Collection {
-            name: response_json.name,
-            id: response_json.id,
-            metadata: response_json.metadata,
-        })
+        Ok(Collection::with_id(
+            response_json.name,
+            response_json.id,
+            response_json.metadata,
+        ))
     }
 
     /// Delete a collection with the given name.
     pub async fn delete_collection(&self, name: &str) -> Result<(), ChromaClientError> {
+        self.check_operation(Operation::DeleteCollection)?;
+        let result = self.send_delete_collection(name).await;
+        self.audit(Operation::DeleteCollection, Some(name), 0, &result);
+        result
+    }
+
+    async fn send_delete_collection(&self, name: &str) -> Result<(), ChromaClientError> {
         self.check_pre_flight_status().await?;
         let url = self.get_url_with_params(&format!("api/v1/collections/{}", name))?;
 
-        let mut headers = self.headers.clone();
-        headers.insert(CONTENT_TYPE, "application/json".parse().unwrap());
-
-        let response = self
-            .client
-            .delete(url)
-            .headers(headers)
-            .send()
-            .await
-            .map_err(ChromaClientError::RequestError)?;
+        let response = self.send(self.client.delete(url)).await?;
 
         if response.status().is_success() {
             Ok(())
         } else {
-            let error_message = format!(
-                "Failed to delete collection with status code: {}",
-                response.status()
-            );
-            Err(ChromaClientError::ResponseStatusError(error_message))
+            let context = "Failed to delete collection with status code: ";
+            let error = ApiError::from_response(context, response).await;
+            Err(ChromaClientError::ResponseStatusError(error))
         }
     }
 
     /// List all collections.
     pub async fn list_collections(&self) -> Result<Vec<Collection>, ChromaClientError> {
+        self.check_operation(Operation::Read)?;
         self.check_pre_flight_status().await?;
         let url = self.get_url_with_params("api/v1/collections")?;
 
-        let response = self
-            .client
-            .get(url)
-            .headers(self.headers.clone())
-            .send()
-            .await
-            .map_err(ChromaClientError::RequestError)?;
+        let response = self.send(self.client.get(url)).await?;
 
         if response.status().is_success() {
-            let response_text = response
-                .text()
+            let body = response
+                .bytes()
                 .await
-                .map_err(|e| ChromaClientError::ResponseError(e))?;
+                .map_err(ChromaClientError::ResponseError)?;
 
-            let response_json: ListCollectionsResponse = serde_json::from_str(&response_text)
-                .map_err(|e| ChromaClientError::ResponseParseError(e))?;
+            let response_json: ListCollectionsResponse =
+                serde_json::from_slice(&body).map_err(ChromaClientError::ResponseParseError)?;
 
             Ok(response_json)
         } else {
-            let error_message = format!(
-                "Failed to list collections with status code: {}",
-                response.status()
-            );
-            Err(ChromaClientError::ResponseStatusError(error_message))
+            let context = "Failed to list collections with status code: ";
+            let error = ApiError::from_response(context, response).await;
+            Err(ChromaClientError::ResponseStatusError(error))
         }
     }
 
-    /// Resets the database. This will delete all collections and entries.
-    pub async fn reset(&self) -> Result<(), ChromaClientError> {
+    /// Resets the database. This will delete all collections and entries, see
+    /// `reset_with_backup` to export them first.
+    pub async fn reset(&self, _confirm: DangerZone) -> Result<(), ChromaClientError> {
+        self.check_operation(Operation::Reset)?;
+        let result = self.send_reset().await;
+        self.audit(Operation::Reset, None, 0, &result);
+        result
+    }
+
+    async fn send_reset(&self) -> Result<(), ChromaClientError> {
         self.check_pre_flight_status().await?;
         let url = self.get_url("api/v1/reset")?;
 
-        let response = self
-            .client
-            .post(url)
-            .headers(self.headers.clone())
-            .send()
-            .await
-            .map_err(ChromaClientError::RequestError)?;
+        let response = self.send(self.client.post(url)).await?;
 
         if response.status().is_success() {
             Ok(())
         } else {
-            let error_message = format!(
-                "Failed to reset with status code: {} - make sure `ALLOW_RESET=TRUE`",
-                response.status()
-            );
-            Err(ChromaClientError::ResponseStatusError(error_message))
+            let context = "Failed to reset, make sure `ALLOW_RESET=TRUE`, status code: ";
+            let error = ApiError::from_response(context, response).await;
+            Err(ChromaClientError::ResponseStatusError(error))
         }
     }
 
@@ -286,30 +992,200 @@ impl ChromaClient {
         self.check_pre_flight_status().await?;
         let url = self.get_url("api/v1/version")?;
 
-        let res = self
-            .client
-            .get(url)
-            .headers(self.headers.clone())
-            .send()
-            .await
-            .map_err(|e| ChromaClientError::RequestError(e))?;
+        let res = self.send(self.client.get(url)).await?;
 
-        let res_text = res
-            .text()
-            .await
-            .map_err(|e| ChromaClientError::ResponseError(e))?;
+        if res.status().is_success() {
+            let res_text = res.text().await.map_err(ChromaClientError::ResponseError)?;
+
+            Ok(res_text)
+        } else {
+            let context = "Failed to get version with status code: ";
+            let error = ApiError::from_response(context, res).await;
+            Err(ChromaClientError::ResponseStatusError(error))
+        }
+    }
+}
+
+/// Reads the chunks received on a channel, end of input once it is closed.
+struct ChunkReader<T> {
+    rx: mpsc::Receiver<T>,
+    chunk: Option<T>,
+    position: usize,
+}
+
+impl<T> ChunkReader<T> {
+    fn new(rx: mpsc::Receiver<T>) -> Self {
+        ChunkReader {
+            rx,
+            chunk: None,
+            position: 0,
+        }
+    }
+}
+
+impl<T: AsRef<[u8]>> Read for ChunkReader<T> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            if let Some(chunk) = &self.chunk {
+                let remaining = &chunk.as_ref()[self.position..];
+                if !remaining.is_empty() {
+                    let n = remaining.len().min(buf.len());
+                    buf[..n].copy_from_slice(&remaining[..n]);
+                    self.position += n;
+                    return Ok(n);
+                }
+            }
+            match self.rx.blocking_recv() {
+                Some(chunk) => {
+                    self.chunk = Some(chunk);
+                    self.position = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+    }
+}
+
+/// Confirmation required by operations that cannot be undone, such as
+/// `ChromaClient::reset`.
+#[derive(Debug, Clone, Copy)]
+pub struct DangerZone(());
 
-        Ok(res_text)
+impl DangerZone {
+    /// Confirm that the data touched by the operation may be lost for good.
+    pub fn i_understand_data_will_be_lost() -> Self {
+        DangerZone(())
     }
 }
 
-/// The parameters to create a new client.
+impl fmt::Debug for ChromaClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChromaClient")
+            .field("path", &self.path)
+            .field("headers", &redact_headers(&self.headers))
+            .field("tenant", &self.tenant)
+            .field("database", &self.database)
+            .field("embedding_encoding", &self.embedding_encoding)
+            .field("preflight", &self.preflight)
+            .field("read_only", &self.read_only)
+            .field("priority", &self.priority)
+            .field("policy", &self.policy)
+            .field("audit", &self.audit)
+            .field("server", &self.server.get())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A client whose server was reached, from `ChromaClient::connect`. Derefs
+/// to `ChromaClient`, so it can be passed to every method taking one, and
+/// its requests do not run the version detection and pre-flight checks
+/// again.
+#[derive(Debug, Clone)]
+pub struct ConnectedClient {
+    client: ChromaClient,
+    server: ServerVersion,
+    max_batch_size: Option<usize>,
+}
+
+impl ConnectedClient {
+    pub fn server(&self) -> &ServerVersion {
+        &self.server
+    }
+
+    /// Most records the server accepts in one write, `None` when it does not
+    /// report it or the pre-flight checks are disabled. Same as the
+    /// `max_batch_size` of the client, without awaiting.
+    pub fn batch_size_limit(&self) -> Option<usize> {
+        self.max_batch_size
+    }
+
+    pub fn into_inner(self) -> ChromaClient {
+        self.client
+    }
+}
+
+impl std::ops::Deref for ConnectedClient {
+    type Target = ChromaClient;
+
+    fn deref(&self) -> &ChromaClient {
+        &self.client
+    }
+}
+
+/// Configuration of a client, from `ChromaClient::redacted_config`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RedactedConfig {
+    pub url: String,
+    pub tenant: String,
+    pub database: String,
+    /// Default headers, secret values replaced by `<redacted>`.
+    pub headers: BTreeMap<String, String>,
+    pub embedding_encoding: String,
+    pub read_only: bool,
+}
+
+fn redact_headers(headers: &HeaderMap) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if value.is_sensitive() || SECRET_HEADERS.contains(&name.as_str()) {
+                REDACTED.to_string()
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).into_owned()
+            };
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
+/// The parameters to create a new client. New fields may be added: start
+/// from `ChromaClientParams::default()` and set the fields needed.
+#[non_exhaustive]
 pub struct ChromaClientParams {
     pub host: String,
     pub port: String,
     pub ssl: bool,
     pub headers: Option<HeaderMap>,
     pub settings: Option<Settings>,
+    /// Appended to the `User-Agent`, as in `chroma-rs/0.1.8 my-app`, and
+    /// sent as the `X-Chroma-Client` header.
+    pub app_name: Option<String>,
+    /// Version appended to `app_name`, as in `my-app/1.2.0`.
+    pub app_version: Option<String>,
+    /// How embeddings are sent, only change it for servers accepting base64.
+    pub embedding_encoding: EmbeddingEncoding,
+    /// Talk HTTP/2 from the start, multiplexing concurrent requests over one
+    /// connection. The server must accept HTTP/2 without upgrade.
+    pub http2_prior_knowledge: bool,
+    /// Interval of the TCP and HTTP/2 keep-alive pings.
+    pub keep_alive_interval: Option<Duration>,
+    /// When to call the pre-flight checks endpoint.
+    pub preflight: PreflightMode,
+    /// Most requests sent at once by the client and its clones, the others
+    /// waiting for a slot. Unbounded when `None`.
+    pub max_in_flight_requests: Option<usize>,
+    /// Version of the server, e.g. `0.5.5`, to skip its detection.
+    pub server_version: Option<String>,
+}
+
+impl fmt::Debug for ChromaClientParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChromaClientParams")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("ssl", &self.ssl)
+            .field("headers", &self.headers.as_ref().map(redact_headers))
+            .field("settings", &self.settings)
+            .field("app_name", &self.app_name)
+            .field("app_version", &self.app_version)
+            .field("embedding_encoding", &self.embedding_encoding)
+            .field("http2_prior_knowledge", &self.http2_prior_knowledge)
+            .field("keep_alive_interval", &self.keep_alive_interval)
+            .field("preflight", &self.preflight)
+            .field("max_in_flight_requests", &self.max_in_flight_requests)
+            .field("server_version", &self.server_version)
+            .finish()
+    }
 }
 
 impl Default for ChromaClientParams {
@@ -320,11 +1196,37 @@ impl Default for ChromaClientParams {
             ssl: false,
             headers: None,
             settings: Some(Settings::default()),
+            app_name: None,
+            app_version: None,
+            embedding_encoding: EmbeddingEncoding::Json,
+            http2_prior_knowledge: false,
+            keep_alive_interval: None,
+            preflight: PreflightMode::Always,
+            max_in_flight_requests: None,
+            server_version: None,
         }
     }
 }
 
+/// Identification of the application, `name/version` or `name`.
+fn app_id(name: Option<&str>, version: Option<&str>) -> Option<String> {
+    match (name?, version) {
+        (name, Some(version)) => Some(format!("{}/{}", name, version)),
+        (name, None) => Some(name.to_string()),
+    }
+}
+
+fn user_agent(app_name: Option<&str>) -> HeaderValue {
+    let default = concat!("chroma-rs/", env!("CARGO_PKG_VERSION"));
+    match app_name {
+        Some(app) => HeaderValue::from_str(&format!("{} {}", default, app))
+            .unwrap_or(HeaderValue::from_static(default)),
+        None => HeaderValue::from_static(default),
+    }
+}
+
 /// The settings for a client.
+#[derive(Debug, Clone)]
 pub struct Settings {
     pub tenant: String,
     pub database: String,
@@ -333,8 +1235,8 @@ pub struct Settings {
 impl Default for Settings {
     fn default() -> Self {
         Settings {
-            tenant: String::from("default_tenant"),
-            database: String::from("default_database"),
+            tenant: String::from(DEFAULT_TENANT),
+            database: String::from(DEFAULT_DATABASE),
         }
     }
 }
@@ -345,10 +1247,18 @@ struct HeartbeatResponse {
     nanosecond_heartbeat: u64,
 }
 
-#[derive(Serialize, Deserialize)]
+fn string_metadata(
+    metadata: Option<HashMap<String, String>>,
+) -> Option<serde_json::Map<String, Value>> {
+    metadata.map(|m| m.into_iter().map(|(k, v)| (k, Value::String(v))).collect())
+}
+
+#[derive(Serialize)]
 struct CreateCollectionRequest {
     name: String,
-    metadata: Option<HashMap<String, String>>,
+    metadata: Option<serde_json::Map<String, Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    schema: Option<Schema>,
     get_or_create: bool,
 }
 
@@ -357,7 +1267,10 @@ struct CreateCollectionResponse {
     name: String,
     id: String,
     metadata: Option<Value>,
+    // Not returned by servers older than 0.4.15
+    #[serde(default)]
     tenant: String,
+    #[serde(default)]
     database: String,
 }
 
@@ -368,9 +1281,356 @@ type ListCollectionsResponse = Vec<Collection>;
 mod tests {
     use super::*;
 
+    /// Client for the tests talking to a server: a fresh container with the
+    /// `testcontainers` feature, the server on localhost otherwise.
+    #[cfg(feature = "testcontainers")]
+    async fn live_client() -> (ChromaClient, crate::testing::ChromaContainer) {
+        let server = crate::testing::ChromaContainer::start().await.unwrap();
+        (server.client().clone(), server)
+    }
+
+    #[cfg(not(feature = "testcontainers"))]
+    async fn live_client() -> (ChromaClient, ()) {
+        (ChromaClient::new(ChromaClientParams::default()), ())
+    }
+
     #[tokio::test]
-    async fn heartbeat() {
+    async fn parse_chunked_body() {
+        let (tx, rx) = mpsc::channel(4);
+        let parser = tokio::task::spawn_blocking(move || {
+            serde_json::from_reader::<_, Value>(ChunkReader::new(rx))
+        });
+
+        for chunk in [&b"{\"ids\": [\"a\", "[..], b"\"b\"]", b"", b"}"] {
+            tx.send(chunk).await.unwrap();
+        }
+        drop(tx);
+
+        let value = parser.await.unwrap().unwrap();
+        assert_eq!(value, serde_json::json!({ "ids": ["a", "b"] }));
+    }
+
+    #[test]
+    fn user_agent_with_app_name() {
+        let version = env!("CARGO_PKG_VERSION");
+
+        assert_eq!(user_agent(None), format!("chroma-rs/{}", version).as_str());
+        assert_eq!(
+            user_agent(Some("my-app")),
+            format!("chroma-rs/{} my-app", version).as_str()
+        );
+        assert_eq!(
+            app_id(Some("my-app"), Some("1.2.0")).as_deref(),
+            Some("my-app/1.2.0")
+        );
+        assert_eq!(app_id(None, Some("1.2.0")), None);
+    }
+
+    #[test]
+    fn body_buffer_is_reused() {
+        let client = ChromaClient::new(ChromaClientParams::default());
+
+        let first = client.serialize_body(&vec![0.5f32; 1024]).unwrap();
+        let allocation = first.as_ptr();
+        drop(first);
+        let second = client.serialize_body(&vec![0.5f32; 1024]).unwrap();
+
+        assert_eq!(second.as_ptr(), allocation);
+        assert!(serde_json::from_slice::<Vec<f32>>(&second).is_ok());
+    }
+
+    #[tokio::test]
+    #[ignore = "needs a Chroma server accepting HTTP/2"]
+    async fn http2_small_queries() {
+        for http2_prior_knowledge in [false, true] {
+            let client = ChromaClient::new(ChromaClientParams {
+                http2_prior_knowledge,
+                keep_alive_interval: Some(Duration::from_secs(30)),
+                ..Default::default()
+            });
+
+            let requests = (0..500).map(|_| client.heartbeat());
+            for result in futures_util::future::join_all(requests).await {
+                result.unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn read_only_client() {
+        let client = ChromaClient::new(ChromaClientParams::default()).read_only(true);
+        let collection = Collection::with_id("docs".into(), "id".into(), None);
+
+        let added = collection.add(&client, Default::default()).await;
+        let reset = client
+            .reset(DangerZone::i_understand_data_will_be_lost())
+            .await;
+
+        assert!(matches!(added, Err(ChromaClientError::ReadOnlyMode)));
+        assert!(matches!(reset, Err(ChromaClientError::ReadOnlyMode)));
+    }
+
+    #[tokio::test]
+    async fn construction_sends_nothing() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = ChromaClient::new(ChromaClientParams {
+            host: String::from("127.0.0.1"),
+            port: listener.local_addr().unwrap().port().to_string(),
+            ..Default::default()
+        })
+        .read_only(true);
+        let _ = client.clone().redacted_config();
+
+        let accepted = tokio::time::timeout(Duration::from_millis(200), listener.accept()).await;
+        assert!(accepted.is_err(), "the client opened a connection");
+
+        let connecting = tokio::spawn(client.connect());
+        let accepted = tokio::time::timeout(Duration::from_secs(5), listener.accept()).await;
+        assert!(accepted.is_ok(), "connect did not reach the server");
+        connecting.abort();
+    }
+
+    #[tokio::test]
+    async fn shutdown_refuses_new_requests() {
         let client = ChromaClient::new(ChromaClientParams::default());
+        let clone = client.clone();
+        let in_flight = InFlight::start(&client.lifecycle).unwrap();
+
+        let shutdown = tokio::spawn(async move { clone.shutdown(Duration::from_secs(5)).await });
+        tokio::task::yield_now().await;
+        assert!(client.is_shut_down());
+        assert!(matches!(
+            client.heartbeat().await,
+            Err(ChromaClientError::ShutDown)
+        ));
+
+        drop(in_flight);
+        assert!(shutdown.await.unwrap());
+        assert!(client.shutdown(Duration::ZERO).await);
+    }
+
+    #[tokio::test]
+    async fn shutdown_waits_for_response_bodies() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = ChromaClient::new(ChromaClientParams {
+            host: String::from("127.0.0.1"),
+            port: listener.local_addr().unwrap().port().to_string(),
+            preflight: PreflightMode::Never,
+            ..Default::default()
+        });
+        client.server.set(ServerVersion::parse("0.5.5")).unwrap();
+
+        let request = client.clone();
+        let count = tokio::spawn(async move {
+            request
+                .get_json::<usize>("api/v1/collections/c0ffee/count")
+                .await
+        });
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buffer = [0; 1024];
+        let _ = socket.read(&mut buffer).await.unwrap();
+        socket
+            .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\n")
+            .await
+            .unwrap();
+
+        // The headers arrived, the body did not.
+        assert!(!client.shutdown(Duration::from_millis(200)).await);
+        socket.write_all(b"42").await.unwrap();
+        assert_eq!(count.await.unwrap().unwrap(), 42);
+        assert!(client.shutdown(Duration::ZERO).await);
+    }
+
+    #[tokio::test]
+    async fn queue_wait_metrics_and_priorities() {
+        let limiter = Arc::new(Limiter::new(1));
+        let first = limiter.acquire(Priority::Normal).await;
+
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let mut waiters = Vec::new();
+        for priority in [Priority::Background, Priority::Interactive] {
+            let (limiter, order) = (limiter.clone(), order.clone());
+            waiters.push(tokio::spawn(async move {
+                let _permit = limiter.acquire(priority).await;
+                order.lock().unwrap().push(priority);
+            }));
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(limiter.metrics().available, 0);
+        drop(first);
+        for waiter in waiters {
+            waiter.await.unwrap();
+        }
+
+        assert_eq!(
+            *order.lock().unwrap(),
+            [Priority::Interactive, Priority::Background]
+        );
+        let metrics = limiter.metrics();
+        assert_eq!((metrics.requests, metrics.available), (3, 1));
+        assert!(metrics.max_wait >= Duration::from_millis(20));
+    }
+
+    #[test]
+    fn secrets_are_redacted() {
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Chroma-Token", "secret-token".parse().unwrap());
+        headers.insert("Authorization", "Bearer secret-key".parse().unwrap());
+        let params = ChromaClientParams {
+            headers: Some(headers),
+            ..Default::default()
+        };
+        let params_debug = format!("{:?}", params);
+
+        let client = ChromaClient::new(params);
+        let config = client.redacted_config();
+
+        assert!(!params_debug.contains("secret"));
+        assert!(!format!("{:?}", client).contains("secret"));
+        assert_eq!(config.headers["x-chroma-token"], "<redacted>");
+        assert_eq!(config.headers["authorization"], "<redacted>");
+        assert_eq!(config.headers["accept"], "application/json");
+    }
+
+    #[tokio::test]
+    async fn audited_calls() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        let client = ChromaClient::new(ChromaClientParams {
+            port: "1".into(),
+            ..Default::default()
+        })
+        .with_audit(move |event: &AuditEvent| recorded.lock().unwrap().push(event.clone()))
+        .with_audit_context("job-7");
+        let collection = Collection::with_id("docs".into(), "id".into(), None);
+        let entries = crate::collection::Entries {
+            ids: vec!["a".into(), "b".into()],
+            ..Default::default()
+        };
+
+        assert!(collection.upsert(&client, entries).await.is_err());
+        assert!(collection.get(&client, Default::default()).await.is_err());
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].operation, Operation::Write);
+        assert_eq!(events[0].collection.as_deref(), Some("docs"));
+        assert_eq!(events[0].ids, 2);
+        assert_eq!(events[0].context.as_deref(), Some("job-7"));
+        assert!(!events[0].succeeded);
+    }
+
+    #[tokio::test]
+    async fn denied_operations() {
+        let policy = OperationPolicy::read_only().allow(Operation::Write);
+        let client = ChromaClient::new(ChromaClientParams::default()).policy(policy);
+        let collection = Collection::with_id("docs".into(), "id".into(), None);
+
+        let deleted = collection.delete(&client, Default::default()).await;
+
+        assert!(matches!(deleted, Err(ChromaClientError::OperationDenied(op)) if op == "delete"));
+        assert!(client.check_operation(Operation::Write).is_ok());
+    }
+
+    #[tokio::test]
+    #[cfg_attr(
+        not(feature = "testcontainers"),
+        ignore = "needs a running Chroma server"
+    )]
+    async fn create_existing_collection() {
+        let (client, _server) = live_client().await;
+        let name = "create-existing-collection";
+        client.create_collection_strict(name, None).await.unwrap();
+
+        let result = client.create_collection_strict(name, None).await;
+        client.delete_collection(name).await.unwrap();
+
+        assert!(matches!(result, Err(ChromaClientError::CollectionAlreadyExists(n)) if n == name));
+    }
+
+    #[tokio::test]
+    #[cfg_attr(
+        not(feature = "testcontainers"),
+        ignore = "needs a running Chroma server"
+    )]
+    async fn get_or_create_existing_collection() {
+        let (client, _server) = live_client().await;
+        let name = "get-or-create-existing-collection";
+        let created = client.create_collection_strict(name, None).await.unwrap();
+
+        let existing = client.get_or_create_collection(name, None).await;
+        client.delete_collection(name).await.unwrap();
+
+        assert_eq!(existing.unwrap().id, created.id);
+    }
+
+    #[tokio::test]
+    #[cfg_attr(
+        not(feature = "testcontainers"),
+        ignore = "needs a running Chroma server"
+    )]
+    async fn int_and_float_metadata_filters() {
+        use crate::collection::{Entries, GetParams, Metadata};
+        use crate::filter::Where;
+        use crate::metadata::{MetadataField, MetadataValue};
+
+        let (client, _server) = live_client().await;
+        let name = "int-and-float-metadata-filters";
+        let collection = client.get_or_create_collection(name, None).await.unwrap();
+        let values = [
+            MetadataValue::Int(4),
+            MetadataValue::Int(5),
+            MetadataValue::Float(5.0),
+            MetadataValue::Float(5.5),
+        ];
+        let entries = Entries {
+            ids: (0..values.len()).map(|i| i.to_string()).collect(),
+            embeddings: Some(vec![vec![0.0, 1.0]; values.len()]),
+            metadatas: Some(
+                values
+                    .iter()
+                    .map(|v| Metadata::from_iter([("n".to_string(), v.clone().into())]))
+                    .collect(),
+            ),
+            documents: None,
+        };
+        collection.upsert(&client, entries).await.unwrap();
+
+        let matching = |value: MetadataValue| {
+            let params = GetParams {
+                where_metadata: Some(Where::gt("n", value).into()),
+                ..Default::default()
+            };
+            let collection = collection.clone();
+            let client = client.clone();
+            async move {
+                let result = collection.get(&client, params).await.unwrap();
+                let mut ids = result.ids;
+                ids.sort();
+                ids
+            }
+        };
+        let above_int = matching(MetadataValue::Int(4)).await;
+        let above_float = matching(MetadataValue::Float(5.0)).await;
+        let stored = collection.get(&client, GetParams::default()).await;
+        client.delete_collection(name).await.unwrap();
+
+        assert!(above_int.contains(&"1".to_string()));
+        assert!(!above_int.contains(&"0".to_string()));
+        assert!(above_float.contains(&"3".to_string()));
+        assert!(!above_float.contains(&"2".to_string()));
+        let stored = stored.unwrap();
+        for (id, metadata) in stored.ids.iter().zip(stored.metadatas.unwrap()) {
+            let value = MetadataValue::from_value(metadata.as_ref().and_then(|m| m.get("n")));
+            assert_eq!(value.as_ref(), values.get(id.parse::<usize>().unwrap()));
+        }
+    }
+
+    #[tokio::test]
+    async fn heartbeat() {
+        let (client, _server) = live_client().await;
 
         let default: u64 = 0;
         let hb = match client.heartbeat().await {
@@ -390,13 +1650,9 @@ mod tests {
 
     #[tokio::test]
     async fn create_and_delete() {
-        let client = ChromaClient::new(ChromaClientParams::default());
+        let (client, _server) = live_client().await;
 
-        let default = Collection {
-            name: "default-collection".into(),
-            id: "null".into(),
-            metadata: None,
-        };
+        let default = Collection::with_id("default-collection".into(), "null".into(), None);
 
         let new_collection = match client.create_collection("john-doe-collection", None).await {
             Ok(new_collection) => new_collection,
@@ -425,13 +1681,9 @@ mod tests {
 
     #[tokio::test]
     async fn get_or_create_and_delete() {
-        let client = ChromaClient::new(ChromaClientParams::default());
+        let (client, _server) = live_client().await;
 
-        let default = Collection {
-            name: "default-collection".into(),
-            id: "null".into(),
-            metadata: None,
-        };
+        let default = Collection::with_id("default-collection".into(), "null".into(), None);
 
         let new_collection = match client
             .get_or_create_collection("john-doe-g-or-c-collection", None)
@@ -1,23 +1,798 @@
+use crate::client::ChromaClient;
+use crate::error::ChromaClientError;
+use crate::filter::Where;
+use crate::generated::{
+    AddEmbedding, DeleteEmbedding, GetEmbedding, QueryEmbedding, UpdateEmbedding,
+};
+use crate::policy::Operation;
+use crate::vectors::DimensionsChecked;
+use futures_util::stream::{self, Stream, StreamExt};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Metadata attached to a record.
+pub type Metadata = serde_json::Map<String, Value>;
+
+/// Metadata key used to flag soft-deleted records.
+pub const DELETED_AT_KEY: &str = "deleted_at";
+
+/// Query embeddings sent per request by `Collection::query_many`.
+const QUERIES_PER_REQUEST: usize = 100;
+
+/// Records read or written per request when going through a whole
+/// collection, unless the caller picks a batch size.
+pub(crate) const PAGE_SIZE: usize = 500;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Collection {
     pub name: String,
     pub id: String,
     pub metadata: Option<Value>,
+    #[serde(skip)]
+    pub(crate) dimensions_checked: DimensionsChecked,
 }
 
 impl Collection {
     pub fn new(name: String, metadata: Option<Value>) -> Self {
+        Collection::with_id(name, String::new(), metadata)
+    }
+
+    pub fn with_id(name: String, id: String, metadata: Option<Value>) -> Self {
         Collection {
             name,
-            id: String::new(),
+            id,
             metadata,
+            dimensions_checked: DimensionsChecked::default(),
         }
     }
 
-    pub fn with_id(name: String, id: String, metadata: Option<Value>) -> Self {
-        Collection { name, id, metadata }
+    /// Get records from the collection.
+    pub async fn get(
+        &self,
+        client: &ChromaClient,
+        params: GetParams,
+    ) -> Result<GetResult, ChromaClientError> {
+        client.check_operation(Operation::Read)?;
+        client
+            .post_json(
+                &format!("api/v1/collections/{}/get", self.id),
+                &GetEmbedding::from(params),
+            )
+            .await
+    }
+
+    /// Number of records in the collection.
+    pub async fn count(&self, client: &ChromaClient) -> Result<usize, ChromaClientError> {
+        client.check_operation(Operation::Read)?;
+        client
+            .get_json(&format!("api/v1/collections/{}/count", self.id))
+            .await
+    }
+
+    /// Add new records to the collection.
+    pub async fn add(
+        &self,
+        client: &ChromaClient,
+        mut entries: Entries,
+    ) -> Result<(), ChromaClientError> {
+        client.check_operation(Operation::Write)?;
+        if let Some(embeddings) = entries.embeddings.as_mut() {
+            self.prepare_embeddings(client, embeddings).await?;
+        }
+        let records = entries.ids.len();
+        let result = match client.check_batch_size(records).await {
+            Ok(()) => client
+                .post_json::<_, Value>(
+                    &format!("api/v1/collections/{}/add", self.id),
+                    &AddEmbedding::from(entries),
+                )
+                .await
+                .map(|_| ()),
+            Err(e) => Err(e),
+        };
+        client.audit(Operation::Write, Some(&self.name), records, &result);
+        result
+    }
+
+    /// Update existing records of the collection.
+    pub async fn update(
+        &self,
+        client: &ChromaClient,
+        mut entries: Entries,
+    ) -> Result<(), ChromaClientError> {
+        client.check_operation(Operation::Write)?;
+        if let Some(embeddings) = entries.embeddings.as_mut() {
+            self.prepare_embeddings(client, embeddings).await?;
+        }
+        let records = entries.ids.len();
+        let result = match client.check_batch_size(records).await {
+            Ok(()) => client
+                .post_json::<_, Value>(
+                    &format!("api/v1/collections/{}/update", self.id),
+                    &UpdateEmbedding::from(entries),
+                )
+                .await
+                .map(|_| ()),
+            Err(e) => Err(e),
+        };
+        client.audit(Operation::Write, Some(&self.name), records, &result);
+        result
+    }
+
+    /// Update the records whose current metadata matches the precondition.
+    /// Records are fetched, checked, and then updated, so a concurrent write
+    /// between the two steps is not detected. Returns the ids that were
+    /// skipped, either because the precondition failed or the record is missing.
+    pub async fn update_if<F>(
+        &self,
+        client: &ChromaClient,
+        mut entries: Entries,
+        precondition: F,
+    ) -> Result<Vec<String>, ChromaClientError>
+    where
+        F: Fn(&Metadata) -> bool,
+    {
+        let current = self
+            .get(
+                client,
+                GetParams {
+                    ids: Some(entries.ids.clone()),
+                    include: Some(vec![Include::Metadatas]),
+                    ..Default::default()
+                },
+            )
+            .await?;
+
+        let matching: Vec<&str> = current
+            .ids
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| {
+                let empty = Metadata::new();
+                precondition(metadata_at(&current.metadatas, *i).unwrap_or(&empty))
+            })
+            .map(|(_, id)| id.as_str())
+            .collect();
+
+        let mask: Vec<bool> = entries
+            .ids
+            .iter()
+            .map(|id| matching.contains(&id.as_str()))
+            .collect();
+        let skipped = entries
+            .ids
+            .iter()
+            .zip(&mask)
+            .filter(|(_, keep)| !**keep)
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        entries.retain_by_mask(&mask);
+        if !entries.ids.is_empty() {
+            self.update(client, entries).await?;
+        }
+
+        Ok(skipped)
+    }
+
+    /// Insert new records or update the existing ones.
+    pub async fn upsert(
+        &self,
+        client: &ChromaClient,
+        mut entries: Entries,
+    ) -> Result<(), ChromaClientError> {
+        client.check_operation(Operation::Write)?;
+        if let Some(embeddings) = entries.embeddings.as_mut() {
+            self.prepare_embeddings(client, embeddings).await?;
+        }
+        let records = entries.ids.len();
+        let result = match client.check_batch_size(records).await {
+            Ok(()) => client
+                .post_json::<_, Value>(
+                    &format!("api/v1/collections/{}/upsert", self.id),
+                    &UpdateEmbedding::from(entries),
+                )
+                .await
+                .map(|_| ()),
+            Err(e) => Err(e),
+        };
+        client.audit(Operation::Write, Some(&self.name), records, &result);
+        result
+    }
+
+    /// Same as `upsert`, split into requests of at most the maximum batch
+    /// size of the server.
+    pub async fn upsert_batched(
+        &self,
+        client: &ChromaClient,
+        entries: Entries,
+    ) -> Result<(), ChromaClientError> {
+        let size = client.max_batch_size().await?.unwrap_or(usize::MAX);
+        for batch in entries.into_batches(size) {
+            self.upsert(client, batch).await?;
+        }
+        Ok(())
+    }
+
+    /// Delete the records matching the given ids and filters.
+    pub async fn delete(
+        &self,
+        client: &ChromaClient,
+        params: DeleteParams,
+    ) -> Result<(), ChromaClientError> {
+        client.check_operation(Operation::Delete)?;
+        let records = params.ids.as_ref().map_or(0, Vec::len);
+        let result = client
+            .post_json::<_, Value>(
+                &format!("api/v1/collections/{}/delete", self.id),
+                &DeleteEmbedding::from(params),
+            )
+            .await
+            .map(|_| ());
+        client.audit(Operation::Delete, Some(&self.name), records, &result);
+        result
+    }
+
+    /// Delete the records matching a metadata filter. With `preview`, nothing
+    /// is deleted and the matching records are only listed, to confirm the
+    /// filter before running it for real. Returns the ids of the matching records.
+    pub async fn delete_where(
+        &self,
+        client: &ChromaClient,
+        filter: Where,
+        preview: bool,
+    ) -> Result<DeleteWhere, ChromaClientError> {
+        let params = GetParams {
+            where_metadata: Some(filter.into()),
+            include: Some(Vec::new()),
+            ..Default::default()
+        };
+        let ids = self.get(client, params).await?.ids;
+
+        let deleted = !preview && !ids.is_empty();
+        if deleted {
+            let params = DeleteParams {
+                ids: Some(ids.clone()),
+                ..Default::default()
+            };
+            self.delete(client, params).await?;
+        }
+        Ok(DeleteWhere { ids, deleted })
+    }
+
+    /// Query the nearest neighbors of the given embeddings.
+    pub async fn query(
+        &self,
+        client: &ChromaClient,
+        mut params: QueryParams,
+    ) -> Result<QueryResult, ChromaClientError> {
+        client.check_operation(Operation::Read)?;
+        self.prepare_embeddings(client, &mut params.query_embeddings)
+            .await?;
+        client
+            .post_json(
+                &format!("api/v1/collections/{}/query", self.id),
+                &QueryEmbedding::from(params),
+            )
+            .await
+    }
+
+    /// Query the nearest neighbors of many embeddings, sending them in batches
+    /// rather than one request each. Returns one result per embedding.
+    pub async fn query_many(
+        &self,
+        client: &ChromaClient,
+        embeddings: Vec<Vec<f32>>,
+        n_results: usize,
+    ) -> Result<Vec<QueryResult>, ChromaClientError> {
+        let mut results = Vec::with_capacity(embeddings.len());
+        for batch in embeddings.chunks(QUERIES_PER_REQUEST) {
+            let params = QueryParams {
+                query_embeddings: batch.to_vec(),
+                n_results,
+                ..Default::default()
+            };
+            results.extend(self.query(client, params).await?.split());
+        }
+        Ok(results)
+    }
+
+    /// Read the records matching `params`, `page_size` per request, from
+    /// `params.offset` on and up to `params.limit` records when set. Pages
+    /// are read by offset: records added or deleted meanwhile may be missed
+    /// or read twice.
+    pub fn pages<'a>(
+        &'a self,
+        client: &'a ChromaClient,
+        params: GetParams,
+        page_size: usize,
+    ) -> GetPages<'a> {
+        GetPages {
+            collection: self,
+            client,
+            offset: params.offset.unwrap_or(0),
+            remaining: params.limit.unwrap_or(usize::MAX),
+            page_size: page_size.max(1),
+            params,
+        }
+    }
+
+    /// Run independent queries, at most `concurrency` at a time, yielding
+    /// their results in the order of `queries`. A failed query yields its
+    /// error without stopping the others.
+    pub fn query_stream<'a, I>(
+        &'a self,
+        client: &'a ChromaClient,
+        queries: I,
+        concurrency: usize,
+    ) -> impl Stream<Item = Result<QueryResult, ChromaClientError>> + 'a
+    where
+        I: IntoIterator<Item = QueryParams>,
+        I::IntoIter: 'a,
+    {
+        stream::iter(queries)
+            .map(move |params| self.query(client, params))
+            .buffered(concurrency.max(1))
+    }
+
+    /// Flag the given records as deleted by setting their `deleted_at` metadata.
+    /// Records stay in the collection and can be brought back with `restore`.
+    pub async fn soft_delete(
+        &self,
+        client: &ChromaClient,
+        ids: &[&str],
+    ) -> Result<(), ChromaClientError> {
+        let deleted_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        self.set_deleted_at(client, ids, Value::from(deleted_at))
+            .await
+    }
+
+    /// Restore records previously flagged with `soft_delete`.
+    pub async fn restore(
+        &self,
+        client: &ChromaClient,
+        ids: &[&str],
+    ) -> Result<(), ChromaClientError> {
+        self.set_deleted_at(client, ids, Value::Null).await
+    }
+
+    async fn set_deleted_at(
+        &self,
+        client: &ChromaClient,
+        ids: &[&str],
+        value: Value,
+    ) -> Result<(), ChromaClientError> {
+        let metadata = Metadata::from_iter([(DELETED_AT_KEY.to_string(), value)]);
+
+        self.update(
+            client,
+            Entries {
+                ids: ids.iter().map(|id| id.to_string()).collect(),
+                metadatas: Some(vec![metadata; ids.len()]),
+                ..Default::default()
+            },
+        )
+        .await
+    }
+
+    /// Same as `get`, but soft-deleted records are left out of the result.
+    pub async fn get_live(
+        &self,
+        client: &ChromaClient,
+        mut params: GetParams,
+    ) -> Result<GetResult, ChromaClientError> {
+        params.include = with_metadatas(params.include);
+        let mut result = self.get(client, params).await?;
+        result.retain(|metadata| !is_soft_deleted(metadata));
+        Ok(result)
+    }
+
+    /// Same as `query`, but soft-deleted records are left out of the result.
+    /// Queries may then return less than `n_results` hits.
+    pub async fn query_live(
+        &self,
+        client: &ChromaClient,
+        mut params: QueryParams,
+    ) -> Result<QueryResult, ChromaClientError> {
+        params.include = with_metadatas(params.include);
+        let mut result = self.query(client, params).await?;
+        result.retain(|metadata| !is_soft_deleted(metadata));
+        Ok(result)
+    }
+}
+
+/// Pages of records returned by `Collection::pages`.
+pub struct GetPages<'a> {
+    collection: &'a Collection,
+    client: &'a ChromaClient,
+    params: GetParams,
+    page_size: usize,
+    offset: usize,
+    /// Records left to read, zero once a short page was read.
+    remaining: usize,
+}
+
+impl GetPages<'_> {
+    /// The next page, `None` once every record was read. After an error,
+    /// calling `next` again sends the same request.
+    pub async fn next(&mut self) -> Result<Option<GetResult>, ChromaClientError> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        let limit = self.page_size.min(self.remaining);
+        let params = GetParams {
+            limit: Some(limit),
+            offset: Some(self.offset),
+            ..self.params.clone()
+        };
+        let page = self.collection.get(self.client, params).await?;
+
+        let fetched = page.ids.len();
+        self.offset += fetched;
+        self.remaining = if fetched < limit {
+            0
+        } else {
+            self.remaining - fetched
+        };
+        Ok((fetched > 0).then_some(page))
+    }
+
+    /// Offset of the next page, the number of records read so far plus the
+    /// starting offset.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+/// The fields of a record the server should return.
+pub use crate::generated::IncludeEnum as Include;
+
+/// Records to write into a collection.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Entries {
+    pub ids: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub embeddings: Option<Vec<Vec<f32>>>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_metadatas"
+    )]
+    pub metadatas: Option<Vec<Metadata>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub documents: Option<Vec<String>>,
+}
+
+/// Chroma rejects empty metadata, send them as `null` instead.
+fn serialize_metadatas<S: serde::Serializer>(
+    metadatas: &Option<Vec<Metadata>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let metadatas = metadatas.as_ref().map(|metadatas| {
+        metadatas
+            .iter()
+            .map(|m| (!m.is_empty()).then_some(m))
+            .collect::<Vec<_>>()
+    });
+    metadatas.serialize(serializer)
+}
+
+impl Entries {
+    /// Split into entries of at most `size` records.
+    pub(crate) fn into_batches(self, size: usize) -> Vec<Entries> {
+        let size = size.max(1);
+        let mut batches = Vec::new();
+        let mut rest = self;
+        while rest.ids.len() > size {
+            let tail = Entries {
+                ids: rest.ids.split_off(size),
+                embeddings: rest.embeddings.as_mut().map(|e| e.split_off(size)),
+                metadatas: rest.metadatas.as_mut().map(|m| m.split_off(size)),
+                documents: rest.documents.as_mut().map(|d| d.split_off(size)),
+            };
+            batches.push(rest);
+            rest = tail;
+        }
+        batches.push(rest);
+        batches
+    }
+
+    /// Copy of the records in `range`.
+    pub(crate) fn slice(&self, range: std::ops::Range<usize>) -> Entries {
+        Entries {
+            ids: self.ids[range.clone()].to_vec(),
+            embeddings: self.embeddings.as_ref().map(|e| e[range.clone()].to_vec()),
+            metadatas: self.metadatas.as_ref().map(|m| m[range.clone()].to_vec()),
+            documents: self.documents.as_ref().map(|d| d[range].to_vec()),
+        }
+    }
+
+    fn retain_by_mask(&mut self, mask: &[bool]) {
+        retain_by_mask(&mut self.ids, mask);
+        if let Some(embeddings) = self.embeddings.as_mut() {
+            retain_by_mask(embeddings, mask);
+        }
+        if let Some(metadatas) = self.metadatas.as_mut() {
+            retain_by_mask(metadatas, mask);
+        }
+        if let Some(documents) = self.documents.as_mut() {
+            retain_by_mask(documents, mask);
+        }
+    }
+}
+
+/// The parameters to get records from a collection.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GetParams {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ids: Option<Vec<String>>,
+    #[serde(rename = "where", skip_serializing_if = "Option::is_none")]
+    pub where_metadata: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub where_document: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include: Option<Vec<Include>>,
+}
+
+/// The parameters to delete records from a collection.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DeleteParams {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ids: Option<Vec<String>>,
+    #[serde(rename = "where", skip_serializing_if = "Option::is_none")]
+    pub where_metadata: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub where_document: Option<Value>,
+}
+
+/// Records matched by `Collection::delete_where`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeleteWhere {
+    pub ids: Vec<String>,
+    /// Whether the records were deleted, false for a preview.
+    pub deleted: bool,
+}
+
+impl DeleteWhere {
+    pub fn count(&self) -> usize {
+        self.ids.len()
+    }
+}
+
+/// The parameters to query a collection.
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryParams {
+    pub query_embeddings: Vec<Vec<f32>>,
+    pub n_results: usize,
+    #[serde(rename = "where", skip_serializing_if = "Option::is_none")]
+    pub where_metadata: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub where_document: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include: Option<Vec<Include>>,
+}
+
+impl Default for QueryParams {
+    fn default() -> Self {
+        QueryParams {
+            query_embeddings: Vec::new(),
+            n_results: 10,
+            where_metadata: None,
+            where_document: None,
+            include: None,
+        }
+    }
+}
+
+/// Records returned by `Collection::get`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GetResult {
+    pub ids: Vec<String>,
+    pub embeddings: Option<Vec<Vec<f32>>>,
+    pub documents: Option<Vec<Option<String>>>,
+    pub metadatas: Option<Vec<Option<Metadata>>>,
+}
+
+impl GetResult {
+    /// Keep only the records whose metadata matches the predicate.
+    pub fn retain<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(Option<&Metadata>) -> bool,
+    {
+        let mask: Vec<bool> = (0..self.ids.len())
+            .map(|i| keep(metadata_at(&self.metadatas, i)))
+            .collect();
+
+        retain_by_mask(&mut self.ids, &mask);
+        if let Some(embeddings) = self.embeddings.as_mut() {
+            retain_by_mask(embeddings, &mask);
+        }
+        if let Some(documents) = self.documents.as_mut() {
+            retain_by_mask(documents, &mask);
+        }
+        if let Some(metadatas) = self.metadatas.as_mut() {
+            retain_by_mask(metadatas, &mask);
+        }
+    }
+}
+
+/// Nearest neighbors returned by `Collection::query`, one list per query embedding.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueryResult {
+    pub ids: Vec<Vec<String>>,
+    pub distances: Option<Vec<Vec<f32>>>,
+    pub embeddings: Option<Vec<Vec<Vec<f32>>>>,
+    pub documents: Option<Vec<Vec<Option<String>>>>,
+    pub metadatas: Option<Vec<Vec<Option<Metadata>>>>,
+}
+
+impl QueryResult {
+    /// Split a result into one result per query embedding.
+    pub fn split(self) -> Vec<QueryResult> {
+        let mut distances = self.distances.map(|d| d.into_iter());
+        let mut embeddings = self.embeddings.map(|e| e.into_iter());
+        let mut documents = self.documents.map(|d| d.into_iter());
+        let mut metadatas = self.metadatas.map(|m| m.into_iter());
+
+        self.ids
+            .into_iter()
+            .map(|ids| QueryResult {
+                ids: vec![ids],
+                distances: distances.as_mut().and_then(|d| d.next()).map(|d| vec![d]),
+                embeddings: embeddings.as_mut().and_then(|e| e.next()).map(|e| vec![e]),
+                documents: documents.as_mut().and_then(|d| d.next()).map(|d| vec![d]),
+                metadatas: metadatas.as_mut().and_then(|m| m.next()).map(|m| vec![m]),
+            })
+            .collect()
+    }
+
+    /// Keep only the hits whose metadata matches the predicate.
+    pub fn retain<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(Option<&Metadata>) -> bool,
+    {
+        for q in 0..self.ids.len() {
+            let metadatas = self.metadatas.as_ref().and_then(|m| m.get(q)).cloned();
+            let mask: Vec<bool> = (0..self.ids[q].len())
+                .map(|i| keep(metadatas.as_ref().and_then(|m| m.get(i)?.as_ref())))
+                .collect();
+
+            retain_by_mask(&mut self.ids[q], &mask);
+            if let Some(distances) = self.distances.as_mut().and_then(|d| d.get_mut(q)) {
+                retain_by_mask(distances, &mask);
+            }
+            if let Some(embeddings) = self.embeddings.as_mut().and_then(|e| e.get_mut(q)) {
+                retain_by_mask(embeddings, &mask);
+            }
+            if let Some(documents) = self.documents.as_mut().and_then(|d| d.get_mut(q)) {
+                retain_by_mask(documents, &mask);
+            }
+            if let Some(metadatas) = self.metadatas.as_mut().and_then(|m| m.get_mut(q)) {
+                retain_by_mask(metadatas, &mask);
+            }
+        }
+    }
+}
+
+fn metadata_at(metadatas: &Option<Vec<Option<Metadata>>>, index: usize) -> Option<&Metadata> {
+    metadatas.as_ref()?.get(index)?.as_ref()
+}
+
+fn retain_by_mask<T>(values: &mut Vec<T>, mask: &[bool]) {
+    let mut keep = mask.iter();
+    values.retain(|_| *keep.next().unwrap_or(&true));
+}
+
+fn with_metadatas(include: Option<Vec<Include>>) -> Option<Vec<Include>> {
+    let mut include = include?;
+    if !include.contains(&Include::Metadatas) {
+        include.push(Include::Metadatas);
+    }
+    Some(include)
+}
+
+fn is_soft_deleted(metadata: Option<&Metadata>) -> bool {
+    metadata
+        .and_then(|m| m.get(DELETED_AT_KEY))
+        .is_some_and(|v| !v.is_null())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn get_result_skips_soft_deleted() {
+        let mut result: GetResult = serde_json::from_value(json!({
+            "ids": ["a", "b", "c"],
+            "embeddings": null,
+            "documents": ["doc a", "doc b", "doc c"],
+            "metadatas": [null, {"deleted_at": 1712000000}, {"deleted_at": null}],
+        }))
+        .unwrap();
+
+        result.retain(|metadata| !is_soft_deleted(metadata));
+
+        assert_eq!(result.ids, vec!["a", "c"]);
+        assert_eq!(
+            result.documents.unwrap(),
+            vec![Some("doc a".to_string()), Some("doc c".to_string())]
+        );
+    }
+
+    #[test]
+    fn query_result_skips_soft_deleted() {
+        let mut result: QueryResult = serde_json::from_value(json!({
+            "ids": [["a", "b"], ["c"]],
+            "distances": [[0.1, 0.2], [0.3]],
+            "embeddings": null,
+            "documents": null,
+            "metadatas": [[{"deleted_at": 1712000000}, {}], [null]],
+        }))
+        .unwrap();
+
+        result.retain(|metadata| !is_soft_deleted(metadata));
+
+        assert_eq!(result.ids, vec![vec!["b"], vec!["c"]]);
+        assert_eq!(result.distances.unwrap(), vec![vec![0.2], vec![0.3]]);
+    }
+
+    #[test]
+    fn empty_metadata_is_sent_as_null() {
+        let entries = Entries {
+            ids: vec!["a".into(), "b".into()],
+            metadatas: Some(vec![
+                Metadata::new(),
+                Metadata::from_iter([("k".into(), json!(1))]),
+            ]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            serde_json::to_value(&entries).unwrap(),
+            json!({"ids": ["a", "b"], "metadatas": [null, {"k": 1}]})
+        );
+    }
+
+    #[test]
+    fn entries_into_batches() {
+        let entries = Entries {
+            ids: vec!["a".into(), "b".into(), "c".into()],
+            documents: Some(vec!["1".into(), "2".into(), "3".into()]),
+            ..Default::default()
+        };
+
+        let batches = entries.into_batches(2);
+
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].ids, vec!["a", "b"]);
+        assert_eq!(batches[1].documents, Some(vec!["3".to_string()]));
+        assert_eq!(batches[1].embeddings, None);
+    }
+
+    #[test]
+    fn split_query_result() {
+        let result: QueryResult = serde_json::from_value(json!({
+            "ids": [["a", "b"], ["c"]],
+            "distances": [[0.1, 0.2], [0.3]],
+            "documents": null
+        }))
+        .unwrap();
+
+        let results = result.split();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[1].ids, vec![vec!["c".to_string()]]);
+        assert_eq!(results[1].distances, Some(vec![vec![0.3]]));
+        assert_eq!(results[1].documents, None);
     }
 }
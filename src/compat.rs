@@ -0,0 +1,157 @@
+//! Differences between Chroma server versions. Requests are written against
+//! the `api/v1` routes and rewritten for the API of the connected server,
+//! detected once per client or set with `ChromaClientParams::server_version`.
+//! Request bodies are adapted for servers missing some of the fields.
+
+use crate::error::ChromaClientError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// First version accepting `uris` and `data`, the multimodal fields.
+const MULTIMODAL: (u64, u64, u64) = (0, 4, 22);
+
+/// Generation of the Chroma HTTP API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ServerApi {
+    /// 0.4.x, `api/v1` routes, tenants and databases only since 0.4.15.
+    V0_4,
+    /// 0.5.x and 0.6.x, `api/v1` routes.
+    V0_5,
+    /// 1.x, `api/v2` routes scoped by tenant and database.
+    V1,
+}
+
+/// Version of the connected server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerVersion {
+    /// Version reported by the server, e.g. `0.5.3`.
+    pub version: String,
+    pub api: ServerApi,
+}
+
+impl ServerVersion {
+    /// Parses the version reported by the server, quoted or not.
+    pub fn parse(version: &str) -> Self {
+        let mut server = ServerVersion {
+            version: version.trim().trim_matches('"').to_string(),
+            api: ServerApi::V1,
+        };
+        server.api = match server.numbers() {
+            (0, minor, _) if minor <= 4 => ServerApi::V0_4,
+            (0, _, _) => ServerApi::V0_5,
+            _ => ServerApi::V1,
+        };
+        server
+    }
+
+    /// Major, minor and patch numbers, missing or invalid parts being 0.
+    pub fn numbers(&self) -> (u64, u64, u64) {
+        let mut parts = self
+            .version
+            .split(['.', '-'])
+            .map(|p| p.parse().unwrap_or(0));
+        (
+            parts.next().unwrap_or(0),
+            parts.next().unwrap_or(0),
+            parts.next().unwrap_or(0),
+        )
+    }
+
+    /// Fails with `UnsupportedByServer` when older than `required`, e.g. `0.4.15`.
+    pub fn require(&self, required: &str) -> Result<(), ChromaClientError> {
+        let required_version = ServerVersion::parse(required);
+        if self.numbers() < required_version.numbers() {
+            return Err(ChromaClientError::UnsupportedByServer {
+                required: required_version.version,
+                actual: self.version.clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl ServerVersion {
+    /// Whether request bodies must go through `adapt_request`.
+    pub(crate) fn needs_request_shims(&self) -> bool {
+        self.numbers() < MULTIMODAL
+    }
+
+    /// Rewrite a request body written for the current API for this server:
+    /// servers older than 0.4.22 reject `uris` and `data` in `include`.
+    pub(crate) fn adapt_request(&self, body: &mut Value) {
+        if self.numbers() < MULTIMODAL {
+            if let Some(include) = body.get_mut("include").and_then(Value::as_array_mut) {
+                include.retain(|field| field != "uris" && field != "data");
+            }
+        }
+    }
+}
+
+impl ServerApi {
+    /// Rewrite an `api/v1/...` path for this API.
+    pub(crate) fn path(self, path: &str, tenant: &str, database: &str) -> String {
+        match (self, path.strip_prefix("api/v1/")) {
+            (ServerApi::V1, Some(route)) if route.starts_with("collections") => {
+                format!("api/v2/tenants/{}/databases/{}/{}", tenant, database, route)
+            }
+            (ServerApi::V1, Some(route)) => format!("api/v2/{}", route),
+            _ => path.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_version() {
+        assert_eq!(ServerVersion::parse("\"0.4.24\"").api, ServerApi::V0_4);
+        assert_eq!(ServerVersion::parse("0.5.5").api, ServerApi::V0_5);
+        assert_eq!(ServerVersion::parse("\"1.0.8\"").api, ServerApi::V1);
+        assert_eq!(ServerVersion::parse("1.0.8").numbers(), (1, 0, 8));
+    }
+
+    #[test]
+    fn require_version() {
+        let server = ServerVersion::parse("0.4.14");
+
+        assert!(server.require("0.4.9").is_ok());
+        assert!(matches!(
+            server.require("0.4.15"),
+            Err(ChromaClientError::UnsupportedByServer { required, actual })
+                if required == "0.4.15" && actual == "0.4.14"
+        ));
+    }
+
+    #[test]
+    fn adapt_requests() {
+        let mut body =
+            serde_json::json!({ "ids": ["a"], "include": ["documents", "uris", "data"] });
+
+        ServerVersion::parse("0.4.22").adapt_request(&mut body);
+        assert_eq!(
+            body["include"],
+            serde_json::json!(["documents", "uris", "data"])
+        );
+        let old = ServerVersion::parse("0.4.14");
+        assert!(old.needs_request_shims());
+        old.adapt_request(&mut body);
+        assert_eq!(body["include"], serde_json::json!(["documents"]));
+    }
+
+    #[test]
+    fn rewrite_paths() {
+        let path = |api: ServerApi, path| api.path(path, "t", "d");
+
+        assert_eq!(
+            path(ServerApi::V0_5, "api/v1/heartbeat"),
+            "api/v1/heartbeat"
+        );
+        assert_eq!(path(ServerApi::V1, "api/v1/heartbeat"), "api/v2/heartbeat");
+        assert_eq!(
+            path(ServerApi::V1, "api/v1/collections/abc/query"),
+            "api/v2/tenants/t/databases/d/collections/abc/query"
+        );
+    }
+}
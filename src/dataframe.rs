@@ -0,0 +1,178 @@
+use crate::client::ChromaClient;
+use crate::collection::{Collection, Metadata};
+use crate::document::{content_hash, Document};
+use crate::embeddings::EmbeddingFunction;
+use crate::error::ChromaClientError;
+use crate::tabular::unique_ids;
+use crate::text::TextSplitter;
+use polars::prelude::{AnyValue, Column, DataFrame, PolarsError};
+use serde_json::Value;
+
+/// How `Collection::add_from_dataframe` reads the rows of a DataFrame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnMapping {
+    /// Column holding the text to embed. Rows without text are skipped.
+    pub text: String,
+    /// Column holding the record ids, the content hash of the text when
+    /// `None`, rows repeating the text of a previous row being skipped.
+    pub id: Option<String>,
+    /// Columns stored as metadata, under their name.
+    pub metadata: Vec<String>,
+    /// Splits long texts into chunks, one record per chunk.
+    pub splitter: Option<TextSplitter>,
+    /// Records embedded and added per request.
+    pub batch_size: usize,
+}
+
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        ColumnMapping {
+            text: String::from("text"),
+            id: None,
+            metadata: Vec::new(),
+            splitter: None,
+            batch_size: 100,
+        }
+    }
+}
+
+impl Collection {
+    /// Embed and add the rows of a DataFrame, in batches of at most
+    /// `batch_size` records and the maximum batch size of the server.
+    /// Returns the number of added records.
+    pub async fn add_from_dataframe(
+        &self,
+        client: &ChromaClient,
+        embedding_function: &dyn EmbeddingFunction,
+        df: &DataFrame,
+        mapping: &ColumnMapping,
+    ) -> Result<usize, ChromaClientError> {
+        let documents = dataframe_documents(df, mapping)?;
+        let max = client.max_batch_size().await?.unwrap_or(usize::MAX);
+
+        for batch in documents.chunks(mapping.batch_size.clamp(1, max)) {
+            self.add_documents(client, embedding_function, batch)
+                .await?;
+        }
+        Ok(documents.len())
+    }
+}
+
+fn dataframe_documents(
+    df: &DataFrame,
+    mapping: &ColumnMapping,
+) -> Result<Vec<Document>, ChromaClientError> {
+    let texts = df
+        .column(&mapping.text)
+        .and_then(Column::str)
+        .map_err(polars_error)?;
+    let ids = match &mapping.id {
+        Some(name) => Some(df.column(name).map_err(polars_error)?),
+        None => None,
+    };
+    let metadata_columns = mapping
+        .metadata
+        .iter()
+        .map(|name| df.column(name))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(polars_error)?;
+
+    let mut documents = Vec::new();
+    for (row, text) in texts.into_iter().enumerate() {
+        let Some(text) = text else {
+            continue;
+        };
+        let id = match ids {
+            Some(ids) => match cell(ids, row)? {
+                Some(Value::String(id)) => id,
+                Some(id) => id.to_string(),
+                None => {
+                    return Err(ChromaClientError::LoaderError(format!(
+                        "row {} has no id",
+                        row
+                    )))
+                }
+            },
+            None => content_hash(text),
+        };
+
+        let mut metadata = Metadata::new();
+        for column in &metadata_columns {
+            if let Some(value) = cell(column, row)? {
+                metadata.insert(column.name().to_string(), value);
+            }
+        }
+
+        let document = Document {
+            id,
+            text: text.to_string(),
+            metadata,
+        };
+        match &mapping.splitter {
+            Some(splitter) => documents.extend(splitter.split_document(&document)),
+            None => documents.push(document),
+        }
+    }
+    unique_ids(documents, mapping.id.is_none())
+}
+
+/// The value of a cell as metadata, `None` for nulls.
+fn cell(column: &Column, row: usize) -> Result<Option<Value>, ChromaClientError> {
+    let value = match column.get(row).map_err(polars_error)? {
+        AnyValue::Null => return Ok(None),
+        AnyValue::Boolean(b) => Value::from(b),
+        AnyValue::String(s) => Value::from(s),
+        AnyValue::StringOwned(s) => Value::from(s.as_str()),
+        AnyValue::Int8(i) => Value::from(i),
+        AnyValue::Int16(i) => Value::from(i),
+        AnyValue::Int32(i) => Value::from(i),
+        AnyValue::Int64(i) => Value::from(i),
+        AnyValue::UInt8(u) => Value::from(u),
+        AnyValue::UInt16(u) => Value::from(u),
+        AnyValue::UInt32(u) => Value::from(u),
+        AnyValue::UInt64(u) => Value::from(u),
+        AnyValue::Float32(f) => Value::from(f),
+        AnyValue::Float64(f) => Value::from(f),
+        other => Value::from(other.to_string()),
+    };
+    Ok(Some(value))
+}
+
+fn polars_error(e: PolarsError) -> ChromaClientError {
+    ChromaClientError::LoaderError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dataframe_rows_to_documents() {
+        let df = DataFrame::new(vec![
+            Column::new(
+                "body".into(),
+                [Some("first text"), None, Some("third"), Some("third")],
+            ),
+            Column::new("year".into(), [Some(2021i64), Some(2022), None, None]),
+        ])
+        .unwrap();
+        let mapping = ColumnMapping {
+            text: "body".into(),
+            metadata: vec!["year".into()],
+            ..Default::default()
+        };
+
+        let documents = dataframe_documents(&df, &mapping).unwrap();
+
+        assert_eq!(documents.len(), 2);
+        assert_eq!(documents[0].id, content_hash("first text"));
+        assert_eq!(documents[0].metadata["year"], 2021);
+        assert!(documents[1].metadata.is_empty());
+
+        let missing = ColumnMapping {
+            text: "title".into(),
+            ..Default::default()
+        };
+        assert!(dataframe_documents(&df, &missing).is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use crate::client::ChromaClient;
+use crate::collection::{Collection, Entries, Metadata};
+use crate::embeddings::EmbeddingFunction;
+use crate::error::ChromaClientError;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+
+/// A piece of text with its metadata, ready to be embedded and stored.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Document {
+    pub id: String,
+    pub text: String,
+    pub metadata: Metadata,
+}
+
+impl Document {
+    pub fn new(id: impl Into<String>, text: impl Into<String>) -> Self {
+        Document {
+            id: id.into(),
+            text: text.into(),
+            metadata: Metadata::new(),
+        }
+    }
+}
+
+impl Collection {
+    /// Embed the documents and add them to the collection.
+    pub async fn add_documents(
+        &self,
+        client: &ChromaClient,
+        embedding_function: &dyn EmbeddingFunction,
+        documents: &[Document],
+    ) -> Result<(), ChromaClientError> {
+        let texts: Vec<&str> = documents.iter().map(|d| d.text.as_str()).collect();
+        let embeddings = embedding_function.embed(&texts).await?;
+
+        self.add(client, to_entries(documents, embeddings)).await
+    }
+
+    /// Embed texts and upsert them with their content hash as id, so syncing
+    /// the same texts again updates the records instead of duplicating them.
+    /// The records are sent in batches of the server's maximum size. Returns
+    /// the id of each text.
+    pub async fn upsert_texts<T: AsRef<str>>(
+        &self,
+        client: &ChromaClient,
+        embedding_function: &dyn EmbeddingFunction,
+        texts: &[T],
+        metadatas: Option<&[Metadata]>,
+    ) -> Result<Vec<String>, ChromaClientError> {
+        let ids: Vec<String> = texts.iter().map(|t| content_hash(t.as_ref())).collect();
+        let documents = text_documents(texts, &ids, metadatas)?;
+        if !documents.is_empty() {
+            self.upsert_documents(client, embedding_function, &documents)
+                .await?;
+        }
+        Ok(ids)
+    }
+
+    async fn upsert_documents(
+        &self,
+        client: &ChromaClient,
+        embedding_function: &dyn EmbeddingFunction,
+        documents: &[Document],
+    ) -> Result<(), ChromaClientError> {
+        let texts: Vec<&str> = documents.iter().map(|d| d.text.as_str()).collect();
+        let embeddings = embedding_function.embed(&texts).await?;
+
+        self.upsert_batched(client, to_entries(documents, embeddings))
+            .await
+    }
+}
+
+/// One document per distinct text, since a request cannot repeat an id.
+fn text_documents<T: AsRef<str>>(
+    texts: &[T],
+    ids: &[String],
+    metadatas: Option<&[Metadata]>,
+) -> Result<Vec<Document>, ChromaClientError> {
+    if metadatas.is_some_and(|m| m.len() != texts.len()) {
+        return Err(ChromaClientError::MetadataError(format!(
+            "expected {} metadatas, one per text",
+            texts.len()
+        )));
+    }
+
+    let mut seen = HashSet::new();
+    let mut documents = Vec::with_capacity(texts.len());
+    for (i, (text, id)) in texts.iter().zip(ids).enumerate() {
+        if !seen.insert(id) {
+            continue;
+        }
+        documents.push(Document {
+            id: id.clone(),
+            text: text.as_ref().to_string(),
+            metadata: metadatas.map(|m| m[i].clone()).unwrap_or_default(),
+        });
+    }
+    Ok(documents)
+}
+
+/// Build the entries to write for documents and their embeddings.
+pub(crate) fn to_entries(documents: &[Document], embeddings: Vec<Vec<f32>>) -> Entries {
+    Entries {
+        ids: documents.iter().map(|d| d.id.clone()).collect(),
+        embeddings: Some(embeddings),
+        metadatas: Some(documents.iter().map(|d| d.metadata.clone()).collect()),
+        documents: Some(documents.iter().map(|d| d.text.clone()).collect()),
+    }
+}
+
+/// Hex encoded SHA-256 of the content, stable across runs and platforms.
+pub fn content_hash(content: impl AsRef<[u8]>) -> String {
+    format!("{:x}", Sha256::digest(content.as_ref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_documents_are_deduplicated() {
+        let texts = ["a", "b", "a"];
+        let ids: Vec<String> = texts.iter().map(content_hash).collect();
+
+        let documents = text_documents(&texts, &ids, None).unwrap();
+
+        assert_eq!(documents.len(), 2);
+        assert_eq!(documents[1].id, content_hash("b"));
+        assert!(text_documents(&texts, &ids, Some(&[Metadata::new()])).is_err());
+    }
+}
@@ -0,0 +1,264 @@
+use crate::client::ChromaClient;
+use crate::collection::Collection;
+use crate::document::content_hash;
+use crate::error::ChromaClientError;
+use async_trait::async_trait;
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+
+/// Computes the embeddings of documents, usually by calling a model provider.
+#[async_trait]
+pub trait EmbeddingFunction: Send + Sync {
+    /// Returns one embedding per document, in the same order.
+    async fn embed(&self, documents: &[&str]) -> Result<Vec<Vec<f32>>, ChromaClientError>;
+
+    /// Describes the function, recorded on the collections it creates.
+    fn info(&self) -> Option<EmbeddingFunctionInfo> {
+        None
+    }
+}
+
+/// Collection metadata keys holding the embedding function description.
+pub const EMBEDDING_FUNCTION_KEY: &str = "embedding_function";
+pub const EMBEDDING_MODEL_KEY: &str = "embedding_model";
+pub const EMBEDDING_DIMENSIONS_KEY: &str = "embedding_dimensions";
+
+/// The embedding function a collection was created with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmbeddingFunctionInfo {
+    pub name: String,
+    pub model: String,
+    pub dimensions: usize,
+}
+
+impl EmbeddingFunctionInfo {
+    fn to_metadata(&self) -> HashMap<String, String> {
+        HashMap::from([
+            (EMBEDDING_FUNCTION_KEY.to_string(), self.name.clone()),
+            (EMBEDDING_MODEL_KEY.to_string(), self.model.clone()),
+            (
+                EMBEDDING_DIMENSIONS_KEY.to_string(),
+                self.dimensions.to_string(),
+            ),
+        ])
+    }
+}
+
+impl Collection {
+    /// The embedding function recorded in the collection metadata, used to
+    /// rebuild the function the collection was created with.
+    pub fn embedding_function_info(&self) -> Option<EmbeddingFunctionInfo> {
+        let metadata = self.metadata.as_ref()?;
+        let get = |key: &str| metadata.get(key)?.as_str().map(str::to_string);
+
+        Some(EmbeddingFunctionInfo {
+            name: get(EMBEDDING_FUNCTION_KEY)?,
+            model: get(EMBEDDING_MODEL_KEY)?,
+            dimensions: get(EMBEDDING_DIMENSIONS_KEY)?.parse().ok()?,
+        })
+    }
+}
+
+impl ChromaClient {
+    /// Create a collection, recording the embedding function in its metadata.
+    pub async fn create_collection_with_embedding_function(
+        &self,
+        name: &str,
+        metadata: Option<HashMap<String, String>>,
+        embedding_function: &dyn EmbeddingFunction,
+    ) -> Result<Collection, ChromaClientError> {
+        let mut metadata = metadata.unwrap_or_default();
+        if let Some(info) = embedding_function.info() {
+            metadata.extend(info.to_metadata());
+        }
+        let metadata = (!metadata.is_empty()).then_some(metadata);
+
+        self.create_collection(name, metadata).await
+    }
+
+    /// Get a collection, failing if it was created with another embedding
+    /// function than the one given.
+    pub async fn get_collection_with_embedding_function(
+        &self,
+        name: &str,
+        embedding_function: &dyn EmbeddingFunction,
+    ) -> Result<Collection, ChromaClientError> {
+        let collection = self.get_collection(name).await?;
+        check_embedding_function(&collection, embedding_function)?;
+        Ok(collection)
+    }
+}
+
+fn check_embedding_function(
+    collection: &Collection,
+    embedding_function: &dyn EmbeddingFunction,
+) -> Result<(), ChromaClientError> {
+    match (
+        collection.embedding_function_info(),
+        embedding_function.info(),
+    ) {
+        (Some(recorded), Some(given)) if recorded != given => {
+            Err(ChromaClientError::EmbeddingError(format!(
+                "collection `{}` was created with {} {} ({} dimensions), got {} {} ({} dimensions)",
+                collection.name,
+                recorded.name,
+                recorded.model,
+                recorded.dimensions,
+                given.name,
+                given.model,
+                given.dimensions
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Embedding function wrapper keeping the most recently used embeddings in
+/// memory, keyed by the SHA-256 of the document content. Documents already in the
+/// cache are never sent to the wrapped function again.
+pub struct CachedEmbeddingFunction<E> {
+    inner: E,
+    cache: Mutex<LruCache<String, Vec<f32>>>,
+}
+
+impl<E: EmbeddingFunction> CachedEmbeddingFunction<E> {
+    /// Creates a cache holding at most `capacity` embeddings.
+    pub fn new(inner: E, capacity: NonZeroUsize) -> Self {
+        CachedEmbeddingFunction {
+            inner,
+            cache: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    /// Number of embeddings currently cached.
+    pub fn len(&self) -> usize {
+        self.cache.lock().unwrap().len()
+    }
+
+    /// Whether the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop every cached embedding.
+    pub fn clear(&self) {
+        self.cache.lock().unwrap().clear();
+    }
+}
+
+#[async_trait]
+impl<E: EmbeddingFunction> EmbeddingFunction for CachedEmbeddingFunction<E> {
+    async fn embed(&self, documents: &[&str]) -> Result<Vec<Vec<f32>>, ChromaClientError> {
+        let keys: Vec<String> = documents.iter().map(content_hash).collect();
+
+        let mut embeddings: Vec<Option<Vec<f32>>> = {
+            let mut cache = self.cache.lock().unwrap();
+            keys.iter().map(|k| cache.get(k).cloned()).collect()
+        };
+
+        let mut missing: Vec<(&String, &str)> = Vec::new();
+        for ((key, document), embedding) in keys.iter().zip(documents).zip(&embeddings) {
+            if embedding.is_none() && !missing.iter().any(|(k, _)| *k == key) {
+                missing.push((key, document));
+            }
+        }
+
+        if !missing.is_empty() {
+            let texts: Vec<&str> = missing.iter().map(|(_, d)| *d).collect();
+            let computed = self.inner.embed(&texts).await?;
+            if computed.len() != texts.len() {
+                return Err(ChromaClientError::EmbeddingError(format!(
+                    "expected {} embeddings, got {}",
+                    texts.len(),
+                    computed.len()
+                )));
+            }
+
+            let mut cache = self.cache.lock().unwrap();
+            for ((key, _), computed) in missing.iter().zip(computed) {
+                for (k, embedding) in keys.iter().zip(embeddings.iter_mut()) {
+                    if k == *key {
+                        *embedding = Some(computed.clone());
+                    }
+                }
+                cache.put((*key).clone(), computed);
+            }
+        }
+
+        Ok(embeddings.into_iter().flatten().collect())
+    }
+
+    fn info(&self) -> Option<EmbeddingFunctionInfo> {
+        self.inner.info()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct CountingEmbedder {
+        embedded: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl EmbeddingFunction for CountingEmbedder {
+        async fn embed(&self, documents: &[&str]) -> Result<Vec<Vec<f32>>, ChromaClientError> {
+            self.embedded.fetch_add(documents.len(), Ordering::SeqCst);
+            Ok(documents.iter().map(|d| vec![d.len() as f32]).collect())
+        }
+    }
+
+    struct ModelEmbedder(&'static str);
+
+    #[async_trait]
+    impl EmbeddingFunction for ModelEmbedder {
+        async fn embed(&self, documents: &[&str]) -> Result<Vec<Vec<f32>>, ChromaClientError> {
+            Ok(vec![vec![0.0; 4]; documents.len()])
+        }
+
+        fn info(&self) -> Option<EmbeddingFunctionInfo> {
+            Some(EmbeddingFunctionInfo {
+                name: "test".into(),
+                model: self.0.into(),
+                dimensions: 4,
+            })
+        }
+    }
+
+    #[test]
+    fn embedding_function_mismatch() {
+        let info = ModelEmbedder("small").info().unwrap();
+        let collection = Collection::with_id(
+            "docs".into(),
+            "id".into(),
+            Some(serde_json::to_value(info.to_metadata()).unwrap()),
+        );
+
+        assert_eq!(collection.embedding_function_info(), Some(info));
+        assert!(check_embedding_function(&collection, &ModelEmbedder("small")).is_ok());
+        assert!(check_embedding_function(&collection, &ModelEmbedder("large")).is_err());
+    }
+
+    #[tokio::test]
+    async fn cached_documents_are_not_embedded_again() {
+        let embedder = CachedEmbeddingFunction::new(
+            CountingEmbedder {
+                embedded: AtomicUsize::new(0),
+            },
+            NonZeroUsize::new(10).unwrap(),
+        );
+
+        let first = embedder.embed(&["a", "bb", "a"]).await.unwrap();
+        let second = embedder.embed(&["bb", "ccc"]).await.unwrap();
+
+        assert_eq!(first, vec![vec![1.0], vec![2.0], vec![1.0]]);
+        assert_eq!(second, vec![vec![2.0], vec![3.0]]);
+        assert_eq!(embedder.inner.embedded.load(Ordering::SeqCst), 3);
+        assert_eq!(embedder.len(), 3);
+    }
+}
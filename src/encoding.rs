@@ -0,0 +1,126 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::Value;
+
+/// Fields of request bodies holding a list of embeddings.
+const EMBEDDING_FIELDS: [&str; 2] = ["embeddings", "query_embeddings"];
+
+/// How embeddings are written in request bodies.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EmbeddingEncoding {
+    /// Arrays of JSON numbers, understood by every server.
+    #[default]
+    Json,
+    /// Base64 strings of the little-endian `f32` values, for servers that
+    /// accept them. Much smaller than decimal numbers for large batches.
+    Base64,
+    /// JSON numbers rounded to this many significant digits. Four or five
+    /// digits roughly halve the payload of high-dimensional embeddings, at
+    /// the cost of slightly different distances: near-ties in the results may
+    /// swap, so check the recall of your queries before lowering it further.
+    Rounded { significant_digits: u8 },
+}
+
+impl EmbeddingEncoding {
+    /// Rewrite the embeddings of a request body with this encoding.
+    pub fn encode_body(self, body: &mut Value) {
+        if self == EmbeddingEncoding::Json {
+            return;
+        }
+        let Some(object) = body.as_object_mut() else {
+            return;
+        };
+
+        for field in EMBEDDING_FIELDS {
+            if let Some(Value::Array(embeddings)) = object.get_mut(field) {
+                for embedding in embeddings {
+                    match self {
+                        EmbeddingEncoding::Json => {}
+                        EmbeddingEncoding::Base64 => {
+                            if let Some(encoded) = encode_embedding(embedding) {
+                                *embedding = Value::String(encoded);
+                            }
+                        }
+                        EmbeddingEncoding::Rounded { significant_digits } => {
+                            round_embedding(embedding, significant_digits)
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn round_embedding(embedding: &mut Value, significant_digits: u8) {
+    let Some(values) = embedding.as_array_mut() else {
+        return;
+    };
+    let precision = usize::from(significant_digits.max(1)) - 1;
+    for value in values {
+        if let Some(rounded) = value
+            .as_f64()
+            .and_then(|v| format!("{:.*e}", precision, v).parse::<f64>().ok())
+        {
+            *value = Value::from(rounded);
+        }
+    }
+}
+
+fn encode_embedding(embedding: &Value) -> Option<String> {
+    let values = embedding.as_array()?;
+    let mut bytes = Vec::with_capacity(values.len() * 4);
+    for value in values {
+        bytes.extend_from_slice(&(value.as_f64()? as f32).to_le_bytes());
+    }
+    Some(STANDARD.encode(bytes))
+}
+
+/// Decode an embedding written with `EmbeddingEncoding::Base64`.
+pub fn decode_base64(encoded: &str) -> Option<Vec<f32>> {
+    let bytes = STANDARD.decode(encoded).ok()?;
+    if bytes.len() % 4 != 0 {
+        return None;
+    }
+    Some(
+        bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn base64_embeddings() {
+        let mut body = json!({
+            "ids": ["a", "b"],
+            "embeddings": [[0.5, -1.0], [2.0, 0.25]],
+            "documents": ["x", "y"],
+        });
+
+        EmbeddingEncoding::Base64.encode_body(&mut body);
+
+        let encoded = body["embeddings"][1].as_str().unwrap();
+        assert_eq!(decode_base64(encoded), Some(vec![2.0, 0.25]));
+        assert_eq!(body["documents"], json!(["x", "y"]));
+    }
+
+    #[test]
+    fn rounded_embeddings() {
+        let mut body = json!({ "query_embeddings": [[0.123_456_f32, -98_765.4_f32, 0.0]] });
+
+        EmbeddingEncoding::Rounded {
+            significant_digits: 3,
+        }
+        .encode_body(&mut body);
+
+        assert_eq!(
+            serde_json::to_string(&body).unwrap(),
+            r#"{"query_embeddings":[[0.123,-98800.0,0.0]]}"#
+        );
+    }
+}
@@ -0,0 +1,149 @@
+//! Stages run on each document before it is embedded, adding metadata or
+//! rewriting its text.
+
+use crate::document::Document;
+use crate::metadata::CREATED_AT_KEY;
+use crate::source::SOURCE_KEY;
+use serde_json::Value;
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Time a record was indexed, in seconds since the Unix epoch.
+pub const INDEXED_AT_KEY: &str = "indexed_at";
+/// Size in bytes of the source file of a record.
+pub const FILE_SIZE_KEY: &str = "file_size";
+/// Last modification of the source file of a record, in seconds since the
+/// Unix epoch.
+pub const MODIFIED_AT_KEY: &str = "modified_at";
+/// Extension of the source file of a record, e.g. `md`.
+pub const EXTENSION_KEY: &str = "extension";
+
+/// A stage of enrichment. Closures taking a `&mut Document` are enrichers.
+pub trait Enricher: Send + Sync {
+    fn enrich(&self, document: &mut Document);
+}
+
+impl<F: Fn(&mut Document) + Send + Sync> Enricher for F {
+    fn enrich(&self, document: &mut Document) {
+        self(document)
+    }
+}
+
+impl fmt::Debug for dyn Enricher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Enricher")
+    }
+}
+
+/// Run the enrichers on each document, in order.
+pub fn enrich_all(enrichers: &[&dyn Enricher], documents: &mut [Document]) {
+    for document in documents {
+        for enricher in enrichers {
+            enricher.enrich(document);
+        }
+    }
+}
+
+/// Sets `indexed_at` to the current time, and `created_at` too when the
+/// document does not have one yet, for `Where::created_after`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Timestamps;
+
+impl Enricher for Timestamps {
+    fn enrich(&self, document: &mut Document) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let metadata = &mut document.metadata;
+        metadata.insert(INDEXED_AT_KEY.into(), Value::from(now));
+        metadata
+            .entry(CREATED_AT_KEY)
+            .or_insert_with(|| Value::from(now));
+    }
+}
+
+/// Sets `file_size`, `modified_at` and `extension` from the file named by
+/// the `source` metadata. Documents without a readable source are left
+/// as they are.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileStats;
+
+impl Enricher for FileStats {
+    fn enrich(&self, document: &mut Document) {
+        let Some(path) = document.metadata.get(SOURCE_KEY).and_then(Value::as_str) else {
+            return;
+        };
+        let path = std::path::PathBuf::from(path);
+        let Ok(stats) = std::fs::metadata(&path) else {
+            return;
+        };
+
+        let metadata = &mut document.metadata;
+        metadata.insert(FILE_SIZE_KEY.into(), Value::from(stats.len()));
+        if let Some(modified_at) = stats
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        {
+            metadata.insert(MODIFIED_AT_KEY.into(), Value::from(modified_at.as_secs()));
+        }
+        if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+            metadata.insert(EXTENSION_KEY.into(), Value::from(extension));
+        }
+    }
+}
+
+/// Sets `language` to the detected language, see `language::tag_language`.
+#[cfg(feature = "language")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Language;
+
+#[cfg(feature = "language")]
+impl Enricher for Language {
+    fn enrich(&self, document: &mut Document) {
+        crate::language::tag_language(std::slice::from_mut(document));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stages_run_in_order() {
+        let dir = std::env::temp_dir().join(format!(
+            "chroma-rs-stages-run-in-order-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notes.md");
+        std::fs::write(&path, "hello").unwrap();
+
+        let mut document = Document::new("a", "hello");
+        document
+            .metadata
+            .insert(SOURCE_KEY.into(), path.display().to_string().into());
+        let upper = |document: &mut Document| document.text = document.text.to_uppercase();
+        let length = |document: &mut Document| {
+            let length = document.text.len();
+            document.metadata.insert("length".into(), length.into());
+        };
+        enrich_all(
+            &[&FileStats, &Timestamps, &upper, &length],
+            std::slice::from_mut(&mut document),
+        );
+
+        assert_eq!(document.text, "HELLO");
+        assert_eq!(document.metadata[FILE_SIZE_KEY], 5);
+        assert_eq!(document.metadata[EXTENSION_KEY], "md");
+        assert!(document.metadata.contains_key(MODIFIED_AT_KEY));
+        assert!(document.metadata.contains_key(INDEXED_AT_KEY));
+        assert_eq!(
+            document.metadata[CREATED_AT_KEY],
+            document.metadata[INDEXED_AT_KEY]
+        );
+        assert_eq!(document.metadata["length"], 5);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,5 +1,12 @@
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use thiserror::Error;
 
+/// Characters of an error response body kept in `ApiError::body`.
+const BODY_EXCERPT_LEN: usize = 512;
+
 #[derive(Error, Debug)]
 pub enum ChromaClientError {
     #[error("Unable to parse URL: {0}")]
@@ -11,7 +18,215 @@ pub enum ChromaClientError {
     #[error("Unable to parse into json response: {0}")]
     ResponseParseError(serde_json::Error),
     #[error("Respond with a bad status: {0}")]
-    ResponseStatusError(String),
+    ResponseStatusError(ApiError),
     #[error("Preflight request failed, status: {0}")]
-    PreflightError(String),
+    PreflightError(ApiError),
+    #[error("Unable to compute embeddings: {0}")]
+    EmbeddingError(String),
+    #[error("Unable to load document: {0}")]
+    LoaderError(String),
+    #[error("I/O error: {0}")]
+    IoError(std::io::Error),
+    #[error("Invalid metadata: {0}")]
+    MetadataError(String),
+    #[error("Collection already exists: {0}")]
+    CollectionAlreadyExists(String),
+    #[error("The client is read-only")]
+    ReadOnlyMode,
+    #[error("Operation denied by the client policy: {0}")]
+    OperationDenied(String),
+    #[error("Not supported by the server: requires Chroma {required}, connected to {actual}")]
+    UnsupportedByServer { required: String, actual: String },
+    #[error("Batch too large: {sent} records sent, the server accepts at most {max}")]
+    BatchTooLarge { sent: usize, max: usize },
+    #[error("The client is shut down")]
+    ShutDown,
+    /// Version 0 stands for a record without any version stored.
+    #[error("Version conflict on {id}: expected version {expected}, found {current}")]
+    VersionConflict {
+        id: String,
+        expected: u64,
+        current: u64,
+    },
+}
+
+impl ChromaClientError {
+    /// Name of the variant, e.g. `ResponseStatusError`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ChromaClientError::UrlParseError(_) => "UrlParseError",
+            ChromaClientError::RequestError(_) => "RequestError",
+            ChromaClientError::ResponseError(_) => "ResponseError",
+            ChromaClientError::ResponseParseError(_) => "ResponseParseError",
+            ChromaClientError::ResponseStatusError(_) => "ResponseStatusError",
+            ChromaClientError::PreflightError(_) => "PreflightError",
+            ChromaClientError::EmbeddingError(_) => "EmbeddingError",
+            ChromaClientError::LoaderError(_) => "LoaderError",
+            ChromaClientError::IoError(_) => "IoError",
+            ChromaClientError::MetadataError(_) => "MetadataError",
+            ChromaClientError::CollectionAlreadyExists(_) => "CollectionAlreadyExists",
+            ChromaClientError::ReadOnlyMode => "ReadOnlyMode",
+            ChromaClientError::OperationDenied(_) => "OperationDenied",
+            ChromaClientError::UnsupportedByServer { .. } => "UnsupportedByServer",
+            ChromaClientError::BatchTooLarge { .. } => "BatchTooLarge",
+            ChromaClientError::ShutDown => "ShutDown",
+            ChromaClientError::VersionConflict { .. } => "VersionConflict",
+        }
+    }
+
+    /// The error response of the server, for errors caused by one.
+    pub fn api_error(&self) -> Option<&ApiError> {
+        match self {
+            ChromaClientError::ResponseStatusError(error)
+            | ChromaClientError::PreflightError(error) => Some(error),
+            _ => None,
+        }
+    }
+
+    /// Whether sending the same request again may succeed: network failures,
+    /// and responses with a 429 or 5xx status. Invalid requests, rejected
+    /// writes and local errors are fatal.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ChromaClientError::RequestError(e) => !e.is_builder(),
+            ChromaClientError::ResponseError(_) => true,
+            ChromaClientError::ResponseStatusError(error)
+            | ChromaClientError::PreflightError(error) => {
+                error.status == 429 || error.status >= 500
+            }
+            ChromaClientError::UrlParseError(_)
+            | ChromaClientError::ResponseParseError(_)
+            | ChromaClientError::EmbeddingError(_)
+            | ChromaClientError::LoaderError(_)
+            | ChromaClientError::IoError(_)
+            | ChromaClientError::MetadataError(_)
+            | ChromaClientError::CollectionAlreadyExists(_)
+            | ChromaClientError::ReadOnlyMode
+            | ChromaClientError::OperationDenied(_)
+            | ChromaClientError::UnsupportedByServer { .. }
+            | ChromaClientError::BatchTooLarge { .. }
+            | ChromaClientError::ShutDown
+            | ChromaClientError::VersionConflict { .. } => false,
+        }
+    }
+}
+
+/// An error response of the server, with what is needed to find the request
+/// in the server logs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiError {
+    /// What failed, with the status and the start of the body.
+    pub message: String,
+    pub status: u16,
+    /// `x-request-id` header of the response.
+    pub request_id: Option<String>,
+    /// `traceparent` header of the response, or `x-trace-id`.
+    pub trace_id: Option<String>,
+    /// Start of the response body.
+    pub body: String,
+}
+
+impl ApiError {
+    /// Read the error response, its message starting with `context`.
+    pub(crate) async fn from_response(context: &str, response: reqwest::Response) -> Self {
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.bytes().await.unwrap_or_default();
+        ApiError::new(context, status, &headers, &body)
+    }
+
+    pub(crate) fn new(context: &str, status: StatusCode, headers: &HeaderMap, body: &[u8]) -> Self {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let body: String = String::from_utf8_lossy(body)
+            .chars()
+            .take(BODY_EXCERPT_LEN)
+            .collect();
+
+        ApiError {
+            message: format!("{}{} - {}", context, status, body),
+            status: status.as_u16(),
+            request_id: header("x-request-id"),
+            trace_id: header("traceparent").or_else(|| header("x-trace-id")),
+            body,
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(request_id) = &self.request_id {
+            write!(f, " (request id {})", request_id)?;
+        }
+        Ok(())
+    }
+}
+
+/// An error in a form that can be serialized, to return it from a service.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub kind: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+impl From<&ChromaClientError> for ErrorResponse {
+    fn from(error: &ChromaClientError) -> Self {
+        ErrorResponse {
+            kind: error.kind().to_string(),
+            message: error.to_string(),
+            status: error.api_error().map(|e| e.status),
+            request_id: error.api_error().and_then(|e| e.request_id.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retryable_errors() {
+        let status = |code: u16| {
+            let status = StatusCode::from_u16(code).unwrap();
+            ChromaClientError::ResponseStatusError(ApiError::new(
+                "",
+                status,
+                &HeaderMap::new(),
+                b"",
+            ))
+        };
+
+        assert!(status(429).is_retryable());
+        assert!(status(503).is_retryable());
+        assert!(!status(400).is_retryable());
+        assert!(!ChromaClientError::BatchTooLarge { sent: 2, max: 1 }.is_retryable());
+    }
+
+    #[test]
+    fn api_error_details() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-request-id", "req-42".parse().unwrap());
+        let body = "x".repeat(1000);
+
+        let error = ApiError::new(
+            "Failed: ",
+            StatusCode::BAD_GATEWAY,
+            &headers,
+            body.as_bytes(),
+        );
+
+        assert_eq!(error.status, 502);
+        assert_eq!(error.body.len(), BODY_EXCERPT_LEN);
+        assert!(error.message.starts_with("Failed: 502 Bad Gateway - xxx"));
+        assert!(error.to_string().ends_with("(request id req-42)"));
+    }
 }
@@ -0,0 +1,75 @@
+use crate::client::ChromaClient;
+use crate::collection::{Collection, Metadata};
+use crate::error::ChromaClientError;
+use crate::filter::Where;
+use crate::jobs::JobHandle;
+use serde_json::Value;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Metadata key holding the time a record expires, in seconds since the Unix epoch.
+pub const EXPIRES_AT_KEY: &str = "expires_at";
+
+/// Set the metadata of a record to expire after `ttl`.
+pub fn expires_in(metadata: &mut Metadata, ttl: Duration) {
+    let expires_at = now().saturating_add(ttl.as_secs());
+    metadata.insert(EXPIRES_AT_KEY.to_string(), Value::from(expires_at));
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Records expired at `now`. Records without `expires_at` never expire.
+fn expired_at(now: u64) -> Where {
+    Where::lt(EXPIRES_AT_KEY, now)
+}
+
+impl Collection {
+    /// Delete the records whose `expires_at` is in the past. Returns the
+    /// number of deleted records.
+    pub async fn expire_now(&self, client: &ChromaClient) -> Result<usize, ChromaClientError> {
+        let deleted = self.delete_where(client, expired_at(now()), false).await?;
+        Ok(deleted.count())
+    }
+
+    /// Call `expire_now` every `interval` in a background task, until the
+    /// job is cancelled or dropped, or the client is shut down. Its progress
+    /// is the number of deleted records. Failed sweeps are retried at the
+    /// next interval.
+    pub fn expire_every(&self, client: &ChromaClient, interval: Duration) -> JobHandle {
+        let collection = self.clone();
+        let client = client.clone();
+
+        JobHandle::spawn(|expired| async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if client.is_shut_down() {
+                    break;
+                }
+                if let Ok(count) = collection.expire_now(&client).await {
+                    expired.add(count);
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expiry_metadata() {
+        let mut metadata = Metadata::new();
+
+        expires_in(&mut metadata, Duration::from_secs(60));
+
+        let expires_at = metadata[EXPIRES_AT_KEY].as_u64().unwrap();
+        assert!(expires_at >= now() + 59);
+        assert_eq!(expired_at(100), Where::lt("expires_at", 100));
+    }
+}
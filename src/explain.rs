@@ -0,0 +1,87 @@
+use crate::client::ChromaClient;
+use crate::collection::{Collection, QueryParams, QueryResult};
+use crate::error::ChromaClientError;
+use crate::policy::Operation;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::{Duration, Instant};
+
+/// Everything about a query, returned by `Collection::query_debug`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryDebug {
+    /// The JSON body sent, with the effective filters.
+    pub request: Value,
+    pub status: u16,
+    /// Time between sending the request and receiving the whole response.
+    pub elapsed: Duration,
+    /// Timing related response headers, such as `server-timing`.
+    pub timing_headers: Vec<(String, String)>,
+    /// The response body as received.
+    pub raw_response: String,
+    /// The parsed result, `None` when the query failed or could not be parsed.
+    pub result: Option<QueryResult>,
+}
+
+impl Collection {
+    /// Run a query and return diagnostics along with its result, to
+    /// troubleshoot relevance issues. Unlike `query`, a failed query is not an
+    /// error: the status and the raw response tell what went wrong.
+    pub async fn query_debug(
+        &self,
+        client: &ChromaClient,
+        mut params: QueryParams,
+    ) -> Result<QueryDebug, ChromaClientError> {
+        client.check_operation(Operation::Read)?;
+        self.prepare_embeddings(client, &mut params.query_embeddings)
+            .await?;
+        let request =
+            serde_json::to_value(&params).map_err(ChromaClientError::ResponseParseError)?;
+
+        let started = Instant::now();
+        let response = client
+            .post_raw(&format!("api/v1/collections/{}/query", self.id), &request)
+            .await?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let raw_response = response
+            .text()
+            .await
+            .map_err(ChromaClientError::ResponseError)?;
+        let elapsed = started.elapsed();
+
+        let timing_headers = headers
+            .iter()
+            .filter(|(name, _)| is_timing_header(name.as_str()))
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let result = status
+            .is_success()
+            .then(|| serde_json::from_str(&raw_response).ok())
+            .flatten();
+
+        Ok(QueryDebug {
+            request,
+            status: status.as_u16(),
+            elapsed,
+            timing_headers,
+            raw_response,
+            result,
+        })
+    }
+}
+
+fn is_timing_header(name: &str) -> bool {
+    name == "server-timing" || name.contains("time") || name.contains("duration")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timing_headers() {
+        assert!(is_timing_header("server-timing"));
+        assert!(is_timing_header("x-process-time"));
+        assert!(!is_timing_header("content-type"));
+    }
+}
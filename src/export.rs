@@ -0,0 +1,328 @@
+use crate::client::{ChromaClient, DangerZone};
+use crate::collection::{
+    Collection, Entries, GetPages, GetParams, GetResult, Include, Metadata, PAGE_SIZE,
+};
+use crate::error::ChromaClientError;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// File listing the collections saved by `ChromaClient::reset_with_backup`.
+pub const BACKUP_MANIFEST: &str = "collections.json";
+
+/// Attempts after a retryable failure in `Collection::export_resumable`.
+const EXPORT_RETRIES: u32 = 3;
+
+/// Delay before the first retry, doubled after each attempt.
+const EXPORT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Progress of `Collection::export_resumable`, saved as JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportCheckpoint {
+    pub collection_id: String,
+    /// Number of records already written.
+    pub offset: usize,
+}
+
+impl ExportCheckpoint {
+    pub fn load(path: &Path) -> Result<Option<Self>, ChromaClientError> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(path).map_err(ChromaClientError::IoError)?;
+        serde_json::from_str(&content)
+            .map(Some)
+            .map_err(ChromaClientError::ResponseParseError)
+    }
+
+    /// Write the checkpoint to a temporary file first, so an interruption
+    /// never leaves a truncated checkpoint behind.
+    pub fn save(&self, path: &Path) -> Result<(), ChromaClientError> {
+        let content = serde_json::to_string(self).map_err(ChromaClientError::ResponseParseError)?;
+        let temporary = path.with_extension("tmp");
+        std::fs::write(&temporary, content).map_err(ChromaClientError::IoError)?;
+        std::fs::rename(&temporary, path).map_err(ChromaClientError::IoError)
+    }
+}
+
+/// A record as written by `Collection::export`, one JSON object per line.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExportRecord {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub document: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Metadata>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Vec<f32>>,
+}
+
+impl ExportRecord {
+    /// Split a get result into records.
+    pub fn from_result(result: GetResult) -> Vec<ExportRecord> {
+        let mut embeddings = result.embeddings.map(|e| e.into_iter());
+        let mut documents = result.documents.map(|d| d.into_iter());
+        let mut metadatas = result.metadatas.map(|m| m.into_iter());
+
+        result
+            .ids
+            .into_iter()
+            .map(|id| ExportRecord {
+                id,
+                document: documents.as_mut().and_then(|d| d.next()).flatten(),
+                metadata: metadatas.as_mut().and_then(|m| m.next()).flatten(),
+                embedding: embeddings.as_mut().and_then(|e| e.next()),
+            })
+            .collect()
+    }
+}
+
+impl ChromaClient {
+    /// Export every collection into the `dir` directory, then reset the
+    /// database. Each collection is written to `<name>.jsonl` and listed in
+    /// `collections.json`; nothing is reset if the backup fails. Returns the
+    /// number of records saved.
+    pub async fn reset_with_backup(
+        &self,
+        dir: impl AsRef<Path>,
+        confirm: DangerZone,
+    ) -> Result<usize, ChromaClientError> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir).map_err(ChromaClientError::IoError)?;
+
+        let collections = self.list_collections().await?;
+        let manifest = serde_json::to_string_pretty(&collections)
+            .map_err(ChromaClientError::ResponseParseError)?;
+        std::fs::write(dir.join(BACKUP_MANIFEST), manifest).map_err(ChromaClientError::IoError)?;
+
+        let mut saved = 0;
+        for collection in &collections {
+            let file = File::create(dir.join(format!("{}.jsonl", collection.name)))
+                .map_err(ChromaClientError::IoError)?;
+            saved += collection
+                .export(self, BufWriter::new(file), PAGE_SIZE)
+                .await?;
+        }
+
+        self.reset(confirm).await?;
+        Ok(saved)
+    }
+}
+
+impl Collection {
+    /// Write every record of the collection as JSON Lines, fetching
+    /// `batch_size` records per request. Returns the number of records written.
+    pub async fn export<W: Write>(
+        &self,
+        client: &ChromaClient,
+        writer: W,
+        batch_size: usize,
+    ) -> Result<usize, ChromaClientError> {
+        self.export_from(client, writer, batch_size, 0, None).await
+    }
+
+    /// Same as `export`, saving the progress to the `checkpoint` file after
+    /// each batch. When the file exists, the export continues where the
+    /// previous run stopped, so `writer` should append to the previous
+    /// output. Requests failing with a retryable error are sent again a few
+    /// times, and the checkpoint is removed once the export is complete.
+    ///
+    /// Records are paged by offset: records added or deleted during the
+    /// export may be missed or written twice. Returns the number of records
+    /// written by this run.
+    pub async fn export_resumable<W: Write>(
+        &self,
+        client: &ChromaClient,
+        writer: W,
+        batch_size: usize,
+        checkpoint: impl AsRef<Path>,
+    ) -> Result<usize, ChromaClientError> {
+        let checkpoint = checkpoint.as_ref();
+        let offset = match ExportCheckpoint::load(checkpoint)? {
+            Some(saved) if saved.collection_id == self.id => saved.offset,
+            _ => 0,
+        };
+
+        let exported = self
+            .export_from(client, writer, batch_size, offset, Some(checkpoint))
+            .await?;
+        std::fs::remove_file(checkpoint).map_err(ChromaClientError::IoError)?;
+        Ok(exported)
+    }
+
+    async fn export_from<W: Write>(
+        &self,
+        client: &ChromaClient,
+        mut writer: W,
+        batch_size: usize,
+        offset: usize,
+        checkpoint: Option<&Path>,
+    ) -> Result<usize, ChromaClientError> {
+        let retries = if checkpoint.is_some() {
+            EXPORT_RETRIES
+        } else {
+            0
+        };
+        let params = GetParams {
+            offset: Some(offset),
+            include: Some(vec![
+                Include::Documents,
+                Include::Metadatas,
+                Include::Embeddings,
+            ]),
+            ..Default::default()
+        };
+        let mut pages = self.pages(client, params, batch_size);
+        let mut exported = 0;
+
+        while let Some(page) = next_page(&mut pages, retries).await? {
+            let records = ExportRecord::from_result(page);
+            for record in &records {
+                serde_json::to_writer(&mut writer, record)
+                    .map_err(ChromaClientError::ResponseParseError)?;
+                writer
+                    .write_all(b"\n")
+                    .map_err(ChromaClientError::IoError)?;
+            }
+
+            exported += records.len();
+            if let Some(path) = checkpoint {
+                writer.flush().map_err(ChromaClientError::IoError)?;
+                let saved = ExportCheckpoint {
+                    collection_id: self.id.clone(),
+                    offset: pages.offset(),
+                };
+                saved.save(path)?;
+            }
+        }
+
+        writer.flush().map_err(ChromaClientError::IoError)?;
+        Ok(exported)
+    }
+
+    /// Upsert records written by `export`, `batch_size` records per request.
+    /// Returns the number of records imported.
+    pub async fn import<R: BufRead>(
+        &self,
+        client: &ChromaClient,
+        reader: R,
+        batch_size: usize,
+    ) -> Result<usize, ChromaClientError> {
+        let batch_size = batch_size.max(1);
+        let mut batch = Vec::with_capacity(batch_size);
+        let mut imported = 0;
+
+        for line in reader.lines() {
+            let line = line.map_err(ChromaClientError::IoError)?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: ExportRecord =
+                serde_json::from_str(&line).map_err(ChromaClientError::ResponseParseError)?;
+            batch.push(record);
+
+            if batch.len() == batch_size {
+                imported += batch.len();
+                self.upsert(client, std::mem::take(&mut batch).into_iter().collect())
+                    .await?;
+            }
+        }
+
+        if !batch.is_empty() {
+            imported += batch.len();
+            self.upsert(client, batch.into_iter().collect()).await?;
+        }
+
+        Ok(imported)
+    }
+}
+
+impl FromIterator<ExportRecord> for Entries {
+    fn from_iter<I: IntoIterator<Item = ExportRecord>>(records: I) -> Self {
+        let records: Vec<ExportRecord> = records.into_iter().collect();
+        let has_documents = records.iter().any(|r| r.document.is_some());
+        let has_embeddings = records.iter().any(|r| r.embedding.is_some());
+        let mut entries = Entries::default();
+
+        for record in records {
+            entries.ids.push(record.id);
+            if has_documents {
+                let documents = entries.documents.get_or_insert_with(Vec::new);
+                documents.push(record.document.unwrap_or_default());
+            }
+            if has_embeddings {
+                let embeddings = entries.embeddings.get_or_insert_with(Vec::new);
+                embeddings.push(record.embedding.unwrap_or_default());
+            }
+            let metadatas = entries.metadatas.get_or_insert_with(Vec::new);
+            metadatas.push(record.metadata.unwrap_or_default());
+        }
+
+        entries
+    }
+}
+
+/// The next page, read again up to `retries` times after a retryable error.
+async fn next_page(
+    pages: &mut GetPages<'_>,
+    retries: u32,
+) -> Result<Option<GetResult>, ChromaClientError> {
+    let mut attempt = 0;
+    loop {
+        match pages.next().await {
+            Ok(page) => return Ok(page),
+            Err(e) if e.is_retryable() && attempt < retries => {
+                tokio::time::sleep(EXPORT_RETRY_DELAY * 2u32.pow(attempt)).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn get_result_to_records() {
+        let result: GetResult = serde_json::from_value(json!({
+            "ids": ["a", "b"],
+            "embeddings": [[1.0], [2.0]],
+            "documents": ["doc a", null],
+            "metadatas": [{"k": 1}, null],
+        }))
+        .unwrap();
+
+        let records = ExportRecord::from_result(result);
+
+        assert_eq!(
+            serde_json::to_value(&records).unwrap(),
+            json!([
+                {"id": "a", "document": "doc a", "metadata": {"k": 1}, "embedding": [1.0]},
+                {"id": "b", "embedding": [2.0]},
+            ])
+        );
+    }
+
+    #[test]
+    fn checkpoint_round_trip() {
+        let path = std::env::temp_dir().join(format!(
+            "chroma-rs-export-checkpoint-{}.json",
+            std::process::id()
+        ));
+        let checkpoint = ExportCheckpoint {
+            collection_id: "c0ffee".into(),
+            offset: 1500,
+        };
+
+        checkpoint.save(&path).unwrap();
+
+        assert_eq!(ExportCheckpoint::load(&path).unwrap(), Some(checkpoint));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(ExportCheckpoint::load(&path).unwrap(), None);
+    }
+}
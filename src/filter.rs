@@ -0,0 +1,830 @@
+use crate::metadata::{unix_seconds, CREATED_AT_KEY};
+use serde::{Serialize, Serializer};
+use serde_json::{json, Map, Value};
+use std::fmt;
+use std::ops::{BitAnd, BitOr, Bound, Not, Range, RangeBounds};
+use std::time::SystemTime;
+
+/// Typed metadata filter, the `where` of get, query and delete requests.
+///
+/// ```
+/// use chromadb_rs::filter::Where;
+/// use chromadb_rs::where_filter;
+///
+/// let filter = where_filter!({"genre": {"$in": ["sci-fi", "fantasy"]}, "year": {"$gte": 2020}});
+///
+/// assert_eq!(
+///     Where::and(vec![
+///         Where::is_in("genre", ["sci-fi", "fantasy"]),
+///         Where::gte("year", 2020),
+///     ]),
+///     Some(filter.clone())
+/// );
+/// assert_eq!(
+///     filter,
+///     Where::is_in("genre", ["sci-fi", "fantasy"]) & Where::gte("year", 2020)
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum Where {
+    Comparison {
+        key: String,
+        op: Operator,
+        value: Value,
+    },
+    And(Vec<Where>),
+    Or(Vec<Where>),
+}
+
+/// Comparison operators of metadata filters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Eq,
+    Ne,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    In,
+    Nin,
+}
+
+impl Operator {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Operator::Eq => "$eq",
+            Operator::Ne => "$ne",
+            Operator::Gt => "$gt",
+            Operator::Gte => "$gte",
+            Operator::Lt => "$lt",
+            Operator::Lte => "$lte",
+            Operator::In => "$in",
+            Operator::Nin => "$nin",
+        }
+    }
+}
+
+impl Operator {
+    /// The operator matching the values this one does not.
+    fn negate(self) -> Self {
+        match self {
+            Operator::Eq => Operator::Ne,
+            Operator::Ne => Operator::Eq,
+            Operator::Gt => Operator::Lte,
+            Operator::Gte => Operator::Lt,
+            Operator::Lt => Operator::Gte,
+            Operator::Lte => Operator::Gt,
+            Operator::In => Operator::Nin,
+            Operator::Nin => Operator::In,
+        }
+    }
+}
+
+impl Where {
+    pub fn compare(key: &str, op: Operator, value: impl Into<Value>) -> Self {
+        Where::Comparison {
+            key: key.to_string(),
+            op,
+            value: value.into(),
+        }
+    }
+
+    pub fn eq(key: &str, value: impl Into<Value>) -> Self {
+        Where::compare(key, Operator::Eq, value)
+    }
+
+    pub fn ne(key: &str, value: impl Into<Value>) -> Self {
+        Where::compare(key, Operator::Ne, value)
+    }
+
+    pub fn gt(key: &str, value: impl Into<Value>) -> Self {
+        Where::compare(key, Operator::Gt, value)
+    }
+
+    pub fn gte(key: &str, value: impl Into<Value>) -> Self {
+        Where::compare(key, Operator::Gte, value)
+    }
+
+    pub fn lt(key: &str, value: impl Into<Value>) -> Self {
+        Where::compare(key, Operator::Lt, value)
+    }
+
+    pub fn lte(key: &str, value: impl Into<Value>) -> Self {
+        Where::compare(key, Operator::Lte, value)
+    }
+
+    /// The value is one of `values`.
+    pub fn is_in<V: Into<Value>>(key: &str, values: impl IntoIterator<Item = V>) -> Self {
+        let values: Vec<Value> = values.into_iter().map(Into::into).collect();
+        Where::compare(key, Operator::In, values)
+    }
+
+    /// The value is none of `values`.
+    pub fn not_in<V: Into<Value>>(key: &str, values: impl IntoIterator<Item = V>) -> Self {
+        let values: Vec<Value> = values.into_iter().map(Into::into).collect();
+        Where::compare(key, Operator::Nin, values)
+    }
+
+    /// The value is in `range`, e.g. `Where::range("price", 10..=100)` for
+    /// `$gte` 10 and `$lte` 100. Excluded bounds use `$gt` and `$lt`. None
+    /// for a range without any bound, which filters nothing.
+    pub fn range<T: Into<Value> + Clone>(key: &str, range: impl RangeBounds<T>) -> Option<Self> {
+        let start = match range.start_bound() {
+            Bound::Included(v) => Some(Where::gte(key, v.clone())),
+            Bound::Excluded(v) => Some(Where::gt(key, v.clone())),
+            Bound::Unbounded => None,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(v) => Some(Where::lte(key, v.clone())),
+            Bound::Excluded(v) => Some(Where::lt(key, v.clone())),
+            Bound::Unbounded => None,
+        };
+        Where::and(start.into_iter().chain(end).collect())
+    }
+
+    /// Every filter matches. A single filter is returned as is, since Chroma
+    /// rejects `$and` with less than two clauses, and None without filters.
+    pub fn and(mut filters: Vec<Where>) -> Option<Self> {
+        match filters.len() {
+            0 | 1 => filters.pop(),
+            _ => Some(Where::And(filters)),
+        }
+    }
+
+    /// Any filter matches. A single filter is returned as is, and None
+    /// without filters.
+    pub fn or(mut filters: Vec<Where>) -> Option<Self> {
+        match filters.len() {
+            0 | 1 => filters.pop(),
+            _ => Some(Where::Or(filters)),
+        }
+    }
+
+    /// The time stored under `key` is after `time`, see
+    /// `MetadataValue::from_datetime`.
+    pub fn after(key: &str, time: SystemTime) -> Self {
+        Where::gt(key, unix_seconds(time))
+    }
+
+    /// The time stored under `key` is before `time`.
+    pub fn before(key: &str, time: SystemTime) -> Self {
+        Where::lt(key, unix_seconds(time))
+    }
+
+    /// The time stored under `key` is in `range`, start included.
+    pub fn between(key: &str, range: Range<SystemTime>) -> Self {
+        Where::And(vec![
+            Where::gte(key, unix_seconds(range.start)),
+            Where::lt(key, unix_seconds(range.end)),
+        ])
+    }
+
+    /// The record was created after `time`, according to its `created_at`.
+    pub fn created_after(time: SystemTime) -> Self {
+        Where::after(CREATED_AT_KEY, time)
+    }
+
+    /// The record was created before `time`, according to its `created_at`.
+    pub fn created_before(time: SystemTime) -> Self {
+        Where::before(CREATED_AT_KEY, time)
+    }
+
+    pub fn to_value(&self) -> Value {
+        match self {
+            Where::Comparison { key, op, value } => {
+                let mut comparison = Map::new();
+                comparison.insert(op.as_str().to_string(), value.clone());
+                let mut filter = Map::new();
+                filter.insert(key.clone(), Value::Object(comparison));
+                Value::Object(filter)
+            }
+            Where::And(filters) => {
+                json!({ "$and": filters.iter().map(Where::to_value).collect::<Vec<_>>() })
+            }
+            Where::Or(filters) => {
+                json!({ "$or": filters.iter().map(Where::to_value).collect::<Vec<_>>() })
+            }
+        }
+    }
+}
+
+/// `a & b` and `a | b` for a filter type: nested `$and` and `$or` are
+/// merged into one.
+macro_rules! filter_operators {
+    ($filter:ident) => {
+        impl BitAnd for $filter {
+            type Output = $filter;
+
+            fn bitand(self, other: $filter) -> $filter {
+                let mut filters = match self {
+                    $filter::And(filters) => filters,
+                    filter => vec![filter],
+                };
+                match other {
+                    $filter::And(other) => filters.extend(other),
+                    filter => filters.push(filter),
+                }
+                $filter::And(filters)
+            }
+        }
+
+        impl BitOr for $filter {
+            type Output = $filter;
+
+            fn bitor(self, other: $filter) -> $filter {
+                let mut filters = match self {
+                    $filter::Or(filters) => filters,
+                    filter => vec![filter],
+                };
+                match other {
+                    $filter::Or(other) => filters.extend(other),
+                    filter => filters.push(filter),
+                }
+                $filter::Or(filters)
+            }
+        }
+    };
+}
+
+filter_operators!(Where);
+filter_operators!(WhereDocument);
+
+/// `!a`: the comparisons are inverted, `$and` and `$or` swapped. Records
+/// without a key compared by `a` may match neither `a` nor `!a`.
+impl Not for Where {
+    type Output = Where;
+
+    fn not(self) -> Where {
+        match self {
+            Where::Comparison { key, op, value } => Where::Comparison {
+                key,
+                op: op.negate(),
+                value,
+            },
+            Where::And(filters) => Where::Or(filters.into_iter().map(Not::not).collect()),
+            Where::Or(filters) => Where::And(filters.into_iter().map(Not::not).collect()),
+        }
+    }
+}
+
+impl Serialize for Where {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_value().serialize(serializer)
+    }
+}
+
+impl From<Where> for Value {
+    fn from(filter: Where) -> Self {
+        filter.to_value()
+    }
+}
+
+/// Typed document filter, the `where_document` of get, query and delete requests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WhereDocument {
+    Contains(String),
+    NotContains(String),
+    And(Vec<WhereDocument>),
+    Or(Vec<WhereDocument>),
+}
+
+impl WhereDocument {
+    pub fn contains(text: &str) -> Self {
+        WhereDocument::Contains(text.to_string())
+    }
+
+    pub fn not_contains(text: &str) -> Self {
+        WhereDocument::NotContains(text.to_string())
+    }
+
+    /// Every filter matches. A single filter is returned as is, and None
+    /// without filters.
+    pub fn and(mut filters: Vec<WhereDocument>) -> Option<Self> {
+        match filters.len() {
+            0 | 1 => filters.pop(),
+            _ => Some(WhereDocument::And(filters)),
+        }
+    }
+
+    /// Any filter matches. A single filter is returned as is, and None
+    /// without filters.
+    pub fn or(mut filters: Vec<WhereDocument>) -> Option<Self> {
+        match filters.len() {
+            0 | 1 => filters.pop(),
+            _ => Some(WhereDocument::Or(filters)),
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        let all = |filters: &[WhereDocument]| {
+            filters
+                .iter()
+                .map(WhereDocument::to_value)
+                .collect::<Vec<_>>()
+        };
+        match self {
+            WhereDocument::Contains(text) => json!({ "$contains": text }),
+            WhereDocument::NotContains(text) => json!({ "$not_contains": text }),
+            WhereDocument::And(filters) => json!({ "$and": all(filters) }),
+            WhereDocument::Or(filters) => json!({ "$or": all(filters) }),
+        }
+    }
+}
+
+/// `!a`: `$contains` and `$not_contains` swapped, as are `$and` and `$or`.
+impl Not for WhereDocument {
+    type Output = WhereDocument;
+
+    fn not(self) -> WhereDocument {
+        match self {
+            WhereDocument::Contains(text) => WhereDocument::NotContains(text),
+            WhereDocument::NotContains(text) => WhereDocument::Contains(text),
+            WhereDocument::And(filters) => {
+                WhereDocument::Or(filters.into_iter().map(Not::not).collect())
+            }
+            WhereDocument::Or(filters) => {
+                WhereDocument::And(filters.into_iter().map(Not::not).collect())
+            }
+        }
+    }
+}
+
+impl Serialize for WhereDocument {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_value().serialize(serializer)
+    }
+}
+
+impl From<WhereDocument> for Value {
+    fn from(filter: WhereDocument) -> Self {
+        filter.to_value()
+    }
+}
+
+/// A filter rejected by `Where::try_from`, with the path of the offending part.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterError {
+    /// Path in the filter, e.g. `$where.$and[1].year.$gte`.
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.path, self.message)
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+impl TryFrom<Value> for Where {
+    type Error = FilterError;
+
+    /// Parse and validate a JSON filter. Objects with several keys are read
+    /// as the `$and` of each key.
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        parse_where(&value, "$where")
+    }
+}
+
+impl TryFrom<&Value> for Where {
+    type Error = FilterError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        parse_where(value, "$where")
+    }
+}
+
+impl TryFrom<Value> for WhereDocument {
+    type Error = FilterError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        parse_where_document(&value, "$where_document")
+    }
+}
+
+impl TryFrom<&Value> for WhereDocument {
+    type Error = FilterError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        parse_where_document(value, "$where_document")
+    }
+}
+
+fn error(path: &str, message: &str) -> FilterError {
+    FilterError {
+        path: path.to_string(),
+        message: message.to_string(),
+    }
+}
+
+fn parse_where(value: &Value, path: &str) -> Result<Where, FilterError> {
+    let object = value
+        .as_object()
+        .ok_or_else(|| error(path, "expects an object"))?;
+
+    let clauses = object
+        .iter()
+        .map(|(key, value)| {
+            let path = format!("{}.{}", path, key);
+            match key.as_str() {
+                "$and" | "$or" => {
+                    let filters = value
+                        .as_array()
+                        .filter(|filters| filters.len() >= 2)
+                        .ok_or_else(|| error(&path, "expects an array of at least two filters"))?
+                        .iter()
+                        .enumerate()
+                        .map(|(i, filter)| parse_where(filter, &format!("{}[{}]", path, i)))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    Ok(if key == "$and" {
+                        Where::And(filters)
+                    } else {
+                        Where::Or(filters)
+                    })
+                }
+                _ if key.starts_with('$') => Err(error(&path, "is not a logical operator")),
+                _ => parse_comparisons(key, value, &path),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Where::and(clauses).ok_or_else(|| error(path, "expects at least one key"))
+}
+
+fn parse_where_document(value: &Value, path: &str) -> Result<WhereDocument, FilterError> {
+    let (key, value) = value
+        .as_object()
+        .filter(|object| object.len() == 1)
+        .and_then(|object| object.iter().next())
+        .ok_or_else(|| error(path, "expects an object with a single operator"))?;
+    let path = format!("{}.{}", path, key);
+
+    match key.as_str() {
+        "$contains" | "$not_contains" => {
+            let text = value
+                .as_str()
+                .ok_or_else(|| error(&path, "expects string"))?;
+            Ok(if key == "$contains" {
+                WhereDocument::contains(text)
+            } else {
+                WhereDocument::not_contains(text)
+            })
+        }
+        "$and" | "$or" => {
+            let filters = value
+                .as_array()
+                .filter(|filters| filters.len() >= 2)
+                .ok_or_else(|| error(&path, "expects an array of at least two filters"))?
+                .iter()
+                .enumerate()
+                .map(|(i, filter)| parse_where_document(filter, &format!("{}[{}]", path, i)))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(if key == "$and" {
+                WhereDocument::And(filters)
+            } else {
+                WhereDocument::Or(filters)
+            })
+        }
+        _ => Err(error(&path, "is not a document operator")),
+    }
+}
+
+fn parse_comparisons(key: &str, value: &Value, path: &str) -> Result<Where, FilterError> {
+    let Some(operators) = value.as_object() else {
+        check_scalar(value, path)?;
+        return Ok(Where::eq(key, value.clone()));
+    };
+
+    let comparisons = operators
+        .iter()
+        .map(|(op, value)| {
+            let path = format!("{}.{}", path, op);
+            let op = match op.as_str() {
+                "$eq" => Operator::Eq,
+                "$ne" => Operator::Ne,
+                "$gt" => Operator::Gt,
+                "$gte" => Operator::Gte,
+                "$lt" => Operator::Lt,
+                "$lte" => Operator::Lte,
+                "$in" => Operator::In,
+                "$nin" => Operator::Nin,
+                _ => return Err(error(&path, "is not a comparison operator")),
+            };
+
+            match op {
+                Operator::Gt | Operator::Gte | Operator::Lt | Operator::Lte => {
+                    if !value.is_number() {
+                        return Err(error(&path, "expects number"));
+                    }
+                }
+                Operator::In | Operator::Nin => {
+                    let values = value
+                        .as_array()
+                        .filter(|values| !values.is_empty())
+                        .ok_or_else(|| error(&path, "expects a non-empty array"))?;
+                    for (i, value) in values.iter().enumerate() {
+                        check_scalar(value, &format!("{}[{}]", path, i))?;
+                    }
+                    if values.iter().any(|v| kind(v) != kind(&values[0])) {
+                        return Err(error(&path, "expects values of a single type"));
+                    }
+                }
+                Operator::Eq | Operator::Ne => check_scalar(value, &path)?,
+            }
+            Ok(Where::compare(key, op, value.clone()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Where::and(comparisons).ok_or_else(|| error(path, "expects at least one operator"))
+}
+
+/// Whether `key` is an operator rather than a metadata key, used by
+/// `where_filter!` to reject unknown operators at compile time.
+#[doc(hidden)]
+pub const fn is_operator(key: &str) -> bool {
+    matches!(key.as_bytes().first(), Some(b'$'))
+}
+
+fn check_scalar(value: &Value, path: &str) -> Result<(), FilterError> {
+    match value {
+        Value::String(_) | Value::Number(_) | Value::Bool(_) => Ok(()),
+        _ => Err(error(path, "expects string, number or bool")),
+    }
+}
+
+fn kind(value: &Value) -> u8 {
+    match value {
+        Value::String(_) => 0,
+        Value::Number(_) => 1,
+        _ => 2,
+    }
+}
+
+/// Builds a [`Where`](crate::filter::Where) from JSON-like syntax. Keys
+/// are string literals and operators are checked at compile time. Several
+/// keys, or several operators on a key, are joined with `$and`, and a bare
+/// value means `$eq`. Bare values are single tokens: write negative numbers
+/// as `{"$eq": -1}`.
+///
+/// ```
+/// use chromadb_rs::where_filter;
+///
+/// let filter = where_filter!({
+///     "$or": [{"author": "le guin"}, {"year": {"$gte": 2020, "$lt": 2025}}]
+/// });
+/// ```
+///
+/// Unknown operators and keys, and `$and` or `$or` with fewer than two
+/// filters, do not compile:
+///
+/// ```compile_fail
+/// chromadb_rs::where_filter!({"year": {"$after": 2020}});
+/// ```
+///
+/// ```compile_fail
+/// chromadb_rs::where_filter!({"$not": {"year": 2020}});
+/// ```
+///
+/// ```compile_fail
+/// chromadb_rs::where_filter!({"$or": [{"year": 2020}]});
+/// ```
+#[macro_export]
+macro_rules! where_filter {
+    ({ $($key:tt : $condition:tt),+ $(,)? }) => {
+        $crate::filter::Where::and(vec![$($crate::where_filter!(@key $key, $condition)),+])
+            .expect("at least one key")
+    };
+    (@key "$and", [ $first:tt $(, $rest:tt)+ $(,)? ]) => {
+        $crate::filter::Where::And(vec![
+            $crate::where_filter!($first),
+            $($crate::where_filter!($rest)),+
+        ])
+    };
+    (@key "$or", [ $first:tt $(, $rest:tt)+ $(,)? ]) => {
+        $crate::filter::Where::Or(vec![
+            $crate::where_filter!($first),
+            $($crate::where_filter!($rest)),+
+        ])
+    };
+    (@key "$and", $filters:tt) => {
+        compile_error!("\"$and\" takes an array of at least two filters")
+    };
+    (@key "$or", $filters:tt) => {
+        compile_error!("\"$or\" takes an array of at least two filters")
+    };
+    (@key $key:literal, { $($op:tt : $value:expr),+ $(,)? }) => {{
+        $crate::where_filter!(@field $key);
+        $crate::filter::Where::and(vec![$($crate::where_filter!(@op $key, $op, $value)),+])
+            .expect("at least one operator")
+    }};
+    (@key $key:literal, $value:expr) => {{
+        $crate::where_filter!(@field $key);
+        $crate::filter::Where::eq($key, $value)
+    }};
+    (@field $key:literal) => {
+        const _: () = assert!(
+            !$crate::filter::is_operator($key),
+            concat!("unsupported where key ", $key)
+        );
+    };
+    (@op $key:tt, "$eq", $value:expr) => { $crate::filter::Where::eq($key, $value) };
+    (@op $key:tt, "$ne", $value:expr) => { $crate::filter::Where::ne($key, $value) };
+    (@op $key:tt, "$gt", $value:expr) => { $crate::filter::Where::gt($key, $value) };
+    (@op $key:tt, "$gte", $value:expr) => { $crate::filter::Where::gte($key, $value) };
+    (@op $key:tt, "$lt", $value:expr) => { $crate::filter::Where::lt($key, $value) };
+    (@op $key:tt, "$lte", $value:expr) => { $crate::filter::Where::lte($key, $value) };
+    (@op $key:tt, "$in", $value:expr) => { $crate::filter::Where::is_in($key, $value) };
+    (@op $key:tt, "$nin", $value:expr) => { $crate::filter::Where::not_in($key, $value) };
+    (@op $key:tt, $op:tt, $value:expr) => {
+        compile_error!(concat!("unsupported where operator ", $op))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn serialize_where() {
+        let filter = where_filter!({
+            "$or": [{"author": "le guin"}, {"year": {"$gte": 2020, "$lt": 2025}}]
+        });
+
+        assert_eq!(
+            Value::from(filter),
+            json!({
+                "$or": [
+                    { "author": { "$eq": "le guin" } },
+                    { "$and": [{ "year": { "$gte": 2020 } }, { "year": { "$lt": 2025 } }] }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn parse_json_filter() {
+        let value = json!({ "$or": [{ "author": "le guin" }, { "year": { "$gte": 2020 } }] });
+
+        assert_eq!(
+            Where::try_from(value).unwrap(),
+            Where::Or(vec![
+                Where::eq("author", "le guin"),
+                Where::gte("year", 2020)
+            ])
+        );
+    }
+
+    #[test]
+    fn empty_and_single_filters() {
+        assert_eq!(Where::and(Vec::new()), None);
+        assert_eq!(Where::or(Vec::new()), None);
+        assert_eq!(WhereDocument::and(Vec::new()), None);
+        assert_eq!(Where::range::<i64>("year", ..), None);
+        assert_eq!(
+            Where::or(vec![Where::eq("genre", "sci-fi")]),
+            Some(Where::eq("genre", "sci-fi"))
+        );
+        assert!(Where::try_from(json!({})).is_err());
+        assert!(Where::try_from(json!({ "year": {} })).is_err());
+    }
+
+    #[test]
+    fn ranges_and_operators() {
+        assert_eq!(
+            Where::range("price", 10..=100),
+            Where::and(vec![Where::gte("price", 10), Where::lte("price", 100)])
+        );
+        assert_eq!(Where::range("price", ..0.5), Some(Where::lt("price", 0.5)));
+        assert_eq!(Where::range("year", 2020..), Some(Where::gte("year", 2020)));
+
+        let filter = Where::range("price", 10..100).unwrap() & Where::eq("genre", "sci-fi")
+            | Where::eq("author", "le guin")
+            | Where::eq("author", "butler");
+        assert_eq!(
+            filter,
+            Where::Or(vec![
+                Where::And(vec![
+                    Where::gte("price", 10),
+                    Where::lt("price", 100),
+                    Where::eq("genre", "sci-fi"),
+                ]),
+                Where::eq("author", "le guin"),
+                Where::eq("author", "butler"),
+            ])
+        );
+    }
+
+    #[test]
+    fn negation() {
+        let genre = Where::eq("genre", "sci-fi");
+        let year = Where::range("year", 2020..).unwrap();
+        let filter = !((genre & year) | Where::is_in("author", ["le guin"]));
+
+        assert_eq!(
+            filter,
+            Where::And(vec![
+                Where::Or(vec![Where::ne("genre", "sci-fi"), Where::lt("year", 2020)]),
+                Where::not_in("author", ["le guin"]),
+            ])
+        );
+
+        let document = !(WhereDocument::contains("rust") | WhereDocument::not_contains("c++"))
+            & WhereDocument::contains("async");
+        assert_eq!(
+            document,
+            WhereDocument::And(vec![
+                WhereDocument::not_contains("rust"),
+                WhereDocument::contains("c++"),
+                WhereDocument::contains("async"),
+            ])
+        );
+    }
+
+    fn key() -> impl Strategy<Value = String> {
+        "[a-z][a-z_]{0,8}"
+    }
+
+    fn scalar() -> impl Strategy<Value = Value> {
+        prop_oneof![
+            "[a-zA-Z0-9 ]{0,12}".prop_map(Value::from),
+            any::<i64>().prop_map(Value::from),
+            // Quarters are exact in binary and decimal, so they survive the JSON text.
+            (-4000i32..4000).prop_map(|q| Value::from(q as f64 / 4.0)),
+            any::<bool>().prop_map(Value::from),
+        ]
+    }
+
+    fn list() -> impl Strategy<Value = Vec<Value>> {
+        prop_oneof![
+            prop::collection::vec("[a-z]{1,6}".prop_map(Value::from), 1..5),
+            prop::collection::vec(any::<i64>().prop_map(Value::from), 1..5),
+            prop::collection::vec(any::<bool>().prop_map(Value::from), 1..5),
+        ]
+    }
+
+    fn comparison() -> impl Strategy<Value = Where> {
+        prop_oneof![
+            (key(), scalar()).prop_map(|(k, v)| Where::eq(&k, v)),
+            (key(), scalar()).prop_map(|(k, v)| Where::ne(&k, v)),
+            (key(), any::<i64>()).prop_map(|(k, v)| Where::gt(&k, v)),
+            (key(), any::<i64>()).prop_map(|(k, v)| Where::gte(&k, v)),
+            (key(), any::<i64>()).prop_map(|(k, v)| Where::lt(&k, v)),
+            (key(), any::<i64>()).prop_map(|(k, v)| Where::lte(&k, v)),
+            (key(), list()).prop_map(|(k, v)| Where::is_in(&k, v)),
+            (key(), list()).prop_map(|(k, v)| Where::not_in(&k, v)),
+        ]
+    }
+
+    fn where_filter() -> impl Strategy<Value = Where> {
+        comparison().prop_recursive(3, 24, 4, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 2..4).prop_map(Where::And),
+                prop::collection::vec(inner, 2..4).prop_map(Where::Or),
+            ]
+        })
+    }
+
+    fn where_document() -> impl Strategy<Value = WhereDocument> {
+        let leaf = prop_oneof![
+            ".{0,12}".prop_map(|t| WhereDocument::contains(&t)),
+            ".{0,12}".prop_map(|t| WhereDocument::not_contains(&t)),
+        ];
+        leaf.prop_recursive(3, 24, 4, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 2..4).prop_map(WhereDocument::And),
+                prop::collection::vec(inner, 2..4).prop_map(WhereDocument::Or),
+            ]
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn where_round_trip(filter in where_filter()) {
+            let text = serde_json::to_string(&filter).unwrap();
+            let value: Value = serde_json::from_str(&text).unwrap();
+
+            prop_assert_eq!(Where::try_from(value).unwrap(), filter);
+        }
+
+        #[test]
+        fn where_document_round_trip(filter in where_document()) {
+            let text = serde_json::to_string(&filter).unwrap();
+            let value: Value = serde_json::from_str(&text).unwrap();
+
+            prop_assert_eq!(WhereDocument::try_from(value).unwrap(), filter);
+        }
+    }
+
+    #[test]
+    fn invalid_filter_error_path() {
+        let value = json!({ "$and": [{ "genre": "sci-fi" }, { "year": { "$gte": "2020" } }] });
+
+        let error = Where::try_from(value).unwrap_err();
+        assert_eq!(error.to_string(), "$where.$and[1].year.$gte expects number");
+
+        let error = Where::try_from(json!({ "year": { "$in": [1, "2"] } })).unwrap_err();
+        assert_eq!(error.path, "$where.year.$in");
+    }
+}
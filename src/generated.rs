@@ -0,0 +1,122 @@
+//! Request bodies of the Chroma v1 API, generated by `build.rs` from the
+//! schemas of `openapi/chroma-v1.json`, a subset of the published OpenAPI
+//! spec. Update the spec file to follow a new server version: the
+//! ergonomic types such as `GetParams` convert into these structs, which
+//! are what the collection methods send.
+
+use crate::collection::{DeleteParams, Entries, GetParams, QueryParams};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+include!(concat!(env!("OUT_DIR"), "/generated.rs"));
+
+impl From<Entries> for AddEmbedding {
+    fn from(entries: Entries) -> Self {
+        AddEmbedding {
+            embeddings: entries.embeddings,
+            // Empty metadata are sent as null, see `Entries`.
+            metadatas: entries.metadatas.map(|metadatas| {
+                metadatas
+                    .into_iter()
+                    .map(|m| (!m.is_empty()).then_some(m))
+                    .collect()
+            }),
+            documents: entries
+                .documents
+                .map(|documents| documents.into_iter().map(Some).collect()),
+            uris: None,
+            ids: entries.ids,
+        }
+    }
+}
+
+impl From<Entries> for UpdateEmbedding {
+    fn from(entries: Entries) -> Self {
+        let add = AddEmbedding::from(entries);
+        UpdateEmbedding {
+            embeddings: add.embeddings,
+            metadatas: add.metadatas,
+            documents: add.documents,
+            uris: add.uris,
+            ids: add.ids,
+        }
+    }
+}
+
+impl From<GetParams> for GetEmbedding {
+    fn from(params: GetParams) -> Self {
+        GetEmbedding {
+            ids: params.ids,
+            r#where: params.where_metadata,
+            where_document: params.where_document,
+            sort: None,
+            limit: params.limit,
+            offset: params.offset,
+            include: params.include,
+        }
+    }
+}
+
+impl From<QueryParams> for QueryEmbedding {
+    fn from(params: QueryParams) -> Self {
+        QueryEmbedding {
+            r#where: params.where_metadata,
+            where_document: params.where_document,
+            query_embeddings: params.query_embeddings,
+            n_results: Some(params.n_results),
+            include: params.include,
+        }
+    }
+}
+
+impl From<DeleteParams> for DeleteEmbedding {
+    fn from(params: DeleteParams) -> Self {
+        DeleteEmbedding {
+            ids: params.ids,
+            r#where: params.where_metadata,
+            where_document: params.where_document,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collection::Include;
+    use serde_json::json;
+
+    fn same_body<P: Serialize, S: Serialize + From<P>>(params: P) {
+        assert_eq!(
+            serde_json::to_value(&params).unwrap(),
+            serde_json::to_value(S::from(params)).unwrap()
+        );
+    }
+
+    #[test]
+    fn params_match_the_spec() {
+        same_body::<_, AddEmbedding>(Entries {
+            ids: vec!["a".into(), "b".into()],
+            embeddings: Some(vec![vec![0.5], vec![1.5]]),
+            metadatas: Some(vec![
+                json!({"page": 1}).as_object().unwrap().clone(),
+                Metadata::new(),
+            ]),
+            documents: Some(vec!["first".into(), "second".into()]),
+        });
+        same_body::<_, GetEmbedding>(GetParams {
+            where_metadata: Some(json!({"page": {"$gt": 1}})),
+            limit: Some(10),
+            include: Some(vec![Include::Documents]),
+            ..Default::default()
+        });
+        same_body::<_, QueryEmbedding>(QueryParams {
+            query_embeddings: vec![vec![0.5, 1.0]],
+            where_document: Some(json!({"$contains": "rust"})),
+            ..Default::default()
+        });
+        same_body::<_, DeleteEmbedding>(DeleteParams {
+            ids: Some(vec!["a".into()]),
+            ..Default::default()
+        });
+    }
+}
@@ -0,0 +1,224 @@
+//! Locations stored as metadata: a bounding box filter narrows the records
+//! on the server, then the exact distance is checked on the client.
+
+use crate::client::ChromaClient;
+use crate::collection::{Collection, Include, Metadata, QueryParams};
+use crate::error::ChromaClientError;
+use crate::filter::Where;
+use crate::hits::QueryHit;
+use crate::metadata::MetadataValue;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Metadata key of the latitude of a record, in degrees.
+pub const LATITUDE_KEY: &str = "lat";
+/// Metadata key of the longitude of a record, in degrees.
+pub const LONGITUDE_KEY: &str = "lon";
+
+/// Mean radius of the Earth.
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// A point on Earth, in degrees.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GeoPoint {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl GeoPoint {
+    pub fn new(lat: f64, lon: f64) -> Self {
+        GeoPoint { lat, lon }
+    }
+
+    /// Store the point as `lat` and `lon` metadata. Both are always floats,
+    /// so filters compare them as such.
+    pub fn insert_into(&self, metadata: &mut Metadata) {
+        metadata.insert(LATITUDE_KEY.into(), MetadataValue::Float(self.lat).into());
+        metadata.insert(LONGITUDE_KEY.into(), MetadataValue::Float(self.lon).into());
+    }
+
+    /// The point stored in the metadata, if any.
+    pub fn from_metadata(metadata: &Metadata) -> Option<Self> {
+        let lat = metadata.get(LATITUDE_KEY)?.as_f64()?;
+        let lon = metadata.get(LONGITUDE_KEY)?.as_f64()?;
+        Some(GeoPoint { lat, lon })
+    }
+
+    /// Great-circle distance to another point, by the haversine formula.
+    pub fn distance_km(&self, other: &GeoPoint) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let dlat = lat2 - lat1;
+        let dlon = (other.lon - self.lon).to_radians();
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_KM * a.sqrt().min(1.0).asin()
+    }
+}
+
+/// A range of latitudes and longitudes. `min_lon` is greater than `max_lon`
+/// for boxes crossing the antimeridian.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BoundingBox {
+    pub min_lat: f64,
+    pub max_lat: f64,
+    pub min_lon: f64,
+    pub max_lon: f64,
+}
+
+impl BoundingBox {
+    /// The smallest box holding every point within `radius_km` of `center`.
+    pub fn around(center: GeoPoint, radius_km: f64) -> Self {
+        let dlat = (radius_km / EARTH_RADIUS_KM).to_degrees();
+        let (min_lat, max_lat) = (center.lat - dlat, center.lat + dlat);
+        if min_lat <= -90.0 || max_lat >= 90.0 {
+            // The box holds a pole, so every longitude.
+            return BoundingBox {
+                min_lat: min_lat.max(-90.0),
+                max_lat: max_lat.min(90.0),
+                min_lon: -180.0,
+                max_lon: 180.0,
+            };
+        }
+
+        let dlon = (dlat.to_radians().sin() / center.lat.to_radians().cos())
+            .min(1.0)
+            .asin()
+            .to_degrees();
+        if dlon >= 180.0 || dlon.is_nan() {
+            return BoundingBox {
+                min_lat,
+                max_lat,
+                min_lon: -180.0,
+                max_lon: 180.0,
+            };
+        }
+        BoundingBox {
+            min_lat,
+            max_lat,
+            min_lon: wrap_longitude(center.lon - dlon),
+            max_lon: wrap_longitude(center.lon + dlon),
+        }
+    }
+
+    pub fn contains(&self, point: &GeoPoint) -> bool {
+        let lon = if self.min_lon <= self.max_lon {
+            (self.min_lon..=self.max_lon).contains(&point.lon)
+        } else {
+            point.lon >= self.min_lon || point.lon <= self.max_lon
+        };
+        (self.min_lat..=self.max_lat).contains(&point.lat) && lon
+    }
+
+    /// Filter on the `lat` and `lon` metadata of the records in the box.
+    pub fn to_where(&self) -> Where {
+        let range = |key, min: f64, max: f64| {
+            vec![
+                Where::gte(key, MetadataValue::Float(min)),
+                Where::lte(key, MetadataValue::Float(max)),
+            ]
+        };
+        let mut filters = range(LATITUDE_KEY, self.min_lat, self.max_lat);
+        if self.min_lon <= self.max_lon {
+            filters.extend(range(LONGITUDE_KEY, self.min_lon, self.max_lon));
+        } else {
+            filters.push(Where::Or(vec![
+                Where::gte(LONGITUDE_KEY, MetadataValue::Float(self.min_lon)),
+                Where::lte(LONGITUDE_KEY, MetadataValue::Float(self.max_lon)),
+            ]));
+        }
+        Where::And(filters)
+    }
+}
+
+fn wrap_longitude(lon: f64) -> f64 {
+    (lon + 180.0).rem_euclid(360.0) - 180.0
+}
+
+/// The hits within `radius_km` of `center`, with their distance. Hits
+/// without a location are dropped.
+pub fn within_radius(
+    hits: Vec<QueryHit>,
+    center: GeoPoint,
+    radius_km: f64,
+) -> Vec<(QueryHit, f64)> {
+    hits.into_iter()
+        .filter_map(|hit| {
+            let point = GeoPoint::from_metadata(hit.metadata.as_ref()?)?;
+            let distance = center.distance_km(&point);
+            (distance <= radius_km).then_some((hit, distance))
+        })
+        .collect()
+}
+
+impl Collection {
+    /// Query the nearest neighbors among the records within `radius_km` of
+    /// `center`, in addition to the filters of `params`. The server filters
+    /// on the bounding box of the circle, and the hits in its corners are
+    /// dropped here; more results are fetched as by `query_filtered`.
+    pub async fn query_near(
+        &self,
+        client: &ChromaClient,
+        mut params: QueryParams,
+        center: GeoPoint,
+        radius_km: f64,
+    ) -> Result<Vec<Vec<QueryHit>>, ChromaClientError> {
+        let area: Value = BoundingBox::around(center, radius_km).to_where().into();
+        params.where_metadata = Some(match params.where_metadata.take() {
+            Some(filter) => json!({ "$and": [filter, area] }),
+            None => area,
+        });
+        if let Some(include) = params.include.as_mut() {
+            if !include.contains(&Include::Metadatas) {
+                include.push(Include::Metadatas);
+            }
+        }
+
+        self.query_filtered(client, params, None, |hit| {
+            hit.metadata
+                .as_ref()
+                .and_then(GeoPoint::from_metadata)
+                .is_some_and(|point| center.distance_km(&point) <= radius_km)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distances_and_boxes() {
+        let paris = GeoPoint::new(48.8566, 2.3522);
+        let london = GeoPoint::new(51.5074, -0.1278);
+        assert!((paris.distance_km(&london) - 343.5).abs() < 1.0);
+
+        let area = BoundingBox::around(paris, 400.0);
+        assert!(area.contains(&london));
+        assert!(!BoundingBox::around(paris, 200.0).contains(&london));
+
+        let fiji = BoundingBox::around(GeoPoint::new(-17.7, 179.9), 50.0);
+        assert!(fiji.min_lon > fiji.max_lon);
+        assert!(fiji.contains(&GeoPoint::new(-17.7, -179.9)));
+        assert_eq!(
+            BoundingBox::around(GeoPoint::new(89.9, 0.0), 50.0).min_lon,
+            -180.0
+        );
+
+        let mut metadata = Metadata::new();
+        london.insert_into(&mut metadata);
+        let hit = |id: &str, metadata| QueryHit {
+            id: id.into(),
+            distance: None,
+            document: None,
+            metadata,
+            embedding: None,
+        };
+        let near = within_radius(
+            vec![hit("london", Some(metadata)), hit("nowhere", None)],
+            paris,
+            400.0,
+        );
+        assert_eq!(near.len(), 1);
+        assert_eq!(near[0].0.id, "london");
+    }
+}
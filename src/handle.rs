@@ -0,0 +1,97 @@
+use crate::client::ChromaClient;
+use crate::collection::{
+    Collection, DeleteParams, Entries, GetParams, GetResult, QueryParams, QueryResult,
+};
+use crate::error::ChromaClientError;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+
+/// A collection known by name only, whose id is looked up on the first data
+/// operation and then cached. Created by `ChromaClient::collection`; clones
+/// share the cached collection.
+#[derive(Debug, Clone)]
+pub struct CollectionHandle {
+    client: ChromaClient,
+    name: String,
+    collection: Arc<OnceCell<Collection>>,
+}
+
+impl ChromaClient {
+    /// A handle to the collection with the given name. No request is sent
+    /// until the handle is used, so a missing collection is only reported then.
+    pub fn collection(&self, name: &str) -> CollectionHandle {
+        CollectionHandle {
+            client: self.clone(),
+            name: name.to_string(),
+            collection: Arc::new(OnceCell::new()),
+        }
+    }
+}
+
+impl CollectionHandle {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the collection was already looked up.
+    pub fn is_resolved(&self) -> bool {
+        self.collection.initialized()
+    }
+
+    /// The collection, looked up on the first call.
+    pub async fn resolve(&self) -> Result<&Collection, ChromaClientError> {
+        self.collection
+            .get_or_try_init(|| self.client.get_collection(&self.name))
+            .await
+    }
+
+    pub async fn get(&self, params: GetParams) -> Result<GetResult, ChromaClientError> {
+        self.resolve().await?.get(&self.client, params).await
+    }
+
+    pub async fn count(&self) -> Result<usize, ChromaClientError> {
+        self.resolve().await?.count(&self.client).await
+    }
+
+    pub async fn add(&self, entries: Entries) -> Result<(), ChromaClientError> {
+        self.resolve().await?.add(&self.client, entries).await
+    }
+
+    pub async fn update(&self, entries: Entries) -> Result<(), ChromaClientError> {
+        self.resolve().await?.update(&self.client, entries).await
+    }
+
+    pub async fn upsert(&self, entries: Entries) -> Result<(), ChromaClientError> {
+        self.resolve().await?.upsert(&self.client, entries).await
+    }
+
+    pub async fn delete(&self, params: DeleteParams) -> Result<(), ChromaClientError> {
+        self.resolve().await?.delete(&self.client, params).await
+    }
+
+    pub async fn query(&self, params: QueryParams) -> Result<QueryResult, ChromaClientError> {
+        self.resolve().await?.query(&self.client, params).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::ChromaClientParams;
+
+    #[test]
+    fn handles_are_lazy() {
+        let client = ChromaClient::new(ChromaClientParams::default());
+
+        let handles: Vec<CollectionHandle> = (0..500)
+            .map(|i| client.collection(&format!("collection-{}", i)))
+            .collect();
+
+        assert!(handles.iter().all(|h| !h.is_resolved()));
+        assert_eq!(handles[7].name(), "collection-7");
+        assert!(Arc::ptr_eq(
+            &handles[0].collection,
+            &handles[0].clone().collection
+        ));
+    }
+}
@@ -0,0 +1,303 @@
+use crate::client::ChromaClient;
+use crate::collection::{Collection, Include, Metadata, QueryParams, QueryResult};
+use crate::error::ChromaClientError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Largest multiple of `n_results` fetched while looking for enough hits
+/// passing a client side filter.
+const MAX_OVERFETCH: usize = 16;
+
+/// Multiple of the hits needed fetched by `Collection::query_grouped`.
+const GROUP_OVERFETCH: usize = 4;
+
+/// A single nearest neighbor of a query embedding.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueryHit {
+    pub id: String,
+    pub distance: Option<f32>,
+    pub document: Option<String>,
+    pub metadata: Option<Metadata>,
+    pub embedding: Option<Vec<f32>>,
+}
+
+/// Retrieval options of `Collection::query_with_options`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QueryOptions {
+    /// Minimum score of the returned hits.
+    pub score_threshold: Option<f32>,
+    /// `n_results` times this factor are fetched, before applying the threshold.
+    pub overfetch_factor: usize,
+}
+
+/// Hits sharing the same value of a metadata key.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HitGroup {
+    /// Value of the key, null for hits without it.
+    pub key: Value,
+    pub hits: Vec<QueryHit>,
+}
+
+impl Default for QueryOptions {
+    fn default() -> Self {
+        QueryOptions {
+            score_threshold: None,
+            overfetch_factor: 2,
+        }
+    }
+}
+
+impl QueryHit {
+    /// Similarity score, `1 - distance`: 1 for an exact match, and the cosine
+    /// similarity in collections using the `cosine` space.
+    pub fn score(&self) -> Option<f32> {
+        self.distance.map(|d| 1.0 - d)
+    }
+}
+
+impl QueryResult {
+    /// Split the result into hits, one list per query embedding.
+    pub fn into_hits(self) -> Vec<Vec<QueryHit>> {
+        let mut distances = self.distances.map(|d| d.into_iter());
+        let mut embeddings = self.embeddings.map(|e| e.into_iter());
+        let mut documents = self.documents.map(|d| d.into_iter());
+        let mut metadatas = self.metadatas.map(|m| m.into_iter());
+
+        self.ids
+            .into_iter()
+            .map(|ids| {
+                let mut distances = distances
+                    .as_mut()
+                    .and_then(|d| d.next())
+                    .map(|d| d.into_iter());
+                let mut embeddings = embeddings
+                    .as_mut()
+                    .and_then(|e| e.next())
+                    .map(|e| e.into_iter());
+                let mut documents = documents
+                    .as_mut()
+                    .and_then(|d| d.next())
+                    .map(|d| d.into_iter());
+                let mut metadatas = metadatas
+                    .as_mut()
+                    .and_then(|m| m.next())
+                    .map(|m| m.into_iter());
+
+                ids.into_iter()
+                    .map(|id| QueryHit {
+                        id,
+                        distance: distances.as_mut().and_then(|d| d.next()),
+                        document: documents.as_mut().and_then(|d| d.next()).flatten(),
+                        metadata: metadatas.as_mut().and_then(|m| m.next()).flatten(),
+                        embedding: embeddings.as_mut().and_then(|e| e.next()),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl Collection {
+    /// Fetch `n_results` times `overfetch_factor` neighbors, then return up
+    /// to `n_results` hits per query scoring at least `score_threshold`.
+    pub async fn query_with_options(
+        &self,
+        client: &ChromaClient,
+        mut params: QueryParams,
+        options: &QueryOptions,
+    ) -> Result<Vec<Vec<QueryHit>>, ChromaClientError> {
+        let k = params.n_results;
+        params.n_results = k * options.overfetch_factor.max(1);
+        if options.score_threshold.is_some() {
+            with_distances(&mut params);
+        }
+
+        Ok(self
+            .query(client, params)
+            .await?
+            .into_hits()
+            .into_iter()
+            .map(|hits| apply_threshold(hits, options.score_threshold, k))
+            .collect())
+    }
+
+    /// Query the nearest neighbors grouped by the value of the `group_by`
+    /// metadata key, so a single source cannot fill the whole result. Returns
+    /// up to `n_results` groups per query, ordered by their best hit, with at
+    /// most `per_group` hits each.
+    pub async fn query_grouped(
+        &self,
+        client: &ChromaClient,
+        mut params: QueryParams,
+        group_by: &str,
+        per_group: usize,
+    ) -> Result<Vec<Vec<HitGroup>>, ChromaClientError> {
+        let groups = params.n_results;
+        let per_group = per_group.max(1);
+        params.n_results = groups * per_group * GROUP_OVERFETCH;
+        if let Some(include) = params.include.as_mut() {
+            if !include.contains(&Include::Metadatas) {
+                include.push(Include::Metadatas);
+            }
+        }
+
+        Ok(self
+            .query(client, params)
+            .await?
+            .into_hits()
+            .into_iter()
+            .map(|hits| group_hits(hits, group_by, groups, per_group))
+            .collect())
+    }
+
+    /// Query the nearest neighbors, keeping only the hits with a score of at
+    /// least `min_score` for which `keep` returns true. More results are
+    /// fetched as long as some query has fewer than `n_results` hits left,
+    /// up to 16 times `n_results`.
+    pub async fn query_filtered<F>(
+        &self,
+        client: &ChromaClient,
+        mut params: QueryParams,
+        min_score: Option<f32>,
+        keep: F,
+    ) -> Result<Vec<Vec<QueryHit>>, ChromaClientError>
+    where
+        F: Fn(&QueryHit) -> bool,
+    {
+        let k = params.n_results;
+        if min_score.is_some() {
+            with_distances(&mut params);
+        }
+        let passes = |hit: &QueryHit| meets_threshold(hit, min_score) && keep(hit);
+
+        let mut fetch = k.max(1);
+        loop {
+            params.n_results = fetch;
+            let hits = self.query(client, params.clone()).await?.into_hits();
+            let exhausted = hits.iter().all(|h| h.len() < fetch);
+
+            let filtered: Vec<Vec<QueryHit>> = hits
+                .into_iter()
+                .map(|h| h.into_iter().filter(|hit| passes(hit)).take(k).collect())
+                .collect();
+
+            let enough = filtered.iter().all(|h| h.len() >= k);
+            if enough || exhausted || fetch >= k * MAX_OVERFETCH {
+                return Ok(filtered);
+            }
+            fetch = (fetch * 2).min(k * MAX_OVERFETCH);
+        }
+    }
+}
+
+fn with_distances(params: &mut QueryParams) {
+    if let Some(include) = params.include.as_mut() {
+        if !include.contains(&Include::Distances) {
+            include.push(Include::Distances);
+        }
+    }
+}
+
+/// Group hits, given best first, keeping the first `groups` groups.
+fn group_hits(hits: Vec<QueryHit>, key: &str, groups: usize, per_group: usize) -> Vec<HitGroup> {
+    let mut grouped: Vec<HitGroup> = Vec::new();
+    for hit in hits {
+        let value = hit
+            .metadata
+            .as_ref()
+            .and_then(|m| m.get(key))
+            .cloned()
+            .unwrap_or(Value::Null);
+
+        match grouped.iter_mut().position(|g| g.key == value) {
+            Some(i) if grouped[i].hits.len() < per_group => grouped[i].hits.push(hit),
+            Some(_) => {}
+            None if grouped.len() < groups => grouped.push(HitGroup {
+                key: value,
+                hits: vec![hit],
+            }),
+            None => {}
+        }
+    }
+    grouped
+}
+
+fn meets_threshold(hit: &QueryHit, threshold: Option<f32>) -> bool {
+    threshold.is_none_or(|min| hit.score().is_some_and(|score| score >= min))
+}
+
+/// The first `k` hits scoring at least `threshold`.
+fn apply_threshold(hits: Vec<QueryHit>, threshold: Option<f32>, k: usize) -> Vec<QueryHit> {
+    hits.into_iter()
+        .filter(|hit| meets_threshold(hit, threshold))
+        .take(k)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn split_result_into_hits() {
+        let result: QueryResult = serde_json::from_value(json!({
+            "ids": [["a", "b"], ["c"]],
+            "distances": [[0.1, 0.4], [0.2]],
+            "documents": [["doc a", null], ["doc c"]],
+            "metadatas": null,
+            "embeddings": null
+        }))
+        .unwrap();
+
+        let hits = result.into_hits();
+
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0][1].id, "b");
+        assert_eq!(hits[0][1].document, None);
+        assert_eq!(hits[0][0].score(), Some(0.9));
+        assert_eq!(hits[1][0].document.as_deref(), Some("doc c"));
+    }
+
+    #[test]
+    fn threshold_keeps_best_hits() {
+        let hits: Vec<QueryHit> = [0.1, 0.3, 0.35, 0.8]
+            .iter()
+            .enumerate()
+            .map(|(i, d)| QueryHit {
+                id: i.to_string(),
+                distance: Some(*d),
+                ..Default::default()
+            })
+            .collect();
+
+        let kept = apply_threshold(hits.clone(), Some(0.6), 2);
+        assert_eq!(
+            kept.iter().map(|h| h.id.as_str()).collect::<Vec<_>>(),
+            ["0", "1"]
+        );
+        assert_eq!(apply_threshold(hits.clone(), Some(0.6), 5).len(), 3);
+        assert_eq!(apply_threshold(hits, None, 5).len(), 4);
+    }
+
+    #[test]
+    fn group_by_source() {
+        let hits: Vec<QueryHit> = ["a", "a", "b", "a", "c", "b"]
+            .iter()
+            .enumerate()
+            .map(|(i, source)| QueryHit {
+                id: i.to_string(),
+                metadata: json!({ "source": source }).as_object().cloned(),
+                ..Default::default()
+            })
+            .collect();
+
+        let groups = group_hits(hits, "source", 2, 2);
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].key, json!("a"));
+        let ids = |g: &HitGroup| g.hits.iter().map(|h| h.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&groups[0]), ["0", "1"]);
+        assert_eq!(ids(&groups[1]), ["2", "5"]);
+    }
+}
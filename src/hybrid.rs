@@ -0,0 +1,385 @@
+//! Lightweight hybrid retrieval on Chroma alone: the top BM25 terms of each
+//! document are stored as `kw_<term>` metadata holding their weight, next
+//! to the dense embedding. A hybrid query runs the nearest neighbor search
+//! and a keyword search on these keys, then fuses both rankings.
+//!
+//! ```no_run
+//! # async fn example(
+//! #     client: &chromadb_rs::client::ChromaClient,
+//! #     collection: &chromadb_rs::collection::Collection,
+//! #     embedding_function: &dyn chromadb_rs::embeddings::EmbeddingFunction,
+//! #     mut documents: Vec<chromadb_rs::document::Document>,
+//! #     query_embedding: Vec<f32>,
+//! # ) -> Result<(), chromadb_rs::error::ChromaClientError> {
+//! use chromadb_rs::hybrid::{HybridOptions, KeywordIndex};
+//!
+//! let index = KeywordIndex::fit(documents.iter().map(|d| d.text.as_str()));
+//! index.tag(&mut documents, 16);
+//! collection.add_documents(client, embedding_function, &documents).await?;
+//!
+//! let hits = collection
+//!     .query_hybrid(client, query_embedding, "tokio runtime", 10, &HybridOptions::default())
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::client::ChromaClient;
+use crate::collection::{
+    Collection, GetParams, GetResult, Include, Metadata, QueryParams, PAGE_SIZE,
+};
+use crate::document::Document;
+use crate::error::ChromaClientError;
+use crate::filter::Where;
+use crate::hits::QueryHit;
+use crate::metadata::MetadataValue;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Prefix of the metadata keys holding the weight of a term.
+pub const KEYWORD_PREFIX: &str = "kw_";
+
+/// BM25 term frequency saturation.
+const K1: f32 = 1.2;
+/// BM25 length normalization.
+const B: f32 = 0.75;
+
+const STOP_WORDS: [&str; 32] = [
+    "a", "an", "and", "are", "as", "at", "be", "by", "for", "from", "has", "in", "is", "it", "its",
+    "of", "on", "or", "that", "the", "this", "to", "was", "were", "will", "with", "not", "but",
+    "can", "if", "into", "than",
+];
+
+/// The lowercase words of the text, without stop words and single characters.
+pub fn terms(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().nth(1).is_some())
+        .map(str::to_lowercase)
+        .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+        .collect()
+}
+
+/// Corpus statistics giving the BM25 weight of the terms of a document.
+/// Serializable, to keep weighting new documents like the previous ones.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct KeywordIndex {
+    documents: usize,
+    total_terms: usize,
+    document_frequency: HashMap<String, usize>,
+}
+
+impl KeywordIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Statistics of a corpus.
+    pub fn fit<'a>(texts: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut index = KeywordIndex::new();
+        for text in texts {
+            index.add(text);
+        }
+        index
+    }
+
+    /// Count a document in the statistics.
+    pub fn add(&mut self, text: &str) {
+        let terms = terms(text);
+        self.documents += 1;
+        self.total_terms += terms.len();
+        for term in terms.into_iter().collect::<HashSet<_>>() {
+            *self.document_frequency.entry(term).or_default() += 1;
+        }
+    }
+
+    /// BM25 weight of each term of the text, highest first.
+    pub fn weights(&self, text: &str) -> Vec<(String, f32)> {
+        let terms = terms(text);
+        let length = terms.len() as f32;
+        let average = match self.documents {
+            0 => length.max(1.0),
+            n => (self.total_terms as f32 / n as f32).max(1.0),
+        };
+        let mut frequencies: HashMap<String, f32> = HashMap::new();
+        for term in terms {
+            *frequencies.entry(term).or_default() += 1.0;
+        }
+
+        let mut weights: Vec<(String, f32)> = frequencies
+            .into_iter()
+            .map(|(term, tf)| {
+                let df = self.document_frequency.get(&term).copied().unwrap_or(0) as f32;
+                let n = self.documents as f32;
+                let idf = (1.0 + (n - df + 0.5) / (df + 0.5)).ln();
+                let weight = idf * tf * (K1 + 1.0) / (tf + K1 * (1.0 - B + B * length / average));
+                (term, weight)
+            })
+            .collect();
+        weights.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        weights
+    }
+
+    /// `kw_<term>` metadata for the `count` highest weighted terms.
+    pub fn keyword_metadata(&self, text: &str, count: usize) -> Metadata {
+        self.weights(text)
+            .into_iter()
+            .take(count)
+            .map(|(term, weight)| {
+                let weight = MetadataValue::Float(f64::from(weight));
+                (format!("{}{}", KEYWORD_PREFIX, term), weight.into())
+            })
+            .collect()
+    }
+
+    /// Add the keyword metadata of `count` terms to each document.
+    pub fn tag(&self, documents: &mut [Document], count: usize) {
+        for document in documents {
+            let keywords = self.keyword_metadata(&document.text, count);
+            document.metadata.extend(keywords);
+        }
+    }
+}
+
+/// Settings of `Collection::query_hybrid`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HybridOptions {
+    /// Records kept from each search before the fusion.
+    pub candidates: usize,
+    /// Reciprocal rank fusion constant: the larger, the more the lower
+    /// ranks count.
+    pub rrf_k: f32,
+}
+
+impl Default for HybridOptions {
+    fn default() -> Self {
+        HybridOptions {
+            candidates: 50,
+            rrf_k: 60.0,
+        }
+    }
+}
+
+/// A hit of a hybrid query with its fused score and its rank in each search,
+/// starting at 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HybridHit {
+    pub hit: QueryHit,
+    pub score: f32,
+    pub dense_rank: Option<usize>,
+    pub keyword_rank: Option<usize>,
+}
+
+impl Collection {
+    /// Fuse the nearest neighbors of `query_embedding` with the records
+    /// whose keywords best match `query_text`, by reciprocal rank fusion.
+    /// The keyword search reads the metadata of every record having one of
+    /// the query terms, ranks them by the sum of their weights and keeps the
+    /// best `candidates`: a query made of common terms pages through much
+    /// of the collection.
+    pub async fn query_hybrid(
+        &self,
+        client: &ChromaClient,
+        query_embedding: Vec<f32>,
+        query_text: &str,
+        n_results: usize,
+        options: &HybridOptions,
+    ) -> Result<Vec<HybridHit>, ChromaClientError> {
+        let candidates = options.candidates.max(n_results);
+        let params = QueryParams {
+            query_embeddings: vec![query_embedding],
+            n_results: candidates,
+            include: Some(vec![
+                Include::Documents,
+                Include::Metadatas,
+                Include::Distances,
+            ]),
+            ..Default::default()
+        };
+        let dense = self
+            .query(client, params)
+            .await?
+            .into_hits()
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+
+        let query_terms: Vec<String> = terms(query_text)
+            .into_iter()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let filter = Where::or(
+            query_terms
+                .iter()
+                .map(|t| Where::gt(&format!("{}{}", KEYWORD_PREFIX, t), 0.0))
+                .collect(),
+        );
+        let keyword = if let Some(filter) = filter {
+            let params = GetParams {
+                where_metadata: Some(filter.to_value()),
+                include: Some(vec![Include::Metadatas]),
+                ..Default::default()
+            };
+            let mut pages = self.pages(client, params, PAGE_SIZE);
+            let mut matches = Vec::new();
+            while let Some(page) = pages.next().await? {
+                matches.extend(get_hits(page));
+            }
+            let mut ranking = keyword_ranking(matches, &query_terms);
+            ranking.truncate(candidates);
+            self.fill_documents(client, &mut ranking).await?;
+            ranking
+        } else {
+            Vec::new()
+        };
+
+        Ok(fuse(dense, keyword, options.rrf_k, n_results))
+    }
+}
+
+impl Collection {
+    /// Fetch the documents of the hits, in one request.
+    async fn fill_documents(
+        &self,
+        client: &ChromaClient,
+        hits: &mut [QueryHit],
+    ) -> Result<(), ChromaClientError> {
+        if hits.is_empty() {
+            return Ok(());
+        }
+        let params = GetParams {
+            ids: Some(hits.iter().map(|h| h.id.clone()).collect()),
+            include: Some(vec![Include::Documents]),
+            ..Default::default()
+        };
+        let result = self.get(client, params).await?;
+        let mut documents: HashMap<String, String> = result
+            .ids
+            .into_iter()
+            .zip(result.documents.unwrap_or_default())
+            .filter_map(|(id, document)| Some((id, document?)))
+            .collect();
+        for hit in hits {
+            hit.document = documents.remove(&hit.id);
+        }
+        Ok(())
+    }
+}
+
+fn get_hits(result: GetResult) -> Vec<QueryHit> {
+    let mut documents = result.documents.map(Vec::into_iter);
+    let mut metadatas = result.metadatas.map(Vec::into_iter);
+    result
+        .ids
+        .into_iter()
+        .map(|id| QueryHit {
+            id,
+            document: documents.as_mut().and_then(Iterator::next).flatten(),
+            metadata: metadatas.as_mut().and_then(Iterator::next).flatten(),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// The hits having some of the terms, by decreasing sum of their weights.
+fn keyword_ranking(hits: Vec<QueryHit>, terms: &[String]) -> Vec<QueryHit> {
+    let score = |hit: &QueryHit| -> f64 {
+        terms
+            .iter()
+            .filter_map(|t| {
+                let key = format!("{}{}", KEYWORD_PREFIX, t);
+                hit.metadata.as_ref()?.get(&key)?.as_f64()
+            })
+            .sum()
+    };
+    let mut scored: Vec<(f64, QueryHit)> = hits
+        .into_iter()
+        .map(|hit| (score(&hit), hit))
+        .filter(|(score, _)| *score > 0.0)
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored.into_iter().map(|(_, hit)| hit).collect()
+}
+
+/// Reciprocal rank fusion of two rankings, keeping the best `n_results`.
+fn fuse(dense: Vec<QueryHit>, keyword: Vec<QueryHit>, k: f32, n_results: usize) -> Vec<HybridHit> {
+    let mut fused: Vec<HybridHit> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+
+    for (from_dense, ranking) in [(true, dense), (false, keyword)] {
+        for (rank, hit) in ranking.into_iter().enumerate() {
+            let position = *positions.entry(hit.id.clone()).or_insert_with(|| {
+                fused.push(HybridHit {
+                    hit: hit.clone(),
+                    score: 0.0,
+                    dense_rank: None,
+                    keyword_rank: None,
+                });
+                fused.len() - 1
+            });
+            let entry = &mut fused[position];
+            entry.score += 1.0 / (k + rank as f32 + 1.0);
+            if from_dense {
+                entry.dense_rank = Some(rank);
+            } else {
+                entry.keyword_rank = Some(rank);
+            }
+        }
+    }
+
+    fused.sort_by(|a, b| b.score.total_cmp(&a.score));
+    fused.truncate(n_results);
+    fused
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(id: &str) -> QueryHit {
+        QueryHit {
+            id: id.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn weights_and_fusion() {
+        let corpus = [
+            "The tokio runtime drives async tasks.",
+            "An async runtime for Rust.",
+            "Rust ownership and borrowing.",
+        ];
+        let index = KeywordIndex::fit(corpus);
+        assert_eq!(
+            terms("The Tokio runtime, a runtime!"),
+            ["tokio", "runtime", "runtime"]
+        );
+
+        let weights = index.weights(corpus[0]);
+        assert_eq!(weights[0].0, "drives");
+        let runtime = weights.iter().find(|(t, _)| t == "runtime").unwrap().1;
+        assert!(weights[0].1 > runtime);
+        let metadata = index.keyword_metadata(corpus[0], 2);
+        assert_eq!(metadata.len(), 2);
+        assert!(metadata.contains_key("kw_drives"));
+
+        let mut candidates = vec![hit("a"), hit("b")];
+        candidates[0].metadata = Some(index.keyword_metadata(corpus[1], 8));
+        candidates[1].metadata = Some(index.keyword_metadata(corpus[0], 8));
+        let keyword = keyword_ranking(candidates, &["tokio".into(), "runtime".into()]);
+        assert_eq!(keyword[0].id, "b");
+
+        let fused = fuse(vec![hit("a"), hit("c")], keyword, 60.0, 2);
+        let ids: Vec<&str> = fused.iter().map(|h| h.hit.id.as_str()).collect();
+        assert_eq!(ids, ["a", "b"]);
+        assert_eq!(
+            (fused[0].dense_rank, fused[0].keyword_rank),
+            (Some(0), Some(1))
+        );
+        assert_eq!(
+            (fused[1].dense_rank, fused[1].keyword_rank),
+            (None, Some(0))
+        );
+    }
+}
@@ -0,0 +1,586 @@
+use crate::batch::{AdaptiveBatching, BatchSizer};
+use crate::client::ChromaClient;
+use crate::collection::{Collection, DeleteParams, Entries, GetParams, Include, QueryParams};
+use crate::document::{content_hash, to_entries, Document};
+use crate::embeddings::EmbeddingFunction;
+use crate::enrich::Enricher;
+use crate::error::ChromaClientError;
+use crate::filter::Where;
+use crate::jobs::{JobHandle, Progress};
+use crate::loaders;
+use crate::metadata::CREATED_AT_KEY;
+use crate::pii::{self, Redactions};
+use crate::simd::cosine_similarity;
+use crate::text::TextSplitter;
+use futures_util::stream::{self, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Index every file of a directory matching a glob pattern: files are loaded,
+/// chunked, embedded and upserted into a collection.
+///
+/// ```no_run
+/// # async fn example(
+/// #     client: &chromadb_rs::client::ChromaClient,
+/// #     collection: &chromadb_rs::collection::Collection,
+/// #     embedding_function: &dyn chromadb_rs::embeddings::EmbeddingFunction,
+/// # ) -> Result<(), chromadb_rs::error::ChromaClientError> {
+/// use chromadb_rs::ingest::IngestPipeline;
+///
+/// let report = IngestPipeline::from_dir("docs", "**/*.md")
+///     .concurrency(8)
+///     .state_file("docs-index.json")
+///     .run(client, collection, embedding_function)
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct IngestPipeline {
+    root: PathBuf,
+    pattern: String,
+    splitter: TextSplitter,
+    concurrency: usize,
+    state_file: Option<PathBuf>,
+    near_duplicate_threshold: Option<f32>,
+    adaptive_batching: Option<AdaptiveBatching>,
+    enrichers: Vec<Arc<dyn Enricher>>,
+    scrub_pii: bool,
+}
+
+/// What an ingestion run did.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IngestReport {
+    pub files_indexed: usize,
+    /// Files skipped because they did not change since the previous run.
+    pub files_unchanged: usize,
+    pub chunks_upserted: usize,
+    /// Chunks of modified files kept without being embedded again, because
+    /// their text did not change.
+    pub chunks_reused: usize,
+    /// Chunks of modified files removed because their text is gone.
+    pub chunks_deleted: usize,
+    /// Chunks skipped because another chunk had the same content.
+    pub duplicate_chunks: usize,
+    /// Chunks skipped because they were too similar to another chunk.
+    pub near_duplicate_chunks: usize,
+    /// Every skipped chunk, with the chunk it duplicates.
+    pub dropped: Vec<DroppedChunk>,
+    /// Personal data masked in the indexed files, see `IngestPipeline::scrub_pii`.
+    pub redactions: Redactions,
+}
+
+/// A chunk skipped by the deduplication.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DroppedChunk {
+    pub id: String,
+    /// Id of the chunk kept in its place, from this run or already in the collection.
+    pub duplicate_of: String,
+    /// Cosine similarity between the two chunks, 1 for exact duplicates.
+    pub similarity: f32,
+}
+
+/// Content hash of every indexed file, persisted between runs.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct IngestState {
+    files: HashMap<String, String>,
+}
+
+impl IngestPipeline {
+    /// Creates a pipeline for the files of `path` matching `glob` (e.g. `**/*.md`).
+    pub fn from_dir(path: impl AsRef<Path>, glob: &str) -> Self {
+        IngestPipeline {
+            root: path.as_ref().to_path_buf(),
+            pattern: glob.to_string(),
+            splitter: TextSplitter::default(),
+            concurrency: 4,
+            state_file: None,
+            near_duplicate_threshold: None,
+            adaptive_batching: None,
+            enrichers: Vec::new(),
+            scrub_pii: false,
+        }
+    }
+
+    /// The splitter used to chunk documents.
+    pub fn splitter(mut self, splitter: TextSplitter) -> Self {
+        self.splitter = splitter;
+        self
+    }
+
+    /// How many files are processed at the same time.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// File recording which files were indexed, so an interrupted or repeated
+    /// run only processes new and modified files.
+    pub fn state_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.state_file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Also skip chunks whose embedding has a cosine similarity of at least
+    /// `threshold` with a chunk of this run or a record of the collection.
+    pub fn near_duplicates(mut self, threshold: f32) -> Self {
+        self.near_duplicate_threshold = Some(threshold);
+        self
+    }
+
+    /// Upsert the chunks in batches sized from the latency of the server,
+    /// shared by the files processed at the same time, instead of one
+    /// request per file.
+    pub fn adaptive_batching(mut self, settings: AdaptiveBatching) -> Self {
+        self.adaptive_batching = Some(settings);
+        self
+    }
+
+    /// Run `enricher` on each chunk before it is embedded, after the
+    /// enrichers added before it.
+    pub fn enrich(mut self, enricher: impl Enricher + 'static) -> Self {
+        self.enrichers.push(Arc::new(enricher));
+        self
+    }
+
+    /// Mask the personal data of each document before it is split, so a
+    /// value cut in two by a chunk boundary is masked too. The values masked
+    /// are counted in `IngestReport::redactions`.
+    pub fn scrub_pii(mut self) -> Self {
+        self.scrub_pii = true;
+        self
+    }
+
+    /// Set the `language` metadata of each chunk to its detected language
+    /// when `detect` is true, the same as `enrich(Language)`.
+    #[cfg(feature = "language")]
+    pub fn detect_language(self, detect: bool) -> Self {
+        if detect {
+            self.enrich(crate::enrich::Language)
+        } else {
+            self
+        }
+    }
+
+    /// Run the pipeline.
+    pub async fn run(
+        &self,
+        client: &ChromaClient,
+        collection: &Collection,
+        embedding_function: &dyn EmbeddingFunction,
+    ) -> Result<IngestReport, ChromaClientError> {
+        self.run_with_progress(client, collection, embedding_function, &Progress::default())
+            .await
+    }
+
+    /// Run the pipeline in a background task. Its progress is the number of
+    /// files processed, indexed or unchanged.
+    pub fn spawn(
+        self,
+        client: &ChromaClient,
+        collection: &Collection,
+        embedding_function: Arc<dyn EmbeddingFunction>,
+    ) -> JobHandle<Result<IngestReport, ChromaClientError>> {
+        let client = client.clone();
+        let collection = collection.clone();
+
+        JobHandle::spawn(|progress| async move {
+            self.run_with_progress(&client, &collection, &*embedding_function, &progress)
+                .await
+        })
+    }
+
+    async fn run_with_progress(
+        &self,
+        client: &ChromaClient,
+        collection: &Collection,
+        embedding_function: &dyn EmbeddingFunction,
+        progress: &Progress,
+    ) -> Result<IngestReport, ChromaClientError> {
+        let state = tokio::sync::Mutex::new(self.load_state().await?);
+        let seen = Mutex::new(HashMap::new());
+        let kept = Mutex::new(Vec::new());
+        let sizer = self.adaptive_batching.map(BatchSizer::new);
+
+        let reports: Vec<IngestReport> = stream::iter(self.files()?)
+            .map(|path| {
+                let context = RunContext {
+                    client,
+                    collection,
+                    embedding_function,
+                    state: &state,
+                    seen: &seen,
+                    kept: &kept,
+                    sizer: sizer.as_ref(),
+                };
+                self.ingest_file(path, context)
+            })
+            .buffer_unordered(self.concurrency)
+            .inspect_ok(|_| progress.add(1))
+            .try_collect()
+            .await?;
+
+        Ok(reports
+            .into_iter()
+            .fold(IngestReport::default(), |mut total, report| {
+                total.files_indexed += report.files_indexed;
+                total.files_unchanged += report.files_unchanged;
+                total.chunks_upserted += report.chunks_upserted;
+                total.chunks_reused += report.chunks_reused;
+                total.chunks_deleted += report.chunks_deleted;
+                total.duplicate_chunks += report.duplicate_chunks;
+                total.near_duplicate_chunks += report.near_duplicate_chunks;
+                total.dropped.extend(report.dropped);
+                total.redactions += report.redactions;
+                total
+            }))
+    }
+
+    async fn ingest_file(
+        &self,
+        path: PathBuf,
+        context: RunContext<'_>,
+    ) -> Result<IngestReport, ChromaClientError> {
+        let RunContext {
+            client,
+            collection,
+            embedding_function,
+            state,
+            seen,
+            kept,
+            sizer,
+        } = context;
+        let mut report = IngestReport::default();
+        let source = path.display().to_string();
+
+        let bytes = tokio::fs::read(&path)
+            .await
+            .map_err(|e| ChromaClientError::LoaderError(format!("{}: {}", source, e)))?;
+        let file_hash = content_hash(&bytes);
+        if state.lock().await.files.get(&source) == Some(&file_hash) {
+            report.files_unchanged = 1;
+            return Ok(report);
+        }
+
+        let mut documents = tokio::task::spawn_blocking(move || loaders::load(&path))
+            .await
+            .map_err(|e| ChromaClientError::LoaderError(format!("{}: {}", source, e)))??;
+        if self.scrub_pii {
+            report.redactions = pii::scrub_documents(&mut documents);
+        }
+
+        let chunks: Vec<Document> = {
+            let mut seen = seen.lock().unwrap();
+            documents
+                .iter()
+                .flat_map(|d| {
+                    let mut chunks = self.splitter.split_document(d);
+                    for chunk in &mut chunks {
+                        chunk.id = chunk_id(&d.id, &chunk.text);
+                    }
+                    for chunk in &mut chunks {
+                        for enricher in &self.enrichers {
+                            enricher.enrich(chunk);
+                        }
+                    }
+                    chunks
+                })
+                .filter(|chunk| match seen.entry(content_hash(&chunk.text)) {
+                    Entry::Occupied(original) => {
+                        report.duplicate_chunks += 1;
+                        report.dropped.push(DroppedChunk {
+                            id: chunk.id.clone(),
+                            duplicate_of: original.get().clone(),
+                            similarity: 1.0,
+                        });
+                        false
+                    }
+                    Entry::Vacant(entry) => {
+                        entry.insert(chunk.id.clone());
+                        true
+                    }
+                })
+                .collect()
+        };
+
+        // Chunk ids derive from their text, so the chunks already stored for
+        // this file are exactly the ones that did not change.
+        let stored = stored_chunks(client, collection, &source).await?;
+        let current: HashSet<&str> = chunks.iter().map(|c| c.id.as_str()).collect();
+        let stale: Vec<String> = stored
+            .keys()
+            .filter(|id| !current.contains(id.as_str()))
+            .cloned()
+            .collect();
+        let (mut reused, mut chunks): (Vec<Document>, Vec<Document>) =
+            chunks.into_iter().partition(|c| stored.contains_key(&c.id));
+
+        // The enrichers saw a new chunk: keep the time it was first stored.
+        for chunk in &mut reused {
+            if let Some(Some(created_at)) = stored.get(&chunk.id) {
+                chunk
+                    .metadata
+                    .insert(CREATED_AT_KEY.to_string(), created_at.clone());
+            }
+        }
+        if !reused.is_empty() {
+            // Only the position of the chunk in its file may have changed.
+            let entries = Entries {
+                ids: reused.iter().map(|c| c.id.clone()).collect(),
+                metadatas: Some(reused.iter().map(|c| c.metadata.clone()).collect()),
+                ..Default::default()
+            };
+            collection.update(client, entries).await?;
+        }
+        if !stale.is_empty() {
+            let params = DeleteParams {
+                ids: Some(stale.clone()),
+                ..Default::default()
+            };
+            collection.delete(client, params).await?;
+        }
+        report.chunks_reused = reused.len();
+        report.chunks_deleted = stale.len();
+
+        if !chunks.is_empty() {
+            let texts: Vec<&str> = chunks.iter().map(|c| c.text.as_str()).collect();
+            let mut embeddings = embedding_function.embed(&texts).await?;
+
+            if let Some(threshold) = self.near_duplicate_threshold {
+                let stored = nearest_stored(client, collection, &chunks, &embeddings).await?;
+                let mut kept = kept.lock().unwrap();
+                let mut keep = Vec::with_capacity(chunks.len());
+                for ((chunk, embedding), stored) in chunks.iter().zip(&embeddings).zip(stored) {
+                    let nearest = [stored, nearest(embedding, &kept)]
+                        .into_iter()
+                        .flatten()
+                        .max_by(|a, b| a.1.total_cmp(&b.1));
+
+                    match nearest {
+                        Some((id, similarity)) if similarity >= threshold => {
+                            report.near_duplicate_chunks += 1;
+                            report.dropped.push(DroppedChunk {
+                                id: chunk.id.clone(),
+                                duplicate_of: id,
+                                similarity,
+                            });
+                            keep.push(false);
+                        }
+                        _ => {
+                            kept.push((chunk.id.clone(), embedding.clone()));
+                            keep.push(true);
+                        }
+                    }
+                }
+
+                let mut mask = keep.iter();
+                chunks.retain(|_| *mask.next().unwrap());
+                let mut mask = keep.iter();
+                embeddings.retain(|_| *mask.next().unwrap());
+            }
+
+            if !chunks.is_empty() {
+                let entries = to_entries(&chunks, embeddings);
+                match sizer {
+                    Some(sizer) => collection.upsert_adaptive(client, entries, sizer).await?,
+                    None => collection.upsert(client, entries).await?,
+                }
+            }
+        }
+
+        report.files_indexed = 1;
+        report.chunks_upserted = chunks.len();
+
+        // Held while writing, so the file always ends with the latest state.
+        let mut state = state.lock().await;
+        state.files.insert(source, file_hash);
+        self.save_state(&state).await?;
+
+        Ok(report)
+    }
+
+    fn files(&self) -> Result<Vec<PathBuf>, ChromaClientError> {
+        let pattern = self.root.join(&self.pattern);
+        let paths = glob::glob(&pattern.to_string_lossy())
+            .map_err(|e| ChromaClientError::LoaderError(e.to_string()))?;
+
+        Ok(paths
+            .filter_map(Result::ok)
+            .filter(|path| path.is_file())
+            .collect())
+    }
+
+    async fn load_state(&self) -> Result<IngestState, ChromaClientError> {
+        let Some(path) = &self.state_file else {
+            return Ok(IngestState::default());
+        };
+        match tokio::fs::read_to_string(path).await {
+            Ok(content) => {
+                serde_json::from_str(&content).map_err(|e| ChromaClientError::IoError(e.into()))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(IngestState::default()),
+            Err(e) => Err(ChromaClientError::IoError(e)),
+        }
+    }
+
+    async fn save_state(&self, state: &IngestState) -> Result<(), ChromaClientError> {
+        if let Some(path) = &self.state_file {
+            let content = serde_json::to_string_pretty(state)
+                .map_err(|e| ChromaClientError::IoError(e.into()))?;
+            tokio::fs::write(path, content)
+                .await
+                .map_err(ChromaClientError::IoError)?;
+        }
+        Ok(())
+    }
+}
+
+/// Shared state of a run, passed to each file.
+#[derive(Clone, Copy)]
+struct RunContext<'a> {
+    client: &'a ChromaClient,
+    collection: &'a Collection,
+    embedding_function: &'a dyn EmbeddingFunction,
+    state: &'a tokio::sync::Mutex<IngestState>,
+    seen: &'a Mutex<HashMap<String, String>>,
+    kept: &'a Mutex<Vec<(String, Vec<f32>)>>,
+    sizer: Option<&'a BatchSizer>,
+}
+
+/// Id of a chunk of a document, the same wherever the text sits in the document.
+fn chunk_id(document_id: &str, text: &str) -> String {
+    format!("{}#chunk={}", document_id, &content_hash(text)[..16])
+}
+
+/// Ids of the chunks stored for a file, with their `created_at` metadata.
+async fn stored_chunks(
+    client: &ChromaClient,
+    collection: &Collection,
+    source: &str,
+) -> Result<HashMap<String, Option<Value>>, ChromaClientError> {
+    let params = GetParams {
+        where_metadata: Some(Where::eq("source", source).into()),
+        include: Some(vec![Include::Metadatas]),
+        ..Default::default()
+    };
+    let result = collection.get(client, params).await?;
+    let metadatas = result.metadatas.unwrap_or_default();
+    Ok(result
+        .ids
+        .into_iter()
+        .enumerate()
+        .map(|(i, id)| {
+            let created_at = metadatas
+                .get(i)
+                .and_then(|m| m.as_ref()?.get(CREATED_AT_KEY).cloned());
+            (id, created_at)
+        })
+        .collect())
+}
+
+/// The record of the collection closest to each chunk, other than the chunk
+/// itself, with its cosine similarity. Cosine collections give it from the
+/// distance, the others from the embeddings of their nearest records.
+async fn nearest_stored(
+    client: &ChromaClient,
+    collection: &Collection,
+    chunks: &[Document],
+    embeddings: &[Vec<f32>],
+) -> Result<Vec<Option<(String, f32)>>, ChromaClientError> {
+    let cosine = collection
+        .metadata
+        .as_ref()
+        .and_then(|m| m.get("hnsw:space")?.as_str())
+        == Some("cosine");
+    let params = QueryParams {
+        query_embeddings: embeddings.to_vec(),
+        // The chunk itself when it is already stored, and its nearest neighbor.
+        n_results: 2,
+        include: Some(if cosine {
+            vec![Include::Distances]
+        } else {
+            vec![Include::Embeddings]
+        }),
+        ..Default::default()
+    };
+    let result = collection.query(client, params).await?;
+
+    Ok(chunks
+        .iter()
+        .zip(embeddings)
+        .enumerate()
+        .map(|(q, (chunk, embedding))| {
+            let ids = result.ids.get(q)?;
+            ids.iter()
+                .enumerate()
+                .filter(|(_, id)| **id != chunk.id)
+                .filter_map(|(i, id)| {
+                    let similarity = if cosine {
+                        1.0 - result.distances.as_ref()?.get(q)?.get(i)?
+                    } else {
+                        cosine_similarity(embedding, result.embeddings.as_ref()?.get(q)?.get(i)?)
+                    };
+                    Some((id.clone(), similarity))
+                })
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+        })
+        .collect())
+}
+
+/// The candidate most similar to the embedding, with its similarity.
+fn nearest(embedding: &[f32], candidates: &[(String, Vec<f32>)]) -> Option<(String, f32)> {
+    candidates
+        .iter()
+        .map(|(id, candidate)| (id, cosine_similarity(embedding, candidate)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(id, similarity)| (id.clone(), similarity))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_candidate() {
+        let candidates = vec![
+            ("a".to_string(), vec![1.0, 0.0]),
+            ("b".to_string(), vec![0.6, 0.8]),
+        ];
+
+        let (id, similarity) = nearest(&[0.0, 1.0], &candidates).unwrap();
+
+        assert_eq!(id, "b");
+        assert!((similarity - 0.8).abs() < 1e-6);
+        assert_eq!(nearest(&[0.0, 1.0], &[]), None);
+    }
+
+    #[tokio::test]
+    async fn state_file_round_trip() {
+        let dir =
+            std::env::temp_dir().join(format!("chroma-rs-ingest-state-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pipeline = IngestPipeline::from_dir(&dir, "*.md").state_file(dir.join("state.json"));
+
+        assert!(pipeline.load_state().await.unwrap().files.is_empty());
+        let mut state = IngestState::default();
+        state.files.insert("a.md".into(), "hash".into());
+        pipeline.save_state(&state).await.unwrap();
+        assert_eq!(pipeline.load_state().await.unwrap().files, state.files);
+
+        std::fs::write(dir.join("state.json"), "{").unwrap();
+        let error = pipeline.load_state().await.unwrap_err();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(error, ChromaClientError::IoError(_)));
+    }
+
+    #[test]
+    fn chunk_ids_follow_the_text() {
+        assert_eq!(chunk_id("a.md", "intro"), chunk_id("a.md", "intro"));
+        assert_ne!(chunk_id("a.md", "intro"), chunk_id("a.md", "intro!"));
+        assert_ne!(chunk_id("a.md", "intro"), chunk_id("b.md", "intro"));
+    }
+}
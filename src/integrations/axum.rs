@@ -0,0 +1,85 @@
+use crate::client::{ChromaClient, ChromaClientParams};
+use crate::error::ChromaClientError;
+use axum::async_trait;
+use axum::extract::{FromRef, FromRequestParts};
+use axum::http::request::Parts;
+use std::convert::Infallible;
+use std::time::Duration;
+
+/// Application state holding a client shared by every request, so they all
+/// reuse the same connection pool.
+///
+/// ```no_run
+/// use axum::{routing::get, Router};
+/// use chromadb_rs::client::ChromaClientParams;
+/// use chromadb_rs::integrations::axum::{Chroma, ChromaState};
+/// use std::time::Duration;
+///
+/// async fn collections(Chroma(client): Chroma) -> String {
+///     match client.list_collections().await {
+///         Ok(collections) => format!("{} collections", collections.len()),
+///         Err(e) => e.to_string(),
+///     }
+/// }
+///
+/// # async fn example() -> Result<(), chromadb_rs::error::ChromaClientError> {
+/// let state = ChromaState::connect(ChromaClientParams::default(), 5, Duration::from_secs(1)).await?;
+/// let app: Router = Router::new()
+///     .route("/collections", get(collections))
+///     .with_state(state);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ChromaState {
+    client: ChromaClient,
+}
+
+impl ChromaState {
+    pub fn new(client: ChromaClient) -> Self {
+        ChromaState { client }
+    }
+
+    /// Creates the client and waits for the server to answer a heartbeat,
+    /// trying up to `attempts` times `delay` apart, so a service does not
+    /// start serving before its database is up.
+    pub async fn connect(
+        params: ChromaClientParams,
+        attempts: usize,
+        delay: Duration,
+    ) -> Result<Self, ChromaClientError> {
+        let client = ChromaClient::new(params);
+
+        let mut attempt = 1;
+        loop {
+            match client.heartbeat().await {
+                Ok(_) => return Ok(ChromaState::new(client)),
+                Err(e) if attempt >= attempts => return Err(e),
+                Err(_) => {
+                    attempt += 1;
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+
+    pub fn client(&self) -> &ChromaClient {
+        &self.client
+    }
+}
+
+/// Extracts the shared client from any state containing a `ChromaState`.
+pub struct Chroma(pub ChromaClient);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Chroma
+where
+    ChromaState: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(_parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Chroma(ChromaState::from_ref(state).client))
+    }
+}
@@ -0,0 +1,4 @@
+//! Helpers to use the client from web frameworks.
+
+#[cfg(feature = "axum")]
+pub mod axum;
@@ -0,0 +1,101 @@
+//! Handles of the background tasks started by the client helpers.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+
+/// Counter of the work done by a job, shared with its handle.
+#[derive(Debug, Clone, Default)]
+pub struct Progress(Arc<AtomicUsize>);
+
+impl Progress {
+    pub fn add(&self, done: usize) {
+        self.0.fetch_add(done, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// A task running in the background, such as `Collection::expire_every`,
+/// `BackupPlan::every` or `IngestPipeline::spawn`. Dropping the handle
+/// cancels the task.
+#[derive(Debug)]
+pub struct JobHandle<T = ()> {
+    task: Option<JoinHandle<T>>,
+    progress: Progress,
+}
+
+impl<T: Send + 'static> JobHandle<T> {
+    /// Run `job` in a tokio task, giving it the progress counter reported by
+    /// the handle.
+    pub fn spawn<F, Fut>(job: F) -> Self
+    where
+        F: FnOnce(Progress) -> Fut,
+        Fut: Future<Output = T> + Send + 'static,
+    {
+        let progress = Progress::default();
+        let task = tokio::spawn(job(progress.clone()));
+        JobHandle {
+            task: Some(task),
+            progress,
+        }
+    }
+
+    /// Work done so far, what is counted depends on the job.
+    pub fn progress(&self) -> usize {
+        self.progress.get()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.task.as_ref().is_none_or(JoinHandle::is_finished)
+    }
+
+    /// Stop the task at its next await point.
+    pub fn cancel(&self) {
+        if let Some(task) = &self.task {
+            task.abort();
+        }
+    }
+
+    /// Wait for the job to end. `None` when it was cancelled or panicked.
+    pub async fn wait(mut self) -> Option<T> {
+        self.task.take()?.await.ok()
+    }
+}
+
+impl<T> Drop for JobHandle<T> {
+    fn drop(&mut self) {
+        if let Some(task) = &self.task {
+            task.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn progress_cancel_and_wait() {
+        let job = JobHandle::spawn(|progress| async move {
+            progress.add(2);
+            "done"
+        });
+        assert_eq!(job.wait().await, Some("done"));
+
+        let job = JobHandle::spawn(|progress| async move {
+            loop {
+                progress.add(1);
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(job.progress() > 0);
+        job.cancel();
+        assert_eq!(job.wait().await, None::<()>);
+    }
+}
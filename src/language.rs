@@ -0,0 +1,52 @@
+//! Language of documents, stored as metadata so queries can filter on it
+//! with `Where::eq(LANGUAGE_KEY, "fra")`.
+
+use crate::document::Document;
+use serde_json::Value;
+
+/// ISO 639-3 code of the language of the text of a record, e.g. `eng`.
+pub const LANGUAGE_KEY: &str = "language";
+
+/// ISO 639-3 code of the language of the text, `None` when the detection is
+/// not reliable, as for very short texts.
+pub fn detect_language(text: &str) -> Option<&'static str> {
+    whatlang::detect(text)
+        .filter(|info| info.is_reliable())
+        .map(|info| info.lang().code())
+}
+
+/// Set the `language` metadata of the documents whose language is detected.
+pub fn tag_language(documents: &mut [Document]) {
+    for document in documents {
+        if let Some(language) = detect_language(&document.text) {
+            document
+                .metadata
+                .insert(LANGUAGE_KEY.into(), Value::from(language));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_detected_languages() {
+        let mut documents = vec![
+            Document::new(
+                "en",
+                "This library stores documents and their embeddings in a vector database, then finds the closest ones for a question.",
+            ),
+            Document::new(
+                "fr",
+                "Cette bibliothèque enregistre les documents et leurs vecteurs dans une base de données, puis retrouve les plus proches.",
+            ),
+            Document::new("short", "ok"),
+        ];
+        tag_language(&mut documents);
+
+        assert_eq!(documents[0].metadata[LANGUAGE_KEY], "eng");
+        assert_eq!(documents[1].metadata[LANGUAGE_KEY], "fra");
+        assert!(!documents[2].metadata.contains_key(LANGUAGE_KEY));
+    }
+}
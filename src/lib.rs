@@ -20,6 +20,74 @@
 //!
 //! Happy coding! 😊
 
+// Lets the derive macros, which refer to `::chromadb_rs`, be used inside the crate.
+extern crate self as chromadb_rs;
+
+pub mod analysis;
+#[cfg(feature = "archive")]
+pub mod archive;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod audit;
+pub mod backup;
+pub mod batch;
+pub mod bench;
 pub mod client;
 pub mod collection;
+pub mod compat;
+#[cfg(feature = "polars")]
+pub mod dataframe;
+pub mod document;
+pub mod embeddings;
+pub mod encoding;
+pub mod enrich;
 pub mod error;
+pub mod expiry;
+pub mod explain;
+pub mod export;
+pub mod filter;
+pub mod generated;
+pub mod geo;
+pub mod handle;
+pub mod hits;
+pub mod hybrid;
+#[cfg(feature = "ingest")]
+pub mod ingest;
+pub mod integrations;
+pub mod jobs;
+#[cfg(feature = "language")]
+pub mod language;
+#[cfg(feature = "loaders")]
+pub mod loaders;
+pub mod memory;
+pub mod metadata;
+#[cfg(feature = "migrate")]
+pub mod migrate;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod models;
+pub mod monitor;
+pub mod pii;
+pub mod policy;
+pub mod projection;
+pub mod query;
+pub mod rag;
+pub mod raw;
+pub mod record;
+pub mod reembed;
+#[cfg(feature = "replay")]
+pub mod replay;
+pub mod schema;
+pub mod simd;
+pub mod source;
+pub mod stats;
+pub mod sync;
+pub mod tabular;
+#[cfg(feature = "testcontainers")]
+pub mod testing;
+pub mod text;
+pub mod usage;
+pub mod vectors;
+pub mod verify;
+pub mod versioning;
+pub mod watch;
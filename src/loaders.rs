@@ -0,0 +1,179 @@
+//! Load files into [`Document`]s. Every document gets `source` and `format`
+//! metadata, plus `title` or `page` when the format provides them.
+
+use crate::collection::Metadata;
+use crate::document::Document;
+use crate::error::ChromaClientError;
+use crate::source::{SourceRef, TITLE_KEY};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+/// Load a file, picking the loader from its extension. Unknown extensions are
+/// loaded as plain text.
+pub fn load(path: &Path) -> Result<Vec<Document>, ChromaClientError> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+
+    match extension.as_deref() {
+        Some("md") | Some("markdown") => load_markdown(path),
+        Some("html") | Some("htm") => load_html(path),
+        #[cfg(feature = "pdf")]
+        Some("pdf") => load_pdf(path),
+        _ => load_text(path),
+    }
+}
+
+/// Load a plain text file as a single document.
+pub fn load_text(path: &Path) -> Result<Vec<Document>, ChromaClientError> {
+    let text = read(path)?;
+    Ok(vec![document(path, "text", text, None)])
+}
+
+/// Load a Markdown file as a single document, titled after its first heading.
+pub fn load_markdown(path: &Path) -> Result<Vec<Document>, ChromaClientError> {
+    let text = read(path)?;
+    let title = text
+        .lines()
+        .find_map(|line| line.strip_prefix("# "))
+        .map(|title| title.trim().to_string());
+
+    let mut document = document(path, "markdown", text, None);
+    if let Some(title) = title {
+        document
+            .metadata
+            .insert(TITLE_KEY.into(), Value::from(title));
+    }
+    Ok(vec![document])
+}
+
+/// Load an HTML file as a single document, keeping only its text content.
+pub fn load_html(path: &Path) -> Result<Vec<Document>, ChromaClientError> {
+    let html = read(path)?;
+    let title = between(&html, "<title>", "</title>").map(|t| t.trim().to_string());
+
+    let mut document = document(path, "html", strip_tags(&html), None);
+    if let Some(title) = title {
+        document
+            .metadata
+            .insert(TITLE_KEY.into(), Value::from(title));
+    }
+    Ok(vec![document])
+}
+
+/// Load a PDF file, one document per page.
+#[cfg(feature = "pdf")]
+pub fn load_pdf(path: &Path) -> Result<Vec<Document>, ChromaClientError> {
+    let pages = pdf_extract::extract_text_by_pages(path)
+        .map_err(|e| ChromaClientError::LoaderError(format!("{}: {}", path.display(), e)))?;
+
+    Ok(pages
+        .into_iter()
+        .enumerate()
+        .filter(|(_, text)| !text.trim().is_empty())
+        .map(|(i, text)| document(path, "pdf", text, Some(i + 1)))
+        .collect())
+}
+
+fn read(path: &Path) -> Result<String, ChromaClientError> {
+    fs::read_to_string(path)
+        .map_err(|e| ChromaClientError::LoaderError(format!("{}: {}", path.display(), e)))
+}
+
+fn document(path: &Path, format: &str, text: String, page: Option<usize>) -> Document {
+    let source = SourceRef {
+        page: page.map(|p| p as u64),
+        ..SourceRef::new(path.display().to_string())
+    };
+    let mut metadata = Metadata::new();
+    source.write_to(&mut metadata);
+    metadata.insert("format".into(), Value::from(format));
+
+    let id = match page {
+        Some(page) => format!("{}#page={}", source.uri, page),
+        None => source.uri,
+    };
+
+    Document { id, text, metadata }
+}
+
+fn between<'a>(text: &'a str, start: &str, end: &str) -> Option<&'a str> {
+    let from = text.find(start)? + start.len();
+    let to = text[from..].find(end)? + from;
+    Some(&text[from..to])
+}
+
+/// Remove tags, comments, scripts and styles, and collapse whitespace.
+fn strip_tags(html: &str) -> String {
+    // ASCII lowercasing keeps the byte offsets, so positions found in
+    // `lower` are valid in `html`.
+    let lower = html.to_ascii_lowercase();
+    let mut text = String::with_capacity(html.len());
+    let mut position = 0;
+
+    while let Some(start) = lower[position..].find('<').map(|i| position + i) {
+        text.push_str(&html[position..start]);
+        text.push(' ');
+
+        let tag = &lower[start..];
+        let closing = if tag.starts_with("<!--") {
+            "-->"
+        } else if tag.starts_with("<script") {
+            "</script>"
+        } else if tag.starts_with("<style") {
+            "</style>"
+        } else {
+            ">"
+        };
+
+        position = match tag.find(closing) {
+            Some(end) => start + end + closing.len(),
+            None => html.len(),
+        };
+    }
+    text.push_str(&html[position..]);
+
+    decode_entities(&text)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_html() {
+        let html = r#"<html><head><title>T</title><style>p { color: red; }</style></head>
+            <body><!-- hidden --><p>Hello&nbsp;<b>world</b> &amp; co</p>
+            <script>alert("x")</script></body></html>"#;
+
+        assert_eq!(strip_tags(html), "T Hello world & co");
+    }
+
+    #[test]
+    fn strips_uppercase_scripts() {
+        let html = "<P>Avant</P><SCRIPT>var x = '<p>';</SCRIPT><Style>p {}</STYLE>Après";
+
+        assert_eq!(strip_tags(html), "Avant Après");
+    }
+
+    #[test]
+    fn keeps_text_after_tags_with_multibyte_characters() {
+        let html = "<p>été</p><a href='é'>café</a> déjà";
+
+        assert_eq!(strip_tags(html), "été café déjà");
+    }
+}
@@ -0,0 +1,319 @@
+use crate::client::ChromaClient;
+use crate::collection::{
+    Collection, DeleteParams, Entries, GetParams, Include, Metadata, QueryParams,
+};
+use crate::embeddings::EmbeddingFunction;
+use crate::error::ChromaClientError;
+use crate::export::ExportRecord;
+use crate::filter::Where;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Sequence of the messages appended by this process, keeping the ids of
+/// messages appended in the same millisecond apart.
+static SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// A message of a chat session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub id: String,
+    pub session_id: String,
+    /// Author of the message, e.g. `user` or `assistant`.
+    pub role: String,
+    pub text: String,
+    /// Milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    /// Orders the messages appended by a process in the same millisecond.
+    pub sequence: u64,
+}
+
+impl ChatMessage {
+    fn metadata(&self) -> Metadata {
+        Metadata::from_iter([
+            (
+                "session_id".to_string(),
+                Value::from(self.session_id.clone()),
+            ),
+            ("role".to_string(), Value::from(self.role.clone())),
+            ("timestamp".to_string(), Value::from(self.timestamp)),
+            ("sequence".to_string(), Value::from(self.sequence)),
+        ])
+    }
+
+    /// Read a message stored by `ChatMemoryStore::append`.
+    fn from_record(record: ExportRecord) -> Option<Self> {
+        let metadata = record.metadata?;
+        Some(ChatMessage {
+            id: record.id,
+            session_id: metadata.get("session_id")?.as_str()?.to_string(),
+            role: metadata.get("role")?.as_str()?.to_string(),
+            text: record.document.unwrap_or_default(),
+            timestamp: metadata.get("timestamp")?.as_u64()?,
+            sequence: metadata
+                .get("sequence")
+                .and_then(Value::as_u64)
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// Chat history stored in a collection: messages are embedded so the
+/// relevant ones can be recalled from long conversations, and each session
+/// keeps at most `max_messages` messages.
+#[derive(Clone)]
+pub struct ChatMemoryStore {
+    client: ChromaClient,
+    collection: Collection,
+    embedding_function: Arc<dyn EmbeddingFunction>,
+    max_messages: Option<usize>,
+    /// Ids of the messages of the sessions appended to, oldest first, so
+    /// appends can prune without fetching the history again.
+    sessions: Arc<Mutex<HashMap<String, VecDeque<String>>>>,
+}
+
+impl ChatMemoryStore {
+    pub fn new(
+        client: ChromaClient,
+        collection: Collection,
+        embedding_function: Arc<dyn EmbeddingFunction>,
+    ) -> Self {
+        ChatMemoryStore {
+            client,
+            collection,
+            embedding_function,
+            max_messages: None,
+            sessions: Arc::default(),
+        }
+    }
+
+    /// Delete the oldest messages of a session once it has more than `max`.
+    /// The ids of a session are fetched on its first append, later appends
+    /// only delete the messages known to this store: messages appended by
+    /// other stores are counted again by `prune`.
+    pub fn max_messages(mut self, max: usize) -> Self {
+        self.max_messages = Some(max);
+        self
+    }
+
+    /// Store a message, timestamped now.
+    pub async fn append(
+        &self,
+        session_id: &str,
+        role: &str,
+        text: &str,
+    ) -> Result<ChatMessage, ChromaClientError> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        let sequence = SEQUENCE.fetch_add(1, Ordering::Relaxed);
+        let message = ChatMessage {
+            id: format!(
+                "{}:{}:{}-{}:{}",
+                session_id,
+                timestamp,
+                std::process::id(),
+                sequence,
+                role
+            ),
+            session_id: session_id.to_string(),
+            role: role.to_string(),
+            text: text.to_string(),
+            timestamp,
+            sequence,
+        };
+
+        let embeddings = self.embedding_function.embed(&[text]).await?;
+        let entries = Entries {
+            ids: vec![message.id.clone()],
+            embeddings: Some(embeddings),
+            metadatas: Some(vec![message.metadata()]),
+            documents: Some(vec![message.text.clone()]),
+        };
+        self.collection.upsert(&self.client, entries).await?;
+
+        if let Some(max) = self.max_messages {
+            self.trim(session_id, &message.id, max).await?;
+        }
+        Ok(message)
+    }
+
+    /// Delete the messages of the session beyond the latest `max`, from the
+    /// ids known to the store.
+    async fn trim(&self, session_id: &str, id: &str, max: usize) -> Result<(), ChromaClientError> {
+        let known = match self.sessions.lock().unwrap().get_mut(session_id) {
+            Some(ids) => {
+                ids.push_back(id.to_string());
+                true
+            }
+            None => false,
+        };
+        if !known {
+            let ids = self.messages(session_id, false).await?;
+            let ids = ids.into_iter().map(|m| m.id).collect();
+            self.sessions
+                .lock()
+                .unwrap()
+                .insert(session_id.to_string(), ids);
+        }
+
+        let excess: Vec<String> = {
+            let mut sessions = self.sessions.lock().unwrap();
+            let ids = sessions.entry(session_id.to_string()).or_default();
+            let excess = ids.len().saturating_sub(max);
+            ids.drain(..excess).collect()
+        };
+        self.delete_ids(excess).await?;
+        Ok(())
+    }
+
+    /// The `k` messages of the session closest to the query, closest first.
+    pub async fn recall(
+        &self,
+        session_id: &str,
+        query: &str,
+        k: usize,
+    ) -> Result<Vec<ChatMessage>, ChromaClientError> {
+        let query_embeddings = self.embedding_function.embed(&[query]).await?;
+        let params = QueryParams {
+            query_embeddings,
+            n_results: k,
+            where_metadata: Some(session(session_id).into()),
+            include: Some(vec![Include::Documents, Include::Metadatas]),
+            ..Default::default()
+        };
+        let hits = self
+            .collection
+            .query(&self.client, params)
+            .await?
+            .into_hits();
+
+        Ok(hits
+            .into_iter()
+            .next()
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|hit| {
+                ChatMessage::from_record(ExportRecord {
+                    id: hit.id,
+                    document: hit.document,
+                    metadata: hit.metadata,
+                    embedding: None,
+                })
+            })
+            .collect())
+    }
+
+    /// Every message of the session, oldest first.
+    pub async fn history(&self, session_id: &str) -> Result<Vec<ChatMessage>, ChromaClientError> {
+        self.messages(session_id, true).await
+    }
+
+    /// The messages of the session, oldest first, with an empty text
+    /// unless `with_text`.
+    async fn messages(
+        &self,
+        session_id: &str,
+        with_text: bool,
+    ) -> Result<Vec<ChatMessage>, ChromaClientError> {
+        let include = match with_text {
+            true => vec![Include::Documents, Include::Metadatas],
+            false => vec![Include::Metadatas],
+        };
+        let params = GetParams {
+            where_metadata: Some(session(session_id).into()),
+            include: Some(include),
+            ..Default::default()
+        };
+        let result = self.collection.get(&self.client, params).await?;
+
+        let mut messages: Vec<ChatMessage> = ExportRecord::from_result(result)
+            .into_iter()
+            .filter_map(ChatMessage::from_record)
+            .collect();
+        messages.sort_by_key(|m| (m.timestamp, m.sequence));
+        Ok(messages)
+    }
+
+    /// Delete the oldest messages of the session, keeping the latest `keep`.
+    /// Returns the number of deleted messages.
+    pub async fn prune(&self, session_id: &str, keep: usize) -> Result<usize, ChromaClientError> {
+        let history = self.messages(session_id, false).await?;
+        let ids = oldest(&history, keep);
+        let deleted = ids.len();
+        self.delete_ids(ids).await?;
+
+        let kept = history.into_iter().skip(deleted).map(|m| m.id).collect();
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(session_id.to_string(), kept);
+        Ok(deleted)
+    }
+
+    /// Delete every message of the session.
+    pub async fn clear(&self, session_id: &str) -> Result<(), ChromaClientError> {
+        let params = DeleteParams {
+            where_metadata: Some(session(session_id).into()),
+            ..Default::default()
+        };
+        self.collection.delete(&self.client, params).await?;
+        self.sessions.lock().unwrap().remove(session_id);
+        Ok(())
+    }
+
+    async fn delete_ids(&self, ids: Vec<String>) -> Result<(), ChromaClientError> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let params = DeleteParams {
+            ids: Some(ids),
+            ..Default::default()
+        };
+        self.collection.delete(&self.client, params).await
+    }
+}
+
+fn session(session_id: &str) -> Where {
+    Where::eq("session_id", session_id)
+}
+
+/// Ids of the messages beyond the latest `keep`, for messages sorted oldest first.
+fn oldest(messages: &[ChatMessage], keep: usize) -> Vec<String> {
+    let excess = messages.len().saturating_sub(keep);
+    messages[..excess].iter().map(|m| m.id.clone()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stored_messages() {
+        let messages: Vec<ChatMessage> = (0..3)
+            .map(|i| ChatMessage {
+                id: format!("s:{}:user", i),
+                session_id: "s".into(),
+                role: "user".into(),
+                text: format!("message {}", i),
+                timestamp: i,
+                sequence: i,
+            })
+            .collect();
+
+        let record = ExportRecord {
+            id: messages[1].id.clone(),
+            document: Some(messages[1].text.clone()),
+            metadata: Some(messages[1].metadata()),
+            embedding: None,
+        };
+
+        assert_eq!(ChatMessage::from_record(record), Some(messages[1].clone()));
+        assert_eq!(oldest(&messages, 1), ["s:0:user", "s:1:user"]);
+        assert!(oldest(&messages, 5).is_empty());
+    }
+}
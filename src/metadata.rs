@@ -0,0 +1,453 @@
+//! Typed metadata: structs mapped to Chroma metadata, usually with
+//! `#[derive(ChromaMetadata)]` from the `derive` feature, and typed keys to
+//! build filters.
+//!
+//! ```ignore
+//! use chromadb_rs::metadata::ChromaMetadata;
+//!
+//! #[derive(ChromaMetadata)]
+//! struct Book {
+//!     #[chroma(rename = "published")]
+//!     year: i64,
+//!     author: Option<String>,
+//! }
+//!
+//! let filter = Book::fields().year().gte(2020);
+//! ```
+
+use crate::collection::Metadata;
+use crate::error::ChromaClientError;
+use crate::filter::{Operator, Where};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::marker::PhantomData;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "derive")]
+pub use chromadb_rs_derive::ChromaMetadata;
+
+/// A type stored as the metadata of a record.
+pub trait ChromaMetadata: Sized {
+    /// Typed keys of the metadata.
+    type Fields;
+
+    fn fields() -> Self::Fields;
+
+    fn to_metadata(&self) -> Metadata;
+
+    fn from_metadata(metadata: &Metadata) -> Result<Self, ChromaClientError>;
+}
+
+/// A type that can be stored as a metadata value.
+pub trait MetadataField: Sized {
+    /// The type compared in filters, `T` for `Option<T>`.
+    type Filter: MetadataField;
+
+    /// The stored value, `None` to leave the key out.
+    fn to_value(&self) -> Option<Value>;
+
+    /// Read a value, `None` when it is missing or has another type.
+    fn from_value(value: Option<&Value>) -> Option<Self>;
+}
+
+macro_rules! metadata_field {
+    ($($ty:ty => |$value:ident| $read:expr),+ $(,)?) => {
+        $(
+            impl MetadataField for $ty {
+                type Filter = $ty;
+
+                fn to_value(&self) -> Option<Value> {
+                    Some(Value::from(self.clone()))
+                }
+
+                fn from_value(value: Option<&Value>) -> Option<Self> {
+                    let $value = value?;
+                    $read
+                }
+            }
+        )+
+    };
+}
+
+metadata_field! {
+    String => |v| v.as_str().map(str::to_string),
+    bool => |v| v.as_bool(),
+    i64 => |v| v.as_i64(),
+    i32 => |v| v.as_i64()?.try_into().ok(),
+    u32 => |v| v.as_u64()?.try_into().ok(),
+    u64 => |v| v.as_u64(),
+    f64 => |v| v.as_f64(),
+    f32 => |v| v.as_f64().map(|f| f as f32),
+}
+
+impl<T: MetadataField> MetadataField for Option<T> {
+    type Filter = T;
+
+    fn to_value(&self) -> Option<Value> {
+        self.as_ref()?.to_value()
+    }
+
+    fn from_value(value: Option<&Value>) -> Option<Self> {
+        match value {
+            None | Some(Value::Null) => Some(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+}
+
+/// A metadata value. Chroma stores integers and floats apart and compares
+/// them in filters as such, so `Int(2)` and `Float(2.0)` are different
+/// values: floats are always sent with a decimal point, and numbers are
+/// read back with the type they were sent with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MetadataValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+impl MetadataValue {
+    /// Read a JSON value, `None` for arrays, objects and null. Integers
+    /// above `i64::MAX` are read as floats.
+    pub fn from_json(value: &Value) -> Option<Self> {
+        match value {
+            Value::Bool(b) => Some(MetadataValue::Bool(*b)),
+            Value::Number(n) => n
+                .as_i64()
+                .map(MetadataValue::Int)
+                .or_else(|| n.as_f64().map(MetadataValue::Float)),
+            Value::String(s) => Some(MetadataValue::Str(s.clone())),
+            _ => None,
+        }
+    }
+}
+
+/// Metadata key of the creation time of a record, set by `enrich::Timestamps`,
+/// see `Where::created_after`.
+pub const CREATED_AT_KEY: &str = "created_at";
+
+impl MetadataValue {
+    /// A time as seconds since the Unix epoch, the format of the times
+    /// written by this crate (`created_at` and `indexed_at` by
+    /// `enrich::Timestamps`, `expires_at` by `expiry`) and the only one
+    /// Chroma can compare in range filters.
+    pub fn from_datetime(time: SystemTime) -> Self {
+        MetadataValue::Int(unix_seconds(time))
+    }
+
+    /// A time as an RFC 3339 string in UTC, e.g. `2024-01-15T09:30:00Z`,
+    /// for metadata read by people or other tools. Such values can only be
+    /// filtered on with `$eq`, `$ne`, `$in` and `$nin`.
+    pub fn rfc3339(time: SystemTime) -> Self {
+        let seconds = unix_seconds(time);
+        let (days, time_of_day) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+        let (year, month, day) = civil_from_days(days);
+        MetadataValue::Str(format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            year,
+            month,
+            day,
+            time_of_day / 3600,
+            time_of_day % 3600 / 60,
+            time_of_day % 60
+        ))
+    }
+
+    /// Read a time written by `from_datetime` or `rfc3339`. RFC 3339
+    /// strings must be in UTC, fractions of seconds are ignored.
+    pub fn to_datetime(&self) -> Option<SystemTime> {
+        let seconds = match self {
+            MetadataValue::Int(seconds) => *seconds,
+            MetadataValue::Float(seconds) if seconds.is_finite() => *seconds as i64,
+            MetadataValue::Str(s) => parse_rfc3339(s)?,
+            _ => return None,
+        };
+        let offset = Duration::from_secs(seconds.unsigned_abs());
+        if seconds >= 0 {
+            UNIX_EPOCH.checked_add(offset)
+        } else {
+            UNIX_EPOCH.checked_sub(offset)
+        }
+    }
+}
+
+pub(crate) fn unix_seconds(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(after) => after.as_secs() as i64,
+        Err(before) => -(before.duration().as_secs() as i64),
+    }
+}
+
+/// Year, month and day of a number of days since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Days since 1970-01-01 of a date.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = year - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (i64::from(month) + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+fn parse_rfc3339(s: &str) -> Option<i64> {
+    let (date, time) = s.split_once(['T', 't', ' '])?;
+    let time = time.strip_suffix(['Z', 'z'])?;
+    let time = time.split_once('.').map_or(time, |(time, _)| time);
+
+    let mut date = date.splitn(3, '-').map(str::parse::<i64>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    let mut time = time.splitn(3, ':').map(str::parse::<i64>);
+    let (hour, minute, second) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let days = days_from_civil(year, month as u32, day as u32);
+    Some(days * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+impl From<MetadataValue> for Value {
+    fn from(value: MetadataValue) -> Self {
+        match value {
+            MetadataValue::Bool(b) => Value::Bool(b),
+            MetadataValue::Int(i) => Value::from(i),
+            MetadataValue::Float(f) => Value::from(f),
+            MetadataValue::Str(s) => Value::String(s),
+        }
+    }
+}
+
+macro_rules! metadata_value_from {
+    ($($ty:ty => $variant:ident),+ $(,)?) => {
+        $(
+            impl From<$ty> for MetadataValue {
+                fn from(value: $ty) -> Self {
+                    MetadataValue::$variant(value.into())
+                }
+            }
+        )+
+    };
+}
+
+metadata_value_from! {
+    bool => Bool,
+    i64 => Int,
+    i32 => Int,
+    u32 => Int,
+    f64 => Float,
+    f32 => Float,
+    String => Str,
+    &str => Str,
+}
+
+impl MetadataField for MetadataValue {
+    type Filter = MetadataValue;
+
+    fn to_value(&self) -> Option<Value> {
+        Some(self.clone().into())
+    }
+
+    fn from_value(value: Option<&Value>) -> Option<Self> {
+        MetadataValue::from_json(value?)
+    }
+}
+
+/// A metadata key holding values of type `T`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Field<T> {
+    key: &'static str,
+    value_type: PhantomData<T>,
+}
+
+impl<T: MetadataField> Field<T> {
+    pub const fn new(key: &'static str) -> Self {
+        Field {
+            key,
+            value_type: PhantomData,
+        }
+    }
+
+    pub fn key(&self) -> &'static str {
+        self.key
+    }
+
+    fn compare(&self, op: Operator, value: T) -> Where {
+        Where::compare(self.key, op, value.to_value().unwrap_or(Value::Null))
+    }
+
+    pub fn eq(&self, value: T) -> Where {
+        self.compare(Operator::Eq, value)
+    }
+
+    pub fn ne(&self, value: T) -> Where {
+        self.compare(Operator::Ne, value)
+    }
+
+    pub fn gt(&self, value: T) -> Where {
+        self.compare(Operator::Gt, value)
+    }
+
+    pub fn gte(&self, value: T) -> Where {
+        self.compare(Operator::Gte, value)
+    }
+
+    pub fn lt(&self, value: T) -> Where {
+        self.compare(Operator::Lt, value)
+    }
+
+    pub fn lte(&self, value: T) -> Where {
+        self.compare(Operator::Lte, value)
+    }
+
+    pub fn is_in(&self, values: impl IntoIterator<Item = T>) -> Where {
+        Where::is_in(self.key, values.into_iter().filter_map(|v| v.to_value()))
+    }
+
+    pub fn not_in(&self, values: impl IntoIterator<Item = T>) -> Where {
+        Where::not_in(self.key, values.into_iter().filter_map(|v| v.to_value()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn round_trip<T: MetadataField>(value: &T) -> Option<T> {
+        T::from_value(value.to_value().as_ref())
+    }
+
+    proptest! {
+        #[test]
+        fn metadata_field_round_trip(
+            s in ".*",
+            b in any::<bool>(),
+            i in any::<i64>(),
+            u in any::<u32>(),
+            f in any::<f64>().prop_filter("finite", |f| f.is_finite()),
+            o in proptest::option::of(any::<i32>()),
+        ) {
+            prop_assert_eq!(round_trip(&s), Some(s));
+            prop_assert_eq!(round_trip(&b), Some(b));
+            prop_assert_eq!(round_trip(&i), Some(i));
+            prop_assert_eq!(round_trip(&u), Some(u));
+            prop_assert_eq!(round_trip(&f), Some(f));
+            prop_assert_eq!(round_trip(&o), Some(o));
+        }
+    }
+
+    #[test]
+    fn int_and_float_stay_apart() {
+        let metadata = Metadata::from_iter([
+            ("int".to_string(), Value::from(MetadataValue::Int(2))),
+            ("float".to_string(), Value::from(MetadataValue::Float(2.0))),
+        ]);
+
+        let json = serde_json::to_string(&metadata).unwrap();
+        assert_eq!(json, r#"{"float":2.0,"int":2}"#);
+        let metadata: Metadata = serde_json::from_str(&json).unwrap();
+        let field = |key| MetadataValue::from_value(metadata.get(key));
+        assert_eq!(field("int"), Some(MetadataValue::Int(2)));
+        assert_eq!(field("float"), Some(MetadataValue::Float(2.0)));
+        assert_eq!(
+            serde_json::from_str::<Vec<MetadataValue>>("[2, 2.0, true, \"2\"]").unwrap(),
+            [2.into(), 2.0.into(), true.into(), "2".into()]
+        );
+        assert_eq!(
+            Where::gt("price", MetadataValue::Float(10.0)),
+            Where::gt("price", 10.0)
+        );
+    }
+
+    #[test]
+    fn datetimes() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_705_311_000);
+
+        assert_eq!(
+            MetadataValue::from_datetime(time),
+            MetadataValue::Int(1_705_311_000)
+        );
+        let text = MetadataValue::rfc3339(time);
+        assert_eq!(text, "2024-01-15T09:30:00Z".into());
+        assert_eq!(text.to_datetime(), Some(time));
+        assert_eq!(
+            MetadataValue::rfc3339(UNIX_EPOCH - Duration::from_secs(86400)),
+            "1969-12-31T00:00:00Z".into()
+        );
+        assert_eq!(
+            MetadataValue::from("2000-02-29T23:59:59.5Z").to_datetime(),
+            Some(UNIX_EPOCH + Duration::from_secs(951_868_799))
+        );
+        assert_eq!(MetadataValue::from("2024-01-15").to_datetime(), None);
+        assert_eq!(
+            Where::created_after(time),
+            Where::gt(CREATED_AT_KEY, 1_705_311_000)
+        );
+    }
+
+    #[cfg(feature = "derive")]
+    #[derive(Debug, PartialEq, ChromaMetadata)]
+    struct Source {
+        path: String,
+    }
+
+    #[cfg(feature = "derive")]
+    #[derive(Debug, PartialEq, ChromaMetadata)]
+    struct Book {
+        #[chroma(rename = "published")]
+        year: i64,
+        author: Option<String>,
+        #[chroma(flatten)]
+        source: Source,
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn derived_metadata() {
+        use serde_json::json;
+
+        let book = Book {
+            year: 1969,
+            author: None,
+            source: Source {
+                path: "books.csv".into(),
+            },
+        };
+
+        let metadata = book.to_metadata();
+        assert_eq!(
+            Value::Object(metadata.clone()),
+            json!({ "published": 1969, "path": "books.csv" })
+        );
+        assert_eq!(Book::from_metadata(&metadata).unwrap(), book);
+
+        let mut wrong = metadata;
+        wrong.insert("published".into(), json!("1969"));
+        assert!(Book::from_metadata(&wrong).is_err());
+
+        assert_eq!(
+            Book::fields().year().gte(2020),
+            Where::gte("published", 2020)
+        );
+        assert_eq!(
+            Book::fields().source().path().eq("a".into()),
+            Where::eq("path", "a")
+        );
+    }
+}
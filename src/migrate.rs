@@ -0,0 +1,427 @@
+//! Migration of records between Chroma and other vector stores.
+
+use crate::client::ChromaClient;
+use crate::collection::{Collection, Entries, GetParams, Include, Metadata, PAGE_SIZE};
+use crate::document::content_hash;
+use crate::error::ChromaClientError;
+use crate::export::ExportRecord;
+use crate::tabular::infer_value;
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+
+/// Payload key keeping the Chroma id of points whose id Qdrant does not
+/// accept.
+pub const CHROMA_ID_KEY: &str = "chroma_id";
+
+/// Layout of the records exported by another vector store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForeignFormat {
+    /// Qdrant points as returned by the scroll API, one JSON object per
+    /// line with `id`, `vector` and `payload`. `vector` names the vector to
+    /// read from points with named vectors.
+    Qdrant { vector: Option<String> },
+    /// Pinecone vectors as returned by fetch, one JSON object per line with
+    /// `id`, `values` and `metadata`.
+    Pinecone,
+    /// CSV with headers, e.g. from `COPY (SELECT ...) TO STDOUT WITH CSV
+    /// HEADER`. The `embedding` column holds pgvector text such as
+    /// `[0.1,0.2]`. Other columns are metadata, and the keys of JSON object
+    /// columns such as `jsonb` metadata are metadata of their own.
+    PgVector { id: String, embedding: String },
+}
+
+/// How `Collection::import_from` reads a foreign export.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportOptions {
+    pub format: ForeignFormat,
+    /// Payload, metadata or column holding the document text, moved out of
+    /// the metadata. For instance `page_content` for Qdrant collections
+    /// written by LangChain, or `text` for Pinecone indexes.
+    pub document: Option<String>,
+}
+
+impl ImportOptions {
+    pub fn new(format: ForeignFormat) -> Self {
+        ImportOptions {
+            format,
+            document: None,
+        }
+    }
+
+    pub fn document(mut self, key: &str) -> Self {
+        self.document = Some(key.to_string());
+        self
+    }
+}
+
+impl Collection {
+    /// Upsert the records of another vector store: payloads and metadata
+    /// become metadata, nested values are stored as JSON strings, and
+    /// vectors are kept as embeddings. Returns the number of records
+    /// imported.
+    pub async fn import_from<R: BufRead>(
+        &self,
+        client: &ChromaClient,
+        reader: R,
+        options: &ImportOptions,
+    ) -> Result<usize, ChromaClientError> {
+        let records = foreign_records(reader, options)?;
+        let max = client.max_batch_size().await?.unwrap_or(usize::MAX);
+
+        for batch in records.chunks(PAGE_SIZE.min(max)) {
+            let entries: Entries = batch.iter().cloned().collect();
+            self.upsert(client, entries).await?;
+        }
+        Ok(records.len())
+    }
+
+    /// Write every record in the layout read by another vector store, one
+    /// page of records at a time. Documents are stored in the payload or the
+    /// metadata under `document_key`. Returns the number of records written.
+    ///
+    /// - Qdrant: JSON Lines of points, to send in batches to the upsert
+    ///   endpoint. Qdrant ids are unsigned integers or UUIDs: other ids are
+    ///   replaced by a UUID derived from them, and kept in the `chroma_id`
+    ///   payload.
+    /// - Pinecone: JSON Lines of vectors, to send in batches to the upsert
+    ///   endpoint. Metadata keep their type.
+    /// - pgvector: CSV with the id, embedding and document columns, and a
+    ///   `metadata` column of JSON objects, for `COPY ... FROM STDIN WITH
+    ///   CSV HEADER`.
+    pub async fn export_to<W: Write>(
+        &self,
+        client: &ChromaClient,
+        writer: W,
+        format: &ForeignFormat,
+        document_key: &str,
+    ) -> Result<usize, ChromaClientError> {
+        let mut writer = writer;
+        if let ForeignFormat::PgVector { id, embedding } = format {
+            let header = [id, embedding, document_key, "metadata"].map(String::from);
+            write_csv(&mut writer, [header])?;
+        }
+
+        let params = GetParams {
+            include: Some(vec![
+                Include::Documents,
+                Include::Metadatas,
+                Include::Embeddings,
+            ]),
+            ..Default::default()
+        };
+        let mut pages = self.pages(client, params, PAGE_SIZE);
+        let mut exported = 0;
+        while let Some(page) = pages.next().await? {
+            let records = ExportRecord::from_result(page);
+
+            match format {
+                ForeignFormat::Qdrant { vector } => {
+                    for record in &records {
+                        write_line(&mut writer, &qdrant_point(record, vector, document_key))?;
+                    }
+                }
+                ForeignFormat::Pinecone => {
+                    for record in &records {
+                        write_line(&mut writer, &pinecone_vector(record, document_key))?;
+                    }
+                }
+                ForeignFormat::PgVector { .. } => {
+                    write_csv(&mut writer, records.iter().map(pgvector_row))?
+                }
+            }
+
+            exported += records.len();
+        }
+
+        writer.flush().map_err(ChromaClientError::IoError)?;
+        Ok(exported)
+    }
+}
+
+fn qdrant_point(record: &ExportRecord, vector: &Option<String>, document_key: &str) -> Value {
+    let mut payload = record.metadata.clone().unwrap_or_default();
+    if let Some(document) = &record.document {
+        payload.insert(document_key.to_string(), Value::from(document.as_str()));
+    }
+    let id = match record.id.parse::<u64>() {
+        Ok(id) => Value::from(id),
+        Err(_) if is_uuid(&record.id) => Value::from(record.id.as_str()),
+        Err(_) => {
+            payload.insert(CHROMA_ID_KEY.to_string(), Value::from(record.id.as_str()));
+            let hash = content_hash(&record.id);
+            Value::from(format!(
+                "{}-{}-{}-{}-{}",
+                &hash[..8],
+                &hash[8..12],
+                &hash[12..16],
+                &hash[16..20],
+                &hash[20..32]
+            ))
+        }
+    };
+    let embedding = json!(record.embedding.clone().unwrap_or_default());
+    let vector = match vector {
+        Some(name) => json!({ name: embedding }),
+        None => embedding,
+    };
+
+    json!({ "id": id, "vector": vector, "payload": payload })
+}
+
+fn pinecone_vector(record: &ExportRecord, document_key: &str) -> Value {
+    let mut metadata = record.metadata.clone().unwrap_or_default();
+    if let Some(document) = &record.document {
+        metadata.insert(document_key.to_string(), Value::from(document.as_str()));
+    }
+    json!({
+        "id": record.id,
+        "values": record.embedding.clone().unwrap_or_default(),
+        "metadata": metadata,
+    })
+}
+
+fn pgvector_row(record: &ExportRecord) -> [String; 4] {
+    [
+        record.id.clone(),
+        json!(record.embedding.clone().unwrap_or_default()).to_string(),
+        record.document.clone().unwrap_or_default(),
+        Value::Object(record.metadata.clone().unwrap_or_default()).to_string(),
+    ]
+}
+
+fn is_uuid(id: &str) -> bool {
+    id.len() == 36
+        && id.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+fn write_line<W: Write>(writer: &mut W, value: &Value) -> Result<(), ChromaClientError> {
+    serde_json::to_writer(&mut *writer, value).map_err(ChromaClientError::ResponseParseError)?;
+    writer.write_all(b"\n").map_err(ChromaClientError::IoError)
+}
+
+fn write_csv<W, I>(writer: &mut W, rows: I) -> Result<(), ChromaClientError>
+where
+    W: Write,
+    I: IntoIterator<Item = [String; 4]>,
+{
+    let mut csv = csv::Writer::from_writer(writer);
+    for row in rows {
+        csv.write_record(&row)
+            .map_err(|e| ChromaClientError::LoaderError(e.to_string()))?;
+    }
+    csv.flush().map_err(ChromaClientError::IoError)
+}
+
+fn foreign_records<R: BufRead>(
+    reader: R,
+    options: &ImportOptions,
+) -> Result<Vec<ExportRecord>, ChromaClientError> {
+    let mut records = match &options.format {
+        ForeignFormat::Qdrant { vector } => json_lines(reader, |point| qdrant(point, vector))?,
+        ForeignFormat::Pinecone => json_lines(reader, pinecone)?,
+        ForeignFormat::PgVector { id, embedding } => pgvector(reader, id, embedding)?,
+    };
+
+    if let Some(key) = &options.document {
+        for record in &mut records {
+            record.document = match record.metadata.as_mut().and_then(|m| m.remove(key)) {
+                Some(Value::String(text)) => Some(text),
+                Some(other) => Some(other.to_string()),
+                None => None,
+            };
+        }
+    }
+    Ok(records)
+}
+
+fn json_lines<R, F>(reader: R, parse: F) -> Result<Vec<ExportRecord>, ChromaClientError>
+where
+    R: BufRead,
+    F: Fn(Metadata) -> Result<ExportRecord, String>,
+{
+    let mut records = Vec::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line.map_err(ChromaClientError::IoError)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let object: Metadata =
+            serde_json::from_str(&line).map_err(ChromaClientError::ResponseParseError)?;
+        let record = parse(object).map_err(|reason| {
+            ChromaClientError::LoaderError(format!("line {}: {}", number + 1, reason))
+        })?;
+        records.push(record);
+    }
+    Ok(records)
+}
+
+fn qdrant(mut point: Metadata, name: &Option<String>) -> Result<ExportRecord, String> {
+    let id = id(point.remove("id"))?;
+    let vector = match (point.remove("vector"), name) {
+        (Some(Value::Object(mut vectors)), Some(name)) => vectors
+            .remove(name)
+            .ok_or_else(|| format!("point {} has no vector `{}`", id, name))?,
+        (Some(Value::Object(vectors)), None) if vectors.len() == 1 => vectors
+            .into_iter()
+            .next()
+            .map(|(_, v)| v)
+            .unwrap_or_default(),
+        (Some(Value::Object(_)), None) => {
+            return Err(format!("point {} has several named vectors", id))
+        }
+        (vector, _) => vector.unwrap_or_default(),
+    };
+
+    Ok(ExportRecord {
+        embedding: embedding(vector, &id)?,
+        metadata: point.remove("payload").and_then(flatten),
+        document: None,
+        id,
+    })
+}
+
+fn pinecone(mut vector: Metadata) -> Result<ExportRecord, String> {
+    let id = id(vector.remove("id"))?;
+    Ok(ExportRecord {
+        embedding: embedding(vector.remove("values").unwrap_or_default(), &id)?,
+        metadata: vector.remove("metadata").and_then(flatten),
+        document: None,
+        id,
+    })
+}
+
+fn pgvector<R: BufRead>(
+    reader: R,
+    id_column: &str,
+    embedding_column: &str,
+) -> Result<Vec<ExportRecord>, ChromaClientError> {
+    let mut reader = csv::Reader::from_reader(reader);
+    let headers = reader
+        .headers()
+        .map_err(|e| ChromaClientError::LoaderError(e.to_string()))?
+        .clone();
+
+    let mut records = Vec::new();
+    for row in reader.records() {
+        let row = row.map_err(|e| ChromaClientError::LoaderError(e.to_string()))?;
+        let mut record = ExportRecord::default();
+        let mut metadata = Metadata::new();
+        for (header, cell) in headers.iter().zip(row.iter()) {
+            if header == id_column {
+                record.id = cell.to_string();
+            } else if header == embedding_column {
+                let vector = serde_json::from_str(cell).map_err(|_| {
+                    ChromaClientError::LoaderError(format!("invalid vector `{}`", cell))
+                })?;
+                record.embedding = Some(vector);
+            } else if let Ok(object) = serde_json::from_str::<Metadata>(cell) {
+                metadata.extend(object);
+            } else {
+                metadata.insert(header.to_string(), infer_value(cell));
+            }
+        }
+        if record.id.is_empty() {
+            return Err(ChromaClientError::LoaderError(format!(
+                "missing id column `{}`",
+                id_column
+            )));
+        }
+        record.metadata = flatten(Value::Object(metadata));
+        records.push(record);
+    }
+    Ok(records)
+}
+
+fn id(value: Option<Value>) -> Result<String, String> {
+    match value {
+        Some(Value::String(id)) => Ok(id),
+        Some(Value::Number(id)) => Ok(id.to_string()),
+        _ => Err(String::from("missing id")),
+    }
+}
+
+fn embedding(value: Value, id: &str) -> Result<Option<Vec<f32>>, String> {
+    match value {
+        Value::Null => Ok(None),
+        value => serde_json::from_value(value)
+            .map(Some)
+            .map_err(|_| format!("invalid vector for {}", id)),
+    }
+}
+
+/// Metadata holding only scalars: nested values become JSON strings and
+/// nulls are left out. `None` when nothing is left.
+fn flatten(value: Value) -> Option<Metadata> {
+    let Value::Object(object) = value else {
+        return None;
+    };
+    let metadata: Metadata = object
+        .into_iter()
+        .filter(|(_, value)| !value.is_null())
+        .map(|(key, value)| match value {
+            Value::Array(_) | Value::Object(_) => (key, Value::from(value.to_string())),
+            value => (key, value),
+        })
+        .collect();
+    (!metadata.is_empty()).then_some(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn foreign_records_round_trip() {
+        let qdrant_points = concat!(
+            r#"{"id": 7, "vector": {"text": [0.5, 1.0]}, "payload": {"page_content": "hello", "tags": ["a"]}}"#,
+            "\n",
+            r#"{"id": "5c56c793-69f3-4fbf-87e6-c4bf54c28c26", "vector": {"text": [1.5, 2.0]}, "payload": null}"#,
+        );
+        let options = ImportOptions::new(ForeignFormat::Qdrant {
+            vector: Some("text".into()),
+        })
+        .document("page_content");
+
+        let records = foreign_records(qdrant_points.as_bytes(), &options).unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&records).unwrap(),
+            json!([
+                {"id": "7", "document": "hello", "metadata": {"tags": "[\"a\"]"}, "embedding": [0.5, 1.0]},
+                {"id": "5c56c793-69f3-4fbf-87e6-c4bf54c28c26", "embedding": [1.5, 2.0]},
+            ])
+        );
+
+        let pinecone_vectors = r#"{"id": "v1", "values": [0.25], "metadata": {"genre": "drama"}}"#;
+        let records = foreign_records(
+            pinecone_vectors.as_bytes(),
+            &ImportOptions::new(ForeignFormat::Pinecone),
+        )
+        .unwrap();
+        assert_eq!(
+            records[0].metadata,
+            json!({"genre": "drama"}).as_object().cloned()
+        );
+
+        let record = ExportRecord {
+            id: "doc-1".into(),
+            document: Some("hello".into()),
+            metadata: None,
+            embedding: Some(vec![0.5]),
+        };
+        let point = qdrant_point(&record, &None, "text");
+        assert!(is_uuid(point["id"].as_str().unwrap()));
+        assert_eq!(
+            point["payload"],
+            json!({"text": "hello", "chroma_id": "doc-1"})
+        );
+        assert_eq!(
+            pinecone_vector(&record, "text"),
+            json!({"id": "doc-1", "values": [0.5], "metadata": {"text": "hello"}})
+        );
+    }
+}
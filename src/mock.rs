@@ -0,0 +1,147 @@
+use crate::client::{ChromaClient, ChromaClientParams};
+use serde::Serialize;
+use serde_json::{json, Value};
+use wiremock::matchers::{body_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Version reported by `MockChroma`, so requests use the `api/v1` routes.
+pub const MOCK_SERVER_VERSION: &str = "0.5.5";
+
+/// An HTTP server answering like Chroma, to check the requests sent by the
+/// client and test how an application handles errors.
+///
+/// Version, pre-flight and heartbeat requests are answered out of the box,
+/// every other route must be set up with `expect`, `respond` or `fail`.
+///
+/// ```no_run
+/// # async fn example() -> Result<(), chromadb_rs::error::ChromaClientError> {
+/// use chromadb_rs::mock::{fixtures, MockChroma};
+///
+/// let chroma = MockChroma::start().await;
+/// let (status, body) = fixtures::already_exists("docs");
+/// chroma.fail("POST", "api/v1/collections", status, body).await;
+///
+/// assert!(chroma.client().create_collection("docs", None).await.is_err());
+/// # Ok(())
+/// # }
+/// ```
+pub struct MockChroma {
+    server: MockServer,
+}
+
+impl MockChroma {
+    pub async fn start() -> Self {
+        let server = MockServer::start().await;
+        let chroma = MockChroma { server };
+
+        chroma
+            .respond("GET", "api/v1/version", json!(MOCK_SERVER_VERSION))
+            .await;
+        chroma
+            .respond(
+                "GET",
+                "api/v1/pre-flight-checks",
+                json!({ "max_batch_size": 1000 }),
+            )
+            .await;
+        chroma
+            .respond(
+                "GET",
+                "api/v1/heartbeat",
+                json!({ "nanosecond heartbeat": 1 }),
+            )
+            .await;
+        chroma
+    }
+
+    /// A client connected to the mock server.
+    pub fn client(&self) -> ChromaClient {
+        let address = self.server.address();
+        ChromaClient::new(ChromaClientParams {
+            host: address.ip().to_string(),
+            port: address.port().to_string(),
+            ..Default::default()
+        })
+    }
+
+    /// The underlying server, to set up custom mocks.
+    pub fn server(&self) -> &MockServer {
+        &self.server
+    }
+
+    /// Answer `response` to exactly one request whose JSON body equals
+    /// `body`. Checked by `verify` or when the mock is dropped.
+    pub async fn expect<B: Serialize, R: Serialize>(
+        &self,
+        http_method: &str,
+        route: &str,
+        body: B,
+        response: R,
+    ) {
+        Mock::given(method(http_method))
+            .and(path(format!("/{}", route)))
+            .and(body_json(body))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response))
+            .expect(1)
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Answer `response` to every request on the route.
+    pub async fn respond<R: Serialize>(&self, http_method: &str, route: &str, response: R) {
+        Mock::given(method(http_method))
+            .and(path(format!("/{}", route)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(response))
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Answer every request on the route with an error, see `fixtures`.
+    pub async fn fail(&self, http_method: &str, route: &str, status: u16, body: Value) {
+        Mock::given(method(http_method))
+            .and(path(format!("/{}", route)))
+            .respond_with(ResponseTemplate::new(status).set_body_json(body))
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Panic if an `expect`ed request was not received.
+    pub async fn verify(&self) {
+        self.server.verify().await;
+    }
+}
+
+/// Error responses as returned by Chroma, as `(status, body)`.
+pub mod fixtures {
+    use serde_json::{json, Value};
+
+    pub fn error(status: u16, error: &str, message: &str) -> (u16, Value) {
+        (status, json!({ "error": error, "message": message }))
+    }
+
+    pub fn already_exists(collection: &str) -> (u16, Value) {
+        let message = format!("Collection {} already exists", collection);
+        error(409, "UniqueConstraintError", &message)
+    }
+
+    pub fn not_found(collection: &str) -> (u16, Value) {
+        let message = format!("Collection {} does not exist.", collection);
+        error(404, "NotFoundError", &message)
+    }
+
+    pub fn unauthorized() -> (u16, Value) {
+        error(401, "AuthError", "Unauthorized")
+    }
+
+    pub fn invalid_dimension(expected: usize, actual: usize) -> (u16, Value) {
+        let message = format!(
+            "Embedding dimension {} does not match collection dimensionality {}",
+            actual, expected
+        );
+        error(400, "InvalidDimensionException", &message)
+    }
+
+    pub fn internal_error() -> (u16, Value) {
+        error(500, "InternalError", "Internal server error")
+    }
+}
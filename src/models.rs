@@ -0,0 +1,43 @@
+//! Every public response type in one place. They all implement `Serialize`,
+//! `Deserialize`, `Clone` and `PartialEq`, so services can return them in
+//! their own responses.
+
+pub use crate::bench::{BenchReport, Percentiles};
+pub use crate::collection::{Collection, GetResult, Metadata, QueryResult};
+pub use crate::document::Document;
+pub use crate::embeddings::EmbeddingFunctionInfo;
+pub use crate::error::ErrorResponse;
+pub use crate::explain::QueryDebug;
+pub use crate::export::ExportRecord;
+pub use crate::hits::{HitGroup, QueryHit};
+#[cfg(feature = "ingest")]
+pub use crate::ingest::{DroppedChunk, IngestReport};
+pub use crate::monitor::HealthSample;
+pub use crate::rag::{Citation, Context};
+pub use crate::reembed::ReembedProgress;
+pub use crate::stats::{CollectionStats, Distribution, KeyStats};
+pub use crate::usage::EmbeddingUsage;
+pub use crate::verify::VerifyReport;
+pub use crate::watch::ChangeEvent;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{ApiError, ChromaClientError};
+
+    #[test]
+    fn error_response() {
+        let status = reqwest::StatusCode::NOT_FOUND;
+        let api_error = ApiError::new("", status, &Default::default(), b"missing");
+        let error = ChromaClientError::ResponseStatusError(api_error);
+
+        assert_eq!(
+            serde_json::to_value(ErrorResponse::from(&error)).unwrap(),
+            serde_json::json!({
+                "kind": "ResponseStatusError",
+                "message": "Respond with a bad status: 404 Not Found - missing",
+                "status": 404
+            })
+        );
+    }
+}
@@ -0,0 +1,130 @@
+use crate::client::ChromaClient;
+use futures_util::stream::{self, Stream};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Number of latest heartbeats the latency statistics are computed on.
+const WINDOW: usize = 20;
+
+/// Result of one heartbeat of `ChromaClient::monitor`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HealthSample {
+    pub available: bool,
+    /// Whether availability changed since the previous heartbeat. The first
+    /// sample is always a change.
+    pub changed: bool,
+    /// Latency of this heartbeat, `None` when it failed.
+    pub latency: Option<Duration>,
+    /// Mean latency of the latest successful heartbeats.
+    pub mean_latency: Duration,
+    /// Mean difference between consecutive latencies.
+    pub jitter: Duration,
+    /// Message of the latest failed heartbeat, even if the server came back since.
+    pub last_error: Option<String>,
+}
+
+impl ChromaClient {
+    /// Send a heartbeat every `interval` and yield its latency and the
+    /// availability of the server, for health endpoints and dashboards. The
+    /// stream ends when the client is shut down.
+    pub fn monitor(&self, interval: Duration) -> impl Stream<Item = HealthSample> + '_ {
+        let ticker = tokio::time::interval(interval);
+
+        stream::unfold(
+            (ticker, Monitor::default()),
+            move |(mut ticker, mut monitor)| async move {
+                ticker.tick().await;
+                if self.is_shut_down() {
+                    return None;
+                }
+
+                let started = Instant::now();
+                let result = self.heartbeat().await.map(|_| started.elapsed());
+                let sample = monitor.record(result.map_err(|e| e.to_string()));
+
+                Some((sample, (ticker, monitor)))
+            },
+        )
+    }
+}
+
+#[derive(Default)]
+struct Monitor {
+    latencies: VecDeque<Duration>,
+    available: Option<bool>,
+    last_error: Option<String>,
+}
+
+impl Monitor {
+    fn record(&mut self, result: Result<Duration, String>) -> HealthSample {
+        let available = result.is_ok();
+        let changed = self.available != Some(available);
+        self.available = Some(available);
+
+        let latency = match result {
+            Ok(latency) => {
+                if self.latencies.len() == WINDOW {
+                    self.latencies.pop_front();
+                }
+                self.latencies.push_back(latency);
+                Some(latency)
+            }
+            Err(e) => {
+                self.last_error = Some(e);
+                None
+            }
+        };
+
+        let mean_latency = match self.latencies.len() {
+            0 => Duration::ZERO,
+            n => self.latencies.iter().sum::<Duration>() / n as u32,
+        };
+        let jitter = match self.latencies.len() {
+            0 | 1 => Duration::ZERO,
+            n => {
+                let diffs: Duration = self
+                    .latencies
+                    .iter()
+                    .zip(self.latencies.iter().skip(1))
+                    .map(|(a, b)| a.abs_diff(*b))
+                    .sum();
+                diffs / (n - 1) as u32
+            }
+        };
+
+        HealthSample {
+            available,
+            changed,
+            latency,
+            mean_latency,
+            jitter,
+            last_error: self.last_error.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn availability_transitions_and_jitter() {
+        let mut monitor = Monitor::default();
+        let ms = Duration::from_millis;
+
+        let first = monitor.record(Ok(ms(10)));
+        let second = monitor.record(Ok(ms(20)));
+        let down = monitor.record(Err("connection refused".into()));
+        let up = monitor.record(Ok(ms(30)));
+
+        assert!(first.changed && first.available);
+        assert!(!second.changed);
+        assert_eq!(second.jitter, ms(10));
+        assert!(down.changed && !down.available);
+        assert_eq!(down.latency, None);
+        assert!(up.changed);
+        assert_eq!(up.mean_latency, ms(20));
+        assert_eq!(up.last_error.as_deref(), Some("connection refused"));
+    }
+}
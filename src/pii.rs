@@ -0,0 +1,272 @@
+//! Masking of personal data in documents before they are embedded and
+//! stored: email addresses, phone numbers and payment card numbers.
+//!
+//! Detection is heuristic: phone numbers need a `+` prefix, an area code in
+//! parentheses or the usual groupings (`555-123-4567`, `06 12 34 56 78`),
+//! card numbers are runs of 13 to 19 digits passing the Luhn check. Dates,
+//! versions, years, timestamps and ids are left alone.
+
+use crate::document::Document;
+use crate::enrich::Enricher;
+use serde::{Deserialize, Serialize};
+use std::ops::AddAssign;
+use std::sync::{Arc, Mutex};
+
+pub const EMAIL_MASK: &str = "[EMAIL]";
+pub const PHONE_MASK: &str = "[PHONE]";
+pub const CARD_MASK: &str = "[CARD]";
+
+/// Number of values masked, by kind.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Redactions {
+    pub emails: usize,
+    pub phones: usize,
+    pub cards: usize,
+    /// Documents with at least one masked value.
+    pub documents: usize,
+}
+
+impl Redactions {
+    pub fn total(&self) -> usize {
+        self.emails + self.phones + self.cards
+    }
+}
+
+impl AddAssign for Redactions {
+    fn add_assign(&mut self, other: Self) {
+        self.emails += other.emails;
+        self.phones += other.phones;
+        self.cards += other.cards;
+        self.documents += other.documents;
+    }
+}
+
+/// The text with its personal data masked.
+pub fn scrub(text: &str) -> (String, Redactions) {
+    let chars: Vec<char> = text.chars().collect();
+    let mut scrubbed = String::with_capacity(text.len());
+    let mut redactions = Redactions::default();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let word_start = i == 0 || !chars[i - 1].is_alphanumeric();
+        if word_start {
+            if let Some(end) = email(&chars, i) {
+                scrubbed.push_str(EMAIL_MASK);
+                redactions.emails += 1;
+                i = end;
+                continue;
+            }
+            if let Some((end, kind)) = number(&chars, i) {
+                match kind {
+                    Number::Card => {
+                        scrubbed.push_str(CARD_MASK);
+                        redactions.cards += 1;
+                    }
+                    Number::Phone => {
+                        scrubbed.push_str(PHONE_MASK);
+                        redactions.phones += 1;
+                    }
+                    Number::Other => scrubbed.extend(&chars[i..end]),
+                }
+                i = end;
+                continue;
+            }
+        }
+        scrubbed.push(chars[i]);
+        i += 1;
+    }
+
+    redactions.documents = usize::from(redactions.total() > 0);
+    (scrubbed, redactions)
+}
+
+/// Mask the personal data of a batch of documents.
+pub fn scrub_documents(documents: &mut [Document]) -> Redactions {
+    let mut total = Redactions::default();
+    for document in documents {
+        let (text, redactions) = scrub(&document.text);
+        document.text = text;
+        total += redactions;
+    }
+    total
+}
+
+/// End of the email address starting at `start`.
+fn email(chars: &[char], start: usize) -> Option<usize> {
+    let local = |c: &char| c.is_alphanumeric() || "._%+-".contains(*c);
+    let domain = |c: &char| c.is_alphanumeric() || ".-".contains(*c);
+
+    let at = start + chars[start..].iter().take_while(|c| local(c)).count();
+    if at == start || chars.get(at) != Some(&'@') {
+        return None;
+    }
+    let mut end = at + 1 + chars[at + 1..].iter().take_while(|c| domain(c)).count();
+    while end > at + 1 && ".-".contains(chars[end - 1]) {
+        end -= 1;
+    }
+
+    let host = &chars[at + 1..end];
+    let dot = host.iter().rposition(|c| *c == '.')?;
+    let tld = &host[dot + 1..];
+    (dot > 0 && tld.len() >= 2 && tld.iter().all(|c| c.is_alphabetic())).then_some(end)
+}
+
+enum Number {
+    Card,
+    Phone,
+    /// Kept as is, and not searched for a shorter number either.
+    Other,
+}
+
+/// End and kind of the number starting at `start`.
+fn number(chars: &[char], start: usize) -> Option<(usize, Number)> {
+    if !(chars[start].is_ascii_digit() || chars[start] == '+' || chars[start] == '(') {
+        return None;
+    }
+
+    let mut digits = Vec::new();
+    // Lengths of the digit groups, split on separators and parentheses.
+    let mut groups: Vec<usize> = Vec::new();
+    let mut end = start;
+    let mut previous_separator = false;
+    let mut in_group = false;
+    for (i, &c) in chars.iter().enumerate().skip(start) {
+        if c.is_ascii_digit() {
+            digits.push(c as u8 - b'0');
+            match groups.last_mut() {
+                Some(length) if in_group => *length += 1,
+                _ => groups.push(1),
+            }
+            end = i + 1;
+            previous_separator = false;
+            in_group = true;
+        } else if (c == '+' && i == start) || c == '(' || c == ')' {
+            previous_separator = false;
+            in_group = false;
+        } else if " -.".contains(c) && !previous_separator {
+            previous_separator = true;
+            in_group = false;
+        } else {
+            break;
+        }
+    }
+    if chars.get(end).is_some_and(|c| c.is_alphanumeric()) {
+        return None;
+    }
+
+    match digits.len() {
+        0 => None,
+        13..=19 if luhn(&digits) => Some((end, Number::Card)),
+        _ if phone_shaped(chars[start], digits.len(), &groups) => Some((end, Number::Phone)),
+        _ => Some((end, Number::Other)),
+    }
+}
+
+/// Whether a number looks like a phone number: an international `+` prefix,
+/// an area code in parentheses, or the usual national groupings. Bare runs of
+/// digits and runs of 4-digit groups (years, timestamps, ids) are not.
+fn phone_shaped(first: char, digits: usize, groups: &[usize]) -> bool {
+    match first {
+        '+' => (8..=15).contains(&digits) && groups.len() > 1,
+        '(' => (10..=11).contains(&digits) && groups.len() > 1,
+        _ => matches!(groups, [3, 3, 4] | [1, 3, 3, 4] | [2, 2, 2, 2, 2]),
+    }
+}
+
+fn luhn(digits: &[u8]) -> bool {
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| match (i % 2, d * 2) {
+            (0, _) => d as u32,
+            (_, doubled) if doubled > 9 => doubled as u32 - 9,
+            (_, doubled) => doubled as u32,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+/// Enricher masking personal data, keeping the count of the values masked
+/// since the last call to `take_report`.
+///
+/// Enrichers of an `IngestPipeline` run on each chunk, so a value split
+/// between two chunks is missed: use `IngestPipeline::scrub_pii` there.
+#[derive(Debug, Default)]
+pub struct PiiScrubber {
+    redactions: Mutex<Redactions>,
+}
+
+impl PiiScrubber {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Values masked since the previous call, e.g. for the last batch.
+    pub fn take_report(&self) -> Redactions {
+        std::mem::take(&mut self.redactions.lock().unwrap())
+    }
+}
+
+impl Enricher for PiiScrubber {
+    fn enrich(&self, document: &mut Document) {
+        let (text, redactions) = scrub(&document.text);
+        document.text = text;
+        *self.redactions.lock().unwrap() += redactions;
+    }
+}
+
+/// Shared with a pipeline, to read the report while it runs.
+impl Enricher for Arc<PiiScrubber> {
+    fn enrich(&self, document: &mut Document) {
+        self.as_ref().enrich(document)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_personal_data() {
+        let (text, redactions) = scrub(
+            "Mail jane.doe+news@example.co.uk or call +33 6 12 34 56 78, \
+             (555) 123-4567. Card 4111 1111 1111 1111, order 4111 1111 1111 1112. \
+             Version 1.2.3 released on 2024-01-15 at a@b.",
+        );
+
+        assert_eq!(
+            text,
+            "Mail [EMAIL] or call [PHONE], [PHONE]. Card [CARD], order 4111 1111 1111 1112. \
+             Version 1.2.3 released on 2024-01-15 at a@b."
+        );
+        assert_eq!(
+            redactions,
+            Redactions {
+                emails: 1,
+                phones: 2,
+                cards: 1,
+                documents: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn keeps_numbers_that_are_not_phones() {
+        let input = "Years 2019 2020 2021, at 1700000000, ISBN 0-306-40615-2, \
+                     order 1234567890.";
+        let (text, redactions) = scrub(input);
+
+        assert_eq!(text, input);
+        assert_eq!(redactions, Redactions::default());
+    }
+
+    #[test]
+    fn masks_usual_phone_groupings() {
+        let (text, redactions) = scrub("Call 555.123.4567 or 06 12 34 56 78.");
+
+        assert_eq!(text, "Call [PHONE] or [PHONE].");
+        assert_eq!(redactions.phones, 2);
+    }
+}
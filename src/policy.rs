@@ -0,0 +1,112 @@
+use crate::error::ChromaClientError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Categories of client operations, allowed or denied by an `OperationPolicy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    /// Get, query and count records, list and get collections, including
+    /// existing collections opened with `get_or_create_collection`.
+    Read,
+    /// Add, update and upsert records.
+    Write,
+    /// Delete records.
+    Delete,
+    CreateCollection,
+    DeleteCollection,
+    Reset,
+}
+
+impl Operation {
+    pub const ALL: [Operation; 6] = [
+        Operation::Read,
+        Operation::Write,
+        Operation::Delete,
+        Operation::CreateCollection,
+        Operation::DeleteCollection,
+        Operation::Reset,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Operation::Read => "read",
+            Operation::Write => "write",
+            Operation::Delete => "delete",
+            Operation::CreateCollection => "create_collection",
+            Operation::DeleteCollection => "delete_collection",
+            Operation::Reset => "reset",
+        }
+    }
+
+    /// Whether the operation changes data on the server.
+    pub fn is_mutating(self) -> bool {
+        self != Operation::Read
+    }
+}
+
+/// The operations a client may run, checked before sending any request.
+/// A client with a restricted policy can be handed to code that should only
+/// do part of the work, e.g. a plugin allowed to query but not to write.
+///
+/// ```
+/// use chromadb_rs::policy::{Operation, OperationPolicy};
+///
+/// let policy = OperationPolicy::read_only().allow(Operation::Write);
+///
+/// assert!(policy.allows(Operation::Write));
+/// assert!(!policy.allows(Operation::Delete));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperationPolicy {
+    denied: HashSet<Operation>,
+}
+
+impl Default for OperationPolicy {
+    fn default() -> Self {
+        OperationPolicy::allow_all()
+    }
+}
+
+impl OperationPolicy {
+    pub fn allow_all() -> Self {
+        OperationPolicy {
+            denied: HashSet::new(),
+        }
+    }
+
+    pub fn deny_all() -> Self {
+        OperationPolicy {
+            denied: Operation::ALL.into_iter().collect(),
+        }
+    }
+
+    /// Only `Operation::Read` is allowed.
+    pub fn read_only() -> Self {
+        OperationPolicy::deny_all().allow(Operation::Read)
+    }
+
+    pub fn allow(mut self, operation: Operation) -> Self {
+        self.denied.remove(&operation);
+        self
+    }
+
+    pub fn deny(mut self, operation: Operation) -> Self {
+        self.denied.insert(operation);
+        self
+    }
+
+    pub fn allows(&self, operation: Operation) -> bool {
+        !self.denied.contains(&operation)
+    }
+
+    pub(crate) fn check(&self, operation: Operation) -> Result<(), ChromaClientError> {
+        if self.allows(operation) {
+            Ok(())
+        } else {
+            Err(ChromaClientError::OperationDenied(
+                operation.as_str().to_string(),
+            ))
+        }
+    }
+}
@@ -0,0 +1,221 @@
+//! Export of embeddings for the TensorFlow Projector
+//! (https://projector.tensorflow.org), to look at the structure of an index.
+
+use crate::client::ChromaClient;
+use crate::collection::{Collection, GetParams, Include, PAGE_SIZE};
+use crate::error::ChromaClientError;
+use crate::export::ExportRecord;
+use crate::simd::{common_dimension, dot};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// File of the vectors, one tab separated row per record.
+pub const VECTORS_FILE: &str = "vectors.tsv";
+
+/// File of the labels, with a header row, in the order of the vectors.
+pub const LABELS_FILE: &str = "metadata.tsv";
+
+/// Iterations of the power method per principal component.
+const PCA_ITERATIONS: usize = 100;
+
+/// Vectors written by `Collection::export_projection`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProjectionFormat {
+    /// The embeddings, projected by the Projector itself.
+    #[default]
+    Embeddings,
+    /// The first two principal components of the embeddings, computed
+    /// locally. Smaller files, and readable by any plotting tool.
+    Pca2d,
+}
+
+impl Collection {
+    /// Write the embeddings of the collection to `vectors.tsv` and their
+    /// labels to `metadata.tsv` in the `dir` directory, the two files loaded
+    /// by the Projector. Labels are the id, the document and one column per
+    /// metadata key. Returns the number of records written.
+    pub async fn export_projection(
+        &self,
+        client: &ChromaClient,
+        dir: impl AsRef<Path>,
+        format: ProjectionFormat,
+    ) -> Result<usize, ChromaClientError> {
+        let records = self.projection_records(client).await?;
+        let mut vectors: Vec<Vec<f32>> = records
+            .iter()
+            .map(|r| r.embedding.clone().unwrap_or_default())
+            .collect();
+        if format == ProjectionFormat::Pca2d {
+            vectors = pca(&vectors, 2)?;
+        }
+
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir).map_err(ChromaClientError::IoError)?;
+        let file = File::create(dir.join(VECTORS_FILE)).map_err(ChromaClientError::IoError)?;
+        write_vectors(BufWriter::new(file), &vectors).map_err(ChromaClientError::IoError)?;
+        let file = File::create(dir.join(LABELS_FILE)).map_err(ChromaClientError::IoError)?;
+        write_labels(BufWriter::new(file), &records).map_err(ChromaClientError::IoError)?;
+
+        Ok(records.len())
+    }
+
+    async fn projection_records(
+        &self,
+        client: &ChromaClient,
+    ) -> Result<Vec<ExportRecord>, ChromaClientError> {
+        let params = GetParams {
+            include: Some(vec![
+                Include::Documents,
+                Include::Metadatas,
+                Include::Embeddings,
+            ]),
+            ..Default::default()
+        };
+        let mut pages = self.pages(client, params, PAGE_SIZE);
+        let mut records = Vec::new();
+        while let Some(page) = pages.next().await? {
+            records.extend(ExportRecord::from_result(page));
+        }
+        Ok(records)
+    }
+}
+
+fn write_vectors<W: Write>(mut writer: W, vectors: &[Vec<f32>]) -> std::io::Result<()> {
+    for vector in vectors {
+        let row: Vec<String> = vector.iter().map(f32::to_string).collect();
+        writeln!(writer, "{}", row.join("\t"))?;
+    }
+    writer.flush()
+}
+
+fn write_labels<W: Write>(mut writer: W, records: &[ExportRecord]) -> std::io::Result<()> {
+    let keys: BTreeSet<&str> = records
+        .iter()
+        .filter_map(|r| r.metadata.as_ref())
+        .flat_map(|m| m.keys().map(String::as_str))
+        .collect();
+
+    let header: Vec<&str> = ["id", "document"]
+        .into_iter()
+        .chain(keys.iter().copied())
+        .collect();
+    writeln!(writer, "{}", header.join("\t"))?;
+    for record in records {
+        let mut row = vec![
+            cell(&record.id),
+            cell(record.document.as_deref().unwrap_or("")),
+        ];
+        for key in &keys {
+            let value = record.metadata.as_ref().and_then(|m| m.get(*key));
+            row.push(match value {
+                None | Some(Value::Null) => String::new(),
+                Some(Value::String(s)) => cell(s),
+                Some(other) => cell(&other.to_string()),
+            });
+        }
+        writeln!(writer, "{}", row.join("\t"))?;
+    }
+    writer.flush()
+}
+
+/// A value on a single line without tabs.
+fn cell(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Project the vectors on their first `components` principal components,
+/// found with the power method and deflation.
+pub fn pca(vectors: &[Vec<f32>], components: usize) -> Result<Vec<Vec<f32>>, ChromaClientError> {
+    let dimension = common_dimension(vectors)?;
+    if vectors.is_empty() || dimension == 0 {
+        return Ok(vec![Vec::new(); vectors.len()]);
+    }
+
+    let mut mean = vec![0.0; dimension];
+    for vector in vectors {
+        for (m, x) in mean.iter_mut().zip(vector) {
+            *m += x / vectors.len() as f32;
+        }
+    }
+    let centered: Vec<Vec<f32>> = vectors
+        .iter()
+        .map(|v| v.iter().zip(&mean).map(|(x, m)| x - m).collect())
+        .collect();
+
+    let mut axes: Vec<Vec<f32>> = Vec::new();
+    for c in 0..components.min(dimension) {
+        // Start from a basis vector, so the result is the same on every run.
+        let mut axis = vec![0.0; dimension];
+        axis[c] = 1.0;
+        for _ in 0..PCA_ITERATIONS {
+            // Covariance times axis, as centered^T (centered axis).
+            let mut next = vec![0.0; dimension];
+            for row in &centered {
+                let weight = dot(row, &axis);
+                for (n, x) in next.iter_mut().zip(row) {
+                    *n += weight * x;
+                }
+            }
+            for previous in &axes {
+                let overlap = dot(&next, previous);
+                for (n, p) in next.iter_mut().zip(previous) {
+                    *n -= overlap * p;
+                }
+            }
+            let norm = dot(&next, &next).sqrt();
+            if norm == 0.0 {
+                break;
+            }
+            axis = next.into_iter().map(|x| x / norm).collect();
+        }
+        axes.push(axis);
+    }
+
+    Ok(centered
+        .iter()
+        .map(|row| axes.iter().map(|axis| dot(row, axis)).collect())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn pca_and_labels() {
+        let vectors = vec![
+            vec![1.0, 1.0, 0.0],
+            vec![2.0, 2.0, 0.0],
+            vec![3.0, 3.0, 0.1],
+        ];
+
+        let projected = pca(&vectors, 2).unwrap();
+
+        // The points lie along (1, 1, 0): the first component carries the spread.
+        assert!((projected[2][0] - projected[0][0]).abs() > 2.8);
+        assert!(projected.iter().all(|p| p[1].abs() < 0.1));
+
+        let records = vec![
+            ExportRecord {
+                id: "a".into(),
+                document: Some("two\tlines\n".into()),
+                metadata: json!({"page": 3}).as_object().cloned(),
+                embedding: None,
+            },
+            ExportRecord {
+                id: "b".into(),
+                ..Default::default()
+            },
+        ];
+        let mut labels = Vec::new();
+        write_labels(&mut labels, &records).unwrap();
+        assert_eq!(
+            String::from_utf8(labels).unwrap(),
+            "id\tdocument\tpage\na\ttwo lines\t3\nb\t\t\n"
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use crate::client::ChromaClient;
+use crate::collection::{Collection, Include, QueryParams, QueryResult};
+use crate::embeddings::EmbeddingFunction;
+use crate::error::ChromaClientError;
+use crate::filter::Where;
+use serde_json::Value;
+
+/// A query built step by step, created by `Collection::query_builder`.
+///
+/// ```no_run
+/// # async fn example(
+/// #     client: &chromadb_rs::client::ChromaClient,
+/// #     collection: &chromadb_rs::collection::Collection,
+/// #     embedding_function: &dyn chromadb_rs::embeddings::EmbeddingFunction,
+/// # ) -> Result<(), chromadb_rs::error::ChromaClientError> {
+/// use chromadb_rs::collection::Include;
+/// use chromadb_rs::filter::Where;
+///
+/// let result = collection
+///     .query_builder()
+///     .texts(["rust async runtimes"])
+///     .embedding_function(embedding_function)
+///     .n_results(10)
+///     .where_(Where::eq("lang", "en"))
+///     .include([Include::Documents, Include::Distances])
+///     .execute(client)
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct QueryRequest<'a> {
+    collection: &'a Collection,
+    params: QueryParams,
+    texts: Vec<String>,
+    embedding_function: Option<&'a dyn EmbeddingFunction>,
+}
+
+impl Collection {
+    /// Start building a query.
+    pub fn query_builder(&self) -> QueryRequest<'_> {
+        QueryRequest {
+            collection: self,
+            params: QueryParams::default(),
+            texts: Vec::new(),
+            embedding_function: None,
+        }
+    }
+}
+
+impl<'a> QueryRequest<'a> {
+    /// Query embeddings, searched along with the embeddings of `texts`.
+    pub fn embeddings<I: IntoIterator<Item = Vec<f32>>>(mut self, embeddings: I) -> Self {
+        self.params.query_embeddings.extend(embeddings);
+        self
+    }
+
+    /// Query texts, embedded with the `embedding_function` when executed.
+    pub fn texts<I: IntoIterator<Item = S>, S: Into<String>>(mut self, texts: I) -> Self {
+        self.texts.extend(texts.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn embedding_function(mut self, embedding_function: &'a dyn EmbeddingFunction) -> Self {
+        self.embedding_function = Some(embedding_function);
+        self
+    }
+
+    pub fn n_results(mut self, n_results: usize) -> Self {
+        self.params.n_results = n_results;
+        self
+    }
+
+    pub fn where_(mut self, filter: Where) -> Self {
+        self.params.where_metadata = Some(filter.into());
+        self
+    }
+
+    pub fn where_document(mut self, filter: Value) -> Self {
+        self.params.where_document = Some(filter);
+        self
+    }
+
+    pub fn include<I: IntoIterator<Item = Include>>(mut self, include: I) -> Self {
+        self.params.include = Some(include.into_iter().collect());
+        self
+    }
+
+    /// The parameters sent, once the texts are embedded.
+    pub async fn into_params(self) -> Result<QueryParams, ChromaClientError> {
+        let mut params = self.params;
+        if !self.texts.is_empty() {
+            let embedding_function = self.embedding_function.ok_or_else(|| {
+                ChromaClientError::EmbeddingError(String::from(
+                    "query texts require an embedding function",
+                ))
+            })?;
+            let texts: Vec<&str> = self.texts.iter().map(String::as_str).collect();
+            params
+                .query_embeddings
+                .extend(embedding_function.embed(&texts).await?);
+        }
+        Ok(params)
+    }
+
+    pub async fn execute(self, client: &ChromaClient) -> Result<QueryResult, ChromaClientError> {
+        let collection = self.collection;
+        let params = self.into_params().await?;
+        collection.query(client, params).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+
+    struct LengthEmbedder;
+
+    #[async_trait]
+    impl EmbeddingFunction for LengthEmbedder {
+        async fn embed(&self, documents: &[&str]) -> Result<Vec<Vec<f32>>, ChromaClientError> {
+            Ok(documents.iter().map(|d| vec![d.len() as f32]).collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn texts_are_embedded() {
+        let collection = Collection::new("docs".into(), None);
+
+        let params = collection
+            .query_builder()
+            .embeddings([vec![0.5]])
+            .texts(["abc"])
+            .embedding_function(&LengthEmbedder)
+            .n_results(3)
+            .where_(Where::eq("lang", "en"))
+            .into_params()
+            .await
+            .unwrap();
+
+        assert_eq!(params.query_embeddings, vec![vec![0.5], vec![3.0]]);
+        assert_eq!(params.n_results, 3);
+        assert_eq!(
+            params.where_metadata,
+            Some(serde_json::json!({"lang": {"$eq": "en"}}))
+        );
+
+        let missing = collection
+            .query_builder()
+            .texts(["abc"])
+            .into_params()
+            .await;
+        assert!(missing.is_err());
+    }
+}
@@ -0,0 +1,188 @@
+use crate::hits::QueryHit;
+use crate::source::{SourceRef, SOURCE_KEY};
+use crate::usage::estimate_tokens;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// How the hits are ordered in the context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContextOrder {
+    /// Best score first.
+    #[default]
+    Score,
+    /// By source, then by position in the source, using the `source` and
+    /// `chunk` metadata set by the loaders and the text splitter.
+    Position,
+}
+
+/// A hit included in the context, numbered as in the emitted text.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Citation {
+    pub number: usize,
+    pub id: String,
+    pub source: Option<String>,
+    /// Title, page and span of the source, from the metadata of the hit.
+    pub reference: Option<SourceRef>,
+    pub score: Option<f32>,
+}
+
+/// The assembled context and the hits it cites.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Context {
+    pub text: String,
+    pub citations: Vec<Citation>,
+    pub tokens: u64,
+}
+
+/// Builds the context of a prompt from query hits: duplicates are removed,
+/// hits are ordered and added as `[n] document` paragraphs while they fit in
+/// the token budget.
+///
+/// ```
+/// use chromadb_rs::hits::QueryHit;
+/// use chromadb_rs::rag::{ContextBuilder, ContextOrder};
+///
+/// let hits = vec![QueryHit {
+///     id: "1".into(),
+///     document: Some("Chroma is a vector database.".into()),
+///     ..Default::default()
+/// }];
+///
+/// let context = ContextBuilder::new(1000)
+///     .order(ContextOrder::Position)
+///     .build(hits);
+/// assert_eq!(context.text, "[1] Chroma is a vector database.");
+/// ```
+pub struct ContextBuilder {
+    token_budget: u64,
+    order: ContextOrder,
+    separator: String,
+    tokenizer: Box<dyn Fn(&str) -> u64 + Send + Sync>,
+}
+
+impl ContextBuilder {
+    /// Creates a builder for contexts of at most `token_budget` tokens.
+    pub fn new(token_budget: u64) -> Self {
+        ContextBuilder {
+            token_budget,
+            order: ContextOrder::default(),
+            separator: String::from("\n\n"),
+            tokenizer: Box::new(estimate_tokens),
+        }
+    }
+
+    pub fn order(mut self, order: ContextOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Text put between two documents.
+    pub fn separator(mut self, separator: &str) -> Self {
+        self.separator = separator.to_string();
+        self
+    }
+
+    /// Counts the tokens of a text, `estimate_tokens` by default.
+    pub fn tokenizer<F>(mut self, tokenizer: F) -> Self
+    where
+        F: Fn(&str) -> u64 + Send + Sync + 'static,
+    {
+        self.tokenizer = Box::new(tokenizer);
+        self
+    }
+
+    pub fn build(&self, hits: Vec<QueryHit>) -> Context {
+        let mut seen = HashSet::new();
+        let mut hits: Vec<QueryHit> = hits
+            .into_iter()
+            .filter(|hit| hit.document.is_some())
+            .filter(|hit| seen.insert(hit.document.clone()))
+            .collect();
+
+        match self.order {
+            ContextOrder::Score => hits.sort_by(|a, b| {
+                let score = |h: &QueryHit| h.score().unwrap_or(f32::MIN);
+                score(b).total_cmp(&score(a))
+            }),
+            ContextOrder::Position => {
+                hits.sort_by(|a, b| (source(a), chunk(a)).cmp(&(source(b), chunk(b))))
+            }
+        }
+
+        let separator_tokens = (self.tokenizer)(&self.separator);
+        let mut context = Context::default();
+        for hit in hits {
+            let number = context.citations.len() + 1;
+            let paragraph = format!("[{}] {}", number, hit.document.as_deref().unwrap_or(""));
+            let mut tokens = (self.tokenizer)(&paragraph);
+            if !context.text.is_empty() {
+                tokens += separator_tokens;
+            }
+            if context.tokens + tokens > self.token_budget {
+                continue;
+            }
+
+            if !context.text.is_empty() {
+                context.text.push_str(&self.separator);
+            }
+            context.text.push_str(&paragraph);
+            context.tokens += tokens;
+            context.citations.push(Citation {
+                number,
+                source: source(&hit).map(str::to_string),
+                reference: hit.source_ref(),
+                score: hit.score(),
+                id: hit.id,
+            });
+        }
+        context
+    }
+}
+
+fn source(hit: &QueryHit) -> Option<&str> {
+    hit.metadata.as_ref()?.get(SOURCE_KEY)?.as_str()
+}
+
+fn chunk(hit: &QueryHit) -> Option<u64> {
+    hit.metadata.as_ref()?.get("chunk").and_then(Value::as_u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn hit(id: &str, document: &str, distance: f32, chunk: u64) -> QueryHit {
+        QueryHit {
+            id: id.to_string(),
+            distance: Some(distance),
+            document: Some(document.to_string()),
+            metadata: json!({ "source": "a.md", "chunk": chunk })
+                .as_object()
+                .cloned(),
+            embedding: None,
+        }
+    }
+
+    #[test]
+    fn build_context() {
+        let hits = vec![
+            hit("2", "second", 0.2, 1),
+            hit("1", "first", 0.1, 0),
+            hit("dup", "first", 0.3, 5),
+            hit("3", "a much longer third document", 0.4, 2),
+        ];
+
+        let context = ContextBuilder::new(9)
+            .order(ContextOrder::Position)
+            .tokenizer(|text| text.split_whitespace().count() as u64)
+            .build(hits);
+
+        assert_eq!(context.text, "[1] first\n\n[2] second");
+        assert_eq!(context.tokens, 4);
+        assert_eq!(context.citations.len(), 2);
+        assert_eq!(context.citations[1].id, "2");
+        assert_eq!(context.citations[1].source.as_deref(), Some("a.md"));
+    }
+}
@@ -0,0 +1,111 @@
+//! Query results borrowing their ids and documents from the response body
+//! instead of copying each of them into a `String`, for large results.
+//!
+//! ```no_run
+//! # async fn example(
+//! #     client: &chromadb_rs::client::ChromaClient,
+//! #     collection: &chromadb_rs::collection::Collection,
+//! #     params: chromadb_rs::collection::QueryParams,
+//! # ) -> Result<(), chromadb_rs::error::ChromaClientError> {
+//! let response = collection.query_raw(client, params).await?;
+//! let result = response.parse()?;
+//! for (id, document) in result.ids[0].iter().zip(&result.documents.unwrap()[0]) {
+//!     println!("{}: {:?}", id.as_str(), document.as_ref().map(|d| d.as_str()));
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::client::ChromaClient;
+use crate::collection::{Collection, Metadata, QueryParams};
+use crate::error::ChromaClientError;
+use crate::generated::QueryEmbedding;
+use crate::policy::Operation;
+use bytes::Bytes;
+use serde::Deserialize;
+use std::borrow::Cow;
+
+/// A string of a response, borrowed unless it contains JSON escapes.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Text<'a>(#[serde(borrow)] pub Cow<'a, str>);
+
+impl Text<'_> {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// `QueryResult` borrowing its strings from a `RawQueryResult`.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct QueryResultRef<'a> {
+    #[serde(borrow)]
+    pub ids: Vec<Vec<Text<'a>>>,
+    pub distances: Option<Vec<Vec<f32>>>,
+    pub embeddings: Option<Vec<Vec<Vec<f32>>>>,
+    #[serde(borrow)]
+    pub documents: Option<Vec<Vec<Option<Text<'a>>>>>,
+    pub metadatas: Option<Vec<Vec<Option<Metadata>>>>,
+}
+
+/// The body of a query response as received, from `Collection::query_raw`.
+#[derive(Debug, Clone)]
+pub struct RawQueryResult {
+    body: Bytes,
+}
+
+impl RawQueryResult {
+    pub fn new(body: Bytes) -> Self {
+        RawQueryResult { body }
+    }
+
+    /// Parse the body, borrowing the ids and documents from it.
+    pub fn parse(&self) -> Result<QueryResultRef<'_>, ChromaClientError> {
+        serde_json::from_slice(&self.body).map_err(ChromaClientError::ResponseParseError)
+    }
+
+    pub fn body(&self) -> &Bytes {
+        &self.body
+    }
+}
+
+impl Collection {
+    /// `query` keeping the response body, to parse it into a
+    /// `QueryResultRef` without copying its strings.
+    pub async fn query_raw(
+        &self,
+        client: &ChromaClient,
+        mut params: QueryParams,
+    ) -> Result<RawQueryResult, ChromaClientError> {
+        client.check_operation(Operation::Read)?;
+        self.prepare_embeddings(client, &mut params.query_embeddings)
+            .await?;
+        let body = client
+            .post_bytes(
+                &format!("api/v1/collections/{}/query", self.id),
+                &QueryEmbedding::from(params),
+            )
+            .await?;
+        Ok(RawQueryResult::new(body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn borrows_unescaped_strings() {
+        let response = RawQueryResult::new(Bytes::from_static(
+            br#"{"ids": [["a", "b\"c"]], "distances": [[0.1, 0.2]], "documents": [["hello", null]]}"#,
+        ));
+
+        let result = response.parse().unwrap();
+
+        assert!(matches!(result.ids[0][0].0, Cow::Borrowed("a")));
+        assert!(matches!(&result.ids[0][1].0, Cow::Owned(id) if id == "b\"c"));
+        let documents = result.documents.unwrap();
+        assert_eq!(documents[0][0].as_ref().unwrap().as_str(), "hello");
+        assert_eq!(documents[0][1], None);
+        assert_eq!(result.distances.unwrap()[0], [0.1, 0.2]);
+    }
+}
@@ -0,0 +1,192 @@
+use crate::client::ChromaClient;
+use crate::collection::{Collection, GetParams, Include, Metadata};
+use crate::error::ChromaClientError;
+use crate::export::ExportRecord;
+use crate::filter::Where;
+use std::marker::PhantomData;
+
+/// A record whose fields depend on what was included in the request: each
+/// of `E`, `D` and `M` is `()` when not included, and `Vec<f32>`, `String`
+/// and `Metadata` respectively when included.
+///
+/// ```no_run
+/// # async fn example(
+/// #     client: &chromadb_rs::client::ChromaClient,
+/// #     collection: &chromadb_rs::collection::Collection,
+/// # ) -> Result<(), chromadb_rs::error::ChromaClientError> {
+/// let records = collection
+///     .records()
+///     .with_documents()
+///     .limit(10)
+///     .fetch(client)
+///     .await?;
+///
+/// for record in &records {
+///     println!("{}: {}", record.id(), record.document());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record<E = (), D = (), M = ()> {
+    id: String,
+    embedding: E,
+    document: D,
+    metadata: M,
+}
+
+impl<E, D, M> Record<E, D, M> {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl<D, M> Record<Vec<f32>, D, M> {
+    pub fn embedding(&self) -> &[f32] {
+        &self.embedding
+    }
+}
+
+impl<E, M> Record<E, String, M> {
+    /// The document, empty for records stored without one.
+    pub fn document(&self) -> &str {
+        &self.document
+    }
+}
+
+impl<E, D> Record<E, D, Metadata> {
+    /// The metadata, empty for records stored without any.
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+}
+
+/// A field of `Record`, built from the value returned by the server.
+pub trait RecordField<T>: Sized {
+    fn from_response(value: Option<T>) -> Self;
+}
+
+impl<T> RecordField<T> for () {
+    fn from_response(_: Option<T>) -> Self {}
+}
+
+impl RecordField<Vec<f32>> for Vec<f32> {
+    fn from_response(value: Option<Vec<f32>>) -> Self {
+        value.unwrap_or_default()
+    }
+}
+
+impl RecordField<String> for String {
+    fn from_response(value: Option<String>) -> Self {
+        value.unwrap_or_default()
+    }
+}
+
+impl RecordField<Metadata> for Metadata {
+    fn from_response(value: Option<Metadata>) -> Self {
+        value.unwrap_or_default()
+    }
+}
+
+/// Get request returning `Record`s, created by `Collection::records`.
+pub struct RecordRequest<'a, E = (), D = (), M = ()> {
+    collection: &'a Collection,
+    params: GetParams,
+    fields: PhantomData<(E, D, M)>,
+}
+
+impl Collection {
+    /// Start a get request returning typed records. Only ids are included
+    /// until `with_embeddings`, `with_documents` or `with_metadatas` is called,
+    /// and the accessors of fields that were not included do not compile:
+    ///
+    /// ```compile_fail
+    /// # async fn example(
+    /// #     client: &chromadb_rs::client::ChromaClient,
+    /// #     collection: &chromadb_rs::collection::Collection,
+    /// # ) -> Result<(), chromadb_rs::error::ChromaClientError> {
+    /// let records = collection.records().with_metadatas().fetch(client).await?;
+    /// println!("{}", records[0].document());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn records(&self) -> RecordRequest<'_> {
+        RecordRequest {
+            collection: self,
+            params: GetParams {
+                include: Some(Vec::new()),
+                ..Default::default()
+            },
+            fields: PhantomData,
+        }
+    }
+}
+
+impl<'a, E, D, M> RecordRequest<'a, E, D, M> {
+    fn include<E2, D2, M2>(mut self, include: Include) -> RecordRequest<'a, E2, D2, M2> {
+        let included = self.params.include.get_or_insert_with(Vec::new);
+        if !included.contains(&include) {
+            included.push(include);
+        }
+        RecordRequest {
+            collection: self.collection,
+            params: self.params,
+            fields: PhantomData,
+        }
+    }
+
+    pub fn with_embeddings(self) -> RecordRequest<'a, Vec<f32>, D, M> {
+        self.include(Include::Embeddings)
+    }
+
+    pub fn with_documents(self) -> RecordRequest<'a, E, String, M> {
+        self.include(Include::Documents)
+    }
+
+    pub fn with_metadatas(self) -> RecordRequest<'a, E, D, Metadata> {
+        self.include(Include::Metadatas)
+    }
+
+    pub fn ids<I: IntoIterator<Item = S>, S: Into<String>>(mut self, ids: I) -> Self {
+        self.params.ids = Some(ids.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn filter(mut self, filter: Where) -> Self {
+        self.params.where_metadata = Some(filter.into());
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.params.limit = Some(limit);
+        self
+    }
+
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.params.offset = Some(offset);
+        self
+    }
+}
+
+impl<E, D, M> RecordRequest<'_, E, D, M>
+where
+    E: RecordField<Vec<f32>>,
+    D: RecordField<String>,
+    M: RecordField<Metadata>,
+{
+    pub async fn fetch(
+        self,
+        client: &ChromaClient,
+    ) -> Result<Vec<Record<E, D, M>>, ChromaClientError> {
+        let result = self.collection.get(client, self.params).await?;
+        Ok(ExportRecord::from_result(result)
+            .into_iter()
+            .map(|r| Record {
+                id: r.id,
+                embedding: E::from_response(r.embedding),
+                document: D::from_response(r.document),
+                metadata: M::from_response(r.metadata),
+            })
+            .collect())
+    }
+}
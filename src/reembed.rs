@@ -0,0 +1,110 @@
+use crate::client::ChromaClient;
+use crate::collection::{Collection, GetParams, Include};
+use crate::embeddings::EmbeddingFunction;
+use crate::error::ChromaClientError;
+use crate::export::ExportRecord;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Progress of a re-embedding migration, reported after each batch.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReembedProgress {
+    /// Records read from the source collection so far.
+    pub processed: usize,
+    /// Records in the source collection when the migration started.
+    pub total: usize,
+    pub reembedded: usize,
+    /// Records already in the target, written by a previous interrupted run.
+    pub already_migrated: usize,
+    /// Records without a document, which cannot be embedded again.
+    pub missing_document: usize,
+}
+
+impl Collection {
+    /// Copy every record into `target`, computing new embeddings of the
+    /// documents with `embedding_function`. Records already in the target are
+    /// skipped, so running it again resumes an interrupted migration.
+    pub async fn reembed_into(
+        &self,
+        client: &ChromaClient,
+        target: &Collection,
+        embedding_function: &dyn EmbeddingFunction,
+        batch_size: usize,
+    ) -> Result<ReembedProgress, ChromaClientError> {
+        self.reembed_into_with_progress(client, target, embedding_function, batch_size, |_| {})
+            .await
+    }
+
+    /// Same as `reembed_into`, calling `on_progress` after each batch.
+    pub async fn reembed_into_with_progress<F>(
+        &self,
+        client: &ChromaClient,
+        target: &Collection,
+        embedding_function: &dyn EmbeddingFunction,
+        batch_size: usize,
+        mut on_progress: F,
+    ) -> Result<ReembedProgress, ChromaClientError>
+    where
+        F: FnMut(&ReembedProgress),
+    {
+        let mut progress = ReembedProgress {
+            total: self.count(client).await?,
+            ..Default::default()
+        };
+
+        let params = GetParams {
+            include: Some(vec![Include::Documents, Include::Metadatas]),
+            ..Default::default()
+        };
+        let mut pages = self.pages(client, params, batch_size);
+        while let Some(page) = pages.next().await? {
+            let records = ExportRecord::from_result(page);
+            let fetched = records.len();
+
+            let params = GetParams {
+                ids: Some(records.iter().map(|r| r.id.clone()).collect()),
+                include: Some(vec![]),
+                ..Default::default()
+            };
+            let migrated: HashSet<String> =
+                target.get(client, params).await?.ids.into_iter().collect();
+
+            let mut pending = Vec::with_capacity(fetched);
+            for record in records {
+                if migrated.contains(&record.id) {
+                    progress.already_migrated += 1;
+                } else if record.document.is_none() {
+                    progress.missing_document += 1;
+                } else {
+                    pending.push(record);
+                }
+            }
+
+            if !pending.is_empty() {
+                let documents: Vec<&str> = pending
+                    .iter()
+                    .filter_map(|r| r.document.as_deref())
+                    .collect();
+                let embeddings = embedding_function.embed(&documents).await?;
+                if embeddings.len() != pending.len() {
+                    return Err(ChromaClientError::EmbeddingError(format!(
+                        "expected {} embeddings, got {}",
+                        pending.len(),
+                        embeddings.len()
+                    )));
+                }
+
+                for (record, embedding) in pending.iter_mut().zip(embeddings) {
+                    record.embedding = Some(embedding);
+                }
+                progress.reembedded += pending.len();
+                target.upsert(client, pending.into_iter().collect()).await?;
+            }
+
+            progress.processed += fetched;
+            on_progress(&progress);
+        }
+
+        Ok(progress)
+    }
+}
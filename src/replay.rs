@@ -0,0 +1,199 @@
+use crate::error::ChromaClientError;
+use reqwest::{Client, Request, Response};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Whether a cassette sends requests to the server or answers them itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    /// Send requests to the server and save every response to the file.
+    Record,
+    /// Answer requests with the responses saved in the file, without any
+    /// network access. A request that was not recorded fails.
+    Replay,
+}
+
+/// One request and the response the server gave.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub method: String,
+    /// Path and query string, without the host.
+    pub path: String,
+    pub body: Option<String>,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub response: String,
+}
+
+/// Responses recorded in a JSON file, attached to a client with
+/// `ChromaClient::with_cassette`.
+///
+/// ```no_run
+/// # async fn example() -> Result<(), chromadb_rs::error::ChromaClientError> {
+/// use chromadb_rs::client::{ChromaClient, ChromaClientParams};
+/// use chromadb_rs::replay::{Cassette, CassetteMode};
+///
+/// // Run once against a server with `CassetteMode::Record`, then commit the file.
+/// let cassette = Cassette::open("tests/cassettes/search.json", CassetteMode::Replay)?;
+/// let client = ChromaClient::new(ChromaClientParams::default()).with_cassette(cassette);
+/// let collections = client.list_collections().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Cassette {
+    path: PathBuf,
+    mode: CassetteMode,
+    interactions: Mutex<Vec<(Interaction, bool)>>,
+}
+
+impl Cassette {
+    /// Open a cassette file. Recording starts from an empty cassette, replaying
+    /// reads the interactions of the file.
+    pub fn open(path: impl AsRef<Path>, mode: CassetteMode) -> Result<Self, ChromaClientError> {
+        let path = path.as_ref().to_path_buf();
+        let interactions = match mode {
+            CassetteMode::Record => Vec::new(),
+            CassetteMode::Replay => {
+                let content = std::fs::read_to_string(&path).map_err(ChromaClientError::IoError)?;
+                serde_json::from_str(&content).map_err(ChromaClientError::ResponseParseError)?
+            }
+        };
+
+        Ok(Cassette {
+            path,
+            mode,
+            interactions: Mutex::new(interactions.into_iter().map(|i| (i, false)).collect()),
+        })
+    }
+
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    pub(crate) async fn send(
+        &self,
+        client: &Client,
+        request: Request,
+    ) -> Result<Response, ChromaClientError> {
+        let method = request.method().to_string();
+        let path = match request.url().query() {
+            Some(query) => format!("{}?{}", request.url().path(), query),
+            None => request.url().path().to_string(),
+        };
+        let body = request
+            .body()
+            .and_then(|b| b.as_bytes())
+            .map(|b| String::from_utf8_lossy(b).into_owned());
+
+        let interaction = match self.mode {
+            CassetteMode::Replay => self.find(&method, &path, &body)?,
+            CassetteMode::Record => {
+                let response = client
+                    .execute(request)
+                    .await
+                    .map_err(ChromaClientError::RequestError)?;
+                let status = response.status().as_u16();
+                let headers = response
+                    .headers()
+                    .iter()
+                    .filter_map(|(k, v)| Some((k.to_string(), v.to_str().ok()?.to_string())))
+                    .collect();
+                let response = response
+                    .text()
+                    .await
+                    .map_err(ChromaClientError::ResponseError)?;
+
+                let interaction = Interaction {
+                    method,
+                    path,
+                    body,
+                    status,
+                    headers,
+                    response,
+                };
+                self.save(interaction.clone())?;
+                interaction
+            }
+        };
+
+        to_response(interaction)
+    }
+
+    /// The first interaction matching the request not replayed yet, so the
+    /// same request recorded twice is answered in order.
+    fn find(
+        &self,
+        method: &str,
+        path: &str,
+        body: &Option<String>,
+    ) -> Result<Interaction, ChromaClientError> {
+        let mut interactions = self.interactions.lock().unwrap();
+        let (interaction, used) = interactions
+            .iter_mut()
+            .find(|(i, used)| !used && i.method == method && i.path == path && &i.body == body)
+            .ok_or_else(|| {
+                let message = format!("no recorded response for {} {}", method, path);
+                ChromaClientError::IoError(io::Error::new(io::ErrorKind::NotFound, message))
+            })?;
+        *used = true;
+        Ok(interaction.clone())
+    }
+
+    fn save(&self, interaction: Interaction) -> Result<(), ChromaClientError> {
+        let mut interactions = self.interactions.lock().unwrap();
+        interactions.push((interaction, true));
+        let recorded: Vec<&Interaction> = interactions.iter().map(|(i, _)| i).collect();
+        let content = serde_json::to_string_pretty(&recorded)
+            .map_err(ChromaClientError::ResponseParseError)?;
+        std::fs::write(&self.path, content).map_err(ChromaClientError::IoError)
+    }
+}
+
+fn to_response(interaction: Interaction) -> Result<Response, ChromaClientError> {
+    let invalid =
+        |e: http::Error| ChromaClientError::IoError(io::Error::new(io::ErrorKind::InvalidData, e));
+    let mut response = http::Response::builder().status(interaction.status);
+    for (name, value) in &interaction.headers {
+        response = response.header(name, value);
+    }
+    Ok(response.body(interaction.response).map_err(invalid)?.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn replay_recorded_responses() {
+        let path =
+            std::env::temp_dir().join(format!("chroma-rs-replay-{}.json", std::process::id()));
+        let interaction = Interaction {
+            method: "GET".into(),
+            path: "/api/v1/heartbeat".into(),
+            body: None,
+            status: 200,
+            headers: vec![("content-type".into(), "application/json".into())],
+            response: "{\"nanosecond heartbeat\": 7}".into(),
+        };
+        std::fs::write(&path, serde_json::to_string(&[&interaction]).unwrap()).unwrap();
+
+        let cassette = Cassette::open(&path, CassetteMode::Replay).unwrap();
+        let client = Client::new();
+        let request = || {
+            client
+                .get("http://chroma:8000/api/v1/heartbeat")
+                .build()
+                .unwrap()
+        };
+
+        let response = cassette.send(&client, request()).await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().await.unwrap(), interaction.response);
+        assert!(cassette.send(&client, request()).await.is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
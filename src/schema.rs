@@ -0,0 +1,110 @@
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+/// Metadata index configuration of a collection, sent on creation so the
+/// server only builds the indexes the filters need.
+///
+/// ```
+/// use chromadb_rs::schema::{Schema, ValueType};
+///
+/// let schema = Schema::new()
+///     .index("year", ValueType::Int)
+///     .index("author", ValueType::String)
+///     .disable_index("body", ValueType::String);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Schema {
+    keys: BTreeMap<String, Vec<(ValueType, bool)>>,
+}
+
+/// Type of a metadata value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    String,
+    Int,
+    Float,
+    Bool,
+}
+
+impl ValueType {
+    fn name(self) -> &'static str {
+        match self {
+            ValueType::String => "string",
+            ValueType::Int => "int",
+            ValueType::Float => "float",
+            ValueType::Bool => "bool",
+        }
+    }
+}
+
+impl Schema {
+    pub fn new() -> Self {
+        Schema::default()
+    }
+
+    /// Index the values of `key` having the given type.
+    pub fn index(self, key: &str, value_type: ValueType) -> Self {
+        self.set(key, value_type, true)
+    }
+
+    /// Do not index the values of `key` having the given type, for keys never
+    /// used in filters.
+    pub fn disable_index(self, key: &str, value_type: ValueType) -> Self {
+        self.set(key, value_type, false)
+    }
+
+    fn set(mut self, key: &str, value_type: ValueType, enabled: bool) -> Self {
+        let types = self.keys.entry(key.to_string()).or_default();
+        types.retain(|(t, _)| *t != value_type);
+        types.push((value_type, enabled));
+        self
+    }
+}
+
+impl Serialize for Schema {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let keys: BTreeMap<&str, BTreeMap<&str, serde_json::Value>> = self
+            .keys
+            .iter()
+            .map(|(key, types)| {
+                let types = types
+                    .iter()
+                    .map(|(value_type, enabled)| {
+                        let index = format!("{}_inverted_index", value_type.name());
+                        let config = json!({ index: { "enabled": enabled, "config": {} } });
+                        (value_type.name(), config)
+                    })
+                    .collect();
+                (key.as_str(), types)
+            })
+            .collect();
+
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry("keys", &keys)?;
+        map.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_schema() {
+        let schema = Schema::new()
+            .disable_index("year", ValueType::Int)
+            .index("year", ValueType::Int);
+
+        assert_eq!(
+            serde_json::to_value(&schema).unwrap(),
+            json!({
+                "keys": {
+                    "year": {
+                        "int": { "int_inverted_index": { "enabled": true, "config": {} } }
+                    }
+                }
+            })
+        );
+    }
+}
@@ -0,0 +1,147 @@
+//! Distance computations over embeddings returned by the server, eight lanes
+//! at a time.
+
+use crate::error::ChromaClientError;
+use wide::f32x8;
+
+const LANES: usize = 8;
+
+/// Number of dimensions shared by the vectors, 0 without vectors. Fails
+/// with `EmbeddingError` on the first vector of another length, for which
+/// the functions of this module would panic.
+pub fn common_dimension(vectors: &[Vec<f32>]) -> Result<usize, ChromaClientError> {
+    let dimension = vectors.first().map_or(0, Vec::len);
+    match vectors.iter().position(|v| v.len() != dimension) {
+        Some(i) => Err(ChromaClientError::EmbeddingError(format!(
+            "vector {} has {} dimensions, the first one {}",
+            i,
+            vectors[i].len(),
+            dimension
+        ))),
+        None => Ok(dimension),
+    }
+}
+
+/// Dot product of two vectors of the same length. Panics otherwise.
+pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+    assert_eq!(a.len(), b.len(), "vectors must have the same length");
+
+    let (a_chunks, a_rest) = a.split_at(a.len() - a.len() % LANES);
+    let (b_chunks, b_rest) = b.split_at(a_chunks.len());
+
+    let mut sum = f32x8::ZERO;
+    for (x, y) in a_chunks
+        .chunks_exact(LANES)
+        .zip(b_chunks.chunks_exact(LANES))
+    {
+        sum = lanes(x).mul_add(lanes(y), sum);
+    }
+
+    sum.reduce_add() + a_rest.iter().zip(b_rest).map(|(x, y)| x * y).sum::<f32>()
+}
+
+/// Squared euclidean distance, what Chroma returns for the `l2` space.
+/// Panics when the lengths differ.
+pub fn squared_l2(a: &[f32], b: &[f32]) -> f32 {
+    assert_eq!(a.len(), b.len(), "vectors must have the same length");
+
+    let (a_chunks, a_rest) = a.split_at(a.len() - a.len() % LANES);
+    let (b_chunks, b_rest) = b.split_at(a_chunks.len());
+
+    let mut sum = f32x8::ZERO;
+    for (x, y) in a_chunks
+        .chunks_exact(LANES)
+        .zip(b_chunks.chunks_exact(LANES))
+    {
+        let d = lanes(x) - lanes(y);
+        sum = d.mul_add(d, sum);
+    }
+
+    sum.reduce_add()
+        + a_rest
+            .iter()
+            .zip(b_rest)
+            .map(|(x, y)| (x - y) * (x - y))
+            .sum::<f32>()
+}
+
+/// Cosine similarity, 0 when one of the vectors is all zeros.
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let norms = (dot(a, a) * dot(b, b)).sqrt();
+    if norms == 0.0 {
+        0.0
+    } else {
+        dot(a, b) / norms
+    }
+}
+
+/// Cosine distance, what Chroma returns for the `cosine` space.
+pub fn cosine_distance(a: &[f32], b: &[f32]) -> f32 {
+    1.0 - cosine_similarity(a, b)
+}
+
+/// Cosine similarity between the query and each embedding.
+pub fn cosine_similarities(query: &[f32], embeddings: &[Vec<f32>]) -> Vec<f32> {
+    let query_norm = dot(query, query).sqrt();
+    embeddings
+        .iter()
+        .map(|e| {
+            let norms = query_norm * dot(e, e).sqrt();
+            if norms == 0.0 {
+                0.0
+            } else {
+                dot(query, e) / norms
+            }
+        })
+        .collect()
+}
+
+/// Scale the vector to a length of 1, leaving all-zero vectors as they are.
+pub fn l2_normalize(v: &mut [f32]) {
+    let norm = dot(v, v).sqrt();
+    if norm > 0.0 {
+        v.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+/// Squared euclidean distance between the query and each embedding.
+pub fn squared_l2_distances(query: &[f32], embeddings: &[Vec<f32>]) -> Vec<f32> {
+    embeddings.iter().map(|e| squared_l2(query, e)).collect()
+}
+
+fn lanes(chunk: &[f32]) -> f32x8 {
+    let array: [f32; LANES] = chunk.try_into().unwrap();
+    f32x8::from(array)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scalar_dot(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(x, y)| x * y).sum()
+    }
+
+    #[test]
+    fn matches_scalar_computation() {
+        // 19 values, so both the simd lanes and the remainder are used
+        let a: Vec<f32> = (0..19).map(|i| i as f32 * 0.5 - 3.0).collect();
+        let b: Vec<f32> = (0..19).map(|i| (i % 5) as f32 - 1.0).collect();
+        let diff: Vec<f32> = a.iter().zip(&b).map(|(x, y)| x - y).collect();
+
+        assert!((dot(&a, &b) - scalar_dot(&a, &b)).abs() < 1e-4);
+        assert!((squared_l2(&a, &b) - scalar_dot(&diff, &diff)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn cosine() {
+        let query = [1.0, 0.0, 0.0];
+        let embeddings = vec![vec![2.0, 0.0, 0.0], vec![0.0, 1.0, 0.0], vec![0.0; 3]];
+
+        assert_eq!(
+            cosine_similarities(&query, &embeddings),
+            vec![1.0, 0.0, 0.0]
+        );
+        assert_eq!(cosine_distance(&query, &[-1.0, 0.0, 0.0]), 2.0);
+    }
+}
@@ -0,0 +1,102 @@
+//! Metadata keys locating the source of a record, set by the loaders and the
+//! text splitter, and read back from hits to cite the sources of an answer.
+
+use crate::collection::Metadata;
+use crate::hits::QueryHit;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::ops::Range;
+
+pub const SOURCE_KEY: &str = "source";
+pub const TITLE_KEY: &str = "title";
+pub const PAGE_KEY: &str = "page";
+/// First character of the source covered by the record.
+pub const SPAN_START_KEY: &str = "span_start";
+/// Character after the last one covered by the record.
+pub const SPAN_END_KEY: &str = "span_end";
+/// Headings of the Markdown section holding the record, as in
+/// `Install > Linux`.
+pub const SECTION_KEY: &str = "section";
+
+/// Where the text of a record comes from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceRef {
+    /// Path or URL of the source.
+    pub uri: String,
+    pub title: Option<String>,
+    /// Page of the source, starting at 1.
+    pub page: Option<u64>,
+    /// Characters of the source, or of its page, covered by the record.
+    pub span: Option<Range<usize>>,
+}
+
+impl SourceRef {
+    pub fn new(uri: impl Into<String>) -> Self {
+        SourceRef {
+            uri: uri.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Store the reference in the metadata of a record.
+    pub fn write_to(&self, metadata: &mut Metadata) {
+        metadata.insert(SOURCE_KEY.into(), Value::from(self.uri.clone()));
+        if let Some(title) = &self.title {
+            metadata.insert(TITLE_KEY.into(), Value::from(title.clone()));
+        }
+        if let Some(page) = self.page {
+            metadata.insert(PAGE_KEY.into(), Value::from(page));
+        }
+        if let Some(span) = &self.span {
+            metadata.insert(SPAN_START_KEY.into(), Value::from(span.start));
+            metadata.insert(SPAN_END_KEY.into(), Value::from(span.end));
+        }
+    }
+
+    /// Read the reference of a record, `None` without a `source` key.
+    pub fn from_metadata(metadata: &Metadata) -> Option<Self> {
+        let position = |key: &str| metadata.get(key).and_then(Value::as_u64);
+
+        Some(SourceRef {
+            uri: metadata.get(SOURCE_KEY)?.as_str()?.to_string(),
+            title: metadata
+                .get(TITLE_KEY)
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            page: position(PAGE_KEY),
+            span: match (position(SPAN_START_KEY), position(SPAN_END_KEY)) {
+                (Some(start), Some(end)) => Some(start as usize..end as usize),
+                _ => None,
+            },
+        })
+    }
+}
+
+impl QueryHit {
+    /// The source of the hit, when its metadata was included.
+    pub fn source_ref(&self) -> Option<SourceRef> {
+        SourceRef::from_metadata(self.metadata.as_ref()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metadata_round_trip() {
+        let source = SourceRef {
+            uri: "docs/guide.pdf".into(),
+            title: Some("Guide".into()),
+            page: Some(3),
+            span: Some(120..480),
+        };
+        let mut metadata = Metadata::new();
+
+        source.write_to(&mut metadata);
+
+        assert_eq!(metadata["span_start"], 120);
+        assert_eq!(SourceRef::from_metadata(&metadata), Some(source));
+        assert_eq!(SourceRef::from_metadata(&Metadata::new()), None);
+    }
+}
@@ -0,0 +1,188 @@
+use crate::client::ChromaClient;
+use crate::collection::{Collection, GetParams, Include};
+use crate::error::ChromaClientError;
+use crate::export::ExportRecord;
+use crate::simd::dot;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+/// Number of evenly spaced pages the sample is read from.
+const SAMPLE_WINDOWS: usize = 10;
+
+/// Statistics computed from a sample of the records of a collection.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CollectionStats {
+    pub count: usize,
+    pub sampled: usize,
+    /// Length of the documents, in characters.
+    pub document_length: Distribution,
+    pub metadata_keys: BTreeMap<String, KeyStats>,
+    /// Euclidean norm of the embeddings.
+    pub embedding_norm: Distribution,
+}
+
+/// How a metadata key is used in the sample.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyStats {
+    /// Records having the key.
+    pub present: usize,
+    /// Distinct values of the key.
+    pub distinct: usize,
+}
+
+/// Summary of a set of values.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Distribution {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub p50: f64,
+    pub p90: f64,
+}
+
+impl Distribution {
+    pub fn from_values(mut values: Vec<f64>) -> Self {
+        if values.is_empty() {
+            return Distribution::default();
+        }
+
+        values.sort_by(|a, b| a.total_cmp(b));
+        let at =
+            |p: f64| values[((values.len() as f64 * p).ceil() as usize).clamp(1, values.len()) - 1];
+
+        Distribution {
+            min: values[0],
+            max: values[values.len() - 1],
+            mean: values.iter().sum::<f64>() / values.len() as f64,
+            p50: at(0.5),
+            p90: at(0.9),
+        }
+    }
+}
+
+impl Collection {
+    /// Compute statistics from about `sample_size` records, read from pages
+    /// spread over the whole collection.
+    pub async fn stats(
+        &self,
+        client: &ChromaClient,
+        sample_size: usize,
+    ) -> Result<CollectionStats, ChromaClientError> {
+        let count = self.count(client).await?;
+        let sample_size = sample_size.min(count);
+
+        let mut records = Vec::with_capacity(sample_size);
+        for (offset, limit) in sample_pages(count, sample_size) {
+            let params = GetParams {
+                limit: Some(limit),
+                offset: Some(offset),
+                include: Some(vec![
+                    Include::Documents,
+                    Include::Metadatas,
+                    Include::Embeddings,
+                ]),
+                ..Default::default()
+            };
+            records.extend(ExportRecord::from_result(self.get(client, params).await?));
+        }
+
+        let mut stats = compute(&records);
+        stats.count = count;
+        Ok(stats)
+    }
+}
+
+/// Offset and limit of each page of the sample.
+fn sample_pages(count: usize, sample_size: usize) -> Vec<(usize, usize)> {
+    if sample_size == 0 {
+        return Vec::new();
+    }
+
+    let windows = SAMPLE_WINDOWS.min(sample_size);
+    (0..windows)
+        .map(|w| {
+            let limit = sample_size / windows + usize::from(w < sample_size % windows);
+            (w * count / windows, limit)
+        })
+        .collect()
+}
+
+fn compute(records: &[ExportRecord]) -> CollectionStats {
+    let mut keys: BTreeMap<String, (usize, HashSet<String>)> = BTreeMap::new();
+    for metadata in records.iter().filter_map(|r| r.metadata.as_ref()) {
+        for (key, value) in metadata {
+            let (present, values) = keys.entry(key.clone()).or_default();
+            *present += 1;
+            values.insert(value.to_string());
+        }
+    }
+
+    CollectionStats {
+        count: records.len(),
+        sampled: records.len(),
+        document_length: Distribution::from_values(
+            records
+                .iter()
+                .filter_map(|r| r.document.as_ref())
+                .map(|d| d.chars().count() as f64)
+                .collect(),
+        ),
+        metadata_keys: keys
+            .into_iter()
+            .map(|(key, (present, values))| {
+                let distinct = values.len();
+                (key, KeyStats { present, distinct })
+            })
+            .collect(),
+        embedding_norm: Distribution::from_values(
+            records
+                .iter()
+                .filter_map(|r| r.embedding.as_ref())
+                .map(|e| (dot(e, e) as f64).sqrt())
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn sample_pages_cover_the_collection() {
+        assert_eq!(sample_pages(1000, 25).len(), 10);
+        assert_eq!(
+            sample_pages(1000, 25).iter().map(|p| p.1).sum::<usize>(),
+            25
+        );
+        assert_eq!(sample_pages(1000, 25)[9].0, 900);
+        assert_eq!(sample_pages(3, 3), vec![(0, 1), (1, 1), (2, 1)]);
+    }
+
+    #[test]
+    fn compute_stats() {
+        let records: Vec<ExportRecord> = [("a", "red"), ("bb", "red"), ("cccc", "blue")]
+            .iter()
+            .map(|(document, color)| ExportRecord {
+                id: document.to_string(),
+                document: Some(document.to_string()),
+                metadata: json!({ "color": color }).as_object().cloned(),
+                embedding: Some(vec![3.0, 4.0]),
+            })
+            .collect();
+
+        let stats = compute(&records);
+
+        assert_eq!(stats.document_length.max, 4.0);
+        assert_eq!(stats.document_length.p50, 2.0);
+        assert_eq!(stats.embedding_norm.mean, 5.0);
+        assert_eq!(
+            stats.metadata_keys["color"],
+            KeyStats {
+                present: 3,
+                distinct: 2
+            }
+        );
+    }
+}
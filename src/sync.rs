@@ -0,0 +1,189 @@
+use crate::client::ChromaClient;
+use crate::collection::{Collection, DeleteParams, GetParams, Include};
+use crate::document::{content_hash, to_entries, Document};
+use crate::embeddings::EmbeddingFunction;
+use crate::error::ChromaClientError;
+use crate::export::ExportRecord;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Options of `Collection::sync`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncOptions {
+    /// Delete the records of the collection missing from the local
+    /// documents. Off by default, as it deletes every record written by
+    /// other means.
+    pub delete_missing: bool,
+    /// Records fetched per request when reading the collection.
+    pub batch_size: usize,
+    /// Compute the changes without applying them.
+    pub dry_run: bool,
+}
+
+impl Default for SyncOptions {
+    fn default() -> Self {
+        SyncOptions {
+            delete_missing: false,
+            batch_size: 500,
+            dry_run: false,
+        }
+    }
+}
+
+/// Ids of the records changed by `Collection::sync`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncReport {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub deleted: Vec<String>,
+    pub unchanged: usize,
+}
+
+impl SyncReport {
+    /// Whether the collection already matched the local documents.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.deleted.is_empty()
+    }
+}
+
+impl Collection {
+    /// Make the collection match the local documents, embedding and writing
+    /// only the documents that are new or whose text or metadata changed,
+    /// in batches of at most the maximum batch size of the server.
+    pub async fn sync(
+        &self,
+        client: &ChromaClient,
+        embedding_function: &dyn EmbeddingFunction,
+        documents: &[Document],
+        options: SyncOptions,
+    ) -> Result<SyncReport, ChromaClientError> {
+        let stored = self.stored_hashes(client, options.batch_size).await?;
+        let report = diff(documents, &stored, options.delete_missing)?;
+        if options.dry_run {
+            return Ok(report);
+        }
+
+        let by_id: HashMap<&str, &Document> =
+            documents.iter().map(|d| (d.id.as_str(), d)).collect();
+        let changed: Vec<Document> = report
+            .added
+            .iter()
+            .chain(&report.updated)
+            .map(|id| by_id[id.as_str()].clone())
+            .collect();
+        let size = client.max_batch_size().await?.unwrap_or(usize::MAX);
+        for batch in changed.chunks(size.max(1)) {
+            let texts: Vec<&str> = batch.iter().map(|d| d.text.as_str()).collect();
+            let embeddings = embedding_function.embed(&texts).await?;
+            self.upsert(client, to_entries(batch, embeddings)).await?;
+        }
+
+        if !report.deleted.is_empty() {
+            let params = DeleteParams {
+                ids: Some(report.deleted.clone()),
+                ..Default::default()
+            };
+            self.delete(client, params).await?;
+        }
+        Ok(report)
+    }
+
+    /// Hash of every record of the collection, by id.
+    async fn stored_hashes(
+        &self,
+        client: &ChromaClient,
+        batch_size: usize,
+    ) -> Result<HashMap<String, String>, ChromaClientError> {
+        let params = GetParams {
+            include: Some(vec![Include::Documents, Include::Metadatas]),
+            ..Default::default()
+        };
+        let mut pages = self.pages(client, params, batch_size);
+        let mut hashes = HashMap::new();
+        while let Some(page) = pages.next().await? {
+            for record in ExportRecord::from_result(page) {
+                let document = Document {
+                    id: record.id,
+                    text: record.document.unwrap_or_default(),
+                    metadata: record.metadata.unwrap_or_default(),
+                };
+                hashes.insert(document.id.clone(), document_hash(&document));
+            }
+        }
+        Ok(hashes)
+    }
+}
+
+/// Hash of the text and metadata of a document.
+fn document_hash(document: &Document) -> String {
+    let metadata = serde_json::to_string(&document.metadata).unwrap_or_default();
+    content_hash(format!("{}\0{}", document.text, metadata))
+}
+
+/// Changes turning the stored records into the local documents.
+fn diff(
+    documents: &[Document],
+    stored: &HashMap<String, String>,
+    delete_missing: bool,
+) -> Result<SyncReport, ChromaClientError> {
+    let mut report = SyncReport::default();
+    let mut local = HashSet::new();
+
+    for document in documents {
+        if !local.insert(document.id.as_str()) {
+            return Err(ChromaClientError::MetadataError(format!(
+                "duplicate document id {}",
+                document.id
+            )));
+        }
+        match stored.get(&document.id) {
+            None => report.added.push(document.id.clone()),
+            Some(hash) if *hash != document_hash(document) => {
+                report.updated.push(document.id.clone())
+            }
+            Some(_) => report.unchanged += 1,
+        }
+    }
+
+    if delete_missing {
+        report.deleted = stored
+            .keys()
+            .filter(|id| !local.contains(id.as_str()))
+            .cloned()
+            .collect();
+        report.deleted.sort();
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_against_stored_records() {
+        let unchanged = Document::new("a", "same");
+        let mut edited = Document::new("b", "text");
+        let stored = HashMap::from([
+            ("a".to_string(), document_hash(&unchanged)),
+            ("b".to_string(), document_hash(&edited)),
+            ("c".to_string(), document_hash(&Document::new("c", "gone"))),
+        ]);
+        edited.metadata.insert("page".into(), 2.into());
+        let documents = [unchanged, edited, Document::new("d", "new")];
+
+        let report = diff(&documents, &stored, true).unwrap();
+
+        assert_eq!(report.added, ["d"]);
+        assert_eq!(report.updated, ["b"]);
+        assert_eq!(report.deleted, ["c"]);
+        assert_eq!(report.unchanged, 1);
+        assert!(diff(&documents, &stored, false).unwrap().deleted.is_empty());
+        assert!(diff(
+            &[Document::new("a", "x"), Document::new("a", "y")],
+            &stored,
+            true
+        )
+        .is_err());
+    }
+}
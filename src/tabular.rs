@@ -0,0 +1,229 @@
+use crate::client::ChromaClient;
+use crate::collection::{Collection, Metadata};
+use crate::document::{content_hash, Document};
+use crate::embeddings::EmbeddingFunction;
+use crate::error::ChromaClientError;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::io::BufRead;
+
+/// Number of rows embedded and added per request.
+const TABULAR_BATCH_SIZE: usize = 100;
+
+/// How the columns of a table map to the fields of a record.
+#[derive(Debug, Clone, Default)]
+pub struct ColumnMapping {
+    /// Column holding the record id. Ids are derived from the document content
+    /// when not set, rows repeating the document of a previous row being
+    /// skipped.
+    pub id: Option<String>,
+    /// Column holding the document to embed.
+    pub document: String,
+    /// Columns stored as metadata. Every other column is used when empty.
+    pub metadata: Vec<String>,
+}
+
+impl ColumnMapping {
+    pub fn new(document: &str) -> Self {
+        ColumnMapping {
+            document: document.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn to_document(&self, mut row: Metadata) -> Result<Document, ChromaClientError> {
+        let text = match row.remove(&self.document) {
+            Some(Value::String(text)) => text,
+            Some(value) => value.to_string(),
+            None => {
+                let error_message = format!("missing document column `{}`", self.document);
+                return Err(ChromaClientError::LoaderError(error_message));
+            }
+        };
+
+        let id = match &self.id {
+            Some(column) => match row.remove(column) {
+                Some(Value::String(id)) => id,
+                Some(value) => value.to_string(),
+                None => {
+                    let error_message = format!("missing id column `{}`", column);
+                    return Err(ChromaClientError::LoaderError(error_message));
+                }
+            },
+            None => content_hash(&text),
+        };
+
+        let metadata = row
+            .into_iter()
+            .filter(|(key, value)| {
+                !value.is_null() && (self.metadata.is_empty() || self.metadata.contains(key))
+            })
+            .map(|(key, value)| match value {
+                // Chroma metadata only holds scalars.
+                Value::Array(_) | Value::Object(_) => (key, Value::from(value.to_string())),
+                value => (key, value),
+            })
+            .collect();
+
+        Ok(Document { id, text, metadata })
+    }
+}
+
+impl Collection {
+    /// Add the rows of a CSV file with headers. Metadata types are inferred
+    /// from the values: booleans, integers and floats are stored as such,
+    /// empty cells are left out. Returns the number of records added.
+    #[cfg(feature = "csv")]
+    pub async fn add_from_csv<R: std::io::Read>(
+        &self,
+        client: &ChromaClient,
+        reader: R,
+        mapping: &ColumnMapping,
+        embedding_function: &dyn EmbeddingFunction,
+    ) -> Result<usize, ChromaClientError> {
+        let mut reader = csv::Reader::from_reader(reader);
+        let headers = reader
+            .headers()
+            .map_err(|e| ChromaClientError::LoaderError(e.to_string()))?
+            .clone();
+
+        let mut rows = Vec::new();
+        for record in reader.records() {
+            let record = record.map_err(|e| ChromaClientError::LoaderError(e.to_string()))?;
+            let row = headers
+                .iter()
+                .zip(record.iter())
+                .map(|(header, cell)| (header.to_string(), infer_value(cell)))
+                .collect();
+            rows.push(mapping.to_document(row)?);
+        }
+
+        let rows = unique_ids(rows, mapping.id.is_none())?;
+        self.add_rows(client, rows, embedding_function).await
+    }
+
+    /// Add the rows of a JSON Lines file, one object per line. Nested values
+    /// are stored as JSON strings. Returns the number of records added.
+    pub async fn add_from_jsonl<R: BufRead>(
+        &self,
+        client: &ChromaClient,
+        reader: R,
+        mapping: &ColumnMapping,
+        embedding_function: &dyn EmbeddingFunction,
+    ) -> Result<usize, ChromaClientError> {
+        let mut rows = Vec::new();
+        for (number, line) in reader.lines().enumerate() {
+            let line = line.map_err(|e| ChromaClientError::LoaderError(e.to_string()))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let row: Metadata = serde_json::from_str(&line).map_err(|e| {
+                ChromaClientError::LoaderError(format!("line {}: {}", number + 1, e))
+            })?;
+            rows.push(mapping.to_document(row)?);
+        }
+
+        let rows = unique_ids(rows, mapping.id.is_none())?;
+        self.add_rows(client, rows, embedding_function).await
+    }
+
+    async fn add_rows(
+        &self,
+        client: &ChromaClient,
+        rows: Vec<Document>,
+        embedding_function: &dyn EmbeddingFunction,
+    ) -> Result<usize, ChromaClientError> {
+        for batch in rows.chunks(TABULAR_BATCH_SIZE) {
+            self.add_documents(client, embedding_function, batch)
+                .await?;
+        }
+        Ok(rows.len())
+    }
+}
+
+/// The documents with distinct ids, as Chroma rejects a batch repeating an
+/// id. Repeated derived ids come from rows with the same content and are
+/// skipped, repeated ids read from the data are an error.
+pub(crate) fn unique_ids(
+    documents: Vec<Document>,
+    derived: bool,
+) -> Result<Vec<Document>, ChromaClientError> {
+    let mut seen = HashSet::new();
+    let mut unique = Vec::with_capacity(documents.len());
+    for document in documents {
+        if seen.insert(document.id.clone()) {
+            unique.push(document);
+        } else if !derived {
+            let error_message = format!("duplicate id `{}`", document.id);
+            return Err(ChromaClientError::LoaderError(error_message));
+        }
+    }
+    Ok(unique)
+}
+
+/// Parse a cell into the most specific metadata type.
+pub fn infer_value(cell: &str) -> Value {
+    let cell = cell.trim();
+    if cell.is_empty() {
+        Value::Null
+    } else if let Ok(b) = cell.parse::<bool>() {
+        Value::from(b)
+    } else if let Ok(i) = cell.parse::<i64>() {
+        Value::from(i)
+    } else if let Some(f) = cell.parse::<f64>().ok().filter(|f| f.is_finite()) {
+        Value::from(f)
+    } else {
+        Value::from(cell)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn infers_metadata_types() {
+        assert_eq!(infer_value("true"), json!(true));
+        assert_eq!(infer_value("42"), json!(42));
+        assert_eq!(infer_value("4.2"), json!(4.2));
+        assert_eq!(infer_value(" "), Value::Null);
+        assert_eq!(infer_value("nan"), json!("nan"));
+        assert_eq!(infer_value("sci-fi"), json!("sci-fi"));
+    }
+
+    #[test]
+    fn maps_row_to_document() {
+        let mapping = ColumnMapping {
+            id: Some("isbn".into()),
+            document: "summary".into(),
+            metadata: vec!["year".into()],
+        };
+        let row = json!({"isbn": 123, "summary": "A book", "year": 1969, "price": 9.5});
+
+        let document = mapping
+            .to_document(row.as_object().unwrap().clone())
+            .unwrap();
+
+        assert_eq!(document.id, "123");
+        assert_eq!(document.text, "A book");
+        assert_eq!(Value::Object(document.metadata), json!({"year": 1969}));
+    }
+
+    #[test]
+    fn repeated_ids() {
+        let rows = || {
+            vec![
+                Document::new(content_hash("same"), "same"),
+                Document::new(content_hash("other"), "other"),
+                Document::new(content_hash("same"), "same"),
+            ]
+        };
+
+        assert_eq!(unique_ids(rows(), true).unwrap().len(), 2);
+        assert!(matches!(
+            unique_ids(rows(), false),
+            Err(ChromaClientError::LoaderError(_))
+        ));
+    }
+}
@@ -0,0 +1,83 @@
+use crate::client::{ChromaClient, ChromaClientParams};
+use crate::error::ChromaClientError;
+use std::io;
+use std::time::Duration;
+use testcontainers::core::{ContainerPort, IntoContainerPort};
+use testcontainers::runners::AsyncRunner;
+use testcontainers::{ContainerAsync, GenericImage, ImageExt};
+
+/// Image tag started by `ChromaContainer::start`.
+pub const DEFAULT_TAG: &str = "latest";
+
+const CHROMA_PORT: ContainerPort = ContainerPort::Tcp(8000);
+const READY_ATTEMPTS: usize = 60;
+const READY_DELAY: Duration = Duration::from_millis(500);
+
+/// A Chroma server running in a Docker container, removed when dropped.
+///
+/// ```no_run
+/// # async fn example() -> Result<(), chromadb_rs::error::ChromaClientError> {
+/// use chromadb_rs::testing::ChromaContainer;
+///
+/// let chroma = ChromaContainer::start().await?;
+/// let collection = chroma.client().create_collection("test", None).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ChromaContainer {
+    container: ContainerAsync<GenericImage>,
+    client: ChromaClient,
+}
+
+impl ChromaContainer {
+    /// Start the `chromadb/chroma` image and wait until it answers heartbeats.
+    pub async fn start() -> Result<Self, ChromaClientError> {
+        Self::start_with_tag(DEFAULT_TAG).await
+    }
+
+    /// Start the given tag of the `chromadb/chroma` image, e.g. `0.5.23`.
+    pub async fn start_with_tag(tag: &str) -> Result<Self, ChromaClientError> {
+        let container = GenericImage::new("chromadb/chroma", tag)
+            .with_exposed_port(CHROMA_PORT)
+            .with_env_var("ALLOW_RESET", "TRUE")
+            .start()
+            .await
+            .map_err(container_error)?;
+        let host = container.get_host().await.map_err(container_error)?;
+        let port = container
+            .get_host_port_ipv4(8000.tcp())
+            .await
+            .map_err(container_error)?;
+
+        let client = ChromaClient::new(ChromaClientParams {
+            host: host.to_string(),
+            port: port.to_string(),
+            ..Default::default()
+        });
+
+        let mut attempts = 0;
+        while let Err(e) = client.heartbeat().await {
+            attempts += 1;
+            if attempts == READY_ATTEMPTS {
+                return Err(e);
+            }
+            tokio::time::sleep(READY_DELAY).await;
+        }
+
+        Ok(ChromaContainer { container, client })
+    }
+
+    /// A client connected to the container.
+    pub fn client(&self) -> &ChromaClient {
+        &self.client
+    }
+
+    /// Id of the Docker container.
+    pub fn id(&self) -> &str {
+        self.container.id()
+    }
+}
+
+fn container_error(error: testcontainers::TestcontainersError) -> ChromaClientError {
+    ChromaClientError::IoError(io::Error::other(error))
+}
@@ -0,0 +1,571 @@
+use crate::document::Document;
+use crate::source::{SECTION_KEY, SPAN_END_KEY, SPAN_START_KEY};
+use serde_json::Value;
+use std::fmt;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Splits text into overlapping chunks of at most `chunk_size` characters,
+/// breaking on whitespace when possible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextSplitter {
+    pub chunk_size: usize,
+    pub chunk_overlap: usize,
+}
+
+impl Default for TextSplitter {
+    fn default() -> Self {
+        TextSplitter {
+            chunk_size: 1000,
+            chunk_overlap: 200,
+        }
+    }
+}
+
+impl TextSplitter {
+    pub fn new(chunk_size: usize, chunk_overlap: usize) -> Self {
+        TextSplitter {
+            chunk_size: chunk_size.max(1),
+            chunk_overlap: chunk_overlap.min(chunk_size.saturating_sub(1)),
+        }
+    }
+
+    /// Split the text into chunks.
+    pub fn split(&self, text: &str) -> Vec<String> {
+        self.split_spans(text)
+            .into_iter()
+            .map(|(_, chunk)| chunk)
+            .collect()
+    }
+
+    /// Split the text into chunks, with the range of characters of each chunk.
+    fn split_spans(&self, text: &str) -> Vec<(Range<usize>, String)> {
+        // The fields are public, so they may bypass the clamping of `new`.
+        let chunk_size = self.chunk_size.max(1);
+        let chunk_overlap = self.chunk_overlap.min(chunk_size - 1);
+        let chars: Vec<char> = text.chars().collect();
+        let mut chunks = Vec::new();
+        let mut start = 0;
+
+        while start < chars.len() {
+            let mut end = (start + chunk_size).min(chars.len());
+            if end < chars.len() {
+                // Prefer breaking after the last whitespace of the window.
+                if let Some(space) = chars[start..end].iter().rposition(|c| c.is_whitespace()) {
+                    if space > 0 {
+                        end = start + space + 1;
+                    }
+                }
+            }
+
+            let chunk: String = chars[start..end].iter().collect();
+            let trimmed = chunk.trim();
+            if !trimmed.is_empty() {
+                let from = start + chunk.chars().take_while(|c| c.is_whitespace()).count();
+                let to = from + trimmed.chars().count();
+                chunks.push((from..to, trimmed.to_string()));
+            }
+            if end == chars.len() {
+                break;
+            }
+
+            // Start the overlap on a word boundary too.
+            let mut next = end - chunk_overlap.min(end - start - 1);
+            if !chars[next - 1].is_whitespace() {
+                if let Some(space) = chars[next..end].iter().position(|c| c.is_whitespace()) {
+                    next += space + 1;
+                }
+            }
+            start = next.max(start + 1);
+        }
+
+        chunks
+    }
+
+    /// Split a document into chunk documents. Chunks keep the metadata of the
+    /// document, get a `chunk` index metadata, the characters of the document
+    /// they cover as `span_start` and `span_end` metadata, and an id of the
+    /// form `<document id>#chunk=<index>`. `IngestPipeline` replaces the
+    /// index with the start of the SHA-256 of the chunk text, so a chunk
+    /// keeps its id when the text before it changes.
+    pub fn split_document(&self, document: &Document) -> Vec<Document> {
+        chunk_document(document, self.split_spans(&document.text))
+    }
+}
+
+/// Splits text into chunks of whole sentences, of at most `chunk_size`
+/// characters, repeating up to `chunk_overlap` characters of sentences at
+/// the start of the next chunk. Sentences end with `.`, `!` or `?` followed
+/// by whitespace, or with a blank line. Sentences longer than `chunk_size`
+/// are split as by `TextSplitter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SentenceSplitter {
+    pub chunk_size: usize,
+    pub chunk_overlap: usize,
+}
+
+impl Default for SentenceSplitter {
+    fn default() -> Self {
+        SentenceSplitter {
+            chunk_size: 1000,
+            chunk_overlap: 200,
+        }
+    }
+}
+
+impl SentenceSplitter {
+    pub fn new(chunk_size: usize, chunk_overlap: usize) -> Self {
+        SentenceSplitter {
+            chunk_size: chunk_size.max(1),
+            chunk_overlap: chunk_overlap.min(chunk_size.saturating_sub(1)),
+        }
+    }
+
+    /// Split the text into chunks.
+    pub fn split(&self, text: &str) -> Vec<String> {
+        let chars: Vec<char> = text.chars().collect();
+        self.split_chars(&chars)
+            .into_iter()
+            .map(|(_, chunk)| chunk)
+            .collect()
+    }
+
+    /// Split a document into chunk documents, with the metadata and ids
+    /// described in `TextSplitter::split_document`.
+    pub fn split_document(&self, document: &Document) -> Vec<Document> {
+        let chars: Vec<char> = document.text.chars().collect();
+        chunk_document(document, self.split_chars(&chars))
+    }
+
+    fn split_chars(&self, chars: &[char]) -> Vec<(Range<usize>, String)> {
+        let sentences = sentences(chars);
+        let mut chunks = Vec::new();
+        let mut start = 0;
+
+        while start < sentences.len() {
+            let first = sentences[start].start;
+            if sentences[start].len() > self.chunk_size {
+                let sentence: String = chars[sentences[start].clone()].iter().collect();
+                let splitter = TextSplitter::new(self.chunk_size, self.chunk_overlap);
+                chunks.extend(
+                    splitter
+                        .split_spans(&sentence)
+                        .into_iter()
+                        .map(|(span, chunk)| (first + span.start..first + span.end, chunk)),
+                );
+                start += 1;
+                continue;
+            }
+
+            let mut end = start + 1;
+            while end < sentences.len() && sentences[end].end - first <= self.chunk_size {
+                end += 1;
+            }
+            let span = first..sentences[end - 1].end;
+            chunks.push((span.clone(), chars[span].iter().collect()));
+            if end == sentences.len() {
+                break;
+            }
+
+            let mut next = end;
+            while next > start + 1
+                && sentences[end - 1].end - sentences[next - 1].start <= self.chunk_overlap
+            {
+                next -= 1;
+            }
+            start = next;
+        }
+
+        chunks
+    }
+}
+
+/// Character ranges of the sentences of the text, without the surrounding
+/// whitespace.
+fn sentences(chars: &[char]) -> Vec<Range<usize>> {
+    let mut sentences = Vec::new();
+    let mut start = None;
+
+    for (i, &c) in chars.iter().enumerate() {
+        let Some(first) = start else {
+            if !c.is_whitespace() {
+                start = Some(i);
+            }
+            continue;
+        };
+        let next = chars.get(i + 1).copied();
+        let closing = matches!(c, '"' | '\'' | ')' | ']' | '»' | '”' | '’')
+            && i > 0
+            && matches!(chars[i - 1], '.' | '!' | '?');
+        let boundary = match next {
+            None => true,
+            Some(next) => {
+                (matches!(c, '.' | '!' | '?') || closing) && next.is_whitespace()
+                    || c == '\n' && next == '\n'
+            }
+        };
+        if boundary {
+            let end = first
+                + chars[first..=i]
+                    .iter()
+                    .rposition(|c| !c.is_whitespace())
+                    .unwrap()
+                + 1;
+            sentences.push(first..end);
+            start = None;
+        }
+    }
+
+    sentences
+}
+
+/// Splits Markdown documents at their headings, then each section into
+/// chunks of whole sentences as `SentenceSplitter` does. Headings stay in
+/// the text of their section, and chunks get the headings leading to them
+/// as `section` metadata, e.g. `Install > Linux`. Lines of fenced code
+/// blocks are never taken for headings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MarkdownSplitter {
+    pub sentences: SentenceSplitter,
+}
+
+impl MarkdownSplitter {
+    pub fn new(chunk_size: usize, chunk_overlap: usize) -> Self {
+        MarkdownSplitter {
+            sentences: SentenceSplitter::new(chunk_size, chunk_overlap),
+        }
+    }
+
+    /// Split the text into chunks, with the section of each chunk.
+    pub fn split(&self, text: &str) -> Vec<(Option<String>, String)> {
+        self.split_sections(text)
+            .into_iter()
+            .map(|(section, _, chunk)| (section, chunk))
+            .collect()
+    }
+
+    /// Split a document into chunk documents, with the metadata and ids
+    /// described in `TextSplitter::split_document` and the `section`
+    /// metadata.
+    pub fn split_document(&self, document: &Document) -> Vec<Document> {
+        let chunks = self.split_sections(&document.text);
+        let sections: Vec<Option<String>> = chunks.iter().map(|c| c.0.clone()).collect();
+        let spans = chunks
+            .into_iter()
+            .map(|(_, span, chunk)| (span, chunk))
+            .collect();
+
+        let mut documents = chunk_document(document, spans);
+        for (document, section) in documents.iter_mut().zip(sections) {
+            if let Some(section) = section {
+                document.metadata.insert(SECTION_KEY.into(), section.into());
+            }
+        }
+        documents
+    }
+
+    fn split_sections(&self, text: &str) -> Vec<(Option<String>, Range<usize>, String)> {
+        let chars: Vec<char> = text.chars().collect();
+        let mut headings: Vec<(usize, String)> = Vec::new();
+        let mut sections = vec![(0, None)];
+        let mut fenced = false;
+        let mut offset = 0;
+
+        for line in text.split_inclusive('\n') {
+            let trimmed = line.trim();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                fenced = !fenced;
+            } else if let Some((level, title)) = heading(trimmed).filter(|_| !fenced) {
+                headings.retain(|(l, _)| *l < level);
+                headings.push((level, title.to_string()));
+                let path: Vec<&str> = headings.iter().map(|(_, t)| t.as_str()).collect();
+                sections.push((offset, Some(path.join(" > "))));
+            }
+            offset += line.chars().count();
+        }
+
+        let ends: Vec<usize> = sections
+            .iter()
+            .skip(1)
+            .map(|(start, _)| *start)
+            .chain([chars.len()])
+            .collect();
+        sections
+            .into_iter()
+            .zip(ends)
+            .flat_map(|((start, section), end)| {
+                self.sentences
+                    .split_chars(&chars[start..end])
+                    .into_iter()
+                    .map(move |(span, chunk)| {
+                        (section.clone(), start + span.start..start + span.end, chunk)
+                    })
+            })
+            .collect()
+    }
+}
+
+/// Level and title of an ATX heading line such as `## Install`.
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let rest = &line[level..];
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with(' ')) {
+        return None;
+    }
+    let title = rest.trim().trim_end_matches('#').trim();
+    (!title.is_empty()).then_some((level, title))
+}
+
+/// Counts the tokens of a text for `TokenSplitter`, implemented by the
+/// tokenizers of the `tiktoken` and `tokenizers` features and by closures.
+pub trait TokenCounter: Send + Sync {
+    fn count_tokens(&self, text: &str) -> usize;
+}
+
+impl<F: Fn(&str) -> usize + Send + Sync> TokenCounter for F {
+    fn count_tokens(&self, text: &str) -> usize {
+        self(text)
+    }
+}
+
+#[cfg(feature = "tiktoken")]
+impl TokenCounter for tiktoken_rs::CoreBPE {
+    fn count_tokens(&self, text: &str) -> usize {
+        self.encode_ordinary(text).len()
+    }
+}
+
+#[cfg(feature = "tokenizers")]
+impl TokenCounter for tokenizers::Tokenizer {
+    fn count_tokens(&self, text: &str) -> usize {
+        // Texts the tokenizer fails on count as one token per character.
+        self.encode(text, false)
+            .map_or(text.chars().count(), |encoding| encoding.len())
+    }
+}
+
+/// Same as `TextSplitter`, with sizes in tokens of the embedding model
+/// instead of characters. Chunks break between words, whose tokens are
+/// counted one word at a time; a word longer than `chunk_size` tokens is a
+/// chunk of its own.
+///
+/// ```ignore
+/// let splitter = TokenSplitter::new(512, 64, tiktoken_rs::cl100k_base()?);
+/// ```
+#[derive(Clone)]
+pub struct TokenSplitter {
+    pub chunk_size: usize,
+    pub chunk_overlap: usize,
+    counter: Arc<dyn TokenCounter>,
+}
+
+impl fmt::Debug for TokenSplitter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenSplitter")
+            .field("chunk_size", &self.chunk_size)
+            .field("chunk_overlap", &self.chunk_overlap)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TokenSplitter {
+    pub fn new(
+        chunk_size: usize,
+        chunk_overlap: usize,
+        counter: impl TokenCounter + 'static,
+    ) -> Self {
+        TokenSplitter {
+            chunk_size: chunk_size.max(1),
+            chunk_overlap: chunk_overlap.min(chunk_size.saturating_sub(1)),
+            counter: Arc::new(counter),
+        }
+    }
+
+    /// Split the text into chunks.
+    pub fn split(&self, text: &str) -> Vec<String> {
+        self.split_spans(text)
+            .into_iter()
+            .map(|(_, chunk)| chunk)
+            .collect()
+    }
+
+    /// Split a document into chunk documents, with the metadata and ids
+    /// described in `TextSplitter::split_document`.
+    pub fn split_document(&self, document: &Document) -> Vec<Document> {
+        chunk_document(document, self.split_spans(&document.text))
+    }
+
+    fn split_spans(&self, text: &str) -> Vec<(Range<usize>, String)> {
+        let words = words(text)
+            .map(|(bytes, chars)| {
+                let tokens = self.counter.count_tokens(&text[bytes.clone()]);
+                (bytes, chars, tokens)
+            })
+            .collect::<Vec<_>>();
+        let mut chunks = Vec::new();
+        let mut start = 0;
+
+        while start < words.len() {
+            let mut end = start;
+            let mut tokens = 0;
+            while end < words.len() && (end == start || tokens + words[end].2 <= self.chunk_size) {
+                tokens += words[end].2;
+                end += 1;
+            }
+
+            let (first, last) = (&words[start], &words[end - 1]);
+            chunks.push((
+                first.1.start..last.1.end,
+                text[first.0.start..last.0.end].to_string(),
+            ));
+            if end == words.len() {
+                break;
+            }
+
+            let mut next = end;
+            let mut overlap = 0;
+            while next > start + 1 && overlap + words[next - 1].2 <= self.chunk_overlap {
+                next -= 1;
+                overlap += words[next].2;
+            }
+            start = next;
+        }
+
+        chunks
+    }
+}
+
+/// Byte and character ranges of the words of the text.
+fn words(text: &str) -> impl Iterator<Item = (Range<usize>, Range<usize>)> + '_ {
+    let mut chars = text.char_indices().enumerate().peekable();
+    std::iter::from_fn(move || {
+        while chars.next_if(|(_, (_, c))| c.is_whitespace()).is_some() {}
+        let (char_start, (byte_start, c)) = chars.next()?;
+        let mut end = (char_start + 1, byte_start + c.len_utf8());
+        while let Some((i, (byte, c))) = chars.next_if(|(_, (_, c))| !c.is_whitespace()) {
+            end = (i + 1, byte + c.len_utf8());
+        }
+        Some((byte_start..end.1, char_start..end.0))
+    })
+}
+
+fn chunk_document(document: &Document, spans: Vec<(Range<usize>, String)>) -> Vec<Document> {
+    spans
+        .into_iter()
+        .enumerate()
+        .map(|(i, (span, text))| {
+            let mut metadata = document.metadata.clone();
+            metadata.insert("chunk".into(), Value::from(i));
+            metadata.insert(SPAN_START_KEY.into(), Value::from(span.start));
+            metadata.insert(SPAN_END_KEY.into(), Value::from(span.end));
+            Document {
+                id: format!("{}#chunk={}", document.id, i),
+                text,
+                metadata,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_on_whitespace_with_overlap() {
+        let splitter = TextSplitter::new(12, 6);
+        let chunks = splitter.split("the quick brown fox jumps");
+
+        assert_eq!(
+            chunks,
+            vec!["the quick", "quick brown", "brown fox", "fox jumps"]
+        );
+    }
+
+    #[test]
+    fn zero_chunk_size_set_on_the_fields() {
+        let splitter = TextSplitter {
+            chunk_size: 0,
+            chunk_overlap: 3,
+        };
+
+        assert_eq!(splitter.split("ab c"), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn split_document_keeps_metadata() {
+        let mut document = Document::new("doc", "aaaa bbbb");
+        document.metadata.insert("source".into(), "a.txt".into());
+
+        let chunks = TextSplitter::new(5, 0).split_document(&document);
+
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1].id, "doc#chunk=1");
+        assert_eq!(chunks[1].text, "bbbb");
+        assert_eq!(chunks[1].metadata["source"], "a.txt");
+        assert_eq!(chunks[1].metadata["chunk"], 1);
+        assert_eq!(chunks[1].metadata["span_start"], 5);
+        assert_eq!(chunks[1].metadata["span_end"], 9);
+    }
+
+    #[test]
+    fn sentences_stay_whole() {
+        let splitter = SentenceSplitter::new(30, 12);
+        let chunks = splitter.split("It rains. The sky is gray!\n\nWe stay in (all day.) Why?");
+
+        assert_eq!(
+            chunks,
+            vec!["It rains. The sky is gray!", "We stay in (all day.) Why?"]
+        );
+        assert_eq!(
+            SentenceSplitter::new(12, 0).split("Short. A sentence too long."),
+            vec!["Short.", "A sentence", "too long."]
+        );
+    }
+
+    #[test]
+    fn markdown_sections() {
+        let text =
+            "Intro.\n# Install\nRun it.\n## Linux\n```\n# not a heading\n```\n# Usage #\nCall it.";
+        let chunks = MarkdownSplitter::new(100, 0).split_document(&Document::new("doc", text));
+
+        let sections: Vec<_> = chunks
+            .iter()
+            .map(|c| c.metadata.get("section").and_then(|s| s.as_str()))
+            .collect();
+        assert_eq!(
+            sections,
+            [
+                None,
+                Some("Install"),
+                Some("Install > Linux"),
+                Some("Usage")
+            ]
+        );
+        assert_eq!(chunks[2].text, "## Linux\n```\n# not a heading\n```");
+        assert_eq!(chunks[3].text, "# Usage #\nCall it.");
+        assert_eq!(chunks[3].metadata["span_end"], text.chars().count());
+    }
+
+    #[test]
+    fn token_sizes_with_overlap() {
+        // One token per 3 characters, rounded up.
+        let splitter = TokenSplitter::new(4, 2, |word: &str| word.chars().count().div_ceil(3));
+        let chunks = splitter.split("the quick brown fox jumps over");
+
+        assert_eq!(
+            chunks,
+            vec![
+                "the quick",
+                "quick brown",
+                "brown fox",
+                "fox jumps",
+                "jumps over"
+            ]
+        );
+        let chunks = splitter.split_document(&Document::new("doc", " é quick"));
+        assert_eq!(chunks[0].text, "é quick");
+        assert_eq!(chunks[0].metadata["span_start"], 1);
+        assert_eq!(chunks[0].metadata["span_end"], 8);
+    }
+}
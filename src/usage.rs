@@ -0,0 +1,143 @@
+use crate::embeddings::{EmbeddingFunction, EmbeddingFunctionInfo};
+use crate::error::ChromaClientError;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+/// Usage of a single embedding call, or the sum of several.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct EmbeddingUsage {
+    pub calls: u64,
+    pub documents: u64,
+    pub tokens: u64,
+    /// Estimated cost, in the currency of the configured price.
+    pub cost: f64,
+}
+
+impl std::ops::AddAssign for EmbeddingUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.calls += other.calls;
+        self.documents += other.documents;
+        self.tokens += other.tokens;
+        self.cost += other.cost;
+    }
+}
+
+/// Hook notified after every embedding call.
+pub trait UsageTracker: Send + Sync {
+    fn record(&self, usage: EmbeddingUsage);
+}
+
+/// Tracker summing the usage of every call it sees. Use one per ingestion run,
+/// or call `take` between runs.
+#[derive(Debug, Default)]
+pub struct UsageTotals {
+    totals: Mutex<EmbeddingUsage>,
+}
+
+impl UsageTotals {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The usage recorded so far.
+    pub fn snapshot(&self) -> EmbeddingUsage {
+        *self.totals.lock().unwrap()
+    }
+
+    /// Returns the usage recorded so far and starts again from zero.
+    pub fn take(&self) -> EmbeddingUsage {
+        std::mem::take(&mut *self.totals.lock().unwrap())
+    }
+}
+
+impl UsageTracker for UsageTotals {
+    fn record(&self, usage: EmbeddingUsage) {
+        *self.totals.lock().unwrap() += usage;
+    }
+}
+
+/// Embedding function wrapper reporting the usage of each call to a tracker.
+///
+/// Providers bill per token, so the token count of each document is estimated
+/// with `token_counter` (about 4 characters per token by default) and the
+/// cost derived from `price_per_million_tokens`.
+pub struct TrackedEmbeddingFunction<E> {
+    inner: E,
+    tracker: Arc<dyn UsageTracker>,
+    price_per_million_tokens: f64,
+    token_counter: fn(&str) -> u64,
+}
+
+impl<E: EmbeddingFunction> TrackedEmbeddingFunction<E> {
+    pub fn new(inner: E, tracker: Arc<dyn UsageTracker>, price_per_million_tokens: f64) -> Self {
+        TrackedEmbeddingFunction {
+            inner,
+            tracker,
+            price_per_million_tokens,
+            token_counter: estimate_tokens,
+        }
+    }
+
+    /// Use a custom token counter, e.g. the provider's tokenizer.
+    pub fn with_token_counter(mut self, token_counter: fn(&str) -> u64) -> Self {
+        self.token_counter = token_counter;
+        self
+    }
+}
+
+#[async_trait]
+impl<E: EmbeddingFunction> EmbeddingFunction for TrackedEmbeddingFunction<E> {
+    async fn embed(&self, documents: &[&str]) -> Result<Vec<Vec<f32>>, ChromaClientError> {
+        let embeddings = self.inner.embed(documents).await?;
+
+        let tokens: u64 = documents.iter().map(|d| (self.token_counter)(d)).sum();
+        self.tracker.record(EmbeddingUsage {
+            calls: 1,
+            documents: documents.len() as u64,
+            tokens,
+            cost: tokens as f64 * self.price_per_million_tokens / 1_000_000.0,
+        });
+
+        Ok(embeddings)
+    }
+
+    fn info(&self) -> Option<EmbeddingFunctionInfo> {
+        self.inner.info()
+    }
+}
+
+/// Rough token count used when no tokenizer is provided.
+pub fn estimate_tokens(text: &str) -> u64 {
+    (text.chars().count() as u64).div_ceil(4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct ZeroEmbedder;
+
+    #[async_trait]
+    impl EmbeddingFunction for ZeroEmbedder {
+        async fn embed(&self, documents: &[&str]) -> Result<Vec<Vec<f32>>, ChromaClientError> {
+            Ok(vec![vec![0.0]; documents.len()])
+        }
+    }
+
+    #[tokio::test]
+    async fn usage_is_aggregated() {
+        let totals = Arc::new(UsageTotals::new());
+        let embedder = TrackedEmbeddingFunction::new(ZeroEmbedder, totals.clone(), 20.0);
+
+        embedder.embed(&["12345678", "1234"]).await.unwrap();
+        embedder.embed(&["1"]).await.unwrap();
+
+        let usage = totals.take();
+        assert_eq!(usage.calls, 2);
+        assert_eq!(usage.documents, 3);
+        assert_eq!(usage.tokens, 4);
+        assert!((usage.cost - 0.00008).abs() < 1e-12);
+        assert_eq!(totals.snapshot(), EmbeddingUsage::default());
+    }
+}
@@ -0,0 +1,172 @@
+//! Processing of the embeddings sent to a collection, added, updated,
+//! upserted or queried, as configured by the collection metadata.
+//!
+//! ```no_run
+//! # async fn example(client: &chromadb_rs::client::ChromaClient) -> Result<(), chromadb_rs::error::ChromaClientError> {
+//! use chromadb_rs::vectors::NORMALIZE_EMBEDDINGS_KEY;
+//! use std::collections::HashMap;
+//!
+//! let metadata = HashMap::from([(NORMALIZE_EMBEDDINGS_KEY.to_string(), "true".to_string())]);
+//! let collection = client.create_collection("docs", Some(metadata)).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::client::ChromaClient;
+use crate::collection::{Collection, GetParams, Include};
+use crate::error::ChromaClientError;
+use crate::simd::l2_normalize;
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+
+/// Collection metadata key: when `true`, embeddings are scaled to a length
+/// of 1 before being sent, as cosine similarity expects for some models.
+pub const NORMALIZE_EMBEDDINGS_KEY: &str = "normalize_embeddings";
+
+/// Collection metadata key: embeddings are cut to this number of
+/// dimensions before being sent, for models trained to keep their first
+/// dimensions meaningful (Matryoshka embeddings). Truncation happens before
+/// normalization.
+pub const TRUNCATE_DIMENSIONS_KEY: &str = "truncate_dimensions";
+
+impl Collection {
+    /// Number of dimensions the embeddings sent to the collection are cut to.
+    pub fn truncate_dimensions(&self) -> Option<usize> {
+        match self.metadata.as_ref()?.get(TRUNCATE_DIMENSIONS_KEY)? {
+            Value::Number(n) => n.as_u64().map(|n| n as usize),
+            Value::String(s) => s.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// Check that the records already stored have the number of dimensions
+    /// the embeddings are truncated to, e.g. after changing the setting.
+    /// Collections without truncation or without records always pass. Run
+    /// before the first embeddings are added or queried through this
+    /// collection.
+    pub async fn check_dimensions(&self, client: &ChromaClient) -> Result<(), ChromaClientError> {
+        let Some(dimensions) = self.truncate_dimensions() else {
+            return Ok(());
+        };
+        let params = GetParams {
+            limit: Some(1),
+            include: Some(vec![Include::Embeddings]),
+            ..Default::default()
+        };
+        let stored = self.get(client, params).await?;
+        match stored.embeddings.as_ref().and_then(|e| e.first()) {
+            Some(embedding) if embedding.len() != dimensions => {
+                Err(ChromaClientError::EmbeddingError(format!(
+                    "collection `{}` truncates embeddings to {} dimensions, its records have {}",
+                    self.name,
+                    dimensions,
+                    embedding.len()
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether the embeddings sent to the collection are L2-normalized.
+    pub fn normalizes_embeddings(&self) -> bool {
+        let value = self
+            .metadata
+            .as_ref()
+            .and_then(|m| m.get(NORMALIZE_EMBEDDINGS_KEY));
+        match value {
+            Some(Value::Bool(normalize)) => *normalize,
+            Some(Value::String(normalize)) => normalize == "true",
+            _ => false,
+        }
+    }
+
+    /// Apply the processing configured for the collection to embeddings
+    /// about to be sent, after checking once per collection that the
+    /// records already stored have the truncated number of dimensions.
+    pub(crate) async fn prepare_embeddings(
+        &self,
+        client: &ChromaClient,
+        embeddings: &mut [Vec<f32>],
+    ) -> Result<(), ChromaClientError> {
+        if !embeddings.is_empty() {
+            self.dimensions_checked
+                .0
+                .get_or_try_init(|| self.check_dimensions(client))
+                .await?;
+        }
+        self.process_embeddings(embeddings)
+    }
+
+    /// Truncate and normalize embeddings as configured. Fails for
+    /// embeddings shorter than the truncation.
+    fn process_embeddings(&self, embeddings: &mut [Vec<f32>]) -> Result<(), ChromaClientError> {
+        if let Some(dimensions) = self.truncate_dimensions() {
+            for embedding in embeddings.iter_mut() {
+                if embedding.len() < dimensions {
+                    return Err(ChromaClientError::EmbeddingError(format!(
+                        "embedding of {} dimensions, collection `{}` truncates to {}",
+                        embedding.len(),
+                        self.name,
+                        dimensions
+                    )));
+                }
+                embedding.truncate(dimensions);
+            }
+        }
+        if self.normalizes_embeddings() {
+            embeddings.iter_mut().for_each(|e| l2_normalize(e));
+        }
+        Ok(())
+    }
+}
+
+/// Whether `check_dimensions` passed for a collection, shared by its clones.
+/// Left out of comparisons and serialization.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DimensionsChecked(Arc<OnceCell<()>>);
+
+impl PartialEq for DimensionsChecked {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn normalized_when_recorded() {
+        let mut embeddings = vec![vec![3.0, 4.0], vec![0.0, 0.0]];
+
+        Collection::new("plain".into(), None)
+            .process_embeddings(&mut embeddings)
+            .unwrap();
+        assert_eq!(embeddings[0], [3.0, 4.0]);
+
+        let metadata = json!({ NORMALIZE_EMBEDDINGS_KEY: "true" });
+        Collection::new("normalized".into(), Some(metadata))
+            .process_embeddings(&mut embeddings)
+            .unwrap();
+        assert_eq!(embeddings, [vec![0.6, 0.8], vec![0.0, 0.0]]);
+    }
+
+    #[test]
+    fn truncated_then_normalized() {
+        let metadata = json!({ TRUNCATE_DIMENSIONS_KEY: 2, NORMALIZE_EMBEDDINGS_KEY: true });
+        let collection = Collection::new("matryoshka".into(), Some(metadata));
+        assert_eq!(collection.truncate_dimensions(), Some(2));
+
+        let mut embeddings = vec![vec![3.0, 4.0, 12.0]];
+        collection.process_embeddings(&mut embeddings).unwrap();
+        assert_eq!(embeddings, [vec![0.6, 0.8]]);
+
+        let mut short = vec![vec![1.0]];
+        assert!(matches!(
+            collection.process_embeddings(&mut short),
+            Err(ChromaClientError::EmbeddingError(_))
+        ));
+    }
+}
@@ -0,0 +1,149 @@
+use crate::client::ChromaClient;
+use crate::collection::{Collection, GetParams, Include};
+use crate::error::ChromaClientError;
+use crate::export::ExportRecord;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+/// Problems found by `Collection::verify`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifyReport {
+    pub records: usize,
+    /// Ids returned more than once.
+    pub duplicate_ids: Vec<String>,
+    pub missing_documents: Vec<String>,
+    pub missing_embeddings: Vec<String>,
+    /// Embedding dimensions found, with the number of records having each.
+    pub dimensions: BTreeMap<usize, usize>,
+    /// Metadata keys holding values of more than one type, with those types.
+    pub metadata_type_drift: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl VerifyReport {
+    /// Whether no problem was found.
+    pub fn is_ok(&self) -> bool {
+        self.duplicate_ids.is_empty()
+            && self.missing_documents.is_empty()
+            && self.missing_embeddings.is_empty()
+            && self.dimensions.len() <= 1
+            && self.metadata_type_drift.is_empty()
+    }
+}
+
+impl Collection {
+    /// Read every record, `batch_size` at a time, and check for duplicate
+    /// ids, missing documents or embeddings, embeddings of different
+    /// dimensions and metadata keys whose values changed type.
+    pub async fn verify(
+        &self,
+        client: &ChromaClient,
+        batch_size: usize,
+    ) -> Result<VerifyReport, ChromaClientError> {
+        let params = GetParams {
+            include: Some(vec![
+                Include::Documents,
+                Include::Metadatas,
+                Include::Embeddings,
+            ]),
+            ..Default::default()
+        };
+        let mut pages = self.pages(client, params, batch_size);
+        let mut checker = Checker::default();
+        while let Some(page) = pages.next().await? {
+            let records = ExportRecord::from_result(page);
+            records.iter().for_each(|record| checker.check(record));
+        }
+
+        Ok(checker.finish())
+    }
+}
+
+#[derive(Default)]
+struct Checker {
+    report: VerifyReport,
+    ids: HashSet<String>,
+    metadata_types: BTreeMap<String, BTreeSet<&'static str>>,
+}
+
+impl Checker {
+    fn check(&mut self, record: &ExportRecord) {
+        self.report.records += 1;
+        if !self.ids.insert(record.id.clone()) {
+            self.report.duplicate_ids.push(record.id.clone());
+        }
+
+        if record.document.is_none() {
+            self.report.missing_documents.push(record.id.clone());
+        }
+        match &record.embedding {
+            Some(embedding) => *self.report.dimensions.entry(embedding.len()).or_default() += 1,
+            None => self.report.missing_embeddings.push(record.id.clone()),
+        }
+
+        for (key, value) in record.metadata.iter().flatten() {
+            self.metadata_types
+                .entry(key.clone())
+                .or_default()
+                .insert(type_name(value));
+        }
+    }
+
+    fn finish(mut self) -> VerifyReport {
+        self.report.metadata_type_drift = self
+            .metadata_types
+            .into_iter()
+            .filter(|(_, types)| types.len() > 1)
+            .map(|(key, types)| (key, types.into_iter().map(String::from).collect()))
+            .collect();
+        self.report
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Number(n) if n.is_f64() => "float",
+        Value::Number(_) => "int",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record(id: &str, embedding: Option<Vec<f32>>, metadata: Value) -> ExportRecord {
+        ExportRecord {
+            id: id.to_string(),
+            document: Some(format!("document {}", id)),
+            metadata: metadata.as_object().cloned(),
+            embedding,
+        }
+    }
+
+    #[test]
+    fn finds_inconsistencies() {
+        let mut checker = Checker::default();
+        checker.check(&record("a", Some(vec![0.0; 3]), json!({ "year": 2020 })));
+        checker.check(&record("b", Some(vec![0.0; 4]), json!({ "year": "2021" })));
+        checker.check(&record("a", None, json!({ "year": 2022 })));
+
+        let report = checker.finish();
+
+        assert!(!report.is_ok());
+        assert_eq!(report.records, 3);
+        assert_eq!(report.duplicate_ids, vec!["a"]);
+        assert_eq!(report.missing_embeddings, vec!["a"]);
+        assert!(report.missing_documents.is_empty());
+        assert_eq!(report.dimensions, BTreeMap::from([(3, 1), (4, 1)]));
+        assert_eq!(
+            report.metadata_type_drift["year"],
+            BTreeSet::from(["int".to_string(), "string".to_string()])
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use crate::client::ChromaClient;
+use crate::collection::{Collection, Entries, GetParams, GetResult, Include, Metadata};
+use crate::error::ChromaClientError;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Metadata key holding the logical id of a versioned record.
+pub const RECORD_ID_KEY: &str = "record_id";
+/// Metadata key holding the version number of a versioned record.
+pub const VERSION_KEY: &str = "version";
+
+impl Collection {
+    /// Store a new version of each record. Every version is kept as its own
+    /// entry (`<id>::v<version>`) tagged with `record_id` and a monotonic
+    /// `version` metadata. Returns the version written for each id.
+    pub async fn upsert_versioned(
+        &self,
+        client: &ChromaClient,
+        entries: Entries,
+    ) -> Result<Vec<u64>, ChromaClientError> {
+        self.write_versions(client, entries, None).await
+    }
+
+    /// Same as `upsert_versioned`, for optimistic concurrency: fails with
+    /// `VersionConflict`, writing nothing, unless the newest version of each
+    /// record is the expected one, 0 for records never written. The check and
+    /// the write are separate requests, so a writer in between goes unnoticed.
+    pub async fn upsert_if_version(
+        &self,
+        client: &ChromaClient,
+        entries: Entries,
+        expected: &[u64],
+    ) -> Result<Vec<u64>, ChromaClientError> {
+        if expected.len() != entries.ids.len() {
+            return Err(ChromaClientError::MetadataError(format!(
+                "expected {} versions, one per id",
+                entries.ids.len()
+            )));
+        }
+        self.write_versions(client, entries, Some(expected)).await
+    }
+
+    async fn write_versions(
+        &self,
+        client: &ChromaClient,
+        entries: Entries,
+        expected: Option<&[u64]>,
+    ) -> Result<Vec<u64>, ChromaClientError> {
+        if entries.ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let ids: Vec<&str> = entries.ids.iter().map(String::as_str).collect();
+        let latest = self.latest_versions(client, &ids).await?;
+        let current: Vec<u64> = ids
+            .iter()
+            .map(|id| latest.get(*id).copied().unwrap_or(0))
+            .collect();
+
+        for ((id, expected), current) in
+            ids.iter().zip(expected.into_iter().flatten()).zip(&current)
+        {
+            if expected != current {
+                return Err(ChromaClientError::VersionConflict {
+                    id: id.to_string(),
+                    expected: *expected,
+                    current: *current,
+                });
+            }
+        }
+        let versions: Vec<u64> = current.iter().map(|v| v + 1).collect();
+
+        let mut metadatas = entries
+            .metadatas
+            .unwrap_or_else(|| vec![Metadata::new(); entries.ids.len()]);
+        for ((metadata, id), version) in metadatas.iter_mut().zip(&entries.ids).zip(&versions) {
+            metadata.insert(RECORD_ID_KEY.to_string(), Value::from(id.as_str()));
+            metadata.insert(VERSION_KEY.to_string(), Value::from(*version));
+        }
+
+        self.upsert(
+            client,
+            Entries {
+                ids: entries
+                    .ids
+                    .iter()
+                    .zip(&versions)
+                    .map(|(id, version)| versioned_id(id, *version))
+                    .collect(),
+                metadatas: Some(metadatas),
+                ..entries
+            },
+        )
+        .await?;
+
+        Ok(versions)
+    }
+
+    /// Get the newest version of each record. Returned ids are the logical
+    /// record ids, the version is available in the `version` metadata.
+    pub async fn get_latest(
+        &self,
+        client: &ChromaClient,
+        ids: &[&str],
+    ) -> Result<GetResult, ChromaClientError> {
+        if ids.is_empty() {
+            return Ok(GetResult::default());
+        }
+        let mut result = self
+            .get(
+                client,
+                GetParams {
+                    where_metadata: Some(json!({ RECORD_ID_KEY: { "$in": ids } })),
+                    include: Some(vec![Include::Documents, Include::Metadatas]),
+                    ..Default::default()
+                },
+            )
+            .await?;
+
+        keep_latest(&mut result);
+        Ok(result)
+    }
+
+    async fn latest_versions(
+        &self,
+        client: &ChromaClient,
+        ids: &[&str],
+    ) -> Result<HashMap<String, u64>, ChromaClientError> {
+        let result = self
+            .get(
+                client,
+                GetParams {
+                    where_metadata: Some(json!({ RECORD_ID_KEY: { "$in": ids } })),
+                    include: Some(vec![Include::Metadatas]),
+                    ..Default::default()
+                },
+            )
+            .await?;
+
+        Ok(latest_by_record(&result))
+    }
+}
+
+fn versioned_id(id: &str, version: u64) -> String {
+    format!("{}::v{}", id, version)
+}
+
+fn record_version(metadata: Option<&Metadata>) -> Option<(&str, u64)> {
+    let metadata = metadata?;
+    let record_id = metadata.get(RECORD_ID_KEY)?.as_str()?;
+    let version = metadata.get(VERSION_KEY)?.as_u64()?;
+    Some((record_id, version))
+}
+
+fn latest_by_record(result: &GetResult) -> HashMap<String, u64> {
+    let mut latest = HashMap::new();
+    let metadatas = result.metadatas.iter().flatten();
+
+    for (record_id, version) in metadatas.filter_map(|m| record_version(m.as_ref())) {
+        let entry = latest.entry(record_id.to_string()).or_insert(version);
+        *entry = (*entry).max(version);
+    }
+    latest
+}
+
+fn keep_latest(result: &mut GetResult) {
+    let latest = latest_by_record(result);
+    result.retain(|metadata| {
+        record_version(metadata).is_some_and(|(id, version)| latest.get(id) == Some(&version))
+    });
+
+    let metadatas = result.metadatas.iter().flatten();
+    result.ids = metadatas
+        .filter_map(|m| record_version(m.as_ref()))
+        .map(|(id, _)| id.to_string())
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_only_newest_versions() {
+        let mut result: GetResult = serde_json::from_value(json!({
+            "ids": ["a::v1", "a::v2", "b::v1"],
+            "embeddings": null,
+            "documents": ["a old", "a new", "b"],
+            "metadatas": [
+                {"record_id": "a", "version": 1},
+                {"record_id": "a", "version": 2},
+                {"record_id": "b", "version": 1},
+            ],
+        }))
+        .unwrap();
+
+        keep_latest(&mut result);
+
+        assert_eq!(result.ids, vec!["a", "b"]);
+        assert_eq!(
+            result.documents.unwrap(),
+            vec![Some("a new".to_string()), Some("b".to_string())]
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use crate::client::ChromaClient;
+use crate::collection::{Collection, GetParams, GetResult, Include};
+use crate::document::content_hash;
+use crate::error::ChromaClientError;
+use futures_util::stream::{self, Stream};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Changes observed in a collection between two polls.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangeEvent {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl ChangeEvent {
+    /// Whether nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.updated.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// SHA-256 of the document and metadata of every record, keyed by id.
+type Snapshot = HashMap<String, String>;
+
+impl Collection {
+    /// Poll the collection every `poll_interval` and yield the changes found
+    /// since the previous poll. The first poll only records the initial state.
+    ///
+    /// Each poll fetches the documents and metadatas of every record, so keep
+    /// the interval reasonable for large collections.
+    pub fn watch<'a>(
+        &'a self,
+        client: &'a ChromaClient,
+        poll_interval: Duration,
+    ) -> impl Stream<Item = Result<ChangeEvent, ChromaClientError>> + 'a {
+        let ticker = tokio::time::interval(poll_interval);
+
+        stream::unfold(
+            (ticker, None::<Snapshot>),
+            move |(mut ticker, mut previous)| async move {
+                loop {
+                    ticker.tick().await;
+
+                    let params = GetParams {
+                        include: Some(vec![Include::Documents, Include::Metadatas]),
+                        ..Default::default()
+                    };
+                    let current = match self.get(client, params).await {
+                        Ok(result) => snapshot(&result),
+                        Err(e) => return Some((Err(e), (ticker, previous))),
+                    };
+
+                    let event = previous.as_ref().map(|p| diff(p, &current));
+                    previous = Some(current);
+
+                    if let Some(event) = event.filter(|e| !e.is_empty()) {
+                        return Some((Ok(event), (ticker, previous)));
+                    }
+                }
+            },
+        )
+    }
+}
+
+fn snapshot(result: &GetResult) -> Snapshot {
+    result
+        .ids
+        .iter()
+        .enumerate()
+        .map(|(i, id)| {
+            let document = result.documents.as_ref().and_then(|d| d.get(i));
+            let metadata = result.metadatas.as_ref().and_then(|m| m.get(i));
+            let content = serde_json::to_string(&(document, metadata)).unwrap_or_default();
+            (id.clone(), content_hash(content))
+        })
+        .collect()
+}
+
+fn diff(previous: &Snapshot, current: &Snapshot) -> ChangeEvent {
+    let mut event = ChangeEvent::default();
+
+    for (id, hash) in current {
+        match previous.get(id) {
+            None => event.added.push(id.clone()),
+            Some(old) if old != hash => event.updated.push(id.clone()),
+            Some(_) => {}
+        }
+    }
+    event.removed = previous
+        .keys()
+        .filter(|id| !current.contains_key(*id))
+        .cloned()
+        .collect();
+
+    event.added.sort();
+    event.updated.sort();
+    event.removed.sort();
+    event
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_snapshots() {
+        let snapshot = |records: [(&str, &str); 3]| -> Snapshot {
+            records
+                .iter()
+                .map(|(id, content)| (id.to_string(), content_hash(content)))
+                .collect()
+        };
+        let previous = snapshot([("a", "1"), ("b", "2"), ("c", "3")]);
+        let current = snapshot([("a", "1"), ("b", "20"), ("d", "4")]);
+
+        let event = diff(&previous, &current);
+
+        assert_eq!(event.added, vec!["d"]);
+        assert_eq!(event.updated, vec!["b"]);
+        assert_eq!(event.removed, vec!["c"]);
+    }
+}
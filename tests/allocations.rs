@@ -0,0 +1,58 @@
+//! Allocations made when parsing a large query result, owned and borrowed.
+
+use bytes::Bytes;
+use chromadb_rs::collection::QueryResult;
+use chromadb_rs::raw::RawQueryResult;
+use serde_json::json;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn allocations<T>(parse: impl FnOnce() -> T) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let parsed = parse();
+    let count = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    drop(parsed);
+    count
+}
+
+#[test]
+fn borrowed_results_allocate_less() {
+    let hits = 1000;
+    let body = json!({
+        "ids": [(0..hits).map(|i| format!("record-{}", i)).collect::<Vec<_>>()],
+        "distances": [vec![0.5; hits]],
+        "documents": [(0..hits).map(|i| format!("document number {}", i)).collect::<Vec<_>>()],
+    });
+    let body = Bytes::from(serde_json::to_vec(&body).unwrap());
+    let raw = RawQueryResult::new(body.clone());
+
+    let owned = allocations(|| serde_json::from_slice::<QueryResult>(&body).unwrap());
+    let borrowed = allocations(|| raw.parse().unwrap());
+
+    // One `String` per id and document saved, the vectors remain.
+    assert!(owned >= 2 * hits, "owned: {}", owned);
+    assert!(
+        borrowed * 20 < owned,
+        "borrowed: {}, owned: {}",
+        borrowed,
+        owned
+    );
+}
@@ -0,0 +1,1176 @@
+//! Exact request bodies sent for each endpoint, checked against a mock server.
+#![cfg(feature = "mock")]
+
+use chromadb_rs::batch::WriteBatch;
+use chromadb_rs::client::{ChromaClient, ChromaClientParams, DangerZone, PreflightMode};
+use chromadb_rs::collection::{Collection, DeleteParams, Entries, GetParams, Include, QueryParams};
+use chromadb_rs::embeddings::EmbeddingFunction;
+use chromadb_rs::error::ChromaClientError;
+use chromadb_rs::filter::Where;
+use chromadb_rs::memory::ChatMemoryStore;
+use chromadb_rs::mock::{fixtures, MockChroma};
+use chromadb_rs::policy::OperationPolicy;
+use serde_json::json;
+use std::sync::Arc;
+
+fn collection_response(name: &str) -> serde_json::Value {
+    json!({ "name": name, "id": "c0ffee", "metadata": null })
+}
+
+fn collection() -> Collection {
+    Collection::with_id("docs".into(), "c0ffee".into(), None)
+}
+
+#[tokio::test]
+async fn create_collection() {
+    let chroma = MockChroma::start().await;
+    chroma
+        .expect(
+            "POST",
+            "api/v1/collections",
+            json!({ "name": "docs", "metadata": null, "get_or_create": false }),
+            collection_response("docs"),
+        )
+        .await;
+
+    let collection = chroma
+        .client()
+        .create_collection("docs", None)
+        .await
+        .unwrap();
+
+    assert_eq!(collection.id, "c0ffee");
+    chroma.verify().await;
+}
+
+#[tokio::test]
+async fn get_or_create_collection() {
+    let chroma = MockChroma::start().await;
+    chroma
+        .expect(
+            "POST",
+            "api/v1/collections",
+            json!({ "name": "docs", "metadata": { "lang": "en" }, "get_or_create": true }),
+            collection_response("docs"),
+        )
+        .await;
+
+    let metadata = [("lang".to_string(), "en".to_string())].into();
+    chroma
+        .client()
+        .get_or_create_collection("docs", Some(metadata))
+        .await
+        .unwrap();
+
+    chroma.verify().await;
+}
+
+#[tokio::test]
+async fn existing_collection() {
+    let chroma = MockChroma::start().await;
+    let (status, body) = fixtures::already_exists("docs");
+    chroma
+        .fail("POST", "api/v1/collections", status, body)
+        .await;
+
+    let result = chroma.client().create_collection("docs", None).await;
+
+    assert!(matches!(result, Err(ChromaClientError::CollectionAlreadyExists(n)) if n == "docs"));
+}
+
+#[tokio::test]
+async fn policy_checks() {
+    let chroma = MockChroma::start().await;
+    chroma
+        .respond(
+            "GET",
+            "api/v1/collections/docs",
+            collection_response("docs"),
+        )
+        .await;
+
+    let reader = chroma.client().policy(OperationPolicy::read_only());
+    let opened = reader.get_or_create_collection("docs", None).await;
+    assert_eq!(opened.unwrap(), collection());
+    assert!(matches!(
+        reader.create_collection("docs", None).await,
+        Err(ChromaClientError::OperationDenied(_))
+    ));
+
+    let denied = chroma.client().policy(OperationPolicy::deny_all());
+    let params = QueryParams {
+        query_embeddings: vec![vec![0.5]],
+        ..Default::default()
+    };
+    assert!(matches!(
+        collection().query_debug(&denied, params).await,
+        Err(ChromaClientError::OperationDenied(_))
+    ));
+    let requests = chroma.server().received_requests().await.unwrap();
+    assert!(requests.iter().all(|r| r.method.as_str() == "GET"));
+}
+
+#[tokio::test]
+async fn list_and_get_collections() {
+    let chroma = MockChroma::start().await;
+    chroma
+        .respond(
+            "GET",
+            "api/v1/collections",
+            json!([collection_response("docs")]),
+        )
+        .await;
+    chroma
+        .respond(
+            "GET",
+            "api/v1/collections/docs",
+            collection_response("docs"),
+        )
+        .await;
+    let client = chroma.client();
+
+    assert_eq!(client.list_collections().await.unwrap(), vec![collection()]);
+    assert_eq!(client.get_collection("docs").await.unwrap(), collection());
+}
+
+#[tokio::test]
+async fn missing_collection() {
+    let chroma = MockChroma::start().await;
+    let (status, body) = fixtures::not_found("missing");
+    chroma
+        .fail("GET", "api/v1/collections/missing", status, body)
+        .await;
+
+    let error = chroma.client().get_collection("missing").await.unwrap_err();
+
+    let api_error = error.api_error().unwrap();
+    assert_eq!(api_error.status, 404);
+    assert!(api_error.body.contains("does not exist"));
+}
+
+#[tokio::test]
+async fn delete_collection() {
+    let chroma = MockChroma::start().await;
+    chroma
+        .respond("DELETE", "api/v1/collections/docs", json!(null))
+        .await;
+    let (status, body) = fixtures::not_found("missing");
+    chroma
+        .fail("DELETE", "api/v1/collections/missing", status, body)
+        .await;
+    let client = chroma.client();
+
+    client.delete_collection("docs").await.unwrap();
+    let error = client.delete_collection("missing").await.unwrap_err();
+
+    assert_eq!(error.api_error().unwrap().status, 404);
+    let requests = chroma.server().received_requests().await.unwrap();
+    let delete = requests
+        .iter()
+        .find(|r| r.method.as_str() == "DELETE")
+        .unwrap();
+    assert_eq!(
+        delete.url.query(),
+        Some("tenant=default_tenant&database=default_database")
+    );
+}
+
+#[tokio::test]
+async fn reset() {
+    let chroma = MockChroma::start().await;
+    chroma.respond("POST", "api/v1/reset", true).await;
+    let client = chroma.client();
+
+    client
+        .reset(DangerZone::i_understand_data_will_be_lost())
+        .await
+        .unwrap();
+
+    let requests = chroma.server().received_requests().await.unwrap();
+    let reset = requests
+        .iter()
+        .find(|r| r.url.path() == "/api/v1/reset")
+        .unwrap();
+    assert_eq!(reset.method.as_str(), "POST");
+    assert!(reset.body.is_empty());
+
+    let (status, body) = fixtures::error(500, "ValueError", "Resetting is not allowed");
+    let chroma = MockChroma::start().await;
+    chroma.fail("POST", "api/v1/reset", status, body).await;
+    let error = chroma
+        .client()
+        .reset(DangerZone::i_understand_data_will_be_lost())
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("ALLOW_RESET"));
+}
+
+#[tokio::test]
+async fn heartbeat() {
+    let chroma = MockChroma::start().await;
+
+    // The mock server answers a heartbeat of 1 nanosecond.
+    let heartbeat = chroma.client().heartbeat().await.unwrap();
+
+    assert_eq!(heartbeat, 1);
+    let requests = chroma.server().received_requests().await.unwrap();
+    assert!(requests
+        .iter()
+        .any(|r| r.method.as_str() == "GET" && r.url.path() == "/api/v1/heartbeat"));
+}
+
+#[tokio::test]
+async fn add_records() {
+    let chroma = MockChroma::start().await;
+    chroma
+        .expect(
+            "POST",
+            "api/v1/collections/c0ffee/add",
+            json!({
+                "ids": ["a", "b"],
+                "embeddings": [[0.5, 1.0], [1.5, 2.0]],
+                "metadatas": [{ "page": 1 }, null],
+                "documents": ["first", "second"],
+            }),
+            true,
+        )
+        .await;
+
+    let entries = Entries {
+        ids: vec!["a".into(), "b".into()],
+        embeddings: Some(vec![vec![0.5, 1.0], vec![1.5, 2.0]]),
+        metadatas: Some(vec![
+            json!({ "page": 1 }).as_object().unwrap().clone(),
+            Default::default(),
+        ]),
+        documents: Some(vec!["first".into(), "second".into()]),
+    };
+    collection().add(&chroma.client(), entries).await.unwrap();
+
+    chroma.verify().await;
+}
+
+#[tokio::test]
+async fn upsert_and_update_records() {
+    let chroma = MockChroma::start().await;
+    let body = json!({ "ids": ["a"], "documents": ["text"] });
+    chroma
+        .expect("POST", "api/v1/collections/c0ffee/upsert", &body, true)
+        .await;
+    chroma
+        .expect("POST", "api/v1/collections/c0ffee/update", &body, true)
+        .await;
+
+    let entries = Entries {
+        ids: vec!["a".into()],
+        documents: Some(vec!["text".into()]),
+        ..Default::default()
+    };
+    let client = chroma.client();
+    collection().upsert(&client, entries.clone()).await.unwrap();
+    collection().update(&client, entries).await.unwrap();
+
+    chroma.verify().await;
+}
+
+#[tokio::test]
+async fn get_records() {
+    let chroma = MockChroma::start().await;
+    chroma
+        .expect(
+            "POST",
+            "api/v1/collections/c0ffee/get",
+            json!({
+                "where": { "page": { "$gt": 1 } },
+                "limit": 10,
+                "offset": 20,
+                "include": ["documents"],
+            }),
+            json!({ "ids": ["a"], "embeddings": null, "documents": ["text"], "metadatas": null }),
+        )
+        .await;
+
+    let params = GetParams {
+        where_metadata: Some(json!({ "page": { "$gt": 1 } })),
+        limit: Some(10),
+        offset: Some(20),
+        include: Some(vec![Include::Documents]),
+        ..Default::default()
+    };
+    let result = collection().get(&chroma.client(), params).await.unwrap();
+
+    assert_eq!(result.documents, Some(vec![Some("text".into())]));
+    chroma.verify().await;
+}
+
+#[tokio::test]
+async fn get_pages() {
+    let chroma = MockChroma::start().await;
+    let page = |ids: &[&str]| json!({ "ids": ids, "embeddings": null, "documents": null, "metadatas": null });
+    let pages = [
+        (
+            json!({ "limit": 2, "offset": 1, "include": [] }),
+            page(&["a", "b"]),
+        ),
+        (
+            json!({ "limit": 2, "offset": 3, "include": [] }),
+            page(&["c", "d"]),
+        ),
+        (
+            json!({ "limit": 1, "offset": 5, "include": [] }),
+            page(&["e"]),
+        ),
+    ];
+    for (body, response) in pages {
+        chroma
+            .expect("POST", "api/v1/collections/c0ffee/get", body, response)
+            .await;
+    }
+
+    let params = GetParams {
+        limit: Some(5),
+        offset: Some(1),
+        include: Some(vec![]),
+        ..Default::default()
+    };
+    let client = chroma.client();
+    let collection = collection();
+    let mut pages = collection.pages(&client, params, 2);
+    let mut ids = Vec::new();
+    while let Some(page) = pages.next().await.unwrap() {
+        ids.extend(page.ids);
+    }
+
+    assert_eq!(ids, ["a", "b", "c", "d", "e"]);
+    assert_eq!(pages.offset(), 6);
+    chroma.verify().await;
+}
+
+#[tokio::test]
+async fn truncating_collection_checks_stored_dimensions_once() {
+    let chroma = MockChroma::start().await;
+    chroma
+        .respond(
+            "POST",
+            "api/v1/collections/c0ffee/get",
+            json!({ "ids": ["a"], "embeddings": [[0.5, 1.0]], "documents": null, "metadatas": null }),
+        )
+        .await;
+    chroma
+        .respond("POST", "api/v1/collections/c0ffee/upsert", true)
+        .await;
+
+    let metadata = json!({ "truncate_dimensions": 2 });
+    let collection = Collection::with_id("docs".into(), "c0ffee".into(), Some(metadata));
+    let client = chroma.client();
+    for id in ["b", "c"] {
+        let entries = Entries {
+            ids: vec![id.into()],
+            embeddings: Some(vec![vec![0.5, 1.0, 1.5]]),
+            ..Default::default()
+        };
+        collection.upsert(&client, entries).await.unwrap();
+    }
+
+    let requests = chroma.server().received_requests().await.unwrap();
+    let gets = requests
+        .iter()
+        .filter(|r| r.url.path().ends_with("/get"))
+        .count();
+    assert_eq!(gets, 1);
+}
+
+#[tokio::test]
+async fn truncating_collection_rejects_stored_dimensions_mismatch() {
+    let chroma = MockChroma::start().await;
+    chroma
+        .respond(
+            "POST",
+            "api/v1/collections/c0ffee/get",
+            json!({ "ids": ["a"], "embeddings": [[0.5, 1.0, 1.5]], "documents": null, "metadatas": null }),
+        )
+        .await;
+
+    let metadata = json!({ "truncate_dimensions": 2 });
+    let collection = Collection::with_id("docs".into(), "c0ffee".into(), Some(metadata));
+    let params = QueryParams {
+        query_embeddings: vec![vec![0.5, 1.0, 1.5]],
+        n_results: 3,
+        ..Default::default()
+    };
+    let result = collection.query(&chroma.client(), params).await;
+
+    assert!(matches!(result, Err(ChromaClientError::EmbeddingError(_))));
+}
+
+#[tokio::test]
+async fn query_records() {
+    let chroma = MockChroma::start().await;
+    chroma
+        .expect(
+            "POST",
+            "api/v1/collections/c0ffee/query",
+            json!({
+                "query_embeddings": [[0.5, 1.0]],
+                "n_results": 3,
+                "where_document": { "$contains": "rust" },
+                "include": ["distances"],
+            }),
+            json!({
+                "ids": [["a"]],
+                "distances": [[0.25]],
+                "embeddings": null,
+                "documents": null,
+                "metadatas": null,
+            }),
+        )
+        .await;
+
+    let params = QueryParams {
+        query_embeddings: vec![vec![0.5, 1.0]],
+        n_results: 3,
+        where_document: Some(json!({ "$contains": "rust" })),
+        include: Some(vec![Include::Distances]),
+        ..Default::default()
+    };
+    let result = collection().query(&chroma.client(), params).await.unwrap();
+
+    assert_eq!(result.distances, Some(vec![vec![0.25]]));
+    chroma.verify().await;
+}
+
+#[tokio::test]
+async fn count_and_delete_records() {
+    let chroma = MockChroma::start().await;
+    chroma
+        .respond("GET", "api/v1/collections/c0ffee/count", 42)
+        .await;
+    chroma
+        .expect(
+            "POST",
+            "api/v1/collections/c0ffee/delete",
+            json!({ "ids": ["a"] }),
+            json!(["a"]),
+        )
+        .await;
+    let client = chroma.client();
+
+    assert_eq!(collection().count(&client).await.unwrap(), 42);
+    let params = DeleteParams {
+        ids: Some(vec!["a".into()]),
+        ..Default::default()
+    };
+    collection().delete(&client, params).await.unwrap();
+
+    chroma.verify().await;
+}
+
+#[tokio::test]
+async fn error_responses() {
+    let chroma = MockChroma::start().await;
+    let (status, body) = fixtures::invalid_dimension(2, 3);
+    chroma
+        .fail("POST", "api/v1/collections/c0ffee/add", status, body)
+        .await;
+
+    let entries = Entries {
+        ids: vec!["a".into()],
+        embeddings: Some(vec![vec![0.5, 1.0, 1.5]]),
+        ..Default::default()
+    };
+    let result = collection().add(&chroma.client(), entries).await;
+
+    assert!(matches!(
+        result,
+        Err(ChromaClientError::ResponseStatusError(error))
+            if error.status == 400 && error.body.contains("dimension")
+    ));
+}
+
+#[tokio::test]
+async fn delete_where_preview() {
+    let chroma = MockChroma::start().await;
+    chroma
+        .respond(
+            "POST",
+            "api/v1/collections/c0ffee/get",
+            json!({ "ids": ["a", "b"], "embeddings": null, "documents": null, "metadatas": null }),
+        )
+        .await;
+    chroma
+        .expect(
+            "POST",
+            "api/v1/collections/c0ffee/delete",
+            json!({ "ids": ["a", "b"] }),
+            json!(["a", "b"]),
+        )
+        .await;
+    let client = chroma.client();
+
+    let preview = collection()
+        .delete_where(&client, Where::eq("draft", true), true)
+        .await
+        .unwrap();
+    assert_eq!((preview.count(), preview.deleted), (2, false));
+
+    let deleted = collection()
+        .delete_where(&client, Where::eq("draft", true), false)
+        .await
+        .unwrap();
+    assert!(deleted.deleted);
+    chroma.verify().await;
+}
+
+#[tokio::test]
+async fn write_batch_rollback() {
+    let chroma = MockChroma::start().await;
+    chroma
+        .expect(
+            "POST",
+            "api/v1/collections/c0ffee/get",
+            json!({ "ids": ["a", "b"], "include": [] }),
+            json!({ "ids": ["a"], "embeddings": null, "documents": null, "metadatas": null }),
+        )
+        .await;
+    chroma
+        .respond("POST", "api/v1/collections/c0ffee/add", true)
+        .await;
+    let (status, body) = fixtures::internal_error();
+    chroma
+        .fail("POST", "api/v1/collections/c0ffee/update", status, body)
+        .await;
+    // Only "b" was created by the batch, "a" was already stored.
+    chroma
+        .expect(
+            "POST",
+            "api/v1/collections/c0ffee/delete",
+            json!({ "ids": ["b"] }),
+            json!(["b"]),
+        )
+        .await;
+
+    let mut batch = WriteBatch::new(&collection());
+    batch
+        .add(Entries {
+            ids: vec!["a".into(), "b".into()],
+            documents: Some(vec!["first".into(), "second".into()]),
+            ..Default::default()
+        })
+        .update(Entries {
+            ids: vec!["c".into()],
+            documents: Some(vec!["third".into()]),
+            ..Default::default()
+        });
+    let result = batch.commit(&chroma.client()).await;
+
+    assert!(matches!(
+        result,
+        Err(ChromaClientError::ResponseStatusError(error)) if error.status == 500
+    ));
+    chroma.verify().await;
+}
+
+#[tokio::test]
+async fn write_batch_without_new_records() {
+    let chroma = MockChroma::start().await;
+    chroma
+        .respond(
+            "POST",
+            "api/v1/collections/c0ffee/get",
+            json!({ "ids": ["a"], "embeddings": null, "documents": null, "metadatas": null }),
+        )
+        .await;
+    chroma
+        .respond("POST", "api/v1/collections/c0ffee/add", true)
+        .await;
+    let (status, body) = fixtures::internal_error();
+    chroma
+        .fail("POST", "api/v1/collections/c0ffee/delete", status, body)
+        .await;
+
+    let mut batch = WriteBatch::new(&collection());
+    batch
+        .add(Entries {
+            ids: vec!["a".into()],
+            documents: Some(vec!["first".into()]),
+            ..Default::default()
+        })
+        .delete(&["z"]);
+    assert!(batch.commit(&chroma.client()).await.is_err());
+
+    // The failing delete is the only one sent: nothing is rolled back.
+    let requests = chroma.server().received_requests().await.unwrap();
+    let deletes = requests
+        .iter()
+        .filter(|r| r.url.path().ends_with("/delete"))
+        .count();
+    assert_eq!(deletes, 1);
+}
+
+struct LengthEmbedder;
+
+#[async_trait::async_trait]
+impl EmbeddingFunction for LengthEmbedder {
+    async fn embed(&self, documents: &[&str]) -> Result<Vec<Vec<f32>>, ChromaClientError> {
+        Ok(documents.iter().map(|d| vec![d.len() as f32]).collect())
+    }
+}
+
+#[tokio::test]
+async fn chat_memory_appends() {
+    let chroma = MockChroma::start().await;
+    chroma
+        .respond(
+            "POST",
+            "api/v1/collections/c0ffee/get",
+            json!({ "ids": [], "embeddings": null, "documents": null, "metadatas": null }),
+        )
+        .await;
+    chroma
+        .respond("POST", "api/v1/collections/c0ffee/upsert", true)
+        .await;
+    chroma
+        .respond("POST", "api/v1/collections/c0ffee/delete", json!([]))
+        .await;
+    let memory = ChatMemoryStore::new(chroma.client(), collection(), Arc::new(LengthEmbedder))
+        .max_messages(1);
+
+    let mut ids = Vec::new();
+    for text in ["a", "b", "c"] {
+        ids.push(memory.append("s", "user", text).await.unwrap().id);
+    }
+
+    // Same role in the same millisecond still gets distinct ids.
+    ids.dedup();
+    assert_eq!(ids.len(), 3);
+    let requests = chroma.server().received_requests().await.unwrap();
+    let count = |route: &str| {
+        requests
+            .iter()
+            .filter(|r| r.url.path().ends_with(route))
+            .count()
+    };
+    // The history, empty here, is fetched on the first append only: then
+    // the ids appended are known and the oldest one is deleted.
+    assert_eq!((count("/get"), count("/delete")), (1, 1));
+    let delete = requests
+        .iter()
+        .find(|r| r.url.path().ends_with("/delete"))
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&delete.body).unwrap();
+    assert_eq!(body, json!({ "ids": [ids[1]] }));
+}
+
+#[tokio::test]
+async fn batch_too_large() {
+    let chroma = MockChroma::start().await;
+    let client = chroma.client();
+    let entries = Entries {
+        ids: (0..1001).map(|i| i.to_string()).collect(),
+        ..Default::default()
+    };
+
+    let error = collection().add(&client, entries).await.unwrap_err();
+
+    assert_eq!(client.max_batch_size().await.unwrap(), Some(1000));
+    assert!(matches!(
+        error,
+        ChromaClientError::BatchTooLarge {
+            sent: 1001,
+            max: 1000
+        }
+    ));
+}
+
+#[tokio::test]
+async fn upsert_texts_in_batches() {
+    let chroma = MockChroma::start().await;
+    chroma
+        .respond("POST", "api/v1/collections/c0ffee/upsert", true)
+        .await;
+    let texts: Vec<String> = (0..1001).map(|i| format!("text {}", i)).collect();
+
+    let ids = collection()
+        .upsert_texts(&chroma.client(), &LengthEmbedder, &texts, None)
+        .await
+        .unwrap();
+
+    assert_eq!(ids.len(), 1001);
+    let requests = chroma.server().received_requests().await.unwrap();
+    let sizes: Vec<usize> = requests
+        .iter()
+        .filter(|r| r.url.path().ends_with("/upsert"))
+        .map(|r| {
+            let body: serde_json::Value = serde_json::from_slice(&r.body).unwrap();
+            body["ids"].as_array().unwrap().len()
+        })
+        .collect();
+    assert_eq!(sizes, [1000, 1]);
+}
+
+#[tokio::test]
+async fn versioned_writes() {
+    let chroma = MockChroma::start().await;
+    let client = chroma.client();
+    chroma
+        .respond(
+            "POST",
+            "api/v1/collections/c0ffee/get",
+            json!({
+                "ids": ["a::v2"],
+                "embeddings": null,
+                "documents": null,
+                "metadatas": [{ "record_id": "a", "version": 2 }],
+            }),
+        )
+        .await;
+    chroma
+        .respond("POST", "api/v1/collections/c0ffee/upsert", true)
+        .await;
+    let entries = || Entries {
+        ids: vec!["a".into()],
+        documents: Some(vec!["a new".into()]),
+        ..Default::default()
+    };
+
+    assert!(collection()
+        .get_latest(&client, &[])
+        .await
+        .unwrap()
+        .ids
+        .is_empty());
+    let error = collection()
+        .upsert_if_version(&client, entries(), &[1])
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        ChromaClientError::VersionConflict {
+            expected: 1,
+            current: 2,
+            ..
+        }
+    ));
+    let versions = collection()
+        .upsert_if_version(&client, entries(), &[2])
+        .await
+        .unwrap();
+    assert_eq!(versions, [3]);
+
+    let requests = chroma.server().received_requests().await.unwrap();
+    let paths: Vec<&str> = requests
+        .iter()
+        .map(|r| r.url.path())
+        .filter(|p| p.starts_with("/api/v1/collections"))
+        .collect();
+    assert_eq!(
+        paths,
+        [
+            "/api/v1/collections/c0ffee/get",
+            "/api/v1/collections/c0ffee/get",
+            "/api/v1/collections/c0ffee/upsert"
+        ]
+    );
+    let upsert: serde_json::Value = serde_json::from_slice(&requests.last().unwrap().body).unwrap();
+    assert_eq!(upsert["ids"], json!(["a::v3"]));
+}
+
+#[tokio::test]
+async fn bench_reports_the_run_error_over_the_cleanup_error() {
+    use chromadb_rs::bench::{self, BenchConfig};
+
+    let chroma = MockChroma::start().await;
+    chroma
+        .respond("POST", "api/v1/collections", collection_response("bench"))
+        .await;
+    let (status, body) = fixtures::invalid_dimension(3, 384);
+    chroma
+        .fail("POST", "api/v1/collections/c0ffee/add", status, body)
+        .await;
+    let (_, body) = fixtures::internal_error();
+    chroma
+        .fail("DELETE", "api/v1/collections/bench", 500, body)
+        .await;
+    let config = BenchConfig {
+        collection: "bench".into(),
+        records: 10,
+        ..Default::default()
+    };
+
+    let error = bench::run(&chroma.client(), &config).await.unwrap_err();
+
+    assert_eq!(error.api_error().unwrap().status, 400);
+    let requests = chroma.server().received_requests().await.unwrap();
+    assert!(requests.iter().any(|r| r.method.as_str() == "DELETE"));
+}
+
+#[tokio::test]
+async fn connect() {
+    let chroma = MockChroma::start().await;
+    chroma
+        .respond("GET", "api/v1/collections/c0ffee/count", 42)
+        .await;
+
+    let client = chroma.client().connect().await.unwrap();
+    let checks = chroma.server().received_requests().await.unwrap().len();
+
+    assert_eq!(client.server().version, "0.5.5");
+    assert_eq!(client.batch_size_limit(), Some(1000));
+    assert_eq!(collection().count(&client).await.unwrap(), 42);
+    assert_eq!(collection().count(&client).await.unwrap(), 42);
+    // Only the two counts: no version probe nor pre-flight check.
+    let requests = chroma.server().received_requests().await.unwrap();
+    assert_eq!(requests.len(), checks + 2);
+}
+
+#[tokio::test]
+async fn preflight_modes() {
+    let chroma = MockChroma::start().await;
+    chroma
+        .respond("GET", "api/v1/collections/c0ffee/count", 42)
+        .await;
+    let address = chroma.server().address();
+    let client = |preflight| {
+        let mut params = ChromaClientParams::default();
+        params.host = address.ip().to_string();
+        params.port = address.port().to_string();
+        params.preflight = preflight;
+        ChromaClient::new(params)
+    };
+    let preflight_checks = || async {
+        let requests = chroma.server().received_requests().await.unwrap();
+        requests
+            .iter()
+            .filter(|r| r.url.path() == "/api/v1/pre-flight-checks")
+            .count()
+    };
+
+    let once = client(PreflightMode::Once);
+    collection().count(&once).await.unwrap();
+    collection().count(&once).await.unwrap();
+    assert_eq!(preflight_checks().await, 1);
+
+    let never = client(PreflightMode::Never);
+    collection().count(&never).await.unwrap();
+    assert_eq!(preflight_checks().await, 1);
+    assert_eq!(never.max_batch_size().await.unwrap(), None);
+}
+
+#[cfg(feature = "ingest")]
+#[tokio::test]
+async fn ingest_scrubs_before_splitting() {
+    use chromadb_rs::ingest::IngestPipeline;
+    use chromadb_rs::text::TextSplitter;
+
+    let dir = std::env::temp_dir().join(format!("chroma-rs-ingest-pii-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("a.txt"), "mail jane.doe@example.com now").unwrap();
+    let chroma = MockChroma::start().await;
+    chroma
+        .respond(
+            "POST",
+            "api/v1/collections/c0ffee/get",
+            json!({ "ids": [], "embeddings": null, "documents": null, "metadatas": null }),
+        )
+        .await;
+    chroma
+        .respond("POST", "api/v1/collections/c0ffee/upsert", true)
+        .await;
+
+    // Chunks of 12 characters would cut the address in two.
+    let report = IngestPipeline::from_dir(&dir, "*.txt")
+        .splitter(TextSplitter::new(12, 0))
+        .scrub_pii()
+        .run(&chroma.client(), &collection(), &LengthEmbedder)
+        .await
+        .unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(report.redactions.emails, 1);
+    let requests = chroma.server().received_requests().await.unwrap();
+    let upsert = requests
+        .iter()
+        .find(|r| r.url.path().ends_with("/upsert"))
+        .unwrap();
+    let body = String::from_utf8_lossy(&upsert.body);
+    assert!(body.contains("[EMAIL]"));
+    assert!(!body.contains("example.com"));
+}
+
+#[cfg(feature = "ingest")]
+#[tokio::test]
+async fn ingest_skips_unchanged_files() {
+    use chromadb_rs::ingest::IngestPipeline;
+
+    let dir = std::env::temp_dir().join(format!("chroma-rs-ingest-runs-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("a.txt"), "first file").unwrap();
+    std::fs::write(dir.join("b.txt"), "second file").unwrap();
+    let chroma = MockChroma::start().await;
+    chroma
+        .respond(
+            "POST",
+            "api/v1/collections/c0ffee/get",
+            json!({ "ids": [], "embeddings": null, "documents": null, "metadatas": null }),
+        )
+        .await;
+    chroma
+        .respond("POST", "api/v1/collections/c0ffee/upsert", true)
+        .await;
+    let pipeline = IngestPipeline::from_dir(&dir, "*.txt").state_file(dir.join("state.json"));
+
+    let first = pipeline
+        .run(&chroma.client(), &collection(), &LengthEmbedder)
+        .await
+        .unwrap();
+    std::fs::write(dir.join("b.txt"), "second file, edited").unwrap();
+    let second = pipeline
+        .run(&chroma.client(), &collection(), &LengthEmbedder)
+        .await
+        .unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!((first.files_indexed, first.chunks_upserted), (2, 2));
+    assert_eq!((second.files_indexed, second.files_unchanged), (1, 1));
+    assert_eq!(second.chunks_upserted, 1);
+}
+
+#[cfg(feature = "ingest")]
+#[tokio::test]
+async fn ingest_keeps_created_at_of_reused_chunks() {
+    use chromadb_rs::enrich::Timestamps;
+    use chromadb_rs::ingest::IngestPipeline;
+
+    let dir = std::env::temp_dir().join(format!("chroma-rs-ingest-created-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("a.txt"), "unchanged text").unwrap();
+    let body = |request: &wiremock::Request| -> serde_json::Value {
+        serde_json::from_slice(&request.body).unwrap()
+    };
+
+    let first = MockChroma::start().await;
+    first
+        .respond(
+            "POST",
+            "api/v1/collections/c0ffee/get",
+            json!({ "ids": [], "embeddings": null, "documents": null, "metadatas": null }),
+        )
+        .await;
+    first
+        .respond("POST", "api/v1/collections/c0ffee/upsert", true)
+        .await;
+    IngestPipeline::from_dir(&dir, "*.txt")
+        .enrich(Timestamps)
+        .run(&first.client(), &collection(), &LengthEmbedder)
+        .await
+        .unwrap();
+    let requests = first.server().received_requests().await.unwrap();
+    let upsert = requests
+        .iter()
+        .find(|r| r.url.path().ends_with("/upsert"))
+        .map(body)
+        .unwrap();
+    let id = upsert["ids"][0].clone();
+
+    // The file changed around the chunk, which is stored since a while.
+    let second = MockChroma::start().await;
+    second
+        .respond(
+            "POST",
+            "api/v1/collections/c0ffee/get",
+            json!({ "ids": [id], "embeddings": null, "documents": null, "metadatas": [{ "created_at": 100 }] }),
+        )
+        .await;
+    second
+        .respond("POST", "api/v1/collections/c0ffee/update", true)
+        .await;
+    let report = IngestPipeline::from_dir(&dir, "*.txt")
+        .enrich(Timestamps)
+        .run(&second.client(), &collection(), &LengthEmbedder)
+        .await
+        .unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(report.chunks_reused, 1);
+    let requests = second.server().received_requests().await.unwrap();
+    let update = requests
+        .iter()
+        .find(|r| r.url.path().ends_with("/update"))
+        .map(body)
+        .unwrap();
+    assert_eq!(update["ids"][0], id);
+    assert_eq!(update["metadatas"][0]["created_at"], 100);
+}
+
+#[cfg(feature = "ingest")]
+#[tokio::test]
+async fn ingest_in_the_background() {
+    use chromadb_rs::ingest::IngestPipeline;
+
+    let dir = std::env::temp_dir().join(format!("chroma-rs-ingest-spawn-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("a.txt"), "first file").unwrap();
+    std::fs::write(dir.join("b.txt"), "second file").unwrap();
+    let chroma = MockChroma::start().await;
+    chroma
+        .respond(
+            "POST",
+            "api/v1/collections/c0ffee/get",
+            json!({ "ids": [], "embeddings": null, "documents": null, "metadatas": null }),
+        )
+        .await;
+    chroma
+        .respond("POST", "api/v1/collections/c0ffee/upsert", true)
+        .await;
+
+    let job = IngestPipeline::from_dir(&dir, "*.txt").spawn(
+        &chroma.client(),
+        &collection(),
+        Arc::new(LengthEmbedder),
+    );
+    while !job.is_finished() {
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    }
+    let progress = job.progress();
+    let report = job.wait().await.unwrap().unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(progress, 2);
+    assert_eq!((report.files_indexed, report.chunks_upserted), (2, 2));
+}
+
+#[tokio::test]
+async fn hybrid_ranks_every_keyword_match() {
+    use chromadb_rs::hybrid::HybridOptions;
+
+    let chroma = MockChroma::start().await;
+    chroma
+        .respond(
+            "POST",
+            "api/v1/collections/c0ffee/query",
+            json!({ "ids": [[]], "distances": [[]], "documents": [[]], "metadatas": [[]] }),
+        )
+        .await;
+    let filter = json!({ "kw_tokio": { "$gt": 0.0 } });
+    let page = |offset: usize| json!({ "where": filter, "limit": 500, "offset": offset, "include": ["metadatas"] });
+    // Storage order puts the best match after the first page.
+    let ids: Vec<String> = (0..500).map(|i| format!("r{}", i)).collect();
+    let metadatas = vec![json!({ "kw_tokio": 0.1 }); 500];
+    chroma
+        .expect(
+            "POST",
+            "api/v1/collections/c0ffee/get",
+            page(0),
+            json!({ "ids": ids, "metadatas": metadatas }),
+        )
+        .await;
+    chroma
+        .expect(
+            "POST",
+            "api/v1/collections/c0ffee/get",
+            page(500),
+            json!({ "ids": ["best"], "metadatas": [{ "kw_tokio": 9.0 }] }),
+        )
+        .await;
+    let candidates: Vec<&str> = std::iter::once("best")
+        .chain(ids.iter().take(49).map(String::as_str))
+        .collect();
+    chroma
+        .expect(
+            "POST",
+            "api/v1/collections/c0ffee/get",
+            json!({ "ids": candidates, "include": ["documents"] }),
+            json!({ "ids": ["best"], "documents": ["tokio runtime"] }),
+        )
+        .await;
+
+    let hits = collection()
+        .query_hybrid(
+            &chroma.client(),
+            vec![0.0],
+            "tokio",
+            1,
+            &HybridOptions::default(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(hits[0].hit.id, "best");
+    assert_eq!(hits[0].hit.document.as_deref(), Some("tokio runtime"));
+    chroma.verify().await;
+}
+
+#[cfg(feature = "ingest")]
+#[tokio::test]
+async fn ingest_near_duplicates_in_cosine_collections() {
+    use chromadb_rs::ingest::IngestPipeline;
+
+    let dir = std::env::temp_dir().join(format!("chroma-rs-ingest-near-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("a.txt"), "almost the same text").unwrap();
+    let chroma = MockChroma::start().await;
+    chroma
+        .respond(
+            "POST",
+            "api/v1/collections/c0ffee/get",
+            json!({ "ids": [], "embeddings": null, "documents": null, "metadatas": null }),
+        )
+        .await;
+    chroma
+        .expect(
+            "POST",
+            "api/v1/collections/c0ffee/query",
+            json!({ "query_embeddings": [[20.0]], "n_results": 2, "include": ["distances"] }),
+            json!({ "ids": [["other"]], "distances": [[0.05]] }),
+        )
+        .await;
+    let collection = Collection::with_id(
+        "docs".into(),
+        "c0ffee".into(),
+        Some(json!({ "hnsw:space": "cosine" })),
+    );
+
+    let report = IngestPipeline::from_dir(&dir, "*.txt")
+        .near_duplicates(0.9)
+        .run(&chroma.client(), &collection, &LengthEmbedder)
+        .await
+        .unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(report.near_duplicate_chunks, 1);
+    assert_eq!(report.dropped[0].duplicate_of, "other");
+    assert!((report.dropped[0].similarity - 0.95).abs() < 1e-6);
+    chroma.verify().await;
+}
+
+#[tokio::test]
+async fn configured_server_version() {
+    let chroma = MockChroma::start().await;
+    chroma
+        .expect(
+            "POST",
+            "api/v1/collections/c0ffee/get",
+            json!({ "ids": ["a"], "include": ["documents"] }),
+            json!({ "ids": ["a"], "documents": ["hello"] }),
+        )
+        .await;
+    let address = chroma.server().address();
+    let mut params = ChromaClientParams::default();
+    params.host = address.ip().to_string();
+    params.port = address.port().to_string();
+    params.preflight = PreflightMode::Never;
+    params.server_version = Some("0.4.14".into());
+    let client = ChromaClient::new(params);
+
+    let params = GetParams {
+        ids: Some(vec!["a".into()]),
+        include: Some(vec![Include::Documents, Include::Uris]),
+        ..Default::default()
+    };
+    collection().get(&client, params).await.unwrap();
+
+    // Neither version probe nor pre-flight check, and no `uris` for 0.4.14.
+    let requests = chroma.server().received_requests().await.unwrap();
+    assert_eq!(requests.len(), 1);
+    chroma.verify().await;
+}